@@ -39,6 +39,7 @@
 
 use std::{
     cmp::Reverse,
+    collections::HashSet,
     env,
     fs::{self, File},
     io::{BufReader, Cursor, Read, Seek},
@@ -46,6 +47,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use chrono::{DateTime, Datelike, SecondsFormat, TimeZone, Timelike, Utc};
+use infer::Infer;
 use log::warn;
 use quick_xml::{
     Writer,
@@ -59,12 +62,20 @@ use crate::builder::content::ContentBuilder;
 use crate::{
     epub::EpubDoc,
     error::{EpubBuilderError, EpubError},
-    types::{ManifestItem, MetadataItem, NavPoint, SpineItem},
-    utils::{check_realtive_link_leakage, local_time, remove_leading_slash},
+    types::{
+        EpubVersion, ManifestItem, MetadataItem, MetadataLinkItem, NavPoint, SpineItem,
+        ValidationIssue,
+    },
+    utils::{
+        check_realtive_link_leakage, is_content_document_type, is_core_media_type,
+        remove_leading_slash, unique_dir_name,
+    },
 };
 
 #[cfg(feature = "content-builder")]
 pub mod content;
+#[cfg(feature = "content-builder")]
+pub mod import;
 
 pub use components::CatalogBuilder;
 #[cfg(feature = "content-builder")]
@@ -156,6 +167,12 @@ pub struct EpubBuilder<Version> {
 
     #[cfg(feature = "content-builder")]
     pub(crate) content: DocumentBuilder,
+
+    /// Whether to produce byte-for-byte identical output for identical input
+    reproducible: bool,
+
+    /// Modification time used for zip entries and `dcterms:modified`
+    mtime: Option<DateTime<Utc>>,
 }
 
 impl EpubBuilder<EpubVersion3> {
@@ -165,7 +182,7 @@ impl EpubBuilder<EpubVersion3> {
     /// - `Ok(EpubBuilder)`: Builder instance created successfully
     /// - `Err(EpubError)`: Error occurred during builder initialization
     pub fn new() -> Result<Self, EpubError> {
-        let temp_dir = env::temp_dir().join(local_time());
+        let temp_dir = env::temp_dir().join(unique_dir_name());
         fs::create_dir(&temp_dir)?;
         fs::create_dir(temp_dir.join("META-INF"))?;
 
@@ -184,6 +201,9 @@ impl EpubBuilder<EpubVersion3> {
 
             #[cfg(feature = "content-builder")]
             content: DocumentBuilder::new(),
+
+            reproducible: false,
+            mtime: None,
         })
     }
 
@@ -217,6 +237,18 @@ impl EpubBuilder<EpubVersion3> {
         self
     }
 
+    /// Add metadata link
+    ///
+    /// Links associate the publication with external records or resources,
+    /// such as an ONIX record, and are only written for EPUB 3 publications.
+    ///
+    /// ## Parameters
+    /// - `link`: Metadata link to add
+    pub fn add_metadata_link(&mut self, link: MetadataLinkItem) -> &mut Self {
+        let _ = self.metadata.add_link(link);
+        self
+    }
+
     /// Add manifest item and corresponding resource file
     ///
     /// The builder will automatically recognize the file type of
@@ -381,6 +413,111 @@ impl EpubBuilder<EpubVersion3> {
         &mut self.content
     }
 
+    /// Validates the publication without building it
+    ///
+    /// Checks the publication for problems that do not prevent it from being built,
+    /// but make it non-conforming:
+    /// - Manifest items whose media type is not an EPUB core media type, and which
+    ///   have no fallback
+    /// - Spine items referencing a resource that is not an XHTML or SVG content document,
+    ///   and whose fallback chain does not reach one
+    ///
+    /// ## Return
+    /// - `Vec<ValidationIssue>`: The issues found, empty if none was found
+    ///
+    /// ## Notes
+    /// - Manifest items are reported in the order of their ids, followed by the spine
+    ///   items in reading order.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut items = self.manifest.manifest.values().collect::<Vec<_>>();
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut issues = items
+            .into_iter()
+            .filter(|item| item.fallback.is_none() && !is_core_media_type(&item.mime))
+            .map(|item| ValidationIssue::ForeignResourceWithoutFallback {
+                manifest_id: item.id.clone(),
+                mime: item.mime.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        for spine_item in self.spine.spine.iter() {
+            let Some(item) = self.manifest.manifest.get(&spine_item.idref) else {
+                continue;
+            };
+
+            let issue = ValidationIssue::SpineItemNotContentDocument {
+                manifest_id: item.id.clone(),
+                mime: item.mime.clone(),
+            };
+            if !self.reaches_content_document(item) && !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        }
+
+        issues
+    }
+
+    /// Checks whether a manifest item or its fallback chain contains a content document
+    ///
+    /// The chain ends at a missing fallback item, and a circular chain is followed
+    /// only once.
+    fn reaches_content_document(&self, item: &ManifestItem) -> bool {
+        let mut visited = HashSet::new();
+        let mut current = Some(item);
+        while let Some(item) = current {
+            if is_content_document_type(&item.mime) {
+                return true;
+            }
+            if !visited.insert(item.id.as_str()) {
+                return false;
+            }
+
+            current = item
+                .fallback
+                .as_ref()
+                .and_then(|fallback| self.manifest.manifest.get(fallback));
+        }
+
+        false
+    }
+
+    /// Set whether the build output is reproducible
+    ///
+    /// When enabled, building the same content twice produces byte-for-byte
+    /// identical EPUB files. Zip entries and the `dcterms:modified` metadata use
+    /// the time set by [`set_mtime`](Self::set_mtime), or `1980-01-01T00:00:00Z`
+    /// if no time has been set, instead of the current time.
+    ///
+    /// ## Parameters
+    /// - `reproducible`: Whether to enable reproducible output, disabled by default
+    pub fn set_reproducible(&mut self, reproducible: bool) -> &mut Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    /// Set the modification time of the publication
+    ///
+    /// The time is used as the last modified time of all zip entries and
+    /// as the value of the `dcterms:modified` metadata, replacing any
+    /// `dcterms:modified` item that has been added.
+    ///
+    /// ## Parameters
+    /// - `mtime`: Modification time
+    ///
+    /// ## Return
+    /// - `Ok(&mut Self)`: Successfully set the modification time
+    /// - `Err(EpubError)`: The time is outside the years 1980 to 2107 supported by zip
+    ///
+    /// ## Notes
+    /// - Zip archives store times with a precision of two seconds.
+    pub fn set_mtime(&mut self, mtime: DateTime<Utc>) -> Result<&mut Self, EpubError> {
+        zip_date_time(&mtime)?;
+
+        self.mtime = Some(mtime);
+        Ok(self)
+    }
+
     /// Builds an EPUB file and saves it to the specified path
     ///
     /// ## Parameters
@@ -389,7 +526,17 @@ impl EpubBuilder<EpubVersion3> {
     /// ## Return
     /// - `Ok(())`: Build successful
     /// - `Err(EpubError)`: Error occurred during the build process
+    ///
+    /// ## Notes
+    /// - The `mimetype` file is always the first entry of the archive, and the
+    ///   other entries follow in a stable order sorted by file name.
     pub fn make(mut self, output_path: impl AsRef<Path>) -> Result<(), EpubError> {
+        let mtime = match self.mtime {
+            Some(mtime) => Some(mtime),
+            None if self.reproducible => Some(Utc.with_ymd_and_hms(1980, 1, 1, 0, 0, 0).unwrap()),
+            None => None,
+        };
+
         // Create the container.xml, navigation document, and OPF files in sequence.
         // The associated metadata will initialized when navigation document is created;
         // therefore, the navigation document must be created before the opf file is created.
@@ -397,6 +544,9 @@ impl EpubBuilder<EpubVersion3> {
         self.make_navigation_document()?;
         #[cfg(feature = "content-builder")]
         self.make_contents()?;
+        if let Some(mtime) = &mtime {
+            self.set_modified_metadata(mtime);
+        }
         self.make_opf_file()?;
         self.remove_empty_dirs()?;
 
@@ -409,9 +559,19 @@ impl EpubBuilder<EpubVersion3> {
         // pack zip file
         let file = File::create(output_path)?;
         let mut zip = ZipWriter::new(file);
-        let options = FileOptions::<()>::default().compression_method(CompressionMethod::Stored);
+        let mut options =
+            FileOptions::<()>::default().compression_method(CompressionMethod::Stored);
+        if let Some(mtime) = &mtime {
+            options = options.last_modified_time(zip_date_time(mtime)?);
+        }
+
+        // The 'mimetype' file must be the first entry in the archive
+        let entries = WalkDir::new(&self.temp_dir).min_depth(1).sort_by(|a, b| {
+            (a.file_name() != "mimetype", a.file_name())
+                .cmp(&(b.file_name() != "mimetype", b.file_name()))
+        });
 
-        for entry in WalkDir::new(&self.temp_dir) {
+        for entry in entries {
             let entry = entry?;
             let path = entry.path();
 
@@ -484,6 +644,7 @@ impl EpubBuilder<EpubVersion3> {
 
         builder.add_rootfile(doc.package_path.clone().to_string_lossy())?;
         builder.metadata.metadata = doc.metadata.clone();
+        builder.metadata.links = doc.metadata_link.clone();
         builder.spine.spine = doc.spine.clone();
         builder.catalog.catalog = doc.catalog.clone();
         builder.catalog.title = doc.catalog_title.clone();
@@ -608,7 +769,7 @@ impl EpubBuilder<EpubVersion3> {
             ("version", "3.0"),
         ])))?;
 
-        self.metadata.make(&mut writer)?;
+        self.metadata.make(&mut writer, EpubVersion::Version3_0)?;
         self.manifest.make(&mut writer)?;
         self.spine.make(&mut writer)?;
 
@@ -623,6 +784,23 @@ impl EpubBuilder<EpubVersion3> {
         Ok(())
     }
 
+    /// Set the `dcterms:modified` metadata to the specified time
+    ///
+    /// Replaces the value of an existing `dcterms:modified` item, or adds
+    /// a new item if none exists.
+    fn set_modified_metadata(&mut self, mtime: &DateTime<Utc>) {
+        let value = mtime.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        let items = &mut self.metadata.metadata;
+        match items
+            .iter_mut()
+            .find(|item| item.property == "dcterms:modified")
+        {
+            Some(item) => item.value = value,
+            None => items.push(MetadataItem::new("dcterms:modified", &value)),
+        }
+    }
+
     /// Remove empty directories under the builder temporary directory
     ///
     /// By enumerate directories under `self.temp_dir` (excluding the root itself)
@@ -663,6 +841,96 @@ impl<Version> Drop for EpubBuilder<Version> {
     }
 }
 
+/// Convert a time to a zip date time
+///
+/// ## Parameters
+/// - `time`: The time to convert
+///
+/// ## Return
+/// - `Ok(zip::DateTime)`: The converted date time
+/// - `Err(EpubError)`: The time is outside the years 1980 to 2107 supported by zip
+fn zip_date_time(time: &DateTime<Utc>) -> Result<zip::DateTime, EpubError> {
+    u16::try_from(time.year())
+        .ok()
+        .and_then(|year| {
+            zip::DateTime::from_date_and_time(
+                year,
+                time.month() as u8,
+                time.day() as u8,
+                time.hour() as u8,
+                time.minute() as u8,
+                time.second() as u8,
+            )
+            .ok()
+        })
+        .ok_or_else(|| {
+            EpubBuilderError::InvalidModificationTime {
+                time: time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            }
+            .into()
+        })
+}
+
+/// Infer the MIME type of a file
+///
+/// The MIME type is inferred from the file content and refined by the file extension.
+/// SVG images are detected by their root element, since they are XML text without
+/// a magic number. When the content is not recognized, the file extension decides.
+///
+/// ## Parameters
+/// - `buf`: The file content, or at least its leading bytes
+/// - `extension`: The lowercase file extension, or an empty string
+fn infer_mime_type<'a>(buf: &[u8], extension: &'a str) -> Option<&'a str> {
+    if is_svg(buf) {
+        return Some("image/svg+xml");
+    }
+
+    match Infer::new().get(buf) {
+        Some(file_type) => Some(refine_mime_type(file_type.mime_type(), extension)),
+        None => mime_type_by_extension(extension),
+    }
+}
+
+/// Checks whether the content is an SVG image
+///
+/// Skips an optional BOM, the XML declaration, processing instructions, comments and
+/// the DOCTYPE, then checks whether the root element is `svg`.
+fn is_svg(buf: &[u8]) -> bool {
+    let mut rest = buf.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(buf);
+
+    loop {
+        rest = rest.trim_ascii_start();
+
+        let terminator: &[u8] = if rest.starts_with(b"<?") {
+            b"?>"
+        } else if rest.starts_with(b"<!--") {
+            b"-->"
+        } else if rest.starts_with(b"<!") {
+            b">"
+        } else {
+            break;
+        };
+
+        match rest
+            .windows(terminator.len())
+            .position(|window| window == terminator)
+        {
+            Some(index) => rest = &rest[index + terminator.len()..],
+            None => return false,
+        }
+    }
+
+    match rest
+        .strip_prefix(b"<svg:svg")
+        .or_else(|| rest.strip_prefix(b"<svg"))
+    {
+        Some(rest) => rest
+            .first()
+            .is_none_or(|ch| ch.is_ascii_whitespace() || b"/>".contains(ch)),
+        None => false,
+    }
+}
+
 /// Refine the MIME type based on file extension
 ///
 /// This function optimizes MIME types that are inferred from file content by using
@@ -684,12 +952,52 @@ fn refine_mime_type<'a>(infer_mime: &'a str, extension: &'a str) -> &'a str {
         ("text/plain", "css") => "text/css",
         ("text/plain", "js") => "application/javascript",
         ("text/plain", "json") => "application/json",
-        ("text/plain", "svg") => "image/svg+xml",
+        ("text/plain", "svg") | ("text/xml", "svg") | ("application/xml", "svg") => "image/svg+xml",
 
         _ => infer_mime,
     }
 }
 
+/// Infer the MIME type based on file extension
+///
+/// This function is used as a fallback when the MIME type cannot be inferred from
+/// file content. Text based formats such as SVG have no magic number, and some media
+/// formats are not recognized by `infer`, so the file extension decides their type.
+///
+/// ## Notes
+/// - Only images, audio, video and fonts are covered. Documents must still be
+///   recognizable by their content.
+fn mime_type_by_extension(extension: &str) -> Option<&'static str> {
+    let mime = match extension {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "aac" => "audio/aac",
+        "flac" => "audio/flac",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "wav" => "audio/wav",
+
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "ogv" => "video/ogg",
+
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+
+        _ => return None,
+    };
+
+    Some(mime)
+}
+
 /// Normalize manifest path to absolute path within EPUB container
 ///
 /// This function takes a path (relative or absolute) and normalizes it to an absolute
@@ -721,6 +1029,7 @@ fn normalize_manifest_path<TempD: AsRef<Path>, S: AsRef<str>, P: AsRef<Path>>(
     let basic_path = remove_leading_slash(opf_path.parent().unwrap());
 
     // convert manifest path to absolute path(physical path)
+    #[cfg_attr(not(windows), allow(unused_mut))]
     let mut target_path = if path.as_ref().starts_with("../") {
         check_realtive_link_leakage(
             temp_dir.as_ref().to_path_buf(),
@@ -753,10 +1062,16 @@ mod tests {
     use std::{env, fs, path::PathBuf};
 
     use crate::{
-        builder::{EpubBuilder, EpubVersion3, normalize_manifest_path, refine_mime_type},
+        builder::{
+            EpubBuilder, EpubVersion3, infer_mime_type, is_svg, mime_type_by_extension,
+            normalize_manifest_path, refine_mime_type,
+        },
         epub::EpubDoc,
         error::{EpubBuilderError, EpubError},
-        types::{ManifestItem, MetadataItem, NavPoint, SpineItem},
+        types::{
+            EpubVersion, ManifestItem, MetadataItem, MetadataLinkItem, NavPoint, SpineItem,
+            ValidationIssue,
+        },
         utils::local_time,
     };
 
@@ -950,6 +1265,55 @@ mod tests {
             assert!(builder.build(&file).is_ok());
         }
 
+        #[test]
+        fn test_make_reproducible() {
+            use chrono::{TimeZone, Utc};
+
+            let make = |path: &PathBuf| {
+                let mut builder = test_helpers::create_full_builder();
+                builder
+                    .add_manifest(
+                        "./test_case/Overview.xhtml",
+                        ManifestItem {
+                            id: "test".to_string(),
+                            path: PathBuf::from("test.xhtml"),
+                            mime: String::new(),
+                            properties: None,
+                            fallback: None,
+                        },
+                    )
+                    .unwrap();
+                builder.set_reproducible(true);
+                builder
+                    .set_mtime(Utc.with_ymd_and_hms(2024, 5, 6, 7, 8, 10).unwrap())
+                    .unwrap();
+                builder.make(path).unwrap();
+            };
+
+            let first = env::temp_dir().join(format!("{}-1.epub", local_time()));
+            let second = env::temp_dir().join(format!("{}-2.epub", local_time()));
+            make(&first);
+            make(&second);
+            assert_eq!(fs::read(&first).unwrap(), fs::read(&second).unwrap());
+
+            let doc = EpubDoc::new(&first).unwrap();
+            assert_eq!(
+                doc.get_metadata_value("dcterms:modified"),
+                Some(vec!["2024-05-06T07:08:10Z".to_string()])
+            );
+            assert_eq!(doc.archive_entry_names()[0], "mimetype");
+
+            let mut builder = EpubBuilder::<EpubVersion3>::new().unwrap();
+            let result = builder.set_mtime(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap());
+            assert_eq!(
+                result.err().unwrap(),
+                EpubBuilderError::InvalidModificationTime {
+                    time: "1970-01-01T00:00:00Z".to_string()
+                }
+                .into()
+            );
+        }
+
         #[test]
         fn test_from() {
             let metadata = vec![
@@ -987,12 +1351,14 @@ mod tests {
                     content: None,
                     children: vec![],
                     play_order: None,
+                    doc_index: 0,
                 },
                 NavPoint {
                     label: "Overview".to_string(),
                     content: None,
                     children: vec![],
                     play_order: None,
+                    doc_index: 1,
                 },
             ];
 
@@ -1298,6 +1664,119 @@ mod tests {
                 "Epub builder error: There are too many items with 'nav' property in the manifest."
             );
         }
+
+        #[test]
+        fn test_validate_foreign_resources() {
+            let mut builder = EpubBuilder::<EpubVersion3>::new().unwrap();
+
+            let items = [
+                ManifestItem::new("chapter", "chapter.xhtml")
+                    .unwrap()
+                    .set_mime("application/xhtml+xml"),
+                ManifestItem::new("data", "data.json")
+                    .unwrap()
+                    .set_mime("application/json"),
+                ManifestItem::new("table", "table.xml")
+                    .unwrap()
+                    .set_mime("application/xml")
+                    .with_fallback("chapter")
+                    .build(),
+                ManifestItem::new("video", "video.mp4")
+                    .unwrap()
+                    .set_mime("video/mp4"),
+                ManifestItem::new("sound", "sound.ogg")
+                    .unwrap()
+                    .set_mime("audio/ogg; codecs=opus"),
+                ManifestItem::new("drawing", "drawing.svg")
+                    .unwrap()
+                    .set_mime("image/svg+xml"),
+            ];
+            for item in items {
+                builder.manifest.manifest.insert(item.id.clone(), item);
+            }
+
+            for idref in [
+                "chapter", "data", "table", "sound", "data", "drawing", "missing",
+            ] {
+                builder.add_spine(SpineItem::new(idref));
+            }
+
+            // the video is not in the spine, but it is still a foreign resource, while
+            // the sound is a core media type, but not a content document
+            assert_eq!(
+                builder.validate(),
+                vec![
+                    ValidationIssue::ForeignResourceWithoutFallback {
+                        manifest_id: "data".to_string(),
+                        mime: "application/json".to_string(),
+                    },
+                    ValidationIssue::ForeignResourceWithoutFallback {
+                        manifest_id: "video".to_string(),
+                        mime: "video/mp4".to_string(),
+                    },
+                    ValidationIssue::SpineItemNotContentDocument {
+                        manifest_id: "data".to_string(),
+                        mime: "application/json".to_string(),
+                    },
+                    ValidationIssue::SpineItemNotContentDocument {
+                        manifest_id: "sound".to_string(),
+                        mime: "audio/ogg; codecs=opus".to_string(),
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn test_validate_image_spine_item() {
+            let mut builder = EpubBuilder::<EpubVersion3>::new().unwrap();
+
+            let items = [
+                ManifestItem::new("page", "page.xhtml")
+                    .unwrap()
+                    .set_mime("application/xhtml+xml"),
+                ManifestItem::new("scan", "scan.png")
+                    .unwrap()
+                    .set_mime("image/png"),
+                ManifestItem::new("photo", "photo.jpg")
+                    .unwrap()
+                    .set_mime("image/jpeg")
+                    .with_fallback("scan")
+                    .build(),
+                ManifestItem::new("plate", "plate.gif")
+                    .unwrap()
+                    .set_mime("image/gif")
+                    .with_fallback("photo")
+                    .build(),
+                ManifestItem::new("figure", "figure.webp")
+                    .unwrap()
+                    .set_mime("image/webp")
+                    .with_fallback("page")
+                    .build(),
+            ];
+            for item in items {
+                builder.manifest.manifest.insert(item.id.clone(), item);
+            }
+
+            for idref in ["page", "scan", "plate", "figure"] {
+                builder.add_spine(SpineItem::new(idref));
+            }
+
+            // images are core media types, but not content documents, and a fallback
+            // chain made only of images does not help
+            assert_eq!(
+                builder.validate(),
+                vec![
+                    ValidationIssue::SpineItemNotContentDocument {
+                        manifest_id: "scan".to_string(),
+                        mime: "image/png".to_string(),
+                    },
+                    ValidationIssue::SpineItemNotContentDocument {
+                        manifest_id: "plate".to_string(),
+                        mime: "image/gif".to_string(),
+                    },
+                ]
+            );
+        }
     }
 
     mod metadata_tests {
@@ -1316,6 +1795,122 @@ mod tests {
             builder.add_metadata(MetadataItem::new("language", "en"));
             assert!(builder.metadata.validate().is_err());
         }
+
+        #[test]
+        fn test_make_metadata_refinements_by_version() {
+            use std::io::Cursor;
+
+            use quick_xml::Writer;
+
+            use crate::types::MetadataRefinement;
+
+            let make = |version: EpubVersion| {
+                let mut builder = EpubBuilder::<EpubVersion3>::new().unwrap();
+                builder.add_metadata(
+                    MetadataItem::new("creator", "Jane Doe")
+                        .with_id("creator")
+                        .append_refinement(MetadataRefinement::new("creator", "role", "aut"))
+                        .append_refinement(MetadataRefinement::new(
+                            "creator",
+                            "file-as",
+                            "Doe, Jane",
+                        ))
+                        .build(),
+                );
+                builder.add_metadata(MetadataItem::new("calibre:series", "Series"));
+
+                let mut writer = Writer::new(Cursor::new(Vec::new()));
+                builder.metadata.make(&mut writer, version).unwrap();
+                String::from_utf8(writer.into_inner().into_inner()).unwrap()
+            };
+
+            let epub2 = make(EpubVersion::Version2_0);
+            assert!(epub2.starts_with(r#"<metadata xmlns:opf="http://www.idpf.org/2007/opf">"#));
+            assert!(epub2.contains(
+                r#"<dc:creator id="creator" opf:role="aut" opf:file-as="Doe, Jane">Jane Doe</dc:creator>"#
+            ));
+            assert!(epub2.contains(r#"<meta name="calibre:series" content="Series"/>"#));
+            assert!(!epub2.contains("refines"));
+            assert!(!epub2.contains("dcterms:modified"));
+
+            let epub3 = make(EpubVersion::Version3_0);
+            assert!(epub3.contains(r#"<dc:creator id="creator">Jane Doe</dc:creator>"#));
+            assert!(epub3.contains(r#"<meta refines="creator" property="role">aut</meta>"#));
+            assert!(
+                epub3.contains(r#"<meta refines="creator" property="file-as">Doe, Jane</meta>"#)
+            );
+            assert!(epub3.contains(r#"<meta property="calibre:series">Series</meta>"#));
+            assert!(epub3.contains(r#"property="dcterms:modified""#));
+        }
+
+        #[test]
+        fn test_make_metadata_link() {
+            let mut builder = test_helpers::create_full_builder();
+            builder
+                .add_manifest(
+                    "./test_case/Overview.xhtml",
+                    ManifestItem {
+                        id: "test".to_string(),
+                        path: PathBuf::from("test.xhtml"),
+                        mime: String::new(),
+                        properties: None,
+                        fallback: None,
+                    },
+                )
+                .unwrap();
+            builder.add_metadata_link(
+                MetadataLinkItem::new("https://example.org/onix.xml", "record")
+                    .with_media_type("application/xml")
+                    .with_properties("onix")
+                    .with_hreflang("en")
+                    .with_refines("#pub-id")
+                    .build(),
+            );
+
+            let file = env::temp_dir().join(format!("{}.epub", local_time()));
+            assert!(builder.make(&file).is_ok());
+
+            let doc = EpubDoc::new(&file).unwrap();
+            assert_eq!(doc.metadata_link.len(), 1);
+
+            let link = &doc.metadata_link[0];
+            assert_eq!(link.href, "https://example.org/onix.xml");
+            assert_eq!(link.rel, "record");
+            assert_eq!(link.mime, Some("application/xml".to_string()));
+            assert_eq!(link.properties, Some("onix".to_string()));
+            assert_eq!(link.hreflang, Some("en".to_string()));
+            assert_eq!(link.refines, Some("pub-id".to_string()));
+        }
+
+        #[test]
+        fn test_make_meta_property_metadata() {
+            use std::io::Cursor;
+
+            use quick_xml::Writer;
+
+            let mut builder = EpubBuilder::<EpubVersion3>::new().unwrap();
+            builder.add_metadata(MetadataItem::meta("schema:accessMode", "textual"));
+            builder.add_metadata(MetadataItem::meta("title", "Alternative Title"));
+            builder.add_metadata(MetadataItem::meta(
+                "dcterms:modified",
+                "2024-01-01T00:00:00Z",
+            ));
+
+            let mut writer = Writer::new(Cursor::new(Vec::new()));
+            builder
+                .metadata
+                .make(&mut writer, EpubVersion::Version3_0)
+                .unwrap();
+            let output = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+
+            assert!(output.contains(r#"<meta property="schema:accessMode">textual</meta>"#));
+            assert!(output.contains(r#"<meta property="dcterms:title">Alternative Title</meta>"#));
+            assert!(!output.contains("<dc:title"));
+            assert_eq!(output.matches("dcterms:modified").count(), 1);
+            assert!(
+                output.contains(r#"<meta property="dcterms:modified">2024-01-01T00:00:00Z</meta>"#)
+            );
+        }
     }
 
     mod utility_tests {
@@ -1378,6 +1973,36 @@ mod tests {
             );
             assert_eq!(refine_mime_type("text/plain", "css"), "text/css");
             assert_eq!(refine_mime_type("text/plain", "unknown"), "text/plain");
+            assert_eq!(refine_mime_type("text/xml", "svg"), "image/svg+xml");
+        }
+
+        #[test]
+        fn test_is_svg() {
+            assert!(is_svg(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+            assert!(is_svg(
+                b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n<!-- drawing -->\n<!DOCTYPE svg>\n<svg>"
+            ));
+            assert!(!is_svg(b"<html><body><svg></svg></body></html>"));
+            assert!(!is_svg(b"<svgx/>"));
+            assert!(!is_svg(b"<?xml version=\"1.0\""));
+        }
+
+        #[test]
+        fn test_infer_mime_type() {
+            let svg = fs::read("./test_case/image.svg").unwrap();
+            assert_eq!(infer_mime_type(&svg, ""), Some("image/svg+xml"));
+
+            let jpg = fs::read("./test_case/image.jpg").unwrap();
+            assert_eq!(infer_mime_type(&jpg, "jpg"), Some("image/jpeg"));
+            assert_eq!(infer_mime_type(b"unknown", "bin"), None);
+        }
+
+        #[test]
+        fn test_mime_type_by_extension() {
+            assert_eq!(mime_type_by_extension("svg"), Some("image/svg+xml"));
+            assert_eq!(mime_type_by_extension("opus"), Some("audio/opus"));
+            assert_eq!(mime_type_by_extension("webm"), Some("video/webm"));
+            assert_eq!(mime_type_by_extension("unknown"), None);
         }
     }
 
@@ -1452,6 +2077,32 @@ mod tests {
             assert!(builder.temp_dir.join("OEBPS/img/image.jpg").exists());
         }
 
+        #[test]
+        fn test_make_contents_with_svg_image() {
+            let mut builder = EpubBuilder::<EpubVersion3>::new().unwrap();
+            builder.add_rootfile("content.opf").unwrap();
+
+            let mut content_builder = ContentBuilder::new("chapter4", "en").unwrap();
+            content_builder
+                .add_image_block(
+                    std::path::PathBuf::from("./test_case/image.svg"),
+                    Some("Vector Image".to_string()),
+                    None,
+                    vec![],
+                )
+                .unwrap();
+
+            builder.add_content("OEBPS/chapter4.xhtml", content_builder);
+
+            assert!(builder.make_contents().is_ok());
+            assert!(builder.temp_dir.join("OEBPS/img/image.svg").exists());
+            assert!(
+                builder.manifest.manifest.values().any(
+                    |item| item.path.ends_with("img/image.svg") && item.mime == "image/svg+xml"
+                )
+            );
+        }
+
         #[test]
         fn test_make_contents_multiple_documents() {
             let mut builder = EpubBuilder::<EpubVersion3>::new().unwrap();
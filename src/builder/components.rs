@@ -1,7 +1,5 @@
 #[cfg(feature = "no-indexmap")]
 use std::collections::HashMap;
-#[cfg(feature = "content-builder")]
-use std::io::Read;
 use std::{
     fs,
     path::{Path, PathBuf},
@@ -10,15 +8,17 @@ use std::{
 use chrono::{SecondsFormat, Utc};
 #[cfg(not(feature = "no-indexmap"))]
 use indexmap::IndexMap;
-use infer::Infer;
 use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
 
 #[cfg(feature = "content-builder")]
 use crate::builder::content::ContentBuilder;
 use crate::{
-    builder::{XmlWriter, normalize_manifest_path, refine_mime_type},
+    builder::{XmlWriter, infer_mime_type, normalize_manifest_path},
     error::{EpubBuilderError, EpubError},
-    types::{ManifestItem, MetadataItem, MetadataSheet, NavPoint, SpineItem},
+    types::{
+        EpubVersion, ManifestItem, MetadataItem, MetadataLinkItem, MetadataSheet, NavPoint,
+        SpineItem,
+    },
     utils::ELEMENT_IN_DC_NAMESPACE,
 };
 
@@ -133,12 +133,15 @@ impl RootfileBuilder {
 pub struct MetadataBuilder {
     /// List of metadata items
     pub(crate) metadata: Vec<MetadataItem>,
+
+    /// List of links to external records and resources
+    pub(crate) links: Vec<MetadataLinkItem>,
 }
 
 impl MetadataBuilder {
     /// Creates a new empty `MetadataBuilder` instance
     pub(crate) fn new() -> Self {
-        Self { metadata: Vec::new() }
+        Self { metadata: Vec::new(), links: Vec::new() }
     }
 
     /// Add a metadata item
@@ -155,11 +158,27 @@ impl MetadataBuilder {
         self
     }
 
+    /// Add a metadata link
+    ///
+    /// Appends a link to an external record or resource, such as an ONIX record,
+    /// which is written as a `<link>` element.
+    ///
+    /// ## Parameters
+    /// - `link`: The metadata link to add
+    ///
+    /// ## Return
+    /// - `&mut Self`: Returns a mutable reference to itself for method chaining
+    pub fn add_link(&mut self, link: MetadataLinkItem) -> &mut Self {
+        self.links.push(link);
+        self
+    }
+
     /// Clear all metadata items
     ///
-    /// Removes all metadata items from the builder.
+    /// Removes all metadata items and metadata links from the builder.
     pub fn clear(&mut self) -> &mut Self {
         self.metadata.clear();
+        self.links.clear();
         self
     }
 
@@ -175,41 +194,123 @@ impl MetadataBuilder {
     ///
     /// Writes the XML representation of the metadata to the provided writer.
     /// This includes all metadata items and their refinements, as well as
-    /// automatically adding a `dcterms:modified` timestamp.
-    pub(crate) fn make(&mut self, writer: &mut XmlWriter) -> Result<(), EpubError> {
-        self.metadata.push(MetadataItem {
-            id: None,
-            property: "dcterms:modified".to_string(),
-            value: Utc::now().to_rfc3339_opts(SecondsFormat::AutoSi, true),
-            lang: None,
-            refined: vec![],
-        });
-
-        writer.write_event(Event::Start(BytesStart::new("metadata")))?;
+    /// automatically adding a `dcterms:modified` timestamp for EPUB 3 when none
+    /// has been added.
+    ///
+    /// ## Parameters
+    /// - `writer`: The XML writer to write the metadata to
+    /// - `version`: The EPUB version that decides how refinements are serialized
+    ///
+    /// ## Notes
+    /// - For EPUB 3, refinements are written as `<meta refines="#id">` elements.
+    /// - For EPUB 2, refinements of Dublin Core elements are written as `opf:`
+    ///   attributes on the element itself, such as `opf:role` and `opf:file-as`,
+    ///   and other metadata is written as `<meta name="..." content="..."/>`.
+    /// - Metadata links are written as `<link>` elements for EPUB 3 only, since
+    ///   EPUB 2 has no `link` element in the metadata.
+    pub(crate) fn make(
+        &mut self,
+        writer: &mut XmlWriter,
+        version: EpubVersion,
+    ) -> Result<(), EpubError> {
+        let mut metadata_start = BytesStart::new("metadata");
+        let has_modified = self
+            .metadata
+            .iter()
+            .any(|item| item.property == "dcterms:modified");
+        if version == EpubVersion::Version2_0 {
+            metadata_start.push_attribute(("xmlns:opf", "http://www.idpf.org/2007/opf"));
+        } else if !has_modified {
+            self.metadata.push(MetadataItem {
+                id: None,
+                property: "dcterms:modified".to_string(),
+                value: Utc::now().to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                lang: None,
+                refined: vec![],
+            });
+        }
+
+        writer.write_event(Event::Start(metadata_start))?;
 
         for metadata in &self.metadata {
-            let tag_name = if ELEMENT_IN_DC_NAMESPACE.contains(&metadata.property.as_str()) {
-                format!("dc:{}", metadata.property)
-            } else {
-                "meta".to_string()
-            };
+            match version {
+                EpubVersion::Version2_0 => Self::make_epub2_item(writer, metadata)?,
+                EpubVersion::Version3_0 => Self::make_epub3_item(writer, metadata)?,
+            }
+        }
+
+        if version == EpubVersion::Version3_0 {
+            for link in &self.links {
+                let mut start = BytesStart::new("link").with_attributes(link.attributes());
+                if let Some(refines) = &link.refines {
+                    start.push_attribute(("refines", format!("#{}", refines).as_str()));
+                }
+                writer.write_event(Event::Empty(start))?;
+            }
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("metadata")))?;
+
+        Ok(())
+    }
 
+    /// Write a metadata item and its refinements as EPUB 3 elements
+    fn make_epub3_item(writer: &mut XmlWriter, metadata: &MetadataItem) -> Result<(), EpubError> {
+        let tag_name = if ELEMENT_IN_DC_NAMESPACE.contains(&metadata.property.as_str()) {
+            format!("dc:{}", metadata.property)
+        } else {
+            "meta".to_string()
+        };
+
+        writer.write_event(Event::Start(
+            BytesStart::new(tag_name.as_str()).with_attributes(metadata.attributes()),
+        ))?;
+        writer.write_event(Event::Text(BytesText::new(metadata.value.as_str())))?;
+        writer.write_event(Event::End(BytesEnd::new(tag_name.as_str())))?;
+
+        for refinement in &metadata.refined {
             writer.write_event(Event::Start(
-                BytesStart::new(tag_name.as_str()).with_attributes(metadata.attributes()),
+                BytesStart::new("meta").with_attributes(refinement.attributes()),
             ))?;
-            writer.write_event(Event::Text(BytesText::new(metadata.value.as_str())))?;
-            writer.write_event(Event::End(BytesEnd::new(tag_name.as_str())))?;
+            writer.write_event(Event::Text(BytesText::new(refinement.value.as_str())))?;
+            writer.write_event(Event::End(BytesEnd::new("meta")))?;
+        }
 
-            for refinement in &metadata.refined {
-                writer.write_event(Event::Start(
-                    BytesStart::new("meta").with_attributes(refinement.attributes()),
-                ))?;
-                writer.write_event(Event::Text(BytesText::new(refinement.value.as_str())))?;
-                writer.write_event(Event::End(BytesEnd::new("meta")))?;
+        Ok(())
+    }
+
+    /// Write a metadata item as an EPUB 2 element
+    ///
+    /// Refinements of Dublin Core elements become attributes of the element. A refinement
+    /// property without a namespace prefix is placed in the `opf` namespace.
+    fn make_epub2_item(writer: &mut XmlWriter, metadata: &MetadataItem) -> Result<(), EpubError> {
+        if !ELEMENT_IN_DC_NAMESPACE.contains(&metadata.property.as_str()) {
+            writer.write_event(Event::Empty(BytesStart::new("meta").with_attributes([
+                ("name", metadata.property.as_str()),
+                ("content", metadata.value.as_str()),
+            ])))?;
+            return Ok(());
+        }
+
+        let tag_name = format!("dc:{}", metadata.property);
+        let mut start = BytesStart::new(tag_name.as_str()).with_attributes(metadata.attributes());
+        for refinement in &metadata.refined {
+            // the id is already written from the metadata item itself
+            if refinement.property == "id" {
+                continue;
             }
+
+            let name = if refinement.property.contains(':') {
+                refinement.property.clone()
+            } else {
+                format!("opf:{}", refinement.property)
+            };
+            start.push_attribute((name.as_str(), refinement.value.as_str()));
         }
 
-        writer.write_event(Event::End(BytesEnd::new("metadata")))?;
+        writer.write_event(Event::Start(start))?;
+        writer.write_event(Event::Text(BytesText::new(metadata.value.as_str())))?;
+        writer.write_event(Event::End(BytesEnd::new(tag_name.as_str())))?;
 
         Ok(())
     }
@@ -226,10 +327,8 @@ impl MetadataBuilder {
             match item.property.as_str() {
                 "title" => has_title = true,
                 "language" => has_language = true,
-                "identifier" => {
-                    if item.id.as_ref().is_some_and(|id| id == "pub-id") {
-                        has_identifier = true;
-                    }
+                "identifier" if item.id.as_ref().is_some_and(|id| id == "pub-id") => {
+                    has_identifier = true;
                 }
                 _ => {}
             }
@@ -339,8 +438,8 @@ impl ManifestBuilder {
         let buf = fs::read(source)?;
 
         // Get the mime type
-        let real_mime = match Infer::new().get(&buf) {
-            Some(infer_mime) => refine_mime_type(infer_mime.mime_type(), &extension),
+        let real_mime = match infer_mime_type(&buf, &extension) {
+            Some(mime) => mime,
             None => {
                 return Err(
                     EpubBuilderError::UnknownFileFormat { file_path: manifest_source }.into(),
@@ -407,10 +506,21 @@ impl ManifestBuilder {
     /// Generate the manifest XML content
     ///
     /// Writes the XML representation of the manifest to the provided writer.
+    /// Items are written in insertion order, or sorted by ID when the
+    /// `no-indexmap` feature is enabled, so that the output is deterministic.
     pub(crate) fn make(&self, writer: &mut XmlWriter) -> Result<(), EpubError> {
         writer.write_event(Event::Start(BytesStart::new("manifest")))?;
 
-        for manifest in self.manifest.values() {
+        #[cfg(feature = "no-indexmap")]
+        let manifests = {
+            let mut manifests = self.manifest.values().collect::<Vec<_>>();
+            manifests.sort_by(|a, b| a.id.cmp(&b.id));
+            manifests
+        };
+        #[cfg(not(feature = "no-indexmap"))]
+        let manifests = self.manifest.values();
+
+        for manifest in manifests {
             writer.write_event(Event::Empty(
                 BytesStart::new("item").with_attributes(manifest.attributes()),
             ))?;
@@ -806,7 +916,6 @@ impl DocumentBuilder {
         temp_dir: PathBuf,
         rootfile: impl AsRef<str>,
     ) -> Result<Vec<ManifestItem>, EpubError> {
-        let mut buf = vec![0; 512];
         let contents = std::mem::take(&mut self.documents);
 
         let mut manifest = Vec::new();
@@ -816,76 +925,19 @@ impl DocumentBuilder {
             // target is relative to the epub file, so we need to normalize it
             let absolute_target =
                 normalize_manifest_path(&temp_dir, &rootfile, &target, &manifest_id)?;
-            let mut resources = content.make(&absolute_target)?;
+            let items = content.make_with_manifest(&absolute_target)?;
 
-            // Helper to compute absolute container path
-            let to_container_path = |p: &PathBuf| -> PathBuf {
-                match p.strip_prefix(&temp_dir) {
-                    Ok(rel) => PathBuf::from("/").join(rel.to_string_lossy().replace("\\", "/")),
-                    Err(_) => unreachable!("path MUST under temp directory"),
-                }
+            // the paths of the items are relative to the directory of the document,
+            // while the manifest uses absolute container paths
+            let document_dir = match absolute_target.parent().map(|p| p.strip_prefix(&temp_dir)) {
+                Some(Ok(rel)) => PathBuf::from("/").join(rel.to_string_lossy().replace("\\", "/")),
+                _ => unreachable!("path MUST under temp directory"),
             };
 
-            // Document (first element, guaranteed to exist)
-            let path = resources.swap_remove(0);
-            let mut file = std::fs::File::open(&path)?;
-            let _ = file.read(&mut buf)?;
-            let extension = path
-                .extension()
-                .map(|e| e.to_string_lossy().to_lowercase())
-                .unwrap_or_default();
-            let mime = match Infer::new().get(&buf) {
-                Some(infer) => refine_mime_type(infer.mime_type(), &extension),
-                None => {
-                    return Err(EpubBuilderError::UnknownFileFormat {
-                        file_path: path.to_string_lossy().to_string(),
-                    }
-                    .into());
-                }
-            }
-            .to_string();
-
-            manifest.push(ManifestItem {
-                id: manifest_id.clone(),
-                path: to_container_path(&path),
-                mime,
-                properties: None,
-                fallback: None,
-            });
-
-            // Other resources (if any): generate stable ids and add to manifest
-            for res in resources {
-                let mut file = fs::File::open(&res)?;
-                let _ = file.read(&mut buf)?;
-                let extension = res
-                    .extension()
-                    .map(|e| e.to_string_lossy().to_lowercase())
-                    .unwrap_or_default();
-                let mime = match Infer::new().get(&buf) {
-                    Some(ft) => refine_mime_type(ft.mime_type(), &extension),
-                    None => {
-                        return Err(EpubBuilderError::UnknownFileFormat {
-                            file_path: path.to_string_lossy().to_string(),
-                        }
-                        .into());
-                    }
-                }
-                .to_string();
-
-                let file_name = res
-                    .file_name()
-                    .map(|s| s.to_string_lossy().to_string())
-                    .unwrap_or_default();
-                let res_id = format!("{}-{}", manifest_id, file_name);
-
-                manifest.push(ManifestItem {
-                    id: res_id,
-                    path: to_container_path(&res),
-                    mime,
-                    properties: None,
-                    fallback: None,
-                });
-            }
+            manifest.extend(items.into_iter().map(|item| ManifestItem {
+                path: document_dir.join(&item.path),
+                ..item
+            }));
         }
 
         Ok(manifest)
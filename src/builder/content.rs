@@ -8,14 +8,14 @@
 //! # fn main() -> Result<(), lib_epub::error::EpubError> {
 //! use lib_epub::{
 //!     builder::content::{Block, BlockBuilder, ContentBuilder},
-//!     types::{BlockType, Footnote},
+//!     types::{BlockType, Footnote, FootnoteAnchor},
 //! };
 //!
 //! let mut block_builder = BlockBuilder::new(BlockType::Title);
 //! block_builder
 //!     .set_content("This is a title")
 //!     .add_footnote(Footnote {
-//!         locate: 15,
+//!         locate: FootnoteAnchor::CharIndex(15),
 //!         content: "This is a footnote.".to_string(),
 //!     });
 //! let block = block_builder.try_into()?;
@@ -46,23 +46,74 @@ use std::{
     fs::{self, File},
     io::{Cursor, Read},
     path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
 
-use infer::{Infer, MatcherType};
+use infer::MatcherType;
 use log::warn;
 use quick_xml::{
-    Reader, Writer,
+    NsReader, Reader, Writer,
     events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    name::{Namespace, ResolveResult},
 };
+use unicode_segmentation::UnicodeSegmentation;
 use walkdir::WalkDir;
 
 use crate::{
-    builder::XmlWriter,
+    builder::{XmlWriter, infer_mime_type},
     error::{EpubBuilderError, EpubError},
-    types::{BlockType, Footnote, StyleOptions},
-    utils::local_time,
+    types::{
+        BlockType, EpubVersion, FontFace, Footnote, FootnoteAnchor, FootnoteMode, ManifestItem,
+        ResourceDirs, RubyRun, StyleOptions, XmlDecl,
+    },
+    utils::{XmlReader, minify_css, sanitize_file_name, unique_dir_name},
 };
 
+/// Returns the name of the element written for the target EPUB version
+///
+/// EPUB 2 content documents use XHTML 1.1, which does not know the HTML5 sectioning
+/// elements, so `div` is written in their place.
+fn element_name(name: &'static str, version: EpubVersion) -> &'static str {
+    match (version, name) {
+        (EpubVersion::Version2_0, "aside" | "figure" | "main") => "div",
+        _ => name,
+    }
+}
+
+/// Prefixes the space separated class names with the class prefix
+///
+/// For example, `content-block text-block` becomes `ch3-content-block ch3-text-block`
+/// with the prefix `ch3`. The class names are returned unchanged for an empty prefix.
+fn class_names(prefix: &str, classes: &str) -> String {
+    if prefix.is_empty() {
+        return classes.to_string();
+    }
+
+    classes
+        .split(' ')
+        .map(|class| format!("{}-{}", prefix, class))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quotes a value as a CSS string
+///
+/// Backslashes and double quotes are escaped, so that file names containing
+/// whitespace, quotes or parentheses can be used in `url()`.
+fn css_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        if ch == '"' || ch == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(ch);
+    }
+    quoted.push('"');
+    quoted
+}
+
 /// Content Block
 ///
 /// The content block is the basic unit of content in a content document.
@@ -114,6 +165,27 @@ pub enum Block {
         footnotes: Vec<Footnote>,
     },
 
+    /// Aside paragraph
+    ///
+    /// This block represents content that is tangentially related to the main text,
+    /// such as a sidebar. The block structure is as follows:
+    ///
+    /// ```xhtml
+    /// <aside class="content-block aside-block" epub:type="{{ aside.epub_type }}">
+    ///     <p>{{ aside.content }}</p>
+    /// </aside>
+    /// ```
+    #[non_exhaustive]
+    Aside {
+        content: String,
+
+        /// The structural semantics of the aside
+        ///
+        /// Defaults to `sidebar` when not set.
+        epub_type: Option<String>,
+        footnotes: Vec<Footnote>,
+    },
+
     /// Heading
     ///
     /// The block structure is as follows:
@@ -230,15 +302,15 @@ pub enum Block {
     /// ```
     ///
     /// ## Notes
-    /// - The MathML markup is inserted directly without validation. Users must ensure
-    ///   the MathML is well-formed.
+    /// - The MathML markup is inserted directly, only its root element is validated to be
+    ///   a `<math>` element in the MathML namespace. Users must ensure the MathML is well-formed.
     /// - The fallback image is displayed when the reading system doesn't support MathML.
     #[non_exhaustive]
     MathML {
         /// MathML element raw data
         ///
-        /// This field stores the raw data of the MathML markup, which we do not verify,
-        /// and the user needs to make sure it is correct.
+        /// This field stores the raw data of the MathML markup. Only its root element is
+        /// verified, and the user needs to make sure the rest is correct.
         element_str: String,
 
         /// Fallback image for the MathML block
@@ -252,24 +324,104 @@ pub enum Block {
 
         footnotes: Vec<Footnote>,
     },
+
+    /// Image gallery block
+    ///
+    /// The block structure is as follows:
+    /// ```xhtml
+    /// <figure class="content-block gallery-block">
+    ///     <!-- One img element is written for each image, in order. -->
+    ///     <img src="{{ image.url }}" alt="{{ image.alt }}" />
+    ///     <figcaption>
+    ///         {{ gallery.caption }}
+    ///     </figcaption>
+    /// </figure>
+    /// ```
+    ///
+    /// ## Notes
+    /// - The caption is shared by all images, so footnotes are attached to the caption.
+    #[non_exhaustive]
+    Gallery {
+        /// Image file paths, each with optional alternative text
+        images: Vec<(PathBuf, Option<String>)>,
+
+        /// Caption for the whole gallery
+        caption: Option<String>,
+
+        footnotes: Vec<Footnote>,
+    },
+
+    /// Ruby annotated paragraph
+    ///
+    /// This block represents a paragraph of text runs, where annotated runs are rendered
+    /// as ruby annotations. The block structure is as follows:
+    ///
+    /// ```xhtml
+    /// <p class="content-block ruby-block">
+    ///     <!-- Runs without an annotation are written as plain text. -->
+    ///     {{ run.base }}
+    ///     <ruby>{{ run.base }}<rt>{{ run.annotation }}</rt></ruby>
+    /// </p>
+    /// ```
+    ///
+    /// ## Notes
+    /// - Ruby blocks do not support footnotes.
+    #[non_exhaustive]
+    Ruby { runs: Vec<RubyRun> },
+
+    /// Raw XHTML fragment
+    ///
+    /// This block holds a trusted, well-formed XHTML fragment that is written into
+    /// the document verbatim, without any wrapping element:
+    ///
+    /// ```xhtml
+    /// {{ element_str }}
+    /// ```
+    ///
+    /// ## Notes
+    /// - Raw blocks do not support footnotes.
+    /// - The fragment is only checked for well-formedness, its content is not validated.
+    #[non_exhaustive]
+    Raw { element_str: String },
 }
 
 impl Block {
     /// Make the block
     ///
     /// Convert block data to xhtml markup.
+    ///
+    /// ## Parameters
+    /// - `writer`: The writer to write XML events
+    /// - `start_index`: The starting value of footnote number
+    /// - `version`: The EPUB version the markup is generated for
+    /// - `resource_hrefs`: The links to the media resources of the block, relative to the
+    ///   content document, as assigned by the builder when the resources were copied
+    /// - `footnote_mode`: The way footnote references are marked up
+    /// - `class_prefix`: The prefix of the generated class names, or an empty string
     pub(crate) fn make(
         &mut self,
         writer: &mut XmlWriter,
         start_index: usize,
+        version: EpubVersion,
+        resource_hrefs: &[String],
+        footnote_mode: FootnoteMode,
+        class_prefix: &str,
     ) -> Result<(), EpubError> {
         match self {
             Block::Text { content, footnotes } => {
-                writer.write_event(Event::Start(
-                    BytesStart::new("p").with_attributes([("class", "content-block text-block")]),
-                ))?;
+                writer.write_event(Event::Start(BytesStart::new("p").with_attributes([(
+                    "class",
+                    class_names(class_prefix, "content-block text-block").as_str(),
+                )])))?;
 
-                Self::make_text(writer, content, footnotes, start_index)?;
+                Self::make_text(
+                    writer,
+                    content,
+                    footnotes,
+                    start_index,
+                    footnote_mode,
+                    class_prefix,
+                )?;
 
                 writer.write_event(Event::End(BytesEnd::new("p")))?;
             }
@@ -277,32 +429,78 @@ impl Block {
             Block::Quote { content, footnotes } => {
                 writer.write_event(Event::Start(BytesStart::new("blockquote").with_attributes(
                     [
-                        ("class", "content-block quote-block"),
+                        (
+                            "class",
+                            class_names(class_prefix, "content-block quote-block").as_str(),
+                        ),
                         ("cite", "SOME ATTR NEED TO BE SET"),
                     ],
                 )))?;
                 writer.write_event(Event::Start(BytesStart::new("p")))?;
 
-                Self::make_text(writer, content, footnotes, start_index)?;
+                Self::make_text(
+                    writer,
+                    content,
+                    footnotes,
+                    start_index,
+                    footnote_mode,
+                    class_prefix,
+                )?;
 
                 writer.write_event(Event::End(BytesEnd::new("p")))?;
                 writer.write_event(Event::End(BytesEnd::new("blockquote")))?;
             }
 
+            Block::Aside { content, epub_type, footnotes } => {
+                let mut aside =
+                    BytesStart::new(element_name("aside", version)).with_attributes([(
+                        "class",
+                        class_names(class_prefix, "content-block aside-block").as_str(),
+                    )]);
+                if version == EpubVersion::Version3_0 {
+                    aside.push_attribute(("epub:type", epub_type.as_deref().unwrap_or("sidebar")));
+                }
+                writer.write_event(Event::Start(aside))?;
+                writer.write_event(Event::Start(BytesStart::new("p")))?;
+
+                Self::make_text(
+                    writer,
+                    content,
+                    footnotes,
+                    start_index,
+                    footnote_mode,
+                    class_prefix,
+                )?;
+
+                writer.write_event(Event::End(BytesEnd::new("p")))?;
+                writer.write_event(Event::End(BytesEnd::new(element_name("aside", version))))?;
+            }
+
             Block::Title { content, footnotes, level } => {
                 let tag_name = format!("h{}", level);
                 writer.write_event(Event::Start(
-                    BytesStart::new(tag_name.as_str())
-                        .with_attributes([("class", "content-block title-block")]),
+                    BytesStart::new(tag_name.as_str()).with_attributes([(
+                        "class",
+                        class_names(class_prefix, "content-block title-block").as_str(),
+                    )]),
                 ))?;
 
-                Self::make_text(writer, content, footnotes, start_index)?;
+                Self::make_text(
+                    writer,
+                    content,
+                    footnotes,
+                    start_index,
+                    footnote_mode,
+                    class_prefix,
+                )?;
 
                 writer.write_event(Event::End(BytesEnd::new(tag_name)))?;
             }
 
             Block::Image { url, alt, caption, footnotes } => {
-                let url = format!("./img/{}", url.file_name().unwrap().to_string_lossy());
+                let url = resource_hrefs
+                    .first()
+                    .ok_or_else(|| Self::missing_href_error(url))?;
 
                 let mut attr = Vec::new();
                 attr.push(("src", url.as_str()));
@@ -311,24 +509,32 @@ impl Block {
                 }
 
                 writer.write_event(Event::Start(
-                    BytesStart::new("figure")
-                        .with_attributes([("class", "content-block image-block")]),
+                    BytesStart::new(element_name("figure", version)).with_attributes([(
+                        "class",
+                        class_names(class_prefix, "content-block image-block").as_str(),
+                    )]),
                 ))?;
                 writer.write_event(Event::Empty(BytesStart::new("img").with_attributes(attr)))?;
 
                 if let Some(caption) = caption {
-                    writer.write_event(Event::Start(BytesStart::new("figcaption")))?;
-
-                    Self::make_text(writer, caption, footnotes, start_index)?;
-
-                    writer.write_event(Event::End(BytesEnd::new("figcaption")))?;
+                    Self::make_caption(
+                        writer,
+                        caption,
+                        footnotes,
+                        start_index,
+                        version,
+                        footnote_mode,
+                        class_prefix,
+                    )?;
                 }
 
-                writer.write_event(Event::End(BytesEnd::new("figure")))?;
+                writer.write_event(Event::End(BytesEnd::new(element_name("figure", version))))?;
             }
 
             Block::Audio { url, fallback, caption, footnotes } => {
-                let url = format!("./audio/{}", url.file_name().unwrap().to_string_lossy());
+                let url = resource_hrefs
+                    .first()
+                    .ok_or_else(|| Self::missing_href_error(url))?;
 
                 let attr = vec![
                     ("src", url.as_str()),
@@ -336,8 +542,10 @@ impl Block {
                 ];
 
                 writer.write_event(Event::Start(
-                    BytesStart::new("figure")
-                        .with_attributes([("class", "content-block audio-block")]),
+                    BytesStart::new(element_name("figure", version)).with_attributes([(
+                        "class",
+                        class_names(class_prefix, "content-block audio-block").as_str(),
+                    )]),
                 ))?;
                 writer.write_event(Event::Start(BytesStart::new("audio").with_attributes(attr)))?;
 
@@ -348,18 +556,24 @@ impl Block {
                 writer.write_event(Event::End(BytesEnd::new("audio")))?;
 
                 if let Some(caption) = caption {
-                    writer.write_event(Event::Start(BytesStart::new("figcaption")))?;
-
-                    Self::make_text(writer, caption, footnotes, start_index)?;
-
-                    writer.write_event(Event::End(BytesEnd::new("figcaption")))?;
+                    Self::make_caption(
+                        writer,
+                        caption,
+                        footnotes,
+                        start_index,
+                        version,
+                        footnote_mode,
+                        class_prefix,
+                    )?;
                 }
 
-                writer.write_event(Event::End(BytesEnd::new("figure")))?;
+                writer.write_event(Event::End(BytesEnd::new(element_name("figure", version))))?;
             }
 
             Block::Video { url, fallback, caption, footnotes } => {
-                let url = format!("./video/{}", url.file_name().unwrap().to_string_lossy());
+                let url = resource_hrefs
+                    .first()
+                    .ok_or_else(|| Self::missing_href_error(url))?;
 
                 let attr = vec![
                     ("src", url.as_str()),
@@ -367,8 +581,10 @@ impl Block {
                 ];
 
                 writer.write_event(Event::Start(
-                    BytesStart::new("figure")
-                        .with_attributes([("class", "content-block video-block")]),
+                    BytesStart::new(element_name("figure", version)).with_attributes([(
+                        "class",
+                        class_names(class_prefix, "content-block video-block").as_str(),
+                    )]),
                 ))?;
                 writer.write_event(Event::Start(BytesStart::new("video").with_attributes(attr)))?;
 
@@ -379,14 +595,18 @@ impl Block {
                 writer.write_event(Event::End(BytesEnd::new("video")))?;
 
                 if let Some(caption) = caption {
-                    writer.write_event(Event::Start(BytesStart::new("figcaption")))?;
-
-                    Self::make_text(writer, caption, footnotes, start_index)?;
-
-                    writer.write_event(Event::End(BytesEnd::new("figcaption")))?;
+                    Self::make_caption(
+                        writer,
+                        caption,
+                        footnotes,
+                        start_index,
+                        version,
+                        footnote_mode,
+                        class_prefix,
+                    )?;
                 }
 
-                writer.write_event(Event::End(BytesEnd::new("figure")))?;
+                writer.write_event(Event::End(BytesEnd::new(element_name("figure", version))))?;
             }
 
             Block::MathML {
@@ -396,34 +616,110 @@ impl Block {
                 footnotes,
             } => {
                 writer.write_event(Event::Start(
-                    BytesStart::new("figure")
-                        .with_attributes([("class", "content-block mathml-block")]),
+                    BytesStart::new(element_name("figure", version)).with_attributes([(
+                        "class",
+                        class_names(class_prefix, "content-block mathml-block").as_str(),
+                    )]),
                 ))?;
 
-                Self::write_mathml_element(writer, element_str)?;
+                Self::write_markup(writer, element_str, |error| {
+                    EpubBuilderError::InvalidMathMLFormat { error }
+                })?;
 
                 if let Some(fallback_path) = fallback_image {
-                    let img_url = format!(
-                        "./img/{}",
-                        fallback_path.file_name().unwrap().to_string_lossy()
-                    );
+                    let img_url = resource_hrefs
+                        .first()
+                        .ok_or_else(|| Self::missing_href_error(fallback_path))?;
 
                     writer.write_event(Event::Empty(BytesStart::new("img").with_attributes([
                         ("src", img_url.as_str()),
-                        ("class", "mathml-fallback"),
+                        (
+                            "class",
+                            class_names(class_prefix, "mathml-fallback").as_str(),
+                        ),
                         ("alt", "Mathematical formula"),
                     ])))?;
                 }
 
                 if let Some(caption) = caption {
-                    writer.write_event(Event::Start(BytesStart::new("figcaption")))?;
+                    Self::make_caption(
+                        writer,
+                        caption,
+                        footnotes,
+                        start_index,
+                        version,
+                        footnote_mode,
+                        class_prefix,
+                    )?;
+                }
+
+                writer.write_event(Event::End(BytesEnd::new(element_name("figure", version))))?;
+            }
+
+            Block::Gallery { images, caption, footnotes } => {
+                writer.write_event(Event::Start(
+                    BytesStart::new(element_name("figure", version)).with_attributes([(
+                        "class",
+                        class_names(class_prefix, "content-block gallery-block").as_str(),
+                    )]),
+                ))?;
+
+                for (index, (path, alt)) in images.iter().enumerate() {
+                    let url = resource_hrefs
+                        .get(index)
+                        .ok_or_else(|| Self::missing_href_error(path))?;
+
+                    let mut attr = vec![("src", url.as_str())];
+                    if let Some(alt) = alt {
+                        attr.push(("alt", alt.as_str()));
+                    }
+                    writer
+                        .write_event(Event::Empty(BytesStart::new("img").with_attributes(attr)))?;
+                }
+
+                if let Some(caption) = caption {
+                    Self::make_caption(
+                        writer,
+                        caption,
+                        footnotes,
+                        start_index,
+                        version,
+                        footnote_mode,
+                        class_prefix,
+                    )?;
+                }
+
+                writer.write_event(Event::End(BytesEnd::new(element_name("figure", version))))?;
+            }
 
-                    Self::make_text(writer, caption, footnotes, start_index)?;
+            Block::Ruby { runs } => {
+                writer.write_event(Event::Start(BytesStart::new("p").with_attributes([(
+                    "class",
+                    class_names(class_prefix, "content-block ruby-block").as_str(),
+                )])))?;
+
+                for run in runs.iter() {
+                    match &run.annotation {
+                        Some(annotation) => {
+                            writer.write_event(Event::Start(BytesStart::new("ruby")))?;
+                            writer.write_event(Event::Text(BytesText::new(&run.base)))?;
+                            writer.write_event(Event::Start(BytesStart::new("rt")))?;
+                            writer.write_event(Event::Text(BytesText::new(annotation)))?;
+                            writer.write_event(Event::End(BytesEnd::new("rt")))?;
+                            writer.write_event(Event::End(BytesEnd::new("ruby")))?;
+                        }
 
-                    writer.write_event(Event::End(BytesEnd::new("figcaption")))?;
+                        None => writer.write_event(Event::Text(BytesText::new(&run.base)))?,
+                    }
                 }
 
-                writer.write_event(Event::End(BytesEnd::new("figure")))?;
+                writer.write_event(Event::End(BytesEnd::new("p")))?;
+            }
+
+            Block::Raw { element_str } => {
+                Self::write_markup(writer, element_str, |error| {
+                    EpubBuilderError::InvalidHtmlFragment { error }
+                })?;
             }
         }
 
@@ -434,11 +730,14 @@ impl Block {
         match self {
             Block::Text { footnotes, .. }
             | Block::Quote { footnotes, .. }
+            | Block::Aside { footnotes, .. }
             | Block::Title { footnotes, .. }
             | Block::Image { footnotes, .. }
             | Block::Audio { footnotes, .. }
             | Block::Video { footnotes, .. }
-            | Block::MathML { footnotes, .. } => footnotes.to_vec(),
+            | Block::MathML { footnotes, .. }
+            | Block::Gallery { footnotes, .. } => footnotes.to_vec(),
+            Block::Ruby { .. } | Block::Raw { .. } => vec![],
         }
     }
 
@@ -484,6 +783,42 @@ impl Block {
         result
     }
 
+    /// Make caption
+    ///
+    /// Writes the caption of a figure. EPUB 2 content documents use XHTML 1.1, which
+    /// has no `figcaption` element, so a `<p class="caption">` element is written instead.
+    fn make_caption(
+        writer: &mut XmlWriter,
+        caption: &str,
+        footnotes: &mut [Footnote],
+        start_index: usize,
+        version: EpubVersion,
+        footnote_mode: FootnoteMode,
+        class_prefix: &str,
+    ) -> Result<(), EpubError> {
+        let (start, end) = match version {
+            EpubVersion::Version2_0 => (
+                BytesStart::new("p")
+                    .with_attributes([("class", class_names(class_prefix, "caption").as_str())]),
+                BytesEnd::new("p"),
+            ),
+            EpubVersion::Version3_0 => (BytesStart::new("figcaption"), BytesEnd::new("figcaption")),
+        };
+
+        writer.write_event(Event::Start(start))?;
+        Self::make_text(
+            writer,
+            caption,
+            footnotes,
+            start_index,
+            footnote_mode,
+            class_prefix,
+        )?;
+        writer.write_event(Event::End(end))?;
+
+        Ok(())
+    }
+
     /// Make text
     ///
     /// This function is used to format text content and footnote markup.
@@ -493,23 +828,30 @@ impl Block {
     /// - `content`: The text content to format
     /// - `footnotes`: The footnotes to format
     /// - `start_index`: The starting value of footnote number
+    /// - `footnote_mode`: The way footnote references are marked up
+    /// - `class_prefix`: The prefix of the generated class names, or an empty string
     fn make_text(
         writer: &mut XmlWriter,
         content: &str,
         footnotes: &mut [Footnote],
         start_index: usize,
+        footnote_mode: FootnoteMode,
+        class_prefix: &str,
     ) -> Result<(), EpubError> {
         if footnotes.is_empty() {
             writer.write_event(Event::Text(BytesText::new(content)))?;
             return Ok(());
         }
 
-        footnotes.sort_unstable();
+        footnotes.sort_by_cached_key(|footnote| {
+            Self::resolve_footnote_anchor(content, footnote.locate).ok()
+        });
 
         // statistical footnote locate and quantity
         let mut position_to_count = HashMap::new();
         for footnote in footnotes.iter() {
-            *position_to_count.entry(footnote.locate).or_insert(0usize) += 1;
+            let position = Self::resolve_footnote_anchor(content, footnote.locate)?;
+            *position_to_count.entry(position).or_insert(0usize) += 1;
         }
 
         let mut positions = position_to_count.keys().copied().collect::<Vec<usize>>();
@@ -525,7 +867,7 @@ impl Block {
                 // get the quantity of the index-th footnote
                 if let Some(&count) = position_to_count.get(&position) {
                     for _ in 0..count {
-                        Self::make_footnotes(writer, current_index)?;
+                        Self::make_footnotes(writer, current_index, footnote_mode, class_prefix)?;
                         current_index += 1;
                     }
                 }
@@ -535,24 +877,144 @@ impl Block {
         Ok(())
     }
 
+    /// Resolves a footnote anchor to a character position
+    ///
+    /// ## Parameters
+    /// - `content`: The text the anchor refers to
+    /// - `anchor`: The footnote anchor to resolve
+    ///
+    /// ## Return
+    /// - `Ok(usize)`: The number of characters before the footnote reference
+    /// - `Err(EpubError)`: The anchor is out of range, or a byte offset is not on a character boundary
+    ///
+    /// ## Notes
+    /// - Every anchor is moved to the end of the grapheme cluster it points into, so the
+    ///   footnote reference never breaks up combining marks or emoji sequences.
+    fn resolve_footnote_anchor(content: &str, anchor: FootnoteAnchor) -> Result<usize, EpubError> {
+        let out_of_range = |max_locate: usize| -> EpubError {
+            EpubBuilderError::InvalidFootnoteLocate {
+                max_locate,
+                unit: anchor.unit().to_string(),
+            }
+            .into()
+        };
+
+        let position = match anchor {
+            FootnoteAnchor::GraphemeIndex(index) => {
+                let max_locate = content.graphemes(true).count();
+                if index == 0 || index > max_locate {
+                    return Err(out_of_range(max_locate));
+                }
+
+                content
+                    .graphemes(true)
+                    .take(index)
+                    .map(|grapheme| grapheme.chars().count())
+                    .sum()
+            }
+
+            FootnoteAnchor::CharIndex(index) => {
+                let max_locate = content.chars().count();
+                if index == 0 || index > max_locate {
+                    return Err(out_of_range(max_locate));
+                }
+
+                index
+            }
+
+            FootnoteAnchor::ByteOffset(offset) => {
+                if offset == 0 || offset > content.len() {
+                    return Err(out_of_range(content.len()));
+                }
+
+                if !content.is_char_boundary(offset) {
+                    return Err(EpubBuilderError::InvalidFootnoteByteOffset { offset }.into());
+                }
+
+                content[..offset].chars().count()
+            }
+
+            FootnoteAnchor::WordIndex(index) => {
+                // the character position right after the last character of each word
+                let mut word_ends = Vec::new();
+                let mut in_word = false;
+                for (position, ch) in content.chars().enumerate() {
+                    if ch.is_whitespace() {
+                        if in_word {
+                            word_ends.push(position);
+                        }
+                        in_word = false;
+                    } else {
+                        in_word = true;
+                    }
+                }
+                if in_word {
+                    word_ends.push(content.chars().count());
+                }
+
+                if index == 0 || index > word_ends.len() {
+                    return Err(out_of_range(word_ends.len()));
+                }
+
+                word_ends[index - 1]
+            }
+        };
+
+        Ok(Self::grapheme_boundary(content, position))
+    }
+
+    /// Returns the first grapheme cluster boundary at or after a character position
+    fn grapheme_boundary(content: &str, position: usize) -> usize {
+        let mut boundary = 0;
+        for grapheme in content.graphemes(true) {
+            if boundary >= position {
+                break;
+            }
+            boundary += grapheme.chars().count();
+        }
+        boundary
+    }
+
     /// Makes footnote reference markup
     #[inline]
-    fn make_footnotes(writer: &mut XmlWriter, index: usize) -> Result<(), EpubError> {
-        writer.write_event(Event::Start(BytesStart::new("a").with_attributes([
+    fn make_footnotes(
+        writer: &mut XmlWriter,
+        index: usize,
+        footnote_mode: FootnoteMode,
+        class_prefix: &str,
+    ) -> Result<(), EpubError> {
+        let mut reference = BytesStart::new("a").with_attributes([
             ("href", format!("#footnote-{}", index).as_str()),
             ("id", format!("ref-{}", index).as_str()),
-            ("class", "footnote-ref"),
-        ])))?;
+            ("class", class_names(class_prefix, "footnote-ref").as_str()),
+        ]);
+        if footnote_mode == FootnoteMode::Popup {
+            reference.push_attribute(("epub:type", "noteref"));
+        }
+        writer.write_event(Event::Start(reference))?;
         writer.write_event(Event::Text(BytesText::new(&format!("[{}]", index))))?;
         writer.write_event(Event::End(BytesEnd::new("a")))?;
 
         Ok(())
     }
 
-    /// Write MathML element
+    /// Creates the error reported when a media resource has not been copied by the builder
+    fn missing_href_error(path: &Path) -> EpubError {
+        EpubBuilderError::TargetIsNotFile {
+            target_path: path.to_string_lossy().to_string(),
+        }
+        .into()
+    }
+
+    /// Write markup string
     ///
-    /// This function will parse the MathML element string and write it to the writer.
-    fn write_mathml_element(writer: &mut XmlWriter, element_str: &str) -> Result<(), EpubError> {
+    /// This function will parse the markup string and write its events to the writer.
+    /// Parsing errors are converted into builder errors by `invalid`.
+    fn write_markup(
+        writer: &mut XmlWriter,
+        element_str: &str,
+        invalid: fn(String) -> EpubBuilderError,
+    ) -> Result<(), EpubError> {
         let mut reader = Reader::from_str(element_str);
 
         loop {
@@ -561,6 +1023,55 @@ impl Block {
 
                 Ok(event) => writer.write_event(event)?,
 
+                Err(err) => return Err(invalid(err.to_string()).into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the root element of a MathML element string
+    ///
+    /// Ensures the first element of the string is a `<math>` element bound to the
+    /// MathML namespace, either by a default namespace or by a prefix. The content
+    /// of the root element is not validated.
+    fn validate_mathml_root(element_str: &str) -> Result<(), EpubError> {
+        const MATHML_NAMESPACE: &[u8] = b"http://www.w3.org/1998/Math/MathML";
+
+        let mut reader = NsReader::from_str(element_str);
+        loop {
+            match reader.read_resolved_event() {
+                Ok((namespace, Event::Start(element) | Event::Empty(element))) => {
+                    let local_name = element.local_name();
+                    let is_mathml = matches!(
+                        namespace,
+                        ResolveResult::Bound(Namespace(namespace)) if namespace == MATHML_NAMESPACE
+                    );
+
+                    if local_name.as_ref() != b"math" || !is_mathml {
+                        return Err(EpubBuilderError::InvalidMathMLFormat {
+                            error: format!(
+                                "The root element must be a 'math' element in the '{}' namespace, but found '{}'.",
+                                String::from_utf8_lossy(MATHML_NAMESPACE),
+                                String::from_utf8_lossy(element.name().as_ref()),
+                            ),
+                        }
+                        .into());
+                    }
+
+                    return Ok(());
+                }
+
+                Ok((_, Event::Eof)) => {
+                    return Err(EpubBuilderError::InvalidMathMLFormat {
+                        error: "The MathML element string does not contain any element."
+                            .to_string(),
+                    }
+                    .into());
+                }
+
+                Ok(_) => continue,
+
                 Err(err) => {
                     return Err(
                         EpubBuilderError::InvalidMathMLFormat { error: err.to_string() }.into(),
@@ -568,26 +1079,22 @@ impl Block {
                 }
             }
         }
-
-        Ok(())
     }
 
     /// Validates the footnotes in a block
     ///
     /// Ensures all footnotes reference valid positions within the content.
-    /// For Text, Quote, and Title blocks, footnotes must be within the character count of the content.
-    /// For Image, Audio, Video, and MathML blocks, footnotes must be within the character count
-    /// of the caption (if a caption is set). Blocks with media but no caption cannot have footnotes.
+    /// For Text, Quote, and Title blocks, footnote anchors must resolve to a position within the content.
+    /// For Image, Audio, Video, MathML, and Gallery blocks, footnote anchors must resolve to a position within
+    /// the caption (if a caption is set). Blocks with media but no caption cannot have footnotes.
     fn validate_footnotes(&self) -> Result<(), EpubError> {
         match self {
             Block::Text { content, footnotes }
             | Block::Quote { content, footnotes }
+            | Block::Aside { content, footnotes, .. }
             | Block::Title { content, footnotes, .. } => {
-                let max_locate = content.chars().count();
                 for footnote in footnotes.iter() {
-                    if footnote.locate == 0 || footnote.locate > max_locate {
-                        return Err(EpubBuilderError::InvalidFootnoteLocate { max_locate }.into());
-                    }
+                    Self::resolve_footnote_anchor(content, footnote.locate)?;
                 }
 
                 Ok(())
@@ -596,22 +1103,24 @@ impl Block {
             Block::Image { caption, footnotes, .. }
             | Block::MathML { caption, footnotes, .. }
             | Block::Video { caption, footnotes, .. }
-            | Block::Audio { caption, footnotes, .. } => {
+            | Block::Audio { caption, footnotes, .. }
+            | Block::Gallery { caption, footnotes, .. } => {
                 if let Some(caption) = caption {
-                    let max_locate = caption.chars().count();
                     for footnote in footnotes.iter() {
-                        if footnote.locate == 0 || footnote.locate > caption.chars().count() {
-                            return Err(
-                                EpubBuilderError::InvalidFootnoteLocate { max_locate }.into()
-                            );
-                        }
+                        Self::resolve_footnote_anchor(caption, footnote.locate)?;
                     }
-                } else if !footnotes.is_empty() {
-                    return Err(EpubBuilderError::InvalidFootnoteLocate { max_locate: 0 }.into());
+                } else if let Some(footnote) = footnotes.first() {
+                    return Err(EpubBuilderError::InvalidFootnoteLocate {
+                        max_locate: 0,
+                        unit: footnote.locate.unit().to_string(),
+                    }
+                    .into());
                 }
 
                 Ok(())
             }
+
+            Block::Ruby { .. } | Block::Raw { .. } => Ok(()),
         }
     }
 
@@ -643,6 +1152,17 @@ impl TryFrom<BlockBuilder> for Block {
                 Block::Quote { content, footnotes: builder.footnotes }
             }
 
+            BlockType::Aside => {
+                let content = builder
+                    .content
+                    .ok_or_else(|| Self::missing_error(builder.block_type, "content"))?;
+                Block::Aside {
+                    content,
+                    epub_type: builder.epub_type,
+                    footnotes: builder.footnotes,
+                }
+            }
+
             BlockType::Title => {
                 let content = builder
                     .content
@@ -707,6 +1227,7 @@ impl TryFrom<BlockBuilder> for Block {
                 let element_str = builder
                     .element_str
                     .ok_or_else(|| Self::missing_error(builder.block_type, "element_str"))?;
+                Self::validate_mathml_root(&element_str)?;
 
                 Block::MathML {
                     element_str,
@@ -715,6 +1236,21 @@ impl TryFrom<BlockBuilder> for Block {
                     footnotes: builder.footnotes,
                 }
             }
+
+            BlockType::Ruby => {
+                if builder.ruby_runs.is_empty() {
+                    return Err(Self::missing_error(builder.block_type, "ruby_runs"));
+                }
+                if let Some(footnote) = builder.footnotes.first() {
+                    return Err(EpubBuilderError::InvalidFootnoteLocate {
+                        max_locate: 0,
+                        unit: footnote.locate.unit().to_string(),
+                    }
+                    .into());
+                }
+
+                Block::Ruby { runs: builder.ruby_runs }
+            }
         };
 
         block.validate_footnotes()?;
@@ -732,13 +1268,13 @@ impl TryFrom<BlockBuilder> for Block {
 /// # fn main() -> Result<(), lib_epub::error::EpubError> {
 /// use lib_epub::{
 ///     builder::content::{Block, BlockBuilder},
-///     types::{BlockType, Footnote}
+///     types::{BlockType, Footnote, FootnoteAnchor}
 /// };
 ///
 /// let mut builder = BlockBuilder::new(BlockType::Text);
 /// builder.set_content("Hello, world!").add_footnote(Footnote {
 ///     content: "This is a footnote.".to_string(),
-///     locate: 13,
+///     locate: FootnoteAnchor::CharIndex(13),
 /// });
 ///
 /// let block: Block = builder.try_into()?;
@@ -777,6 +1313,12 @@ pub struct BlockBuilder {
     /// Fallback image path for MathML blocks (displayed when MathML cannot be rendered)
     fallback_image: Option<PathBuf>,
 
+    /// Structural semantics for Aside blocks, written as the `epub:type` attribute
+    epub_type: Option<String>,
+
+    /// Text runs for Ruby blocks
+    ruby_runs: Vec<RubyRun>,
+
     /// Footnotes associated with the block content
     footnotes: Vec<Footnote>,
 }
@@ -799,13 +1341,15 @@ impl BlockBuilder {
             fallback: None,
             element_str: None,
             fallback_image: None,
+            epub_type: None,
+            ruby_runs: vec![],
             footnotes: vec![],
         }
     }
 
     /// Sets the text content of the block
     ///
-    /// Used for Text, Quote, and Title block types.
+    /// Used for Text, Quote, Aside, and Title block types.
     ///
     /// ## Parameters
     /// - `content`: The text content to set
@@ -879,6 +1423,33 @@ impl BlockBuilder {
         self
     }
 
+    /// Sets the structural semantics of an Aside block
+    ///
+    /// Only applicable to Aside block types. The value is written as the
+    /// `epub:type` attribute of the `<aside>` element, such as `sidebar` or `pullquote`.
+    ///
+    /// ## Parameters
+    /// - `epub_type`: The structural semantics of the aside
+    pub fn set_epub_type(&mut self, epub_type: &str) -> &mut Self {
+        self.epub_type = Some(epub_type.to_string());
+        self
+    }
+
+    /// Adds a text run to a Ruby block
+    ///
+    /// Only applicable to Ruby block types. Runs are written in the order they are added.
+    ///
+    /// ## Parameters
+    /// - `base`: The base text of the run
+    /// - `annotation`: The ruby annotation of the base text, or `None` for plain text
+    pub fn add_ruby_run(&mut self, base: &str, annotation: Option<&str>) -> &mut Self {
+        self.ruby_runs.push(RubyRun {
+            base: base.to_string(),
+            annotation: annotation.map(str::to_string),
+        });
+        self
+    }
+
     /// Sets the fallback text for audio or video content
     ///
     /// Used for Audio and Video block types.
@@ -894,8 +1465,10 @@ impl BlockBuilder {
     /// Sets the raw MathML element string
     ///
     /// Only applicable to MathML block types.
-    /// This method accepts the raw MathML markup data without validation.
-    /// The user is responsible for ensuring the MathML is well-formed.
+    /// This method accepts the raw MathML markup data without validation. When the
+    /// block is built, only the root element is checked to be a `<math>` element in
+    /// the MathML namespace; the user is responsible for ensuring the rest of the
+    /// MathML is well-formed.
     ///
     /// ## Parameters
     /// - `element_str`: The raw MathML markup string
@@ -979,8 +1552,14 @@ impl BlockBuilder {
         let read_size = file.read(&mut buf)?;
         let header_bytes = &buf[..read_size];
 
-        match Infer::new().get(header_bytes) {
-            Some(file_type) if !types.contains(&file_type.matcher_type()) => {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let matcher_type = infer_mime_type(header_bytes, &extension).map(Self::matcher_type_of);
+
+        match matcher_type {
+            Some(matcher_type) if !types.contains(&matcher_type) => {
                 Err(EpubBuilderError::NotExpectedFileFormat.into())
             }
 
@@ -992,6 +1571,17 @@ impl BlockBuilder {
             _ => Ok(()),
         }
     }
+
+    /// Returns the matcher type of the MIME type
+    fn matcher_type_of(mime: &str) -> MatcherType {
+        match mime.split('/').next() {
+            Some("image") => MatcherType::Image,
+            Some("audio") => MatcherType::Audio,
+            Some("video") => MatcherType::Video,
+            Some("font") => MatcherType::Font,
+            _ => MatcherType::Text,
+        }
+    }
 }
 
 /// Content Builder
@@ -1014,14 +1604,36 @@ pub struct ContentBuilder {
     pub(crate) blocks: Vec<Block>,
     pub(crate) language: String,
     pub(crate) title: String,
+    pub(crate) chapter_number: Option<usize>,
+    pub(crate) chapter_label_format: String,
+    pub(crate) label_title_block: bool,
     pub(crate) styles: StyleOptions,
 
     pub(crate) temp_dir: PathBuf,
+    pub(crate) temp_dir_removed: bool,
     pub(crate) css_files: Vec<PathBuf>,
+    pub(crate) font_faces: Vec<FontFace>,
+    pub(crate) inline_style: Option<bool>,
+    pub(crate) minify_css: bool,
+    pub(crate) footnote_heading: Option<String>,
+    pub(crate) footnote_mode: FootnoteMode,
+    pub(crate) class_prefix: String,
+    pub(crate) drop_cap: bool,
+    pub(crate) drop_cap_size: f32,
+    pub(crate) drop_cap_float: bool,
+    pub(crate) body_type: Option<String>,
+    pub(crate) xml_declaration: Option<XmlDecl>,
+    pub(crate) target_version: EpubVersion,
+    pub(crate) resource_dirs: ResourceDirs,
+    pub(crate) sanitize_resource_names: bool,
+
+    /// The file names of the copied resources, keyed by resource type and source path
+    pub(crate) resource_names: HashMap<(&'static str, PathBuf), String>,
 }
 
 impl ContentBuilder {
-    // TODO: Handle resource naming conflicts
+    /// The number of times the temporary directory removal is attempted on drop
+    const CLEANUP_ATTEMPTS: u64 = 3;
 
     /// Creates a new ContentBuilder instance
     ///
@@ -1029,9 +1641,32 @@ impl ContentBuilder {
     /// A temporary directory is automatically created to store media files during construction.
     ///
     /// ## Parameters
+    /// - `id`: The unique identifier for the content document
     /// - `language`: The language code for the document
     pub fn new(id: &str, language: &str) -> Result<Self, EpubError> {
-        let temp_dir = env::temp_dir().join(local_time());
+        Self::with_temp_dir(id, language, env::temp_dir())
+    }
+
+    /// Creates a new ContentBuilder instance with a custom temporary directory
+    ///
+    /// Works like [`new`](Self::new), but the temporary directory storing media files
+    /// during construction is created inside `dir` instead of the system temporary
+    /// directory.
+    ///
+    /// ## Parameters
+    /// - `id`: The unique identifier for the content document
+    /// - `language`: The language code for the document
+    /// - `dir`: The directory to create the temporary directory in, which must exist
+    ///
+    /// ## Notes
+    /// - Only the created temporary directory is removed when the builder is dropped,
+    ///   `dir` itself is left untouched.
+    pub fn with_temp_dir<P: AsRef<Path>>(
+        id: &str,
+        language: &str,
+        dir: P,
+    ) -> Result<Self, EpubError> {
+        let temp_dir = dir.as_ref().join(unique_dir_name());
         fs::create_dir(&temp_dir)?;
 
         Ok(Self {
@@ -1039,9 +1674,28 @@ impl ContentBuilder {
             blocks: vec![],
             language: language.to_string(),
             title: String::new(),
+            chapter_number: None,
+            chapter_label_format: "Chapter {n}: {title}".to_string(),
+            label_title_block: false,
             styles: StyleOptions::default(),
             temp_dir,
+            temp_dir_removed: false,
             css_files: vec![],
+            font_faces: vec![],
+            inline_style: None,
+            minify_css: false,
+            footnote_heading: None,
+            footnote_mode: FootnoteMode::List,
+            class_prefix: String::new(),
+            drop_cap: false,
+            drop_cap_size: 3.0,
+            drop_cap_float: true,
+            body_type: None,
+            xml_declaration: Some(XmlDecl::default()),
+            target_version: EpubVersion::Version3_0,
+            resource_dirs: ResourceDirs::default(),
+            sanitize_resource_names: false,
+            resource_names: HashMap::new(),
         })
     }
 
@@ -1051,35 +1705,291 @@ impl ContentBuilder {
         self
     }
 
-    /// Sets the styles for the document
-    pub fn set_styles(&mut self, styles: StyleOptions) -> &mut Self {
-        self.styles = styles;
+    /// Sets the chapter number of the document
+    ///
+    /// When a chapter number is set, the document title is written as a chapter label
+    /// formatted by [`set_chapter_label_format`](Self::set_chapter_label_format), such as
+    /// `Chapter 3: Title`. By default, the document is not numbered.
+    ///
+    /// ## Parameters
+    /// - `number`: The chapter number
+    pub fn set_chapter_number(&mut self, number: usize) -> &mut Self {
+        self.chapter_number = Some(number);
         self
     }
 
-    /// Adds a CSS file to the document
+    /// Sets the format of the chapter label
     ///
-    /// Copies the CSS file to a temporary directory for inclusion in the EPUB package.
-    /// The CSS file will be linked in the document's head section when generating the output.
+    /// In the format, `{n}` is replaced by the chapter number and `{title}` by the title.
+    /// The default format is `Chapter {n}: {title}`.
     ///
     /// ## Parameters
-    /// - `css_path`: The path to the CSS file to add
+    /// - `format`: The format of the chapter label
     ///
-    /// ## Return
-    /// - `Ok(&mut self)`: If the file exists and is accessible
-    /// - `Err(EpubError)`: If the file does not exist or is not accessible
-    pub fn add_css_file(&mut self, css_path: PathBuf) -> Result<&mut Self, EpubError> {
-        if !css_path.is_file() {
-            return Err(EpubBuilderError::TargetIsNotFile {
-                target_path: css_path.to_string_lossy().to_string(),
-            }
-            .into());
-        }
+    /// ## Notes
+    /// - If the format has no `{title}` placeholder, the label is followed by a space
+    ///   and the title.
+    /// - The format only takes effect when a chapter number is set.
+    pub fn set_chapter_label_format(&mut self, format: &str) -> &mut Self {
+        self.chapter_label_format = format.to_string();
+        self
+    }
+
+    /// Sets whether the first title block is labeled with the chapter number
+    ///
+    /// By default, only the document title is labeled. When enabled, the first title
+    /// block of the document is written as a chapter label as well, and its footnotes
+    /// are moved along with the text.
+    ///
+    /// ## Parameters
+    /// - `enabled`: Whether to label the first title block
+    pub fn set_label_title_block(&mut self, enabled: bool) -> &mut Self {
+        self.label_title_block = enabled;
+        self
+    }
+
+    /// Sets the styles for the document
+    pub fn set_styles(&mut self, styles: StyleOptions) -> &mut Self {
+        self.styles = styles;
+        self
+    }
+
+    /// Sets whether the generated stylesheet is written to the document
+    ///
+    /// By default, the generated `<style>` element is only written when no CSS
+    /// file has been added. Calling this function decides it explicitly, so the
+    /// generated styles can be combined with, or dropped in favor of, external
+    /// stylesheets.
+    ///
+    /// ## Parameters
+    /// - `enabled`: Whether to write the generated `<style>` element
+    ///
+    /// ## Notes
+    /// - When both are written, the `<style>` element precedes the stylesheet links,
+    ///   so that rules in the CSS files take precedence.
+    pub fn set_inline_style(&mut self, enabled: bool) -> &mut Self {
+        self.inline_style = Some(enabled);
+        self
+    }
+
+    /// Sets whether the generated stylesheet is minified
+    ///
+    /// When enabled, comments and redundant whitespace are removed from the generated
+    /// `<style>` element, which reduces the size of every content document carrying it.
+    /// By default, the stylesheet is written in a readable form.
+    ///
+    /// ## Parameters
+    /// - `minify`: Whether to minify the generated stylesheet
+    pub fn set_minify_css(&mut self, minify: bool) -> &mut Self {
+        self.minify_css = minify;
+        self
+    }
+
+    /// Sets the heading displayed above the footnotes section
+    ///
+    /// When a heading is set, an `<h2 class="footnote-heading">` element with the
+    /// given text is written at the top of the footnotes section. By default no
+    /// heading is written.
+    ///
+    /// ## Parameters
+    /// - `heading`: The heading text, or `None` to omit the heading
+    pub fn set_footnote_heading(&mut self, heading: Option<&str>) -> &mut Self {
+        self.footnote_heading = heading.map(str::to_string);
+        self
+    }
+
+    /// Sets how the footnotes of the document are rendered
+    ///
+    /// By default, footnotes are collected in a list at the end of the document. In
+    /// [`FootnoteMode::Popup`] mode, every footnote is written as its own
+    /// `<aside epub:type="footnote">` element and the references are marked with
+    /// `epub:type="noteref"`, so that reading systems can show the notes as popups.
+    ///
+    /// ## Parameters
+    /// - `mode`: The footnote rendering mode
+    ///
+    /// ## Notes
+    /// - `epub:type` is not available in EPUB 2 content documents, so documents made
+    ///   for EPUB 2 always use the list mode.
+    pub fn set_footnote_mode(&mut self, mode: FootnoteMode) -> &mut Self {
+        self.footnote_mode = mode;
+        self
+    }
+
+    /// Sets the prefix of the class names generated for the document
+    ///
+    /// By default, every document uses the same class names, such as `content-block`
+    /// and `footnote-item`, so that styles written for one document also apply to the
+    /// others. When a prefix is set, it is joined to every generated class name with
+    /// a `-`, e.g. `ch3-content-block`, in both the markup and the generated stylesheet.
+    /// The body is given the class `ch3-root`, and the rules of the generated stylesheet
+    /// that target elements, such as `p` or `blockquote`, are scoped under it.
+    ///
+    /// ## Parameters
+    /// - `prefix`: The class prefix, or an empty string for no prefix
+    ///
+    /// ## Notes
+    /// - The prefix should be a valid CSS identifier, such as `ch3`, or the generated
+    ///   selectors will not match the markup.
+    pub fn set_class_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.class_prefix = prefix.to_string();
+        self
+    }
+
+    /// Sets whether the first paragraph of the document starts with a drop cap
+    ///
+    /// When enabled, the generated stylesheet enlarges the first letter of the first
+    /// text block with a `::first-letter` rule.
+    ///
+    /// ## Parameters
+    /// - `drop_cap`: Whether to write the drop cap rule, which is disabled by default
+    ///
+    /// ## Notes
+    /// - The rule selects the first `p` element of the document, so no drop cap is
+    ///   shown when a ruby block comes before the first text block.
+    /// - Like the rest of the generated stylesheet, the rule is not written when the
+    ///   generated style is replaced by CSS files.
+    pub fn set_drop_cap(&mut self, drop_cap: bool) -> &mut Self {
+        self.drop_cap = drop_cap;
+        self
+    }
+
+    /// Sets the size of the drop cap
+    ///
+    /// ## Parameters
+    /// - `size`: The font size of the drop cap in `em` (default: 3.0)
+    pub fn set_drop_cap_size(&mut self, size: f32) -> &mut Self {
+        self.drop_cap_size = size;
+        self
+    }
+
+    /// Sets whether the drop cap floats beside the following lines
+    ///
+    /// ## Parameters
+    /// - `float`: `true` (default) for a drop cap spanning several lines, or `false`
+    ///   for a raised cap that sits on the first line
+    pub fn set_drop_cap_float(&mut self, float: bool) -> &mut Self {
+        self.drop_cap_float = float;
+        self
+    }
+
+    /// Sets the structural semantics of the document body
+    ///
+    /// The value is written as the `epub:type` attribute of the `<body>` element,
+    /// e.g. `bodymatter`, `frontmatter` or `backmatter`. By default, the `<body>`
+    /// element has no `epub:type` attribute.
+    ///
+    /// ## Parameters
+    /// - `body_type`: The `epub:type` value of the `<body>` element
+    pub fn set_body_type(&mut self, body_type: &str) -> &mut Self {
+        self.body_type = Some(body_type.to_string());
+        self
+    }
+
+    /// Sets the XML declaration written at the top of the document
+    ///
+    /// By default, the document starts with `<?xml version="1.0" encoding="UTF-8"?>`.
+    ///
+    /// ## Parameters
+    /// - `declaration`: The XML declaration, or `None` to omit the declaration,
+    ///   e.g. when the document is served as `text/html`
+    pub fn set_xml_declaration(&mut self, declaration: Option<XmlDecl>) -> &mut Self {
+        self.xml_declaration = declaration;
+        self
+    }
+
+    /// Sets the EPUB version the document is generated for
+    ///
+    /// By default, an EPUB 3 content document is generated. When targeting EPUB 2,
+    /// the document is generated as XHTML 1.1: the XHTML 1.1 DOCTYPE is written,
+    /// `div` elements are used in place of `main`, `aside` and `figure`, captions
+    /// are written as `<p class="caption">`, and no `epub:type` attribute is written.
+    ///
+    /// ## Parameters
+    /// - `version`: The target EPUB version
+    ///
+    /// ## Notes
+    /// - Audio, video and MathML blocks are still written as is, since XHTML 1.1 has
+    ///   no equivalent for them. EPUB 2 reading systems render their fallback content.
+    pub fn set_target_version(&mut self, version: EpubVersion) -> &mut Self {
+        self.target_version = version;
+        self
+    }
+
+    /// Sets the names of the directories holding the resources of the document
+    ///
+    /// By default, images, audio, video and CSS files are placed in the `img`, `audio`,
+    /// `video` and `css` directories next to the document. The directories are used both
+    /// when copying the resources in `make` and in the links written to the document,
+    /// so they can be changed at any time before the document is made.
+    ///
+    /// ## Parameters
+    /// - `dirs`: The directory names, relative to the document, e.g. `images` or `media/audio`
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If all directory names are valid
+    /// - `Err(EpubError)`: If a directory name is empty, absolute, or contains an empty,
+    ///   `.` or `..` segment
+    pub fn set_resource_dirs(&mut self, dirs: ResourceDirs) -> Result<&mut Self, EpubError> {
+        for dir in [&dirs.image, &dirs.audio, &dirs.video, &dirs.css] {
+            let is_valid = dir
+                .split(['/', '\\'])
+                .all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+
+            if !is_valid {
+                return Err(
+                    EpubBuilderError::InvalidTargetPath { target_path: dir.clone() }.into(),
+                );
+            }
+        }
+
+        self.resource_dirs = dirs;
+        Ok(self)
+    }
+
+    /// Sets whether the file names of resources are sanitized
+    ///
+    /// By default, media resources and fonts keep their original file names when they
+    /// are copied into the document, which may contain spaces or non-ASCII characters
+    /// that some reading systems mishandle. When enabled, the file names are reduced
+    /// to ASCII letters, digits, `-` and `_`, e.g. `my photo (1).png` is copied as
+    /// `my-photo-1.png`, and the links in the document use the sanitized names.
+    ///
+    /// ## Parameters
+    /// - `sanitize`: Whether to sanitize the file names of resources
+    ///
+    /// ## Notes
+    /// - Resources are copied when they are added, so this option only applies to the
+    ///   resources added after it is set.
+    /// - When sanitized names collide, a numeric suffix is appended to the file stem in
+    ///   the order the resources were added, e.g. `my-photo-1-1.png`.
+    pub fn set_sanitize_resource_names(&mut self, sanitize: bool) -> &mut Self {
+        self.sanitize_resource_names = sanitize;
+        self
+    }
+
+    /// Adds a CSS file to the document
+    ///
+    /// Copies the CSS file to a temporary directory for inclusion in the EPUB package.
+    /// The CSS file will be linked in the document's head section when generating the output.
+    ///
+    /// ## Parameters
+    /// - `css_path`: The path to the CSS file to add
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If the file exists and is accessible
+    /// - `Err(EpubError)`: If the file does not exist or is not accessible
+    pub fn add_css_file(&mut self, css_path: PathBuf) -> Result<&mut Self, EpubError> {
+        if !css_path.is_file() {
+            return Err(EpubBuilderError::TargetIsNotFile {
+                target_path: css_path.to_string_lossy().to_string(),
+            }
+            .into());
+        }
 
         // we can assert that this path target to a file, so unwrap is safe here
         let file_name = css_path.file_name().unwrap().to_string_lossy().to_string();
-        let target_dir = self.temp_dir.join("css");
-        fs::create_dir_all(&target_dir)?;
+        let target_dir = self.create_temp_dir("css")?;
 
         let target_path = target_dir.join(&file_name);
         fs::copy(&css_path, &target_path)?;
@@ -1087,6 +1997,69 @@ impl ContentBuilder {
         Ok(self)
     }
 
+    /// Removes the last added CSS file from the document
+    ///
+    /// Deletes the copy of the CSS file from the temporary directory, and the file will
+    /// no longer be linked in the document's head section.
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If the file was removed, or no CSS file was added
+    /// - `Err(EpubError)`: If the copy of the file could not be deleted, in which case
+    ///   the file stays in the document
+    pub fn remove_last_css_file(&mut self) -> Result<&mut Self, EpubError> {
+        if let Some(css_file) = self.css_files.last() {
+            fs::remove_file(css_file)?;
+            self.css_files.pop();
+        }
+
+        Ok(self)
+    }
+
+    /// Removes all CSS files from the document
+    ///
+    /// Deletes the copies of the CSS files from the temporary directory.
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If all files were removed
+    /// - `Err(EpubError)`: If the copy of a file could not be deleted, in which case
+    ///   that file and the files after it stay in the document
+    pub fn clear_css_files(&mut self) -> Result<&mut Self, EpubError> {
+        while !self.css_files.is_empty() {
+            fs::remove_file(&self.css_files[0])?;
+            self.css_files.remove(0);
+        }
+
+        Ok(self)
+    }
+
+    /// Adds a font face to the document
+    ///
+    /// Copies the font file to a temporary directory for inclusion in the EPUB package.
+    /// The generated stylesheet declares the font with an `@font-face` rule, so that it
+    /// can be referenced by its family name in `TextStyle::font_family`.
+    ///
+    /// ## Parameters
+    /// - `font_face`: The font face to add
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If the font file exists and is accessible
+    /// - `Err(EpubError)`: If the font file does not exist or is not accessible
+    ///
+    /// ## Notes
+    /// - The `@font-face` rules are only written when the generated stylesheet is written.
+    pub fn add_font_face(&mut self, font_face: FontFace) -> Result<&mut Self, EpubError> {
+        if !font_face.src.is_file() {
+            return Err(EpubBuilderError::TargetIsNotFile {
+                target_path: font_face.src.to_string_lossy().to_string(),
+            }
+            .into());
+        }
+
+        self.copy_to_temp(&font_face.src, "fonts")?;
+        self.font_faces.push(font_face);
+        Ok(self)
+    }
+
     /// Adds a block to the document
     ///
     /// Adds a constructed Block to the document.
@@ -1097,12 +2070,13 @@ impl ContentBuilder {
         self.blocks.push(block);
 
         match self.blocks.last() {
-            Some(Block::Image { .. }) | Some(Block::Audio { .. }) | Some(Block::Video { .. }) => {
-                self.handle_resource()?
-            }
+            Some(Block::Image { .. })
+            | Some(Block::Audio { .. })
+            | Some(Block::Video { .. })
+            | Some(Block::Gallery { .. }) => self.handle_resource(self.blocks.len() - 1)?,
 
             Some(Block::MathML { fallback_image, .. }) if fallback_image.is_some() => {
-                self.handle_resource()?;
+                self.handle_resource(self.blocks.len() - 1)?;
             }
 
             _ => {}
@@ -1111,6 +2085,121 @@ impl ContentBuilder {
         Ok(self)
     }
 
+    /// Inserts a block into the document at the specified position
+    ///
+    /// Shifts all blocks after the position to the right. Media resources referenced
+    /// by the block are handled the same way as in [`add_block`](Self::add_block).
+    ///
+    /// ## Parameters
+    /// - `index`: The position to insert the block at, which may be equal to the block count
+    /// - `block`: The Block to insert
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If the block was inserted
+    /// - `Err(EpubError)`: If the index is out of range, or the media resources could not be
+    ///   copied, in which case the block is not inserted
+    pub fn insert_block(&mut self, index: usize, block: Block) -> Result<&mut Self, EpubError> {
+        if index > self.blocks.len() {
+            return Err(
+                EpubBuilderError::BlockIndexOutOfRange { index, len: self.blocks.len() }.into(),
+            );
+        }
+
+        self.blocks.insert(index, block);
+        if let Err(err) = self.handle_resource(index) {
+            self.blocks.remove(index);
+            return Err(err);
+        }
+
+        Ok(self)
+    }
+
+    /// Swaps two blocks in the document
+    ///
+    /// ## Parameters
+    /// - `a`: The position of the first block
+    /// - `b`: The position of the second block
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If the blocks were swapped
+    /// - `Err(EpubError)`: If either index is out of range
+    pub fn swap_blocks(&mut self, a: usize, b: usize) -> Result<&mut Self, EpubError> {
+        let len = self.blocks.len();
+        if let Some(&index) = [a, b].iter().find(|&&index| index >= len) {
+            return Err(EpubBuilderError::BlockIndexOutOfRange { index, len }.into());
+        }
+
+        self.blocks.swap(a, b);
+        Ok(self)
+    }
+
+    /// Returns the number of blocks in the document
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns the blocks of the document in order
+    ///
+    /// This allows inspecting the document being built, such as rendering an outline,
+    /// without consuming the builder.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Appends the blocks of another builder to the document
+    ///
+    /// The blocks of `other` are appended after the blocks of this builder, and the
+    /// media resources they reference are moved from the temporary directory of `other`
+    /// to the temporary directory of this builder. A resource whose file name is already
+    /// used by another resource of this builder is renamed with a numeric suffix, e.g.
+    /// `image-1.jpg`, while a resource copied from the same source by both builders is
+    /// kept only once.
+    ///
+    /// ## Parameters
+    /// - `other`: The builder whose blocks are appended, which is consumed
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If the blocks were appended
+    /// - `Err(EpubError)`: If a resource of `other` could not be moved, in which case
+    ///   the document is left unchanged
+    ///
+    /// ## Notes
+    /// - Footnotes are numbered in document order when the document is made, so the
+    ///   footnotes of `other` are numbered after the footnotes of this builder.
+    /// - Only the blocks are merged. The title, styles, CSS files, font faces and the
+    ///   other settings of `other` are discarded.
+    pub fn merge(&mut self, mut other: ContentBuilder) -> Result<&mut Self, EpubError> {
+        let resources = other
+            .blocks
+            .iter()
+            .flat_map(Self::block_resources)
+            .map(|(resource_type, url)| (resource_type, url.to_path_buf()))
+            .collect::<Vec<_>>();
+
+        let mut moved: Vec<((&'static str, PathBuf), String)> = Vec::new();
+        for key in resources {
+            if self.resource_names.contains_key(&key)
+                || moved.iter().any(|(moved, _)| *moved == key)
+            {
+                continue;
+            }
+
+            match self.move_from(&other, key.0, &key.1) {
+                Ok(file_name) => moved.push((key, file_name)),
+                Err(err) => {
+                    for ((resource_type, _), file_name) in moved {
+                        let _ = fs::remove_file(self.temp_dir.join(resource_type).join(file_name));
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        self.resource_names.extend(moved);
+        self.blocks.append(&mut other.blocks);
+        Ok(self)
+    }
+
     /// Adds a text block to the document
     ///
     /// Convenience method that creates and adds a Text block using the provided content and footnotes.
@@ -1149,6 +2238,47 @@ impl ContentBuilder {
         Ok(self)
     }
 
+    /// Adds an aside block to the document
+    ///
+    /// Convenience method that creates and adds an Aside block using the provided content,
+    /// structural semantics and footnotes.
+    ///
+    /// ## Parameters
+    /// - `content`: The text of the aside
+    /// - `epub_type`: The structural semantics of the aside, `sidebar` if `None`
+    /// - `footnotes`: A vector of footnotes associated with the aside
+    pub fn add_aside_block(
+        &mut self,
+        content: &str,
+        epub_type: Option<&str>,
+        footnotes: Vec<Footnote>,
+    ) -> Result<&mut Self, EpubError> {
+        let mut builder = BlockBuilder::new(BlockType::Aside);
+        builder.set_content(content).set_footnotes(footnotes);
+        if let Some(epub_type) = epub_type {
+            builder.set_epub_type(epub_type);
+        }
+
+        self.blocks.push(builder.try_into()?);
+        Ok(self)
+    }
+
+    /// Adds a ruby annotated paragraph to the document
+    ///
+    /// Convenience method that creates and adds a Ruby block using the provided text runs.
+    ///
+    /// ## Parameters
+    /// - `runs`: The text runs of the paragraph, at least one is required
+    pub fn add_ruby_block(&mut self, runs: Vec<RubyRun>) -> Result<&mut Self, EpubError> {
+        let mut builder = BlockBuilder::new(BlockType::Ruby);
+        for run in runs {
+            builder.add_ruby_run(&run.base, run.annotation.as_deref());
+        }
+
+        self.blocks.push(builder.try_into()?);
+        Ok(self)
+    }
+
     /// Adds a heading block to the document
     ///
     /// Convenience method that creates and adds a Title block with the specified level.
@@ -1202,7 +2332,47 @@ impl ContentBuilder {
         }
 
         self.blocks.push(builder.try_into()?);
-        self.handle_resource()?;
+        self.handle_resource(self.blocks.len() - 1)?;
+        Ok(self)
+    }
+
+    /// Adds an image gallery to the document
+    ///
+    /// Creates a Gallery block, which groups several images in one figure with a
+    /// single shared caption.
+    ///
+    /// ## Parameters
+    /// - `images`: The paths to the image files, each with optional alternative text
+    /// - `caption`: Optional caption text for the whole gallery
+    /// - `footnotes`: A vector of footnotes associated with the caption
+    ///
+    /// ## Return
+    /// - `Ok(&mut self)`: If the gallery was added
+    /// - `Err(EpubError)`: If no image is given, an image is not a valid image file,
+    ///   or the footnotes do not fit the caption
+    pub fn add_figure_group(
+        &mut self,
+        images: Vec<(PathBuf, Option<String>)>,
+        caption: Option<String>,
+        footnotes: Vec<Footnote>,
+    ) -> Result<&mut Self, EpubError> {
+        if images.is_empty() {
+            return Err(EpubBuilderError::MissingNecessaryBlockData {
+                block_type: "Gallery".to_string(),
+                missing_data: "'images'".to_string(),
+            }
+            .into());
+        }
+
+        for (path, _) in images.iter() {
+            BlockBuilder::is_target_type(path, vec![MatcherType::Image])?;
+        }
+
+        let block = Block::Gallery { images, caption, footnotes };
+        block.validate_footnotes()?;
+
+        self.blocks.push(block);
+        self.handle_resource(self.blocks.len() - 1)?;
         Ok(self)
     }
 
@@ -1234,7 +2404,7 @@ impl ContentBuilder {
         }
 
         self.blocks.push(builder.try_into()?);
-        self.handle_resource()?;
+        self.handle_resource(self.blocks.len() - 1)?;
         Ok(self)
     }
 
@@ -1266,7 +2436,7 @@ impl ContentBuilder {
         }
 
         self.blocks.push(builder.try_into()?);
-        self.handle_resource()?;
+        self.handle_resource(self.blocks.len() - 1)?;
         Ok(self)
     }
 
@@ -1301,20 +2471,88 @@ impl ContentBuilder {
         }
 
         self.blocks.push(builder.try_into()?);
-        self.handle_resource()?;
+        self.handle_resource(self.blocks.len() - 1)?;
         Ok(self)
     }
 
-    /// Builds content document
+    /// Adds a raw XHTML fragment to the document
     ///
-    /// The final constructed content document has the following structure:
+    /// The fragment is written into the document verbatim, which allows markup that
+    /// the other blocks cannot express. It must be trusted, well-formed XHTML, since
+    /// only its well-formedness is checked.
     ///
-    /// ```xhtml
-    /// <body>
-    ///     <main>
-    ///         <!-- The specific block structure can be queried in the Block docs. -->
+    /// ## Parameters
+    /// - `html`: The raw XHTML fragment, which may contain several sibling elements
+    ///
+    /// ## Return
+    /// - `Ok(&mut Self)`: The fragment was added successfully
+    /// - `Err(EpubError)`: The fragment is empty or is not well-formed
+    pub fn add_raw_html(&mut self, html: &str) -> Result<&mut Self, EpubError> {
+        if html.trim().is_empty() {
+            return Err(EpubBuilderError::InvalidHtmlFragment {
+                error: "The fragment is empty.".to_string(),
+            }
+            .into());
+        }
+
+        // Wrapping the fragment in a single root element catches unclosed and stray tags
+        if let Err(err) = XmlReader::parse(&format!("<fragment>{}</fragment>", html)) {
+            return Err(EpubBuilderError::InvalidHtmlFragment { error: err.to_string() }.into());
+        }
+
+        self.blocks
+            .push(Block::Raw { element_str: html.to_string() });
+        Ok(self)
+    }
+
+    /// Checks the document for problems without making it
+    ///
+    /// This function runs the checks that would otherwise only fail during `make`,
+    /// without writing any file, so that all problems can be reported at once.
+    ///
+    /// ## Return
+    /// - `Vec<EpubBuilderError>`: The problems found, in block order, which is empty
+    ///   if the document is ready to be made
+    ///
+    /// ## Notes
+    /// - The footnotes of every block are validated against the block content or caption.
+    /// - Every media resource referenced by a block must have been copied to the
+    ///   temporary directory, which is not the case after `cleanup` is called.
+    pub fn validate(&self) -> Vec<EpubBuilderError> {
+        let mut errors = Vec::new();
+        for block in self.blocks.iter() {
+            if let Err(EpubError::EpubBuilderError { source }) = block.validate_footnotes() {
+                errors.push(source);
+            }
+
+            for (resource_type, url) in Self::block_resources(block) {
+                let copied = self
+                    .resource_name(resource_type, url)
+                    .is_some_and(|name| self.temp_dir.join(resource_type).join(name).is_file());
+
+                if !copied {
+                    errors.push(EpubBuilderError::TargetIsNotFile {
+                        target_path: url.to_string_lossy().to_string(),
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Builds content document
+    ///
+    /// The final constructed content document has the following structure:
+    ///
+    /// ```xhtml
+    /// <body epub:type="{{ body_type }}"> <!-- epub:type is only present when a body type is set. -->
+    ///     <main>
+    ///         <!-- The specific block structure can be queried in the Block docs. -->
     ///     </main>
     ///     <aside>
+    ///         <!-- Only present when a footnote heading is set. -->
+    ///         <h2 class="footnote-heading" epub:type="footnotes">{{ footnote_heading }}</h2>
     ///         <ul class="footnote-list">
     ///             <!-- Each footnote has the same structure. -->
     ///             <li class="footnote-item" id="footnote-{{ index }}">
@@ -1328,6 +2566,20 @@ impl ContentBuilder {
     /// </body>
     /// ```
     ///
+    /// In [`FootnoteMode::Popup`] mode, the footnotes section is written as follows instead:
+    ///
+    /// ```xhtml
+    /// <section class="footnote-list">
+    ///     <h2 class="footnote-heading" epub:type="footnotes">{{ footnote_heading }}</h2>
+    ///     <aside id="footnote-{{ index }}" class="footnote-item" epub:type="footnote">
+    ///         <p>
+    ///             <a href="#ref-{{ index }}">[{{ index }}]</a>
+    ///             {{ footnote.content }}
+    ///         </p>
+    ///     </aside>
+    /// </section>
+    /// ```
+    ///
     /// ## Parameters
     /// - `target`: The file path where the document should be written
     ///
@@ -1354,18 +2606,27 @@ impl ContentBuilder {
         self.make_content(&target)?;
         result.push(target.as_ref().to_path_buf());
 
-        // Copy all resource files (images, audio, video) from temp directory to target directory
-        for resource_type in ["img", "audio", "video", "css"] {
+        // Copy all resource files (images, audio, video) from temp directory to target directory,
+        // the temporary directory always uses the default names
+        let dirs = &self.resource_dirs;
+        for (resource_type, dir) in [
+            ("img", dirs.image.as_str()),
+            ("audio", dirs.audio.as_str()),
+            ("video", dirs.video.as_str()),
+            ("css", dirs.css.as_str()),
+            ("fonts", "fonts"),
+        ] {
             let source = self.temp_dir.join(resource_type);
             if !source.is_dir() {
                 continue;
             }
 
-            let target = target_dir.join(resource_type);
+            let target = target_dir.join(dir);
             fs::create_dir_all(&target)?;
 
             for entry in WalkDir::new(&source)
                 .min_depth(1)
+                .sort_by_file_name()
                 .into_iter()
                 .filter_map(|result| result.ok())
                 .filter(|entry| entry.file_type().is_file())
@@ -1381,65 +2642,227 @@ impl ContentBuilder {
         Ok(result)
     }
 
+    /// Make the content document and return its manifest entries
+    ///
+    /// Behaves like `make`, but describes every written file as a `ManifestItem`, so that
+    /// the content document and its resources can be registered in a package directly.
+    ///
+    /// ## Parameters
+    /// - `target`: The file path where the document should be written
+    ///
+    /// ## Return
+    /// - `Ok(Vec<ManifestItem>)`: The manifest items of the content document, followed
+    ///   by those of the resources it uses
+    /// - `Err(EpubError)`: Error occurred during the making process, or the format of a
+    ///   written file could not be recognized
+    ///
+    /// ## Notes
+    /// - The id of the content document is the id of the builder, while the id of each
+    ///   resource is the id of the builder followed by the file name of the resource.
+    /// - The paths are relative to the directory of the content document, which is how
+    ///   the document refers to its resources.
+    pub fn make_with_manifest<P: AsRef<Path>>(
+        &mut self,
+        target: P,
+    ) -> Result<Vec<ManifestItem>, EpubError> {
+        let files = self.make(&target)?;
+        let base_dir = target.as_ref().parent().unwrap_or(Path::new(""));
+
+        let mut buf = vec![0; 512];
+        let mut manifest = Vec::with_capacity(files.len());
+        for (index, file) in files.into_iter().enumerate() {
+            let read = File::open(&file)?.read(&mut buf)?;
+            let extension = file
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let mime = infer_mime_type(&buf[..read], &extension)
+                .ok_or_else(|| EpubBuilderError::UnknownFileFormat {
+                    file_path: file.to_string_lossy().to_string(),
+                })?
+                .to_string();
+
+            let relative = file.strip_prefix(base_dir).unwrap_or(&file);
+            let id = if index == 0 {
+                self.id.clone()
+            } else {
+                let file_name = relative
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                format!("{}-{}", self.id, file_name)
+            };
+
+            manifest.push(ManifestItem {
+                id,
+                path: PathBuf::from(relative.to_string_lossy().replace("\\", "/")),
+                mime,
+                properties: None,
+                fallback: None,
+            });
+        }
+
+        Ok(manifest)
+    }
+
     /// Write the document to a file
     ///
     /// Constructs the final XHTML document from all added blocks and writes it to the specified output path.
+    /// The document is parsed back before it is written, and nothing is written if it is not
+    /// well-formed XML.
     ///
     /// ## Parameters
     /// - `target_path`: The file path where the XHTML document should be written
     fn make_content<P: AsRef<Path>>(&mut self, target_path: P) -> Result<(), EpubError> {
         let mut writer = Writer::new(Cursor::new(Vec::new()));
 
-        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
-        writer.write_event(Event::Start(BytesStart::new("html").with_attributes([
+        if let Some(decl) = &self.xml_declaration {
+            writer.write_event(Event::Decl(BytesDecl::new(
+                &decl.version,
+                decl.encoding.as_deref(),
+                decl.standalone.as_deref(),
+            )))?;
+        }
+        if self.target_version == EpubVersion::Version2_0 {
+            writer.write_event(Event::DocType(BytesText::from_escaped(
+                r#"html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd""#,
+            )))?;
+        }
+        let mut html = BytesStart::new("html").with_attributes([
             ("xmlns", "http://www.w3.org/1999/xhtml"),
             ("xml:lang", self.language.as_str()),
-        ])))?;
+        ]);
+        let has_footnotes = self
+            .blocks
+            .iter()
+            .any(|block| !block.take_footnotes().is_empty());
+        let uses_epub_type = self.target_version == EpubVersion::Version3_0
+            && (self.footnote_heading.is_some()
+                || self.body_type.is_some()
+                || (self.footnote_mode == FootnoteMode::Popup && has_footnotes)
+                || self
+                    .blocks
+                    .iter()
+                    .any(|block| matches!(block, Block::Aside { .. })));
+        if uses_epub_type {
+            html.push_attribute(("xmlns:epub", "http://www.idpf.org/2007/ops"));
+        }
+        writer.write_event(Event::Start(html))?;
 
         // make head
         writer.write_event(Event::Start(BytesStart::new("head")))?;
+        let title = match self.chapter_label(&self.title) {
+            Some((label, _)) => label.trim().to_string(),
+            None => self.title.clone(),
+        };
         writer.write_event(Event::Start(BytesStart::new("title")))?;
-        writer.write_event(Event::Text(BytesText::new(&self.title)))?;
+        writer.write_event(Event::Text(BytesText::new(&title)))?;
         writer.write_event(Event::End(BytesEnd::new("title")))?;
 
-        if self.css_files.is_empty() {
+        if self.inline_style.unwrap_or(self.css_files.is_empty()) {
             self.make_style(&mut writer)?;
-        } else {
-            for css_file in self.css_files.iter() {
-                // we can assert that this path target to a file, so unwrap is safe here
-                let file_name = css_file.file_name().unwrap().to_string_lossy().to_string();
+        }
 
-                writer.write_event(Event::Empty(BytesStart::new("link").with_attributes([
-                    ("href", format!("./css/{}", file_name).as_str()),
-                    ("rel", "stylesheet"),
-                    ("type", "text/css"),
-                ])))?;
-            }
+        for css_file in self.css_files.iter() {
+            // we can assert that this path target to a file, so unwrap is safe here
+            let file_name = css_file.file_name().unwrap().to_string_lossy().to_string();
+
+            writer.write_event(Event::Empty(BytesStart::new("link").with_attributes([
+                (
+                    "href",
+                    format!("./{}/{}", self.resource_dirs.css, file_name).as_str(),
+                ),
+                ("rel", "stylesheet"),
+                ("type", "text/css"),
+            ])))?;
         }
 
         writer.write_event(Event::End(BytesEnd::new("head")))?;
 
         // make body
-        writer.write_event(Event::Start(BytesStart::new("body")))?;
-        writer.write_event(Event::Start(BytesStart::new("main")))?;
+        let version = self.target_version;
+        let mut body = BytesStart::new("body");
+        let body_type = self
+            .body_type
+            .as_ref()
+            .filter(|_| version == EpubVersion::Version3_0);
+        if let Some(body_type) = body_type {
+            body.push_attribute(("epub:type", body_type.as_str()));
+        }
+        if !self.class_prefix.is_empty() {
+            // the root class scopes the element rules of the generated stylesheet
+            body.push_attribute(("class", class_names(&self.class_prefix, "root").as_str()));
+        }
+        writer.write_event(Event::Start(body))?;
+        writer.write_event(Event::Start(BytesStart::new(element_name("main", version))))?;
+
+        let footnote_mode = match version {
+            EpubVersion::Version2_0 => FootnoteMode::List,
+            EpubVersion::Version3_0 => self.footnote_mode,
+        };
+
+        let labeled_index = self
+            .blocks
+            .iter()
+            .position(|block| matches!(block, Block::Title { .. }))
+            .filter(|_| self.label_title_block);
 
         let mut footnote_index = 1;
         let mut footnotes = Vec::new();
-        for block in self.blocks.iter_mut() {
-            block.make(&mut writer, footnote_index)?;
+        for index in 0..self.blocks.len() {
+            let resource_hrefs = self.resource_hrefs(&self.blocks[index]);
+            let mut labeled = if labeled_index == Some(index) {
+                self.labeled_title_block(&self.blocks[index])?
+            } else {
+                None
+            };
+
+            let block = match labeled.as_mut() {
+                Some(block) => block,
+                None => &mut self.blocks[index],
+            };
+            block.make(
+                &mut writer,
+                footnote_index,
+                version,
+                &resource_hrefs,
+                footnote_mode,
+                &self.class_prefix,
+            )?;
 
             footnotes.append(&mut block.take_footnotes());
             footnote_index = footnotes.len() + 1;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("main")))?;
+        writer.write_event(Event::End(BytesEnd::new(element_name("main", version))))?;
 
-        Self::make_footnotes(&mut writer, footnotes)?;
+        match footnote_mode {
+            FootnoteMode::List => Self::make_footnotes(
+                &mut writer,
+                footnotes,
+                self.footnote_heading.as_deref(),
+                version,
+                &self.class_prefix,
+            )?,
+            FootnoteMode::Popup => Self::make_popup_footnotes(
+                &mut writer,
+                footnotes,
+                self.footnote_heading.as_deref(),
+                &self.class_prefix,
+            )?,
+        }
         writer.write_event(Event::End(BytesEnd::new("body")))?;
         writer.write_event(Event::End(BytesEnd::new("html")))?;
 
         let file_path = PathBuf::from(target_path.as_ref());
         let file_data = writer.into_inner().into_inner();
+
+        // raw markup is written as is, so make sure the document is still well-formed
+        let document = String::from_utf8_lossy(&file_data);
+        if let Err(err) = XmlReader::parse(&document) {
+            return Err(EpubBuilderError::GeneratedMalformedXml { error: err.to_string() }.into());
+        }
+
         fs::write(file_path, file_data)?;
 
         Ok(())
@@ -1447,9 +2870,49 @@ impl ContentBuilder {
 
     /// Generates CSS styles for the document
     fn make_style(&self, writer: &mut XmlWriter) -> Result<(), EpubError> {
-        let style = format!(
+        let mut style = String::new();
+        for font_face in self.font_faces.iter() {
+            let file_name = self.resource_name("fonts", &font_face.src).ok_or_else(|| {
+                EpubBuilderError::TargetIsNotFile {
+                    target_path: font_face.src.to_string_lossy().to_string(),
+                }
+            })?;
+            style.push_str(&format!(
+                r#"
+            @font-face {{
+                font-family: "{family}";
+                src: url({url});
+                font-weight: {weight};
+                font-style: {font_style};
+            }}"#,
+                family = font_face.family,
+                url = css_string(&format!("./fonts/{}", file_name)),
+                weight = font_face.weight.as_deref().unwrap_or("normal"),
+                font_style = font_face.style.as_deref().unwrap_or("normal"),
+            ));
+        }
+
+        // with a class prefix, the element rules only apply inside the prefixed root
+        // class of the body, so that they do not leak into other documents
+        let (class_prefix, root, scope, all) = if self.class_prefix.is_empty() {
+            (
+                String::new(),
+                "body".to_string(),
+                String::new(),
+                "*".to_string(),
+            )
+        } else {
+            let root = format!(".{}-root", self.class_prefix);
+            (
+                format!("{}-", self.class_prefix),
+                root.clone(),
+                format!("{} ", root),
+                format!("{root}, {root} *"),
+            )
+        };
+        style.push_str(&format!(
             r#"
-            * {{
+            {all} {{
                 margin: 0;
                 padding: 0;
                 font-family: {font_family};
@@ -1457,33 +2920,41 @@ impl ContentBuilder {
                 background-color: {background};
                 color: {text};
             }}
-            body, p, div, span, li, td, th {{
-                font-size: {font_size}rem;
-                line-height: {line_height}em;
+            {root}, {scope}p, {scope}div, {scope}span, {scope}li, {scope}td, {scope}th {{
+                font-size: {font_size}{font_size_unit};
+                line-height: {line_height}{line_height_unit};
                 font-weight: {font_weight};
                 font-style: {font_style};
                 letter-spacing: {letter_spacing};
             }}
-            body {{ margin: {margin}px; }}
-            p {{ text-indent: {text_indent}em; }}
-            a {{ color: {link_color}; text-decoration: none; }}
-            figcaption {{ text-align: center; line-height: 1em; }}
-            blockquote {{ padding: 1em 2em; }}
-            blockquote > p {{ font-style: italic; }}
-            .content-block {{ margin-bottom: {paragraph_spacing}px; }}
-            .image-block > img,
-            .audio-block > audio,
-            .video-block > video {{ width: 100%; }}
-            .footnote-ref {{ font-size: 0.5em; vertical-align: super; }}
-            .footnote-list {{ list-style: none; padding: 0; }}
-            .footnote-item > p {{ text-indent: 0; }}
+            {root} {{ margin: {margin}px; }}
+            {scope}p {{ text-indent: {text_indent}em; }}
+            {scope}a {{ color: {link_color}; text-decoration: none; }}
+            {scope}figcaption, .{prefix}caption {{ text-align: center; line-height: 1em; }}
+            {scope}blockquote {{ padding: 1em 2em; }}
+            {scope}blockquote > p {{ font-style: italic; }}
+            .{prefix}aside-block {{ padding: 1em; border: 1px solid {text}; }}
+            .{prefix}content-block {{ margin-bottom: {paragraph_spacing}px; }}
+            .{prefix}image-block > img,
+            .{prefix}audio-block > audio,
+            .{prefix}video-block > video {{ width: 100%; }}
+            .{prefix}footnote-ref {{ font-size: 0.5em; vertical-align: super; }}
+            .{prefix}footnote-list {{ list-style: none; padding: 0; }}
+            .{prefix}footnote-item > p {{ text-indent: 0; }}
             "#,
-            font_family = self.styles.text.font_family,
+            font_family = self.font_family_stack(),
             text_align = self.styles.layout.text_align,
             background = self.styles.color_scheme.background,
             text = self.styles.color_scheme.text,
             font_size = self.styles.text.font_size,
+            font_size_unit = self.styles.text.font_size_unit,
             line_height = self.styles.text.line_height,
+            line_height_unit = self
+                .styles
+                .text
+                .line_height_unit
+                .map(|unit| unit.to_string())
+                .unwrap_or_default(),
             font_weight = self.styles.text.font_weight,
             font_style = self.styles.text.font_style,
             letter_spacing = self.styles.text.letter_spacing,
@@ -1491,30 +2962,160 @@ impl ContentBuilder {
             text_indent = self.styles.text.text_indent,
             link_color = self.styles.color_scheme.link,
             paragraph_spacing = self.styles.layout.paragraph_spacing,
-        );
+            prefix = class_prefix,
+        ));
+
+        if self.drop_cap {
+            let float = if self.drop_cap_float {
+                "float: left; line-height: 1; margin-right: 0.1em;"
+            } else {
+                "float: none;"
+            };
+            style.push_str(&format!(
+                r#"
+            .{prefix}content-block.{prefix}text-block:first-of-type::first-letter {{ font-size: {size}em; {float} }}
+            "#,
+                prefix = class_prefix,
+                size = self.drop_cap_size,
+            ));
+        }
+
+        let style = if self.minify_css {
+            minify_css(&style)
+        } else {
+            style
+        };
 
-        writer.write_event(Event::Start(BytesStart::new("style")))?;
+        let mut style_element = BytesStart::new("style");
+        if self.target_version == EpubVersion::Version2_0 {
+            // the type attribute is required in XHTML 1.1
+            style_element.push_attribute(("type", "text/css"));
+        }
+        writer.write_event(Event::Start(style_element))?;
         writer.write_event(Event::Text(BytesText::new(&style)))?;
         writer.write_event(Event::End(BytesEnd::new("style")))?;
 
         Ok(())
     }
 
+    /// Returns the font family stack of the document
+    ///
+    /// Families in `TextStyle::font_family` that match an added font face are quoted,
+    /// so that family names containing whitespace reference the embedded font.
+    fn font_family_stack(&self) -> String {
+        self.styles
+            .text
+            .font_family
+            .split(',')
+            .map(|family| {
+                let family = family.trim();
+                let name = family.trim_matches(|ch| ch == '"' || ch == '\'');
+                if self
+                    .font_faces
+                    .iter()
+                    .any(|font_face| font_face.family == name)
+                {
+                    format!("\"{}\"", name)
+                } else {
+                    family.to_string()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Formats the chapter label of a title
+    ///
+    /// ## Return
+    /// - `Some((String, usize))`: The chapter label, and the number of characters
+    ///   written before the title in it
+    /// - `None`: No chapter number is set
+    fn chapter_label(&self, title: &str) -> Option<(String, usize)> {
+        let number = self.chapter_number?.to_string();
+        let format = if self.chapter_label_format.contains("{title}") {
+            self.chapter_label_format.clone()
+        } else {
+            format!("{} {{title}}", self.chapter_label_format)
+        };
+
+        // the format is guaranteed to contain the placeholder here
+        let (before, after) = format.split_once("{title}").unwrap();
+        let before = before.replace("{n}", &number);
+        let after = after.replace("{n}", &number);
+        Some((
+            format!("{}{}{}", before, title, after),
+            before.chars().count(),
+        ))
+    }
+
+    /// Creates a copy of a title block whose content is labeled with the chapter number
+    ///
+    /// The footnotes of the copy are anchored by character index, shifted by the text
+    /// written before the title. Returns `None` for other blocks, or if no chapter
+    /// number is set.
+    fn labeled_title_block(&self, block: &Block) -> Result<Option<Block>, EpubError> {
+        let Block::Title { content, footnotes, level } = block else {
+            return Ok(None);
+        };
+        let Some((label, offset)) = self.chapter_label(content) else {
+            return Ok(None);
+        };
+
+        let footnotes = footnotes
+            .iter()
+            .map(|footnote| {
+                let position = Block::resolve_footnote_anchor(content, footnote.locate)?;
+                Ok(Footnote {
+                    locate: FootnoteAnchor::CharIndex(position + offset),
+                    content: footnote.content.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, EpubError>>()?;
+
+        Ok(Some(Block::Title {
+            content: label,
+            footnotes,
+            level: *level,
+        }))
+    }
+
     /// Generates the footnotes section in the document
     ///
     /// Creates an aside element containing an unordered list of all footnotes.
     /// Each footnote is rendered as a list item with a backlink to its reference in the text.
-    fn make_footnotes(writer: &mut XmlWriter, footnotes: Vec<Footnote>) -> Result<(), EpubError> {
-        writer.write_event(Event::Start(BytesStart::new("aside")))?;
-        writer.write_event(Event::Start(
-            BytesStart::new("ul").with_attributes([("class", "footnote-list")]),
-        ))?;
-
-        let mut index = 1;
-        for footnote in footnotes.into_iter() {
+    /// If a heading is given, it is written above the list.
+    fn make_footnotes(
+        writer: &mut XmlWriter,
+        footnotes: Vec<Footnote>,
+        heading: Option<&str>,
+        version: EpubVersion,
+        class_prefix: &str,
+    ) -> Result<(), EpubError> {
+        writer.write_event(Event::Start(BytesStart::new(element_name(
+            "aside", version,
+        ))))?;
+        if let Some(heading) = heading {
+            let mut h2 = BytesStart::new("h2").with_attributes([(
+                "class",
+                class_names(class_prefix, "footnote-heading").as_str(),
+            )]);
+            if version == EpubVersion::Version3_0 {
+                h2.push_attribute(("epub:type", "footnotes"));
+            }
+            writer.write_event(Event::Start(h2))?;
+            writer.write_event(Event::Text(BytesText::new(heading)))?;
+            writer.write_event(Event::End(BytesEnd::new("h2")))?;
+        }
+
+        writer.write_event(Event::Start(BytesStart::new("ul").with_attributes([(
+            "class",
+            class_names(class_prefix, "footnote-list").as_str(),
+        )])))?;
+
+        for (index, footnote) in (1..).zip(footnotes) {
             writer.write_event(Event::Start(BytesStart::new("li").with_attributes([
                 ("id", format!("footnote-{}", index).as_str()),
-                ("class", "footnote-item"),
+                ("class", class_names(class_prefix, "footnote-item").as_str()),
             ])))?;
             writer.write_event(Event::Start(BytesStart::new("p")))?;
 
@@ -1528,68 +3129,280 @@ impl ContentBuilder {
 
             writer.write_event(Event::End(BytesEnd::new("p")))?;
             writer.write_event(Event::End(BytesEnd::new("li")))?;
-
-            index += 1;
         }
 
         writer.write_event(Event::End(BytesEnd::new("ul")))?;
-        writer.write_event(Event::End(BytesEnd::new("aside")))?;
+        writer.write_event(Event::End(BytesEnd::new(element_name("aside", version))))?;
+
+        Ok(())
+    }
+
+    /// Generates the footnotes section in the document as popup notes
+    ///
+    /// Creates a section element containing one `<aside epub:type="footnote">` element
+    /// for each footnote, with a backlink to its reference in the text. If a heading
+    /// is given, it is written above the notes.
+    fn make_popup_footnotes(
+        writer: &mut XmlWriter,
+        footnotes: Vec<Footnote>,
+        heading: Option<&str>,
+        class_prefix: &str,
+    ) -> Result<(), EpubError> {
+        writer.write_event(Event::Start(BytesStart::new("section").with_attributes([
+            ("class", class_names(class_prefix, "footnote-list").as_str()),
+        ])))?;
+        if let Some(heading) = heading {
+            writer.write_event(Event::Start(BytesStart::new("h2").with_attributes([
+                (
+                    "class",
+                    class_names(class_prefix, "footnote-heading").as_str(),
+                ),
+                ("epub:type", "footnotes"),
+            ])))?;
+            writer.write_event(Event::Text(BytesText::new(heading)))?;
+            writer.write_event(Event::End(BytesEnd::new("h2")))?;
+        }
+
+        for (index, footnote) in (1..).zip(footnotes) {
+            writer.write_event(Event::Start(BytesStart::new("aside").with_attributes([
+                ("id", format!("footnote-{}", index).as_str()),
+                ("class", class_names(class_prefix, "footnote-item").as_str()),
+                ("epub:type", "footnote"),
+            ])))?;
+            writer.write_event(Event::Start(BytesStart::new("p")))?;
+
+            writer.write_event(Event::Start(
+                BytesStart::new("a")
+                    .with_attributes([("href", format!("#ref-{}", index).as_str())]),
+            ))?;
+            writer.write_event(Event::Text(BytesText::new(&format!("[{}]", index))))?;
+            writer.write_event(Event::End(BytesEnd::new("a")))?;
+            writer.write_event(Event::Text(BytesText::new(&footnote.content)))?;
+
+            writer.write_event(Event::End(BytesEnd::new("p")))?;
+            writer.write_event(Event::End(BytesEnd::new("aside")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("section")))?;
 
         Ok(())
     }
 
     /// Automatically handles media resources
     ///
-    /// Copies media files (images, audio, video) from their original locations
-    /// to the temporary directory for inclusion in the EPUB package.
-    fn handle_resource(&mut self) -> Result<(), EpubError> {
-        match self.blocks.last() {
-            Some(Block::Image { url, .. }) => self.copy_to_temp(url, "img")?,
+    /// Copies media files (images, audio, video) of the block at `index` from their
+    /// original locations to the temporary directory for inclusion in the EPUB package.
+    fn handle_resource(&mut self, index: usize) -> Result<(), EpubError> {
+        let resources = match self.blocks.get(index) {
+            Some(block) => Self::block_resources(block)
+                .into_iter()
+                .map(|(resource_type, url)| (resource_type, url.to_path_buf()))
+                .collect::<Vec<_>>(),
+            None => return Ok(()),
+        };
 
-            Some(Block::Video { url, .. }) => self.copy_to_temp(url, "video")?,
+        for (resource_type, url) in resources {
+            self.copy_to_temp(url, resource_type)?;
+        }
 
-            Some(Block::Audio { url, .. }) => self.copy_to_temp(url, "audio")?,
+        Ok(())
+    }
 
-            Some(Block::MathML { fallback_image: Some(url), .. }) => {
-                self.copy_to_temp(url, "img")?
-            }
+    /// Returns the resource types and the source paths of the media resources of a block
+    fn block_resources(block: &Block) -> Vec<(&'static str, &Path)> {
+        match block {
+            Block::Image { url, .. } => vec![("img", url)],
+            Block::Video { url, .. } => vec![("video", url)],
+            Block::Audio { url, .. } => vec![("audio", url)],
+            Block::MathML { fallback_image: Some(url), .. } => vec![("img", url)],
+            Block::Gallery { images, .. } => images
+                .iter()
+                .map(|(url, _)| ("img", url.as_path()))
+                .collect(),
+            _ => vec![],
+        }
+    }
 
-            _ => {}
+    /// Returns the links to the media resources of a block, relative to the document
+    ///
+    /// The links point to the copies of the resources made when the block was added,
+    /// in the directory configured for their resource type. An empty list is returned
+    /// if any of the resources has not been copied.
+    fn resource_hrefs(&self, block: &Block) -> Vec<String> {
+        Self::block_resources(block)
+            .into_iter()
+            .map(|(resource_type, url)| {
+                let dir = match resource_type {
+                    "video" => &self.resource_dirs.video,
+                    "audio" => &self.resource_dirs.audio,
+                    _ => &self.resource_dirs.image,
+                };
+
+                let file_name = self.resource_name(resource_type, url)?;
+                Some(format!("./{}/{}", dir, file_name))
+            })
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default()
+    }
+
+    /// Returns the file name of the copy of a resource in the temporary directory
+    fn resource_name(&self, resource_type: &'static str, source: &Path) -> Option<&str> {
+        self.resource_names
+            .get(&(resource_type, source.to_path_buf()))
+            .map(String::as_str)
+    }
+
+    /// Removes the temporary directory of the builder
+    ///
+    /// The temporary directory is removed automatically when the builder is dropped,
+    /// but errors can only be logged there. Calling this function removes the directory
+    /// explicitly and reports the error to the caller.
+    ///
+    /// ## Return
+    /// - `Ok(())`: The temporary directory was removed, or had already been removed
+    /// - `Err(EpubError)`: The temporary directory could not be removed
+    ///
+    /// ## Notes
+    /// - Media resources and CSS files added before the cleanup are removed with the
+    ///   temporary directory, so the builder should not be used to make a document
+    ///   after this function is called.
+    /// - Adding a resource after the cleanup recreates the temporary directory, which
+    ///   is then removed again when the builder is dropped.
+    pub fn cleanup(&mut self) -> Result<(), EpubError> {
+        if self.temp_dir_removed {
+            return Ok(());
+        }
+
+        match fs::remove_dir_all(&self.temp_dir) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
         }
 
+        self.temp_dir_removed = true;
         Ok(())
     }
 
-    #[inline]
-    fn copy_to_temp(&self, source: impl AsRef<Path>, resource_type: &str) -> Result<(), EpubError> {
-        let target_dir = self.temp_dir.join(resource_type);
+    /// Creates a directory in the temporary directory of the builder
+    ///
+    /// The temporary directory is recreated if `cleanup` has removed it, and is then
+    /// removed again by the next cleanup or when the builder is dropped.
+    fn create_temp_dir(&mut self, name: &str) -> Result<PathBuf, EpubError> {
+        let target_dir = self.temp_dir.join(name);
         fs::create_dir_all(&target_dir)?;
+        self.temp_dir_removed = false;
+        Ok(target_dir)
+    }
+
+    /// Copies a resource to the temporary directory
+    ///
+    /// The copy keeps the file name of the resource, unless another resource of the
+    /// same type already uses it, in which case a numeric suffix is appended to the
+    /// file stem, e.g. `image-1.jpg`. Copying the same source again reuses its name.
+    fn copy_to_temp(
+        &mut self,
+        source: impl AsRef<Path>,
+        resource_type: &'static str,
+    ) -> Result<(), EpubError> {
+        let target_dir = self.create_temp_dir(resource_type)?;
 
         let source = source.as_ref();
-        let target_path = target_dir.join(source.file_name().unwrap());
+        let file_name = match self.resource_name(resource_type, source) {
+            Some(file_name) => file_name.to_string(),
+            None => Self::unique_file_name(&target_dir, source, self.sanitize_resource_names),
+        };
 
-        fs::copy(source, &target_path)?;
+        fs::copy(source, target_dir.join(&file_name))?;
+        self.resource_names
+            .insert((resource_type, source.to_path_buf()), file_name);
         Ok(())
     }
+
+    /// Moves the copy of a resource from the temporary directory of another builder
+    ///
+    /// The file is renamed if its name is already used in the temporary directory of
+    /// this builder, and the new file name is returned.
+    fn move_from(
+        &mut self,
+        other: &ContentBuilder,
+        resource_type: &'static str,
+        source: &Path,
+    ) -> Result<String, EpubError> {
+        let from = other
+            .resource_name(resource_type, source)
+            .map(|name| other.temp_dir.join(resource_type).join(name))
+            .filter(|path| path.is_file())
+            .ok_or_else(|| EpubBuilderError::TargetIsNotFile {
+                target_path: source.to_string_lossy().to_string(),
+            })?;
+
+        let target_dir = self.create_temp_dir(resource_type)?;
+
+        let file_name = Self::unique_file_name(&target_dir, &from, false);
+        let to = target_dir.join(&file_name);
+
+        // renaming fails when the temporary directories are on different file systems
+        if fs::rename(&from, &to).is_err() {
+            fs::copy(&from, &to)?;
+        }
+
+        Ok(file_name)
+    }
+
+    /// Picks a file name for `source` that is not used yet in `target_dir`
+    ///
+    /// When `sanitize` is set, the file name is sanitized before it is checked.
+    fn unique_file_name(target_dir: &Path, source: &Path, sanitize: bool) -> String {
+        // resources are checked to be files when they are added, so unwrap is safe here
+        let mut file_name = source.file_name().unwrap().to_string_lossy().to_string();
+        if sanitize {
+            file_name = sanitize_file_name(&file_name);
+        }
+        if !target_dir.join(&file_name).exists() {
+            return file_name;
+        }
+
+        let path = Path::new(&file_name);
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+
+        (1..)
+            .map(|index| format!("{}-{}{}", stem, index, extension))
+            .find(|file_name| !target_dir.join(file_name).exists())
+            .unwrap()
+    }
 }
 
 impl Drop for ContentBuilder {
     fn drop(&mut self) {
-        if let Err(err) = fs::remove_dir_all(&self.temp_dir) {
-            warn!("{}", err);
-        };
+        // removal may fail while a file handle is still open, e.g. on Windows
+        for attempt in 1..=Self::CLEANUP_ATTEMPTS {
+            match self.cleanup() {
+                Ok(()) => break,
+                Err(err) if attempt == Self::CLEANUP_ATTEMPTS => warn!("{}", err),
+                Err(_) => thread::sleep(Duration::from_millis(10 * attempt)),
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     mod block_builder_tests {
-        use std::path::PathBuf;
+        use std::{io::Cursor, path::PathBuf};
+
+        use quick_xml::Writer;
 
         use crate::{
             builder::content::{Block, BlockBuilder},
             error::{EpubBuilderError, EpubError},
-            types::{BlockType, Footnote},
+            types::{BlockType, EpubVersion, Footnote, FootnoteAnchor, FootnoteMode},
         };
 
         #[test]
@@ -1763,6 +3576,22 @@ mod tests {
             assert_eq!(err, EpubBuilderError::NotExpectedFileFormat.into());
         }
 
+        #[test]
+        fn test_set_url_by_extension() {
+            // SVG is XML text without a magic number
+            let svg_path = PathBuf::from("./test_case/image.svg");
+            let mut builder = BlockBuilder::new(BlockType::Image);
+            assert!(builder.set_url(&svg_path).is_ok());
+            assert!(Block::try_from(builder).is_ok());
+
+            // a raw Opus stream is not recognized by its header
+            let opus_path = PathBuf::from("./test_case/audio.opus");
+            let mut builder = BlockBuilder::new(BlockType::Audio);
+            assert!(builder.set_url(&opus_path).is_ok());
+            builder.set_fallback("Audio not supported");
+            assert!(Block::try_from(builder).is_ok());
+        }
+
         #[test]
         fn test_set_url_nonexistent_file() {
             let nonexistent_path = PathBuf::from("./test_case/nonexistent.jpg");
@@ -1780,6 +3609,40 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_mathml_root_validation() {
+            let invalid = [
+                r#"<div xmlns="http://www.w3.org/1998/Math/MathML"><mi>x</mi></div>"#,
+                r#"<math><mi>x</mi></math>"#,
+                r#"<math xmlns="http://www.w3.org/1999/xhtml"><mi>x</mi></math>"#,
+                "",
+            ];
+            for element_str in invalid {
+                let mut builder = BlockBuilder::new(BlockType::MathML);
+                builder.set_mathml_element(element_str);
+
+                let result: Result<Block, EpubError> = builder.try_into();
+                assert!(matches!(
+                    result,
+                    Err(EpubError::EpubBuilderError {
+                        source: EpubBuilderError::InvalidMathMLFormat { .. }
+                    })
+                ));
+            }
+
+            let valid = [
+                r#"<math xmlns="http://www.w3.org/1998/Math/MathML"><mi>x</mi></math>"#,
+                r#"<!-- formula --><m:math xmlns:m="http://www.w3.org/1998/Math/MathML"><m:mi>x</m:mi></m:math>"#,
+            ];
+            for element_str in valid {
+                let mut builder = BlockBuilder::new(BlockType::MathML);
+                builder.set_mathml_element(element_str);
+
+                let result: Result<Block, EpubError> = builder.try_into();
+                assert!(result.is_ok());
+            }
+        }
+
         #[test]
         fn test_set_fallback_image_invalid_type() {
             let audio_path = PathBuf::from("./test_case/audio.mp3");
@@ -1893,11 +3756,11 @@ mod tests {
             builder.set_content("This is a test");
 
             let note1 = Footnote {
-                locate: 5,
+                locate: FootnoteAnchor::CharIndex(5),
                 content: "First footnote".to_string(),
             };
             let note2 = Footnote {
-                locate: 10,
+                locate: FootnoteAnchor::CharIndex(10),
                 content: "Second footnote".to_string(),
             };
 
@@ -1922,7 +3785,7 @@ mod tests {
 
             // Footnote locate exceeds content length
             builder.add_footnote(Footnote {
-                locate: 100,
+                locate: FootnoteAnchor::CharIndex(100),
                 content: "Invalid footnote".to_string(),
             });
 
@@ -1932,64 +3795,270 @@ mod tests {
             let result = result.unwrap_err();
             assert_eq!(
                 result,
-                EpubBuilderError::InvalidFootnoteLocate { max_locate: 5 }.into()
+                EpubBuilderError::InvalidFootnoteLocate {
+                    max_locate: 5,
+                    unit: "characters".to_string()
+                }
+                .into()
             );
         }
 
         #[test]
-        fn test_footnote_on_media_without_caption() {
-            let img_path = PathBuf::from("./test_case/image.jpg");
-            let mut builder = BlockBuilder::new(BlockType::Image);
-            builder.set_url(&img_path).unwrap();
-
-            builder.add_footnote(Footnote { locate: 1, content: "Note".to_string() });
+        fn test_footnote_anchor_by_byte_offset_and_word_index() {
+            let mut builder = BlockBuilder::new(BlockType::Text);
+            builder
+                .set_content("Hello 世界 wide world")
+                .set_footnotes(vec![
+                    Footnote {
+                        locate: FootnoteAnchor::WordIndex(3),
+                        content: "After wide".to_string(),
+                    },
+                    Footnote {
+                        // "Hello " is 6 bytes, and each of "世界" is 3 bytes
+                        locate: FootnoteAnchor::ByteOffset(12),
+                        content: "After 世界".to_string(),
+                    },
+                ]);
 
-            let result: Result<Block, EpubError> = builder.try_into();
-            assert!(result.is_err());
+            let block: Result<Block, EpubError> = builder.try_into();
+            assert!(block.is_ok());
 
-            let result = result.unwrap_err();
-            assert_eq!(
-                result,
-                EpubBuilderError::InvalidFootnoteLocate { max_locate: 0 }.into()
+            let mut block = block.unwrap();
+            let mut writer = Writer::new(Cursor::new(Vec::new()));
+            assert!(
+                block
+                    .make(
+                        &mut writer,
+                        1,
+                        EpubVersion::Version3_0,
+                        &[],
+                        FootnoteMode::List,
+                        ""
+                    )
+                    .is_ok()
             );
-        }
-    }
-
-    mod content_builder_tests {
-        use std::{env, fs, path::PathBuf};
-
-        use crate::{
-            builder::content::ContentBuilder,
-            types::{ColorScheme, Footnote, PageLayout, TextAlign, TextStyle},
-            utils::local_time,
-        };
 
-        #[test]
-        fn test_create_content_builder() {
-            let builder = ContentBuilder::new("chapter1", "en");
-            assert!(builder.is_ok());
+            let xhtml = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+            assert!(xhtml.contains(
+                "Hello 世界<a href=\"#footnote-1\" id=\"ref-1\" class=\"footnote-ref\">[1]</a> wide\
+                <a href=\"#footnote-2\" id=\"ref-2\" class=\"footnote-ref\">[2]</a> world"
+            ));
 
-            let builder = builder.unwrap();
-            assert_eq!(builder.id, "chapter1");
+            // footnotes are numbered in the order they appear in the text
+            let footnotes = block.take_footnotes();
+            assert_eq!(footnotes[0].content, "After 世界");
+            assert_eq!(footnotes[1].content, "After wide");
         }
 
         #[test]
-        fn test_set_title() {
-            let builder = ContentBuilder::new("chapter1", "en");
-            assert!(builder.is_ok());
+        fn test_footnote_anchor_keeps_grapheme_cluster() {
+            // the family emoji is five characters joined into a single grapheme cluster
+            let content = "Family: \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} together";
 
-            let mut builder = builder.unwrap();
-            builder.set_title("My Chapter").set_title("Another Title");
+            let mut builder = BlockBuilder::new(BlockType::Text);
+            builder.set_content(content).add_footnote(Footnote {
+                locate: 9.into(),
+                content: "Note".to_string(),
+            });
 
-            assert_eq!(builder.title, "Another Title");
-        }
+            let block: Result<Block, EpubError> = builder.try_into();
+            assert!(block.is_ok());
 
-        #[test]
-        fn test_add_text_block() {
-            let builder = ContentBuilder::new("chapter1", "en");
-            assert!(builder.is_ok());
+            let mut block = block.unwrap();
+            let mut writer = Writer::new(Cursor::new(Vec::new()));
+            assert!(
+                block
+                    .make(
+                        &mut writer,
+                        1,
+                        EpubVersion::Version3_0,
+                        &[],
+                        FootnoteMode::List,
+                        ""
+                    )
+                    .is_ok()
+            );
 
-            let mut builder = builder.unwrap();
+            let xhtml = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+            assert!(xhtml.contains("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}<a href"));
+
+            // positions inside the cluster are moved to the end of the cluster
+            assert_eq!(
+                Block::resolve_footnote_anchor(content, FootnoteAnchor::GraphemeIndex(9)).unwrap(),
+                13
+            );
+            assert_eq!(
+                Block::resolve_footnote_anchor(content, FootnoteAnchor::CharIndex(9)).unwrap(),
+                13
+            );
+            assert_eq!(
+                Block::resolve_footnote_anchor(content, FootnoteAnchor::ByteOffset(12)).unwrap(),
+                13
+            );
+            assert_eq!(
+                Block::resolve_footnote_anchor(content, FootnoteAnchor::CharIndex(8)).unwrap(),
+                8
+            );
+            assert_eq!(
+                Block::resolve_footnote_anchor(content, FootnoteAnchor::GraphemeIndex(19)),
+                Err(EpubBuilderError::InvalidFootnoteLocate {
+                    max_locate: 18,
+                    unit: "graphemes".to_string()
+                }
+                .into())
+            );
+
+            let mut builder = BlockBuilder::new(BlockType::Text);
+            builder.set_content(content).add_footnote(Footnote {
+                locate: FootnoteAnchor::CharIndex(10),
+                content: "Note".to_string(),
+            });
+
+            let mut block: Block = builder.try_into().unwrap();
+            let mut writer = Writer::new(Cursor::new(Vec::new()));
+            assert!(
+                block
+                    .make(
+                        &mut writer,
+                        1,
+                        EpubVersion::Version3_0,
+                        &[],
+                        FootnoteMode::List,
+                        ""
+                    )
+                    .is_ok()
+            );
+
+            let xhtml = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+            assert!(xhtml.contains("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}<a href"));
+        }
+
+        #[test]
+        fn test_invalid_footnote_anchor() {
+            let content = "Hello 世界";
+            let cases = [
+                (
+                    FootnoteAnchor::ByteOffset(7),
+                    EpubBuilderError::InvalidFootnoteByteOffset { offset: 7 },
+                ),
+                (
+                    FootnoteAnchor::ByteOffset(13),
+                    EpubBuilderError::InvalidFootnoteLocate {
+                        max_locate: 12,
+                        unit: "bytes".to_string(),
+                    },
+                ),
+                (
+                    FootnoteAnchor::WordIndex(3),
+                    EpubBuilderError::InvalidFootnoteLocate {
+                        max_locate: 2,
+                        unit: "words".to_string(),
+                    },
+                ),
+                (
+                    FootnoteAnchor::CharIndex(0),
+                    EpubBuilderError::InvalidFootnoteLocate {
+                        max_locate: 8,
+                        unit: "characters".to_string(),
+                    },
+                ),
+            ];
+
+            for (anchor, error) in cases {
+                let mut builder = BlockBuilder::new(BlockType::Text);
+                builder.set_content(content).add_footnote(Footnote {
+                    locate: anchor,
+                    content: "Note".to_string(),
+                });
+
+                let result: Result<Block, EpubError> = builder.try_into();
+                assert!(result.is_err());
+                assert_eq!(result.unwrap_err(), error.into());
+            }
+
+            // the range is reported in the unit of the rejected anchor
+            assert_eq!(
+                Block::resolve_footnote_anchor(content, FootnoteAnchor::WordIndex(3))
+                    .unwrap_err()
+                    .to_string(),
+                "Epub builder error: The footnote locate must be in the range of [0, 2] words."
+            );
+            assert_eq!(
+                Block::resolve_footnote_anchor(content, FootnoteAnchor::WordIndex(1)).unwrap(),
+                5
+            );
+            assert_eq!(
+                Block::resolve_footnote_anchor(content, FootnoteAnchor::ByteOffset(9)).unwrap(),
+                7
+            );
+        }
+
+        #[test]
+        fn test_footnote_on_media_without_caption() {
+            let img_path = PathBuf::from("./test_case/image.jpg");
+            let mut builder = BlockBuilder::new(BlockType::Image);
+            builder.set_url(&img_path).unwrap();
+
+            builder.add_footnote(Footnote {
+                locate: FootnoteAnchor::CharIndex(1),
+                content: "Note".to_string(),
+            });
+
+            let result: Result<Block, EpubError> = builder.try_into();
+            assert!(result.is_err());
+
+            let result = result.unwrap_err();
+            assert_eq!(
+                result,
+                EpubBuilderError::InvalidFootnoteLocate {
+                    max_locate: 0,
+                    unit: "characters".to_string()
+                }
+                .into()
+            );
+        }
+    }
+
+    mod content_builder_tests {
+        use std::{env, fs, path::PathBuf};
+
+        use crate::{
+            builder::content::{Block, BlockBuilder, ContentBuilder},
+            error::{EpubBuilderError, EpubError},
+            types::{
+                BlockType, ColorScheme, EpubVersion, FontFace, Footnote, FootnoteAnchor,
+                FootnoteMode, PageLayout, ResourceDirs, RubyRun, SizeUnit, StyleOptions, TextAlign,
+                TextStyle, XmlDecl,
+            },
+            utils::local_time,
+        };
+
+        #[test]
+        fn test_create_content_builder() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let builder = builder.unwrap();
+            assert_eq!(builder.id, "chapter1");
+        }
+
+        #[test]
+        fn test_set_title() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder.set_title("My Chapter").set_title("Another Title");
+
+            assert_eq!(builder.title, "Another Title");
+        }
+
+        #[test]
+        fn test_add_text_block() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
             let result = builder.add_text_block("This is a paragraph", vec![]);
             assert!(result.is_ok());
         }
@@ -2012,7 +4081,9 @@ mod tests {
             let custom_styles = crate::types::StyleOptions {
                 text: TextStyle {
                     font_size: 1.5,
+                    font_size_unit: SizeUnit::Rem,
                     line_height: 1.8,
+                    line_height_unit: Some(SizeUnit::Em),
                     font_family: "Georgia, serif".to_string(),
                     font_weight: "bold".to_string(),
                     font_style: "italic".to_string(),
@@ -2183,11 +4254,11 @@ mod tests {
 
             let footnotes = vec![
                 Footnote {
-                    locate: 10,
+                    locate: FootnoteAnchor::CharIndex(10),
                     content: "This is a footnote".to_string(),
                 },
                 Footnote {
-                    locate: 15,
+                    locate: FootnoteAnchor::CharIndex(15),
                     content: "Another footnote".to_string(),
                 },
             ];
@@ -2208,74 +4279,1408 @@ mod tests {
         }
 
         #[test]
-        fn test_add_css_file() {
+        fn test_make_content_with_footnote_heading() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+            let footnotes = vec![Footnote {
+                locate: FootnoteAnchor::CharIndex(4),
+                content: "This is a footnote".to_string(),
+            }];
+
             let builder = ContentBuilder::new("chapter1", "en");
             assert!(builder.is_ok());
 
             let mut builder = builder.unwrap();
-            let result = builder.add_css_file(PathBuf::from("./test_case/style.css"));
+            builder
+                .add_text_block("This is a paragraph.", footnotes)
+                .unwrap();
 
-            assert!(result.is_ok());
-            assert_eq!(builder.css_files.len(), 1);
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(!content.contains("footnote-heading"));
+
+            builder.set_footnote_heading(Some("Notes"));
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(
+                r#"<aside><h2 class="footnote-heading" epub:type="footnotes">Notes</h2><ul"#
+            ));
+            assert!(content.contains(r#"xmlns:epub="http://www.idpf.org/2007/ops""#));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
         }
 
         #[test]
-        fn test_add_css_file_nonexistent() {
+        fn test_make_content_with_body_type() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
             let builder = ContentBuilder::new("chapter1", "en");
             assert!(builder.is_ok());
 
             let mut builder = builder.unwrap();
-            let result = builder.add_css_file(PathBuf::from("nonexistent.css"));
-            assert!(result.is_err());
+            builder
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains("<body><main>"));
+            assert!(!content.contains("xmlns:epub"));
+
+            builder.set_body_type("bodymatter");
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(r#"<body epub:type="bodymatter"><main>"#));
+            assert!(content.contains(r#"xmlns:epub="http://www.idpf.org/2007/ops""#));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
         }
 
         #[test]
-        fn test_add_multiple_css_files() {
+        fn test_make_content_with_xml_declaration() {
             let temp_dir = env::temp_dir().join(local_time());
             assert!(fs::create_dir_all(&temp_dir).is_ok());
 
-            let css_path1 = temp_dir.join("style1.css");
-            let css_path2 = temp_dir.join("style2.css");
-            assert!(fs::write(&css_path1, "body { color: red; }").is_ok());
-            assert!(fs::write(&css_path2, "p { font-size: 16px; }").is_ok());
+            let output_path = temp_dir.join("chapter.xhtml");
 
             let builder = ContentBuilder::new("chapter1", "en");
             assert!(builder.is_ok());
 
             let mut builder = builder.unwrap();
-            assert!(builder.add_css_file(css_path1).is_ok());
-            assert!(builder.add_css_file(css_path2).is_ok());
+            builder
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
 
-            assert_eq!(builder.css_files.len(), 2);
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><html"#));
+
+            builder.set_xml_declaration(Some(XmlDecl::new().with_standalone(Some("no")).build()));
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(
+                content
+                    .starts_with(r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?><html"#)
+            );
+
+            builder.set_xml_declaration(None);
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.starts_with("<html"));
 
             assert!(fs::remove_dir_all(&temp_dir).is_ok());
         }
-    }
 
-    mod block_tests {
-        use std::path::PathBuf;
+        #[test]
+        fn test_make_content_with_svg_image() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            // SVG content is detected regardless of the file extension
+            let svg_path = temp_dir.join("diagram");
+            fs::copy("./test_case/image.svg", &svg_path).unwrap();
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_image_block(PathBuf::from("./test_case/image.svg"), None, None, vec![])
+                .unwrap()
+                .add_image_block(svg_path, None, None, vec![])
+                .unwrap();
 
-        use crate::{builder::content::Block, types::Footnote};
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(r#"<img src="./img/image.svg"/>"#));
+            assert!(content.contains(r#"<img src="./img/diagram"/>"#));
+            assert!(temp_dir.join("img/image.svg").exists());
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
 
         #[test]
-        fn test_take_footnotes_from_text_block() {
-            let footnotes = vec![Footnote { locate: 5, content: "Note".to_string() }];
+        fn test_make_content_with_figure_group() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let photo_path = temp_dir.join("photo.jpg");
+            fs::copy("./test_case/image.jpg", &photo_path).unwrap();
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            let result = builder.add_figure_group(
+                vec![
+                    (
+                        PathBuf::from("./test_case/image.jpg"),
+                        Some("First".to_string()),
+                    ),
+                    (PathBuf::from("./test_case/image.svg"), None),
+                    (photo_path, Some("Third".to_string())),
+                ],
+                Some("A small gallery".to_string()),
+                vec![Footnote {
+                    locate: FootnoteAnchor::CharIndex(7),
+                    content: "Gallery note".to_string(),
+                }],
+            );
+            assert!(result.is_ok());
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+
+            let start = content
+                .find(r#"<figure class="content-block gallery-block">"#)
+                .unwrap();
+            let end = start + content[start..].find("</figure>").unwrap();
+            let gallery = &content[start..end];
+
+            assert_eq!(gallery.matches("<img ").count(), 3);
+            assert_eq!(gallery.matches("<figcaption>").count(), 1);
+            assert!(gallery.contains(r#"<img src="./img/image.jpg" alt="First"/>"#));
+            assert!(gallery.contains(r#"<img src="./img/image.svg"/>"#));
+            assert!(gallery.contains(r#"<img src="./img/photo.jpg" alt="Third"/>"#));
+            assert!(gallery.find("photo.jpg").unwrap() < gallery.find("<figcaption>").unwrap());
+            assert!(gallery.contains(r##"<figcaption>A small<a href="#footnote-1""##));
+            assert!(content.contains("Gallery note"));
+
+            assert!(temp_dir.join("img/image.jpg").exists());
+            assert!(temp_dir.join("img/image.svg").exists());
+            assert!(temp_dir.join("img/photo.jpg").exists());
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_sanitized_resource_names() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let spaced_path = temp_dir.join("my photo (1).png");
+            let dashed_path = temp_dir.join("my-photo-1.png");
+            fs::copy("./test_case/image.jpg", &spaced_path).unwrap();
+            fs::copy("./test_case/image.jpg", &dashed_path).unwrap();
+
+            let output_path = temp_dir.join("output").join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder.set_sanitize_resource_names(true);
+            assert!(
+                builder
+                    .add_image_block(spaced_path, None, None, vec![])
+                    .is_ok()
+            );
+            assert!(
+                builder
+                    .add_image_block(dashed_path, None, None, vec![])
+                    .is_ok()
+            );
+
+            let result = builder.make(&output_path);
+            assert!(result.is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+
+            assert!(content.contains(r#"<img src="./img/my-photo-1.png"/>"#));
+            assert!(content.contains(r#"<img src="./img/my-photo-1-1.png"/>"#));
+            assert!(!content.contains("my photo (1)"));
+
+            let output_dir = temp_dir.join("output");
+            assert!(output_dir.join("img/my-photo-1.png").exists());
+            assert!(output_dir.join("img/my-photo-1-1.png").exists());
+            assert!(!output_dir.join("img/my photo (1).png").exists());
+
+            let manifest = result.unwrap();
+            assert!(
+                manifest
+                    .iter()
+                    .any(|path| path.ends_with("img/my-photo-1.png"))
+            );
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_merge_content_builders() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(temp_dir.join("first")).is_ok());
+            assert!(fs::create_dir_all(temp_dir.join("second")).is_ok());
+
+            // both images are named image.jpg, but come from different sources
+            let first_image = temp_dir.join("first").join("image.jpg");
+            let second_image = temp_dir.join("second").join("image.jpg");
+            fs::copy("./test_case/image.jpg", &first_image).unwrap();
+            fs::copy("./test_case/image.jpg", &second_image).unwrap();
+
+            let footnote = |index, content: &str| Footnote {
+                locate: FootnoteAnchor::CharIndex(index),
+                content: content.to_string(),
+            };
+
+            let mut builder = ContentBuilder::new("chapter1", "en").unwrap();
+            builder
+                .add_text_block("First section.", vec![footnote(5, "First note.")])
+                .unwrap()
+                .add_image_block(first_image.clone(), None, None, vec![])
+                .unwrap();
+
+            let mut other = ContentBuilder::new("chapter2", "en").unwrap();
+            other
+                .add_text_block("Second section.", vec![footnote(6, "Second note.")])
+                .unwrap()
+                .add_image_block(
+                    second_image,
+                    None,
+                    Some("Caption".to_string()),
+                    vec![footnote(7, "Third note.")],
+                )
+                .unwrap()
+                .add_image_block(first_image, None, None, vec![])
+                .unwrap();
+            let other_temp_dir = other.temp_dir.clone();
+
+            assert!(builder.merge(other).is_ok());
+            assert_eq!(builder.block_count(), 5);
+            assert!(!other_temp_dir.exists());
+
+            let output_path = temp_dir.join("output").join("chapter.xhtml");
+            let result = builder.make(&output_path);
+            assert!(result.is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+
+            for index in 1..=3 {
+                assert!(content.contains(&format!(r##"<a href="#ref-{index}">[{index}]</a>"##)));
+            }
+            assert!(!content.contains("footnote-4"));
+            assert!(content.contains(r#"<img src="./img/image.jpg"/>"#));
+            assert!(content.contains(r#"<img src="./img/image-1.jpg"/>"#));
+
+            let output_dir = temp_dir.join("output");
+            assert!(output_dir.join("img/image.jpg").exists());
+            assert!(output_dir.join("img/image-1.jpg").exists());
+            assert!(!output_dir.join("img/image-2.jpg").exists());
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_add_figure_group_invalid() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            let result = builder.add_figure_group(vec![], None, vec![]);
+            assert_eq!(
+                result.err().unwrap(),
+                EpubBuilderError::MissingNecessaryBlockData {
+                    block_type: "Gallery".to_string(),
+                    missing_data: "'images'".to_string(),
+                }
+                .into()
+            );
+
+            let result = builder.add_figure_group(
+                vec![
+                    (PathBuf::from("./test_case/image.jpg"), None),
+                    (PathBuf::from("./test_case/audio.mp3"), None),
+                ],
+                None,
+                vec![],
+            );
+            assert!(result.is_err());
+            assert_eq!(builder.block_count(), 0);
+        }
+
+        #[test]
+        fn test_validate() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_text_block("Valid text", vec![])
+                .unwrap()
+                .add_image_block(PathBuf::from("./test_case/image.jpg"), None, None, vec![])
+                .unwrap();
+            assert!(builder.validate().is_empty());
 
             let block = Block::Text {
-                content: "Hello world".to_string(),
-                footnotes: footnotes.clone(),
+                content: "Short".to_string(),
+                footnotes: vec![Footnote {
+                    locate: FootnoteAnchor::CharIndex(10),
+                    content: "Out of range".to_string(),
+                }],
             };
+            builder.add_block(block).unwrap();
+            assert_eq!(
+                builder.validate(),
+                vec![EpubBuilderError::InvalidFootnoteLocate {
+                    max_locate: 5,
+                    unit: "characters".to_string()
+                }]
+            );
 
-            let taken = block.take_footnotes();
-            assert_eq!(taken.len(), 1);
-            assert_eq!(taken[0].content, "Note");
+            // the copied media resources are removed with the temporary directory
+            assert!(builder.cleanup().is_ok());
+            assert_eq!(
+                builder.validate(),
+                vec![
+                    EpubBuilderError::TargetIsNotFile {
+                        target_path: "./test_case/image.jpg".to_string(),
+                    },
+                    EpubBuilderError::InvalidFootnoteLocate {
+                        max_locate: 5,
+                        unit: "characters".to_string()
+                    },
+                ]
+            );
         }
 
         #[test]
-        fn test_take_footnotes_from_quote_block() {
-            let footnotes = vec![
-                Footnote { locate: 3, content: "First".to_string() },
-                Footnote { locate: 8, content: "Second".to_string() },
+        fn test_make_content_with_popup_footnotes() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .set_footnote_mode(FootnoteMode::Popup)
+                .add_text_block(
+                    "Hello world",
+                    vec![
+                        Footnote {
+                            locate: FootnoteAnchor::CharIndex(5),
+                            content: "First note".to_string(),
+                        },
+                        Footnote {
+                            locate: FootnoteAnchor::CharIndex(11),
+                            content: "Second note".to_string(),
+                        },
+                    ],
+                )
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+
+            assert!(content.contains(r#"xmlns:epub="http://www.idpf.org/2007/ops""#));
+            assert!(content.contains(
+                r##"<a href="#footnote-1" id="ref-1" class="footnote-ref" epub:type="noteref">[1]</a>"##
+            ));
+            assert!(content.contains(
+                r##"<a href="#footnote-2" id="ref-2" class="footnote-ref" epub:type="noteref">[2]</a>"##
+            ));
+            assert!(
+                content.contains(
+                    r#"<aside id="footnote-1" class="footnote-item" epub:type="footnote">"#
+                )
+            );
+            assert!(
+                content.contains(
+                    r#"<aside id="footnote-2" class="footnote-item" epub:type="footnote">"#
+                )
+            );
+            assert!(!content.contains("<li"));
+
+            // EPUB 2 documents fall back to the list mode
+            builder.set_target_version(EpubVersion::Version2_0);
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(!content.contains("epub:type"));
+            assert!(content.contains(r#"<li id="footnote-1" class="footnote-item">"#));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_chapter_number() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter3", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .set_title("Forces")
+                .add_title_block(
+                    "Forces",
+                    1,
+                    vec![Footnote {
+                        locate: FootnoteAnchor::GraphemeIndex(6),
+                        content: "Title note".to_string(),
+                    }],
+                )
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains("<title>Forces</title>"));
+
+            builder
+                .set_chapter_number(3)
+                .set_chapter_label_format("Chapter {n}: {title}");
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains("<title>Chapter 3: Forces</title>"));
+            assert!(content.contains(r#"<h1 class="content-block title-block">Forces<a"#));
+
+            builder.set_label_title_block(true);
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(
+                r##"<h1 class="content-block title-block">Chapter 3: Forces<a href="#footnote-1""##
+            ));
+
+            // the label is followed by the title if the format has no placeholder
+            builder.set_chapter_label_format("§{n}.");
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains("<title>§3. Forces</title>"));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_resource_dirs() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_image_block(PathBuf::from("./test_case/image.jpg"), None, None, vec![])
+                .unwrap()
+                .add_audio_block(
+                    PathBuf::from("./test_case/audio.mp3"),
+                    "Audio".to_string(),
+                    None,
+                    vec![],
+                )
+                .unwrap()
+                .add_css_file(PathBuf::from("./test_case/style.css"))
+                .unwrap();
+
+            // the directories can be changed after the resources are added
+            let result = builder.set_resource_dirs(ResourceDirs {
+                image: "images".to_string(),
+                audio: "media/audio".to_string(),
+                ..Default::default()
+            });
+            assert!(result.is_ok());
+
+            let resources = builder.make(&output_path);
+            assert!(resources.is_ok());
+            let resources = resources.unwrap();
+            assert!(resources.contains(&temp_dir.join("images/image.jpg")));
+            assert!(resources.contains(&temp_dir.join("media/audio/audio.mp3")));
+            assert!(resources.contains(&temp_dir.join("css/style.css")));
+            assert!(!temp_dir.join("img").exists());
+
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(r#"<img src="./images/image.jpg"/>"#));
+            assert!(content.contains(r#"<audio src="./media/audio/audio.mp3""#));
+            assert!(content.contains(r#"<link href="./css/style.css""#));
+
+            for invalid in ["", "/images", "../images", "images/./jpg"] {
+                let result = builder.set_resource_dirs(ResourceDirs {
+                    image: invalid.to_string(),
+                    ..Default::default()
+                });
+                assert!(result.is_err());
+            }
+            assert_eq!(builder.resource_dirs.image, "images");
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_same_file_names() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let first_image = temp_dir.join("first/image.jpg");
+            let second_image = temp_dir.join("second/image.jpg");
+            for image in [&first_image, &second_image] {
+                fs::create_dir_all(image.parent().unwrap()).unwrap();
+                fs::copy("./test_case/image.jpg", image).unwrap();
+            }
+
+            let output_path = temp_dir.join("output/chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_image_block(first_image.clone(), None, None, vec![])
+                .unwrap()
+                .add_image_block(second_image, None, None, vec![])
+                .unwrap()
+                .add_image_block(first_image, None, None, vec![])
+                .unwrap();
+
+            let resources = builder.make(&output_path);
+            assert!(resources.is_ok());
+            let resources = resources.unwrap();
+            assert_eq!(resources.len(), 3);
+            assert!(resources.contains(&temp_dir.join("output/img/image.jpg")));
+            assert!(resources.contains(&temp_dir.join("output/img/image-1.jpg")));
+
+            // the same source keeps the name it was given first
+            let content = fs::read_to_string(&output_path).unwrap();
+            let sources = content
+                .match_indices("<img src=\"")
+                .map(|(index, _)| content[index + 10..].split('"').next().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                sources,
+                vec!["./img/image.jpg", "./img/image-1.jpg", "./img/image.jpg"]
+            );
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_manifest() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("text/chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_title_block("Chapter 1", 1, vec![])
+                .unwrap()
+                .add_image_block(PathBuf::from("./test_case/image.jpg"), None, None, vec![])
+                .unwrap();
+
+            let manifest = builder.make_with_manifest(&output_path);
+            assert!(manifest.is_ok());
+
+            let manifest = manifest
+                .unwrap()
+                .into_iter()
+                .map(|item| (item.id, item.path, item.mime))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                manifest,
+                vec![
+                    (
+                        "chapter1".to_string(),
+                        PathBuf::from("chapter.xhtml"),
+                        "application/xhtml+xml".to_string()
+                    ),
+                    (
+                        "chapter1-image.jpg".to_string(),
+                        PathBuf::from("img/image.jpg"),
+                        "image/jpeg".to_string()
+                    ),
+                ]
+            );
+            assert!(temp_dir.join("text/img/image.jpg").is_file());
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_for_epub2() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+            let footnotes = vec![Footnote {
+                locate: FootnoteAnchor::CharIndex(4),
+                content: "Aside note".to_string(),
+            }];
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .set_target_version(EpubVersion::Version2_0)
+                .set_body_type("bodymatter")
+                .set_footnote_heading(Some("Notes"))
+                .add_aside_block("A sidebar about history.", Some("pullquote"), footnotes)
+                .unwrap()
+                .add_image_block(
+                    PathBuf::from("./test_case/image.jpg"),
+                    None,
+                    Some("Caption".to_string()),
+                    vec![],
+                )
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.starts_with(
+                r#"<?xml version="1.0" encoding="UTF-8"?><!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd"><html"#
+            ));
+            assert!(content.contains(r#"<style type="text/css">"#));
+            assert!(content.contains("<body><div>"));
+            assert!(content.contains(r#"<div class="content-block aside-block"><p>"#));
+            assert!(content.contains(r#"<div class="content-block image-block"><img"#));
+            assert!(content.contains(r#"<p class="caption">Caption</p>"#));
+            assert!(content.contains(r#"<div><h2 class="footnote-heading">Notes</h2>"#));
+            assert!(!content.contains("epub:"));
+            assert!(!content.contains("<aside"));
+            assert!(!content.contains("<figure"));
+            assert!(!content.contains("<main"));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_aside_block() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+            let footnotes = vec![Footnote {
+                locate: FootnoteAnchor::WordIndex(2),
+                content: "Aside note".to_string(),
+            }];
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_aside_block("A sidebar about history.", Some("pullquote"), footnotes)
+                .unwrap()
+                .add_aside_block("Another sidebar.", None, vec![])
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(r#"xmlns:epub="http://www.idpf.org/2007/ops""#));
+            assert!(content.contains(
+                r##"<aside class="content-block aside-block" epub:type="pullquote"><p>A sidebar<a href="#footnote-1""##
+            ));
+            assert!(content.contains(
+                r#"<aside class="content-block aside-block" epub:type="sidebar"><p>Another sidebar.</p></aside>"#
+            ));
+            assert!(content.contains("Aside note"));
+
+            // footnotes are validated against the aside content
+            let result = builder.add_aside_block(
+                "Short.",
+                None,
+                vec![Footnote {
+                    locate: FootnoteAnchor::WordIndex(2),
+                    content: "Invalid".to_string(),
+                }],
+            );
+            assert!(result.is_err());
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_unbalanced_mathml() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_mathml_block(
+                    r#"<math xmlns="http://www.w3.org/1998/Math/MathML"><mi>x</mi>"#.to_string(),
+                    None,
+                    None,
+                    vec![],
+                )
+                .unwrap();
+
+            let result = builder.make(&output_path);
+            assert!(result.is_err());
+            assert!(matches!(
+                result.unwrap_err(),
+                EpubError::EpubBuilderError {
+                    source: EpubBuilderError::GeneratedMalformedXml { .. }
+                }
+            ));
+            assert!(!output_path.exists());
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_ruby_block() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "ja");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_ruby_block(vec![
+                    RubyRun {
+                        base: "漢字".to_string(),
+                        annotation: Some("かんじ".to_string()),
+                    },
+                    RubyRun {
+                        base: "を".to_string(), annotation: None
+                    },
+                    RubyRun {
+                        base: "勉強".to_string(),
+                        annotation: Some("べんきょう".to_string()),
+                    },
+                    RubyRun {
+                        base: "する。".to_string(),
+                        annotation: None,
+                    },
+                ])
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(
+                r#"<p class="content-block ruby-block"><ruby>漢字<rt>かんじ</rt></ruby>を<ruby>勉強<rt>べんきょう</rt></ruby>する。</p>"#
+            ));
+
+            let result = builder.add_ruby_block(vec![]);
+            assert!(result.is_err());
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_raw_html() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            let fragment = r#"<p class="note">Hi <b>there</b> &amp; welcome</p><hr/>"#;
+            assert!(builder.add_raw_html(fragment).is_ok());
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(fragment));
+
+            for invalid in ["", "<p>unclosed", "</p>", "<p><b>crossed</p></b>"] {
+                let result = builder.add_raw_html(invalid);
+                assert!(result.is_err());
+            }
+            assert_eq!(builder.blocks.len(), 1);
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_insert_and_swap_blocks() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_text_block("First paragraph.", vec![])
+                .unwrap()
+                .add_text_block("Second paragraph.", vec![])
+                .unwrap();
+
+            let mut title = BlockBuilder::new(BlockType::Title);
+            title.set_content("Generated Title").set_title_level(1);
+            assert!(
+                builder
+                    .insert_block(0, Block::try_from(title).unwrap())
+                    .is_ok()
+            );
+
+            let mut image = BlockBuilder::new(BlockType::Image);
+            image
+                .set_url(&PathBuf::from("./test_case/image.jpg"))
+                .unwrap();
+            assert!(
+                builder
+                    .insert_block(2, Block::try_from(image).unwrap())
+                    .is_ok()
+            );
+            assert!(builder.temp_dir.join("img/image.jpg").is_file());
+
+            assert!(matches!(builder.blocks[0], Block::Title { .. }));
+            assert!(
+                matches!(&builder.blocks[1], Block::Text { content, .. } if content == "First paragraph.")
+            );
+            assert!(matches!(builder.blocks[2], Block::Image { .. }));
+            assert!(
+                matches!(&builder.blocks[3], Block::Text { content, .. } if content == "Second paragraph.")
+            );
+
+            assert!(builder.swap_blocks(1, 3).is_ok());
+            assert!(
+                matches!(&builder.blocks[1], Block::Text { content, .. } if content == "Second paragraph.")
+            );
+            assert!(
+                matches!(&builder.blocks[3], Block::Text { content, .. } if content == "First paragraph.")
+            );
+
+            let mut text = BlockBuilder::new(BlockType::Text);
+            text.set_content("Out of range");
+            let result = builder.insert_block(5, Block::try_from(text).unwrap());
+            assert_eq!(
+                result.err().unwrap(),
+                EpubBuilderError::BlockIndexOutOfRange { index: 5, len: 4 }.into()
+            );
+
+            let result = builder.swap_blocks(0, 4);
+            assert_eq!(
+                result.err().unwrap(),
+                EpubBuilderError::BlockIndexOutOfRange { index: 4, len: 4 }.into()
+            );
+        }
+
+        #[test]
+        fn test_inspect_blocks() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            assert_eq!(builder.block_count(), 0);
+            assert!(builder.blocks().is_empty());
+
+            builder
+                .add_title_block("Chapter", 1, vec![])
+                .unwrap()
+                .add_text_block("A paragraph.", vec![])
+                .unwrap()
+                .add_quote_block("A quote.", vec![])
+                .unwrap();
+
+            assert_eq!(builder.block_count(), 3);
+            let blocks = builder.blocks();
+            assert!(matches!(blocks[0], Block::Title { level: 1, .. }));
+            assert!(matches!(blocks[1], Block::Text { .. }));
+            assert!(matches!(blocks[2], Block::Quote { .. }));
+        }
+
+        #[test]
+        fn test_create_many_content_builders() {
+            let builders = (0..100)
+                .map(|index| ContentBuilder::new(&format!("chapter{index}"), "en"))
+                .collect::<Result<Vec<ContentBuilder>, EpubError>>();
+            assert!(builders.is_ok());
+
+            let builders = builders.unwrap();
+            assert!(builders.iter().all(|builder| builder.temp_dir.is_dir()));
+        }
+
+        #[test]
+        fn test_content_builder_with_temp_dir() {
+            let dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&dir).is_ok());
+
+            let builder = ContentBuilder::with_temp_dir("chapter1", "en", &dir);
+            assert!(builder.is_ok());
+
+            let builder = builder.unwrap();
+            let temp_dir = builder.temp_dir.clone();
+            assert_eq!(temp_dir.parent(), Some(dir.as_path()));
+            assert!(temp_dir.is_dir());
+
+            drop(builder);
+            assert!(!temp_dir.exists());
+            assert!(dir.is_dir());
+
+            assert!(fs::remove_dir_all(&dir).is_ok());
+        }
+
+        #[test]
+        fn test_content_builder_cleanup() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_css_file(PathBuf::from("./test_case/style.css"))
+                .unwrap();
+            assert!(builder.temp_dir.is_dir());
+
+            assert!(builder.cleanup().is_ok());
+            assert!(!builder.temp_dir.exists());
+
+            // cleanup is idempotent, and dropping afterwards does nothing
+            assert!(builder.cleanup().is_ok());
+            assert!(builder.temp_dir_removed);
+
+            // reusing the builder recreates the temporary directory, which is removed on drop
+            builder
+                .add_css_file(PathBuf::from("./test_case/style.css"))
+                .unwrap();
+            assert!(builder.temp_dir.is_dir());
+            assert!(!builder.temp_dir_removed);
+
+            let temp_dir = builder.temp_dir.clone();
+            drop(builder);
+            assert!(!temp_dir.exists());
+        }
+
+        #[test]
+        fn test_add_css_file() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            let result = builder.add_css_file(PathBuf::from("./test_case/style.css"));
+
+            assert!(result.is_ok());
+            assert_eq!(builder.css_files.len(), 1);
+        }
+
+        #[test]
+        fn test_make_content_with_inline_style() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_css_file(PathBuf::from("./test_case/style.css"))
+                .unwrap()
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
+
+            // the generated style is dropped by default when a css file is added
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(r#"<link href="./css/style.css""#));
+            assert!(!content.contains("<style>"));
+
+            builder.set_inline_style(true);
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(r#"<link href="./css/style.css""#));
+            assert!(content.contains("<style>"));
+
+            builder.set_inline_style(false);
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(!content.contains("<style>"));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_minified_css() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .set_minify_css(true)
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            let start = content.find("<style>").unwrap() + "<style>".len();
+            let end = content.find("</style>").unwrap();
+            let style = &content[start..end];
+
+            assert!(!style.contains('\n'));
+            assert!(!style.contains("  "));
+            assert!(style.contains("body{margin:"));
+            assert!(style.contains("blockquote&gt;p{font-style:italic;}"));
+            assert!(style.contains(".footnote-ref{"));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_class_prefix() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter3", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .set_class_prefix("ch3")
+                .set_footnote_heading(Some("Notes"))
+                .add_text_block(
+                    "This is a paragraph.",
+                    vec![Footnote {
+                        locate: FootnoteAnchor::CharIndex(4),
+                        content: "A note".to_string(),
+                    }],
+                )
+                .unwrap()
+                .add_image_block(
+                    PathBuf::from("./test_case/image.jpg"),
+                    None,
+                    Some("Caption".to_string()),
+                    vec![],
+                )
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+
+            assert!(content.contains(r#"<p class="ch3-content-block ch3-text-block">"#));
+            assert!(content.contains(r#"<figure class="ch3-content-block ch3-image-block">"#));
+            assert!(content.contains(r#"class="ch3-footnote-ref""#));
+            assert!(content.contains(r#"<h2 class="ch3-footnote-heading""#));
+            assert!(content.contains(r#"<ul class="ch3-footnote-list">"#));
+            assert!(content.contains(r#"class="ch3-footnote-item""#));
+            assert!(!content.contains(r#"class="content-block"#));
+
+            let start = content.find("<style>").unwrap() + "<style>".len();
+            let end = content.find("</style>").unwrap();
+            let style = &content[start..end];
+
+            assert!(style.contains(".ch3-content-block {"));
+            assert!(style.contains(".ch3-image-block &gt; img"));
+            assert!(style.contains(".ch3-footnote-ref {"));
+            assert!(style.contains(".ch3-footnote-item &gt; p {"));
+            assert!(!style.contains(".content-block"));
+
+            // element rules are scoped under the root class of the body
+            assert!(content.contains(r#"<body class="ch3-root">"#));
+            assert!(style.contains(".ch3-root, .ch3-root * {"));
+            assert!(style.contains(".ch3-root { margin: "));
+            assert!(style.contains(".ch3-root p { text-indent: "));
+            assert!(style.contains(".ch3-root a { color: "));
+            assert!(style.contains(".ch3-root blockquote &gt; p {"));
+            assert!(!style.contains("\n            * {"));
+            assert!(!style.contains("body"));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_size_units() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let mut builder = ContentBuilder::new("chapter1", "en").unwrap();
+            builder.set_styles(StyleOptions {
+                text: TextStyle::new()
+                    .with_font_size(16.0)
+                    .with_font_size_unit(SizeUnit::Px)
+                    .with_line_height(1.5)
+                    .with_line_height_unit(None)
+                    .build(),
+                ..StyleOptions::default()
+            });
+            builder
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains("font-size: 16px;"));
+            assert!(content.contains("line-height: 1.5;"));
+            assert!(!content.contains("font-size: 16rem;"));
+
+            // the default units are kept
+            let mut builder = ContentBuilder::new("chapter2", "en").unwrap();
+            builder
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains("font-size: 1rem;"));
+            assert!(content.contains("line-height: 1.6em;"));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_drop_cap() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+            let selector = ".content-block.text-block:first-of-type::first-letter";
+
+            let mut builder = ContentBuilder::new("chapter1", "en").unwrap();
+            builder
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
+
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(!content.contains("::first-letter"));
+
+            builder.set_drop_cap(true);
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(&format!(
+                "{selector} {{ font-size: 3em; float: left; line-height: 1; margin-right: 0.1em; }}"
+            )));
+
+            builder
+                .set_drop_cap_size(2.5)
+                .set_drop_cap_float(false)
+                .set_class_prefix("ch1");
+            assert!(builder.make(&output_path).is_ok());
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(
+                ".ch1-content-block.ch1-text-block:first-of-type::first-letter { font-size: 2.5em; float: none; }"
+            ));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_font_face() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let output_path = temp_dir.join("chapter.xhtml");
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            let mut styles = StyleOptions::default();
+            styles.text.font_family = "Le Murmure, serif".to_string();
+
+            builder
+                .set_styles(styles)
+                .add_font_face(FontFace {
+                    family: "Le Murmure".to_string(),
+                    src: PathBuf::from("./test_case/le-murmure.otf"),
+                    weight: None,
+                    style: Some("italic".to_string()),
+                })
+                .unwrap()
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
+
+            let result = builder.make(&output_path);
+            assert!(result.is_ok());
+            assert!(
+                result
+                    .unwrap()
+                    .contains(&temp_dir.join("fonts/le-murmure.otf"))
+            );
+
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains("@font-face"));
+            assert!(content.contains("font-family: &quot;Le Murmure&quot;;"));
+            assert!(content.contains("src: url(&quot;./fonts/le-murmure.otf&quot;);"));
+            assert!(content.contains("font-style: italic;"));
+            assert!(content.contains("font-family: &quot;Le Murmure&quot;, serif;"));
+
+            let result = builder.add_font_face(FontFace {
+                family: "Missing".to_string(),
+                src: PathBuf::from("./test_case/missing.otf"),
+                weight: None,
+                style: None,
+            });
+            assert!(result.is_err());
+
+            // a font face whose file was never copied must not produce an empty url
+            builder.font_faces.push(FontFace {
+                family: "Uncopied".to_string(),
+                src: PathBuf::from("./test_case/uncopied.otf"),
+                weight: None,
+                style: None,
+            });
+            let result = builder.make(&output_path);
+            assert_eq!(
+                result.unwrap_err(),
+                EpubBuilderError::TargetIsNotFile {
+                    target_path: "./test_case/uncopied.otf".to_string()
+                }
+                .into()
+            );
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_make_content_with_quoted_font_url() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let font_path = temp_dir.join("le \"murmure\" (italic).otf");
+            assert!(fs::copy("./test_case/le-murmure.otf", &font_path).is_ok());
+
+            let mut builder = ContentBuilder::new("chapter1", "en").unwrap();
+            builder
+                .add_font_face(FontFace {
+                    family: "Le Murmure".to_string(),
+                    src: font_path,
+                    weight: None,
+                    style: None,
+                })
+                .unwrap()
+                .add_text_block("This is a paragraph.", vec![])
+                .unwrap();
+
+            let output_path = temp_dir.join("chapter.xhtml");
+            assert!(builder.make(&output_path).is_ok());
+
+            let content = fs::read_to_string(&output_path).unwrap();
+            assert!(content.contains(
+                r#"src: url(&quot;./fonts/le \&quot;murmure\&quot; (italic).otf&quot;);"#
+            ));
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+
+        #[test]
+        fn test_remove_css_files() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            builder
+                .add_css_file(PathBuf::from("./test_case/style.css"))
+                .unwrap()
+                .add_css_file(PathBuf::from("./test_case/Overview.xhtml"))
+                .unwrap();
+            assert_eq!(builder.css_files.len(), 2);
+
+            let last = builder.css_files[1].clone();
+            assert!(builder.remove_last_css_file().is_ok());
+            assert_eq!(builder.css_files.len(), 1);
+            assert!(!last.exists());
+
+            // the copy removed by someone else is reported, and the state is kept
+            assert!(fs::remove_file(&builder.css_files[0]).is_ok());
+            let result = builder.remove_last_css_file();
+            assert!(matches!(result, Err(EpubError::IOError { .. })));
+            assert_eq!(builder.css_files.len(), 1);
+
+            let result = builder.clear_css_files();
+            assert!(matches!(result, Err(EpubError::IOError { .. })));
+            assert_eq!(builder.css_files.len(), 1);
+
+            builder
+                .add_css_file(PathBuf::from("./test_case/style.css"))
+                .unwrap();
+            builder.css_files.remove(0);
+            assert!(builder.clear_css_files().is_ok());
+            assert!(builder.css_files.is_empty());
+            assert!(builder.remove_last_css_file().is_ok());
+        }
+
+        #[test]
+        fn test_add_css_file_nonexistent() {
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            let result = builder.add_css_file(PathBuf::from("nonexistent.css"));
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_add_multiple_css_files() {
+            let temp_dir = env::temp_dir().join(local_time());
+            assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+            let css_path1 = temp_dir.join("style1.css");
+            let css_path2 = temp_dir.join("style2.css");
+            assert!(fs::write(&css_path1, "body { color: red; }").is_ok());
+            assert!(fs::write(&css_path2, "p { font-size: 16px; }").is_ok());
+
+            let builder = ContentBuilder::new("chapter1", "en");
+            assert!(builder.is_ok());
+
+            let mut builder = builder.unwrap();
+            assert!(builder.add_css_file(css_path1).is_ok());
+            assert!(builder.add_css_file(css_path2).is_ok());
+
+            assert_eq!(builder.css_files.len(), 2);
+
+            assert!(fs::remove_dir_all(&temp_dir).is_ok());
+        }
+    }
+
+    mod block_tests {
+        use std::path::PathBuf;
+
+        use crate::{
+            builder::content::Block,
+            types::{Footnote, FootnoteAnchor},
+        };
+
+        #[test]
+        fn test_take_footnotes_from_text_block() {
+            let footnotes = vec![Footnote {
+                locate: FootnoteAnchor::CharIndex(5),
+                content: "Note".to_string(),
+            }];
+
+            let block = Block::Text {
+                content: "Hello world".to_string(),
+                footnotes: footnotes.clone(),
+            };
+
+            let taken = block.take_footnotes();
+            assert_eq!(taken.len(), 1);
+            assert_eq!(taken[0].content, "Note");
+        }
+
+        #[test]
+        fn test_take_footnotes_from_quote_block() {
+            let footnotes = vec![
+                Footnote {
+                    locate: FootnoteAnchor::CharIndex(3),
+                    content: "First".to_string(),
+                },
+                Footnote {
+                    locate: FootnoteAnchor::CharIndex(8),
+                    content: "Second".to_string(),
+                },
             ];
 
             let block = Block::Quote {
@@ -2291,7 +5696,7 @@ mod tests {
         fn test_take_footnotes_from_image_block() {
             let img_path = PathBuf::from("test.png");
             let footnotes = vec![Footnote {
-                locate: 2,
+                locate: FootnoteAnchor::CharIndex(2),
                 content: "Image note".to_string(),
             }];
 
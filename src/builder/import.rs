@@ -0,0 +1,560 @@
+//! HTML import functionality
+//!
+//! This module converts an existing HTML document into content documents, splitting
+//! it into chapters at the headings of a chosen level. Each chapter is returned as a
+//! [`ContentBuilder`], so that it can be styled and made like any other content.
+//!
+//! ## Usage
+//! ``` rust, no_run
+//! # #[cfg(feature = "content-builder")] {
+//! # fn main() -> Result<(), lib_epub::error::EpubError> {
+//! use lib_epub::{builder::import::from_html, types::HeadingLevel};
+//!
+//! let html = std::fs::read_to_string("book/index.html")?;
+//! let chapters = from_html(&html, HeadingLevel::H1, "book")?;
+//! for (index, mut chapter) in chapters.into_iter().enumerate() {
+//!     chapter.make(format!("chapter_{}.xhtml", index + 1))?;
+//! }
+//! # Ok(())
+//! # }
+//! # }
+//! ```
+//!
+//! ## Notes
+//!
+//! - Requires `content-builder` feature to use this module.
+//! - Only the text structure and the images of the document are kept: paragraphs,
+//!   headings, list items and quotes become text, title and quote blocks, while
+//!   inline formatting, tables and scripts are dropped.
+
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use quick_xml::{
+    Reader,
+    escape::unescape,
+    events::{BytesStart, Event},
+};
+
+use crate::{
+    builder::content::ContentBuilder, error::EpubError, types::HeadingLevel,
+    utils::resolve_general_ref,
+};
+
+/// Elements whose text forms a block of its own
+const CAPTURED_ELEMENTS: [&str; 13] = [
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "blockquote",
+    "pre",
+    "dt",
+    "dd",
+    "figcaption",
+];
+
+/// Elements whose text flows into the surrounding block
+const INLINE_ELEMENTS: [&str; 27] = [
+    "a", "abbr", "b", "bdi", "bdo", "br", "cite", "code", "data", "del", "dfn", "em", "font", "i",
+    "ins", "kbd", "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u",
+];
+
+/// Elements whose content is not part of the text
+const SKIPPED_ELEMENTS: [&str; 4] = ["script", "style", "template", "noscript"];
+
+/// Splits an HTML document into chapters
+///
+/// The document is read in order, and a new chapter is started at each heading of
+/// the `split_on` level, titled after the text of the heading. Content found before
+/// the first such heading forms a chapter of its own, titled after the `<title>`
+/// of the document.
+///
+/// Paragraphs and list items become text blocks, headings become title blocks and
+/// quotes become quote blocks. Images become image blocks, with their `src` resolved
+/// against `base_dir`.
+///
+/// ## Parameters
+/// - `html`: The HTML document, which is read leniently: void elements do not need
+///   to be closed, and end tags do not need to match their start tags
+/// - `split_on`: The level of the headings starting a new chapter
+/// - `base_dir`: The directory the relative `src` of images are resolved against
+///
+/// ## Return
+/// - `Ok(Vec<ContentBuilder>)`: One builder per chapter, with ids `chapter_1`, `chapter_2`, ...
+///   and the language of the `lang` attribute of the document, `en` if absent
+/// - `Err(EpubError)`: The document could not be parsed, or an image does not exist or
+///   is not in a supported format
+///
+/// ## Notes
+/// - Remote and `data:` images are skipped with a warning.
+/// - Named character references other than the predefined XML entities and
+///   `&nbsp;` are dropped.
+pub fn from_html<P: AsRef<Path>>(
+    html: &str,
+    split_on: HeadingLevel,
+    base_dir: P,
+) -> Result<Vec<ContentBuilder>, EpubError> {
+    let mut reader = Reader::from_str(html);
+    reader.config_mut().check_end_names = false;
+
+    let mut importer = HtmlImporter::new(split_on, base_dir.as_ref());
+    let mut skip_depth = 0usize;
+    let mut in_head = false;
+    let mut in_title = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+
+            Event::Start(e) => {
+                let name = tag_name(&e);
+                if skip_depth > 0 || SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    skip_depth += 1;
+                    continue;
+                }
+
+                match name.as_str() {
+                    "html" => importer.set_language(&e),
+                    "head" => in_head = true,
+                    "title" if in_head => in_title = true,
+                    "body" => in_head = false,
+                    _ if in_head => {}
+                    _ => importer.start_element(&name, &e)?,
+                }
+            }
+
+            Event::Empty(e) => {
+                let name = tag_name(&e);
+                if skip_depth == 0 && !in_head {
+                    importer.start_element(&name, &e)?;
+                }
+            }
+
+            Event::End(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_ascii_lowercase();
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                    continue;
+                }
+
+                match name.as_str() {
+                    "head" => in_head = false,
+                    "title" => in_title = false,
+                    _ if in_head => {}
+                    _ => importer.end_element(&name)?,
+                }
+            }
+
+            Event::Text(e) if skip_depth == 0 => {
+                let text = e.decode().map_err(quick_xml::Error::from)?;
+                if in_title {
+                    importer.title.push_str(&text);
+                } else if !in_head {
+                    importer.push_text(&text);
+                }
+            }
+
+            Event::CData(e) if skip_depth == 0 && !in_head => {
+                importer.push_text(&e.decode().map_err(quick_xml::Error::from)?);
+            }
+
+            Event::GeneralRef(e) if skip_depth == 0 => {
+                let text = resolve_general_ref(&e)?;
+                if in_title {
+                    importer.title.push_str(&text);
+                } else if !in_head {
+                    importer.push_text(&text);
+                }
+            }
+
+            _ => continue,
+        }
+    }
+
+    importer.finish()
+}
+
+/// Returns the lowercase local name of an element
+fn tag_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).to_ascii_lowercase()
+}
+
+/// Returns the unescaped value of an attribute of an element
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attr| attr.key.as_ref().eq_ignore_ascii_case(name.as_bytes()))
+        .map(|attr| {
+            let value = String::from_utf8_lossy(&attr.value).to_string();
+            unescape(&value)
+                .map(|value| value.to_string())
+                .unwrap_or(value)
+        })
+}
+
+/// Text being collected for a block
+struct Capture {
+    /// The element the text belongs to, `None` for text outside of any block element
+    element: Option<String>,
+
+    text: String,
+    pending_space: bool,
+}
+
+/// State of the conversion of an HTML document into chapters
+struct HtmlImporter {
+    split_on: HeadingLevel,
+    base_dir: PathBuf,
+    language: String,
+
+    /// The content of the `<title>` element of the document
+    title: String,
+
+    chapters: Vec<ContentBuilder>,
+    current: Option<ContentBuilder>,
+    captures: Vec<Capture>,
+}
+
+impl HtmlImporter {
+    fn new(split_on: HeadingLevel, base_dir: &Path) -> Self {
+        Self {
+            split_on,
+            base_dir: base_dir.to_path_buf(),
+            language: "en".to_string(),
+            title: String::new(),
+            chapters: vec![],
+            current: None,
+            captures: vec![],
+        }
+    }
+
+    fn set_language(&mut self, element: &BytesStart) {
+        if let Some(language) =
+            attribute(element, "lang").or_else(|| attribute(element, "xml:lang"))
+        {
+            self.language = language;
+        }
+    }
+
+    fn start_element(&mut self, name: &str, element: &BytesStart) -> Result<(), EpubError> {
+        if name == "br" {
+            if let Some(capture) = self.captures.last_mut() {
+                capture.pending_space = true;
+            }
+            return Ok(());
+        }
+
+        if INLINE_ELEMENTS.contains(&name) {
+            return Ok(());
+        }
+
+        self.flush()?;
+        if CAPTURED_ELEMENTS.contains(&name) {
+            self.captures.push(Capture {
+                element: Some(name.to_string()),
+                text: String::new(),
+                pending_space: false,
+            });
+        } else if name == "img" {
+            self.add_image(element)?;
+        }
+
+        Ok(())
+    }
+
+    fn end_element(&mut self, name: &str) -> Result<(), EpubError> {
+        if INLINE_ELEMENTS.contains(&name) {
+            return Ok(());
+        }
+
+        self.flush()?;
+        if let Some(index) = self
+            .captures
+            .iter()
+            .rposition(|capture| capture.element.as_deref() == Some(name))
+        {
+            // unclosed elements inside the closed one end with it
+            while self.captures.len() > index {
+                self.flush()?;
+                self.captures.pop();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.captures.is_empty() {
+            if text.trim().is_empty() {
+                return;
+            }
+
+            self.captures.push(Capture {
+                element: None,
+                text: String::new(),
+                pending_space: false,
+            });
+        }
+
+        let capture = self.captures.last_mut().expect("a capture was just pushed");
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                capture.pending_space = true;
+                continue;
+            }
+
+            if capture.pending_space && !capture.text.is_empty() {
+                capture.text.push(' ');
+            }
+            capture.pending_space = false;
+            capture.text.push(ch);
+        }
+    }
+
+    /// Adds the text collected by the innermost capture as a block
+    ///
+    /// Text outside of any block element is ended by any block boundary, so its
+    /// capture is removed as well.
+    fn flush(&mut self) -> Result<(), EpubError> {
+        let Some(capture) = self.captures.last_mut() else {
+            return Ok(());
+        };
+
+        let text = std::mem::take(&mut capture.text);
+        capture.pending_space = false;
+        let element = capture.element.clone();
+        if element.is_none() {
+            self.captures.pop();
+        }
+
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let heading_level = element
+            .as_deref()
+            .and_then(|element| element.strip_prefix('h'))
+            .and_then(|level| level.parse::<usize>().ok());
+        let in_quote = self
+            .captures
+            .iter()
+            .any(|capture| capture.element.as_deref() == Some("blockquote"));
+
+        match heading_level {
+            Some(level) => {
+                if level == self.split_on.level() {
+                    self.start_chapter(&text)?;
+                }
+                self.chapter()?.add_title_block(&text, level, vec![])?;
+            }
+            None if in_quote => {
+                self.chapter()?.add_quote_block(&text, vec![])?;
+            }
+            None => {
+                self.chapter()?.add_text_block(&text, vec![])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_image(&mut self, element: &BytesStart) -> Result<(), EpubError> {
+        let Some(src) = attribute(element, "src") else {
+            return Ok(());
+        };
+
+        let src = src.split(['#', '?']).next().unwrap_or_default();
+        if src.is_empty() || src.contains(':') {
+            warn!("Skipping the image '{src}', which is not a local file.");
+            return Ok(());
+        }
+
+        let url = self.base_dir.join(src.trim_start_matches('/'));
+        let alt = attribute(element, "alt").filter(|alt| !alt.is_empty());
+        self.chapter()?.add_image_block(url, alt, None, vec![])?;
+        Ok(())
+    }
+
+    /// Ends the current chapter and starts a new one
+    fn start_chapter(&mut self, title: &str) -> Result<(), EpubError> {
+        if let Some(chapter) = self.current.take() {
+            self.chapters.push(chapter);
+        }
+
+        let mut chapter = self.new_chapter()?;
+        chapter.set_title(title);
+        self.current = Some(chapter);
+        Ok(())
+    }
+
+    /// Returns the current chapter, starting one for content found before the first heading
+    fn chapter(&mut self) -> Result<&mut ContentBuilder, EpubError> {
+        if self.current.is_none() {
+            let mut chapter = self.new_chapter()?;
+            chapter.set_title(self.title.trim());
+            self.current = Some(chapter);
+        }
+
+        Ok(self
+            .current
+            .as_mut()
+            .expect("the current chapter was just set"))
+    }
+
+    fn new_chapter(&self) -> Result<ContentBuilder, EpubError> {
+        ContentBuilder::new(
+            &format!("chapter_{}", self.chapters.len() + 1),
+            &self.language,
+        )
+    }
+
+    fn finish(mut self) -> Result<Vec<ContentBuilder>, EpubError> {
+        while !self.captures.is_empty() {
+            self.flush()?;
+            self.captures.pop();
+        }
+
+        if let Some(chapter) = self.current.take() {
+            self.chapters.push(chapter);
+        }
+
+        Ok(self.chapters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{
+        builder::{content::Block, import::from_html},
+        types::HeadingLevel,
+        utils::local_time,
+    };
+
+    const HTML: &str = r#"<!DOCTYPE html>
+        <html lang="fr">
+        <head>
+            <meta charset="utf-8">
+            <title>Le livre</title>
+            <style>p { color: red; }</style>
+        </head>
+        <body>
+            <h1>First chapter</h1>
+            <p>Some <em>emphasized</em> text,<br>on two lines.</p>
+            <h2>A section</h2>
+            <ul>
+                <li>One</li>
+                <li>Two &amp; three
+            </ul>
+            <h1>Second chapter</h1>
+            <blockquote><p>A quote.</p></blockquote>
+            <p><img src="test_case/image.jpg" alt="An image"> After the image.</p>
+            <script>document.write("ignored");</script>
+            <h1>Third chapter</h1>
+            <div>Loose text</div>
+        </body>
+        </html>"#;
+
+    #[test]
+    fn test_from_html() {
+        let chapters = from_html(HTML, HeadingLevel::H1, ".");
+        assert!(chapters.is_ok());
+
+        let chapters = chapters.unwrap();
+        assert_eq!(chapters.len(), 3);
+
+        let blocks = chapters[0].blocks();
+        assert_eq!(blocks.len(), 5);
+        assert!(
+            matches!(&blocks[0], Block::Title { content, level: 1, .. } if content == "First chapter")
+        );
+        assert!(
+            matches!(&blocks[1], Block::Text { content, .. } if content == "Some emphasized text, on two lines.")
+        );
+        assert!(
+            matches!(&blocks[2], Block::Title { content, level: 2, .. } if content == "A section")
+        );
+        assert!(matches!(&blocks[3], Block::Text { content, .. } if content == "One"));
+        assert!(matches!(&blocks[4], Block::Text { content, .. } if content == "Two & three"));
+
+        let blocks = chapters[1].blocks();
+        assert_eq!(blocks.len(), 4);
+        assert!(matches!(&blocks[1], Block::Quote { content, .. } if content == "A quote."));
+        assert!(matches!(&blocks[2], Block::Image { alt: Some(alt), .. } if alt == "An image"));
+        assert!(matches!(&blocks[3], Block::Text { content, .. } if content == "After the image."));
+
+        let blocks = chapters[2].blocks();
+        assert_eq!(blocks.len(), 2);
+        assert!(matches!(&blocks[1], Block::Text { content, .. } if content == "Loose text"));
+
+        let temp_dir = env::temp_dir().join(local_time());
+        assert!(fs::create_dir_all(&temp_dir).is_ok());
+
+        let mut chapter = chapters.into_iter().nth(1).unwrap();
+        let output_path = temp_dir.join("chapter_2.xhtml");
+        assert!(chapter.make(&output_path).is_ok());
+
+        let content = fs::read_to_string(&output_path).unwrap();
+        assert!(content.contains("<title>Second chapter</title>"));
+        assert!(content.contains(r#"xml:lang="fr""#));
+        assert!(!content.contains("ignored"));
+        assert!(temp_dir.join("img/image.jpg").exists());
+
+        assert!(fs::remove_dir_all(&temp_dir).is_ok());
+    }
+
+    #[test]
+    fn test_from_html_with_named_entities() {
+        let html = r#"<html><head><title>Caf&eacute;</title></head><body>
+            <h1>Don&rsquo;t panic</h1>
+            <p>Wait&mdash;what&hellip; &copy; &unknown; &#8220;quoted&#x201D;</p>
+        </body></html>"#;
+
+        let chapters = from_html(html, HeadingLevel::H1, ".");
+        assert!(chapters.is_ok());
+
+        let chapters = chapters.unwrap();
+        assert_eq!(chapters.len(), 1);
+
+        let blocks = chapters[0].blocks();
+        assert!(
+            matches!(&blocks[0], Block::Title { content, .. } if content == "Don\u{2019}t panic")
+        );
+
+        // unknown entities are kept as they are
+        assert!(matches!(
+            &blocks[1],
+            Block::Text { content, .. }
+                if content == "Wait\u{2014}what\u{2026} \u{a9} &unknown; \u{201c}quoted\u{201d}"
+        ));
+    }
+
+    #[test]
+    fn test_from_html_with_preamble() {
+        let html = r#"<html><head><title>Book</title></head><body>
+            <p>Preface</p>
+            <h2>One</h2><p>First</p>
+            <h2>Two</h2><p>Second</p>
+            <h3>Not split</h3>
+        </body></html>"#;
+
+        let chapters = from_html(html, HeadingLevel::H2, ".");
+        assert!(chapters.is_ok());
+
+        let chapters = chapters.unwrap();
+        assert_eq!(chapters.len(), 3);
+        assert_eq!(chapters[0].blocks().len(), 1);
+        assert_eq!(chapters[1].blocks().len(), 2);
+        assert_eq!(chapters[2].blocks().len(), 3);
+
+        assert!(from_html("", HeadingLevel::H1, ".").unwrap().is_empty());
+        assert!(from_html(r#"<p><img src="missing.png"></p>"#, HeadingLevel::H1, ".").is_err());
+    }
+}
@@ -21,7 +21,7 @@
 //! - Supports more EPUB specification features, such as media overlay and scripts.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::{self, File},
     io::{BufReader, Read, Seek},
     path::{Path, PathBuf},
@@ -29,6 +29,7 @@ use std::{
         Arc, Mutex,
         atomic::{AtomicUsize, Ordering},
     },
+    time::Duration,
 };
 
 #[cfg(not(feature = "no-indexmap"))]
@@ -36,18 +37,54 @@ use indexmap::IndexMap;
 use zip::{ZipArchive, result::ZipError};
 
 use crate::{
-    error::EpubError,
+    error::{EpubError, FallbackError},
     types::{
-        EncryptionData, EpubVersion, ManifestItem, MetadataItem, MetadataLinkItem,
-        MetadataRefinement, MetadataSheet, NavPoint, SpineItem,
+        BookSummary, CfiLocation, Chapter, EncryptionData, EpubVersion, FetchPolicy,
+        GuideReference, ManifestItem, MetadataItem, MetadataLinkItem, MetadataRefinement,
+        MetadataSheet, NavPoint, PageSpread, ParseMode, PlayOrderIssue, ReadingOrder, ReadingUnit,
+        RenditionInfo, SearchHit, SearchOptions, Signature, SpineItem,
     },
     utils::{
-        DecodeBytes, NormalizeWhitespace, XmlElement, XmlReader, adobe_font_dencryption,
-        check_realtive_link_leakage, compression_method_check, get_file_in_zip_archive,
-        idpf_font_dencryption,
+        CfiPath, DecodeBytes, NormalizeWhitespace, XmlElement, XmlReader, adobe_font_dencryption,
+        adobe_obfuscation_key, apply_adobe_obfuscation, apply_idpf_obfuscation,
+        check_realtive_link_leakage, compression_method_check, escape_cfi_assertion,
+        extract_chapter_title, extract_plain_text, find_file_in_zip_archive,
+        get_file_in_zip_archive, guess_language_by_script, idpf_font_dencryption,
+        idpf_obfuscation_key, is_font_data, is_valid_language_tag, normalize_container_path,
+        parse_cfi_path, parse_clock_value,
     },
 };
 
+/// The namespace of Dublin Core metadata elements
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// The namespace of OPF package document elements
+const OPF_NAMESPACE: &str = "http://www.idpf.org/2007/opf";
+
+/// The base IRI of unprefixed EPUB 3 meta properties
+const META_VOCABULARY: &str = "http://idpf.org/epub/vocab/package/meta/#";
+
+/// The prefixes reserved by the EPUB 3 specification, which can be used without
+/// being declared in the `prefix` attribute of the package element
+const RESERVED_PREFIXES: [(&str, &str); 10] = [
+    ("a11y", "http://www.idpf.org/epub/vocab/package/a11y/#"),
+    ("dcterms", "http://purl.org/dc/terms/"),
+    ("marc", "http://id.loc.gov/vocabulary/"),
+    ("media", "http://www.idpf.org/epub/vocab/overlays/#"),
+    ("msv", "http://www.idpf.org/epub/vocab/structure/magazine/#"),
+    (
+        "onix",
+        "http://www.editeur.org/ONIX/book/codelists/current.html#",
+    ),
+    (
+        "prism",
+        "http://www.prismstandard.org/specifications/3.0/PRISM_CV_Spec_3.0.htm#",
+    ),
+    ("rendition", "http://www.idpf.org/vocab/rendition/#"),
+    ("schema", "http://schema.org/"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+];
+
 /// EPUB document parser, representing a loaded and parsed EPUB publication
 ///
 /// The `EpubDoc` structure is the core of the entire EPUB parsing library.
@@ -80,6 +117,9 @@ pub struct EpubDoc<R: Read + Seek> {
     /// The path to the target epub file
     pub(crate) epub_path: PathBuf,
 
+    /// The renditions declared in the container, in declaration order
+    renditions: Vec<RenditionInfo>,
+
     /// The path to the OPF file
     pub package_path: PathBuf,
 
@@ -100,6 +140,17 @@ pub struct EpubDoc<R: Read + Seek> {
     /// Data in metadata that points to external files
     pub metadata_link: Vec<MetadataLinkItem>,
 
+    /// The prefixes declared in the `prefix` attribute of the package element,
+    /// mapping each prefix name to its IRI
+    ///
+    /// The prefixes reserved by the EPUB 3 specification are not included unless
+    /// the package declares them explicitly.
+    pub prefixes: HashMap<String, String>,
+
+    /// Refinements that refine other elements than metadata items,
+    /// such as the durations of media overlays, grouped by the refined id
+    resource_refinements: HashMap<String, Vec<MetadataRefinement>>,
+
     /// A list of resources contained inside an epub extracted from OPF
     ///
     /// All resources in the epub file are declared here, and undeclared resources
@@ -125,6 +176,9 @@ pub struct EpubDoc<R: Read + Seek> {
     #[cfg(feature = "no-indexmap")]
     pub manifest: HashMap<String, ManifestItem>,
 
+    /// The ids of the manifest items, in the order they are declared in the OPF
+    manifest_order: Vec<String>,
+
     /// Physical reading order of publications extracted from OPF
     ///
     /// This attribute declares the order in which multiple files
@@ -134,17 +188,55 @@ pub struct EpubDoc<R: Read + Seek> {
     /// The encryption.xml extracted from the META-INF directory
     pub encryption: Option<Vec<EncryptionData>>,
 
+    /// References declared in the `<guide>` element of the OPF
+    ///
+    /// This field is empty if the package document has no guide.
+    pub guide: Vec<GuideReference>,
+
     /// The navigation data of the epub file
     pub catalog: Vec<NavPoint>,
 
     /// The title of the catalog
     pub catalog_title: String,
 
+    /// The page list of the publication
+    ///
+    /// This is read from the `pageList` of the NCX file, or from the `page-list`
+    /// navigation of the EPUB 3 navigation document. The field is empty if the
+    /// publication declares no page list.
+    pub page_list: Vec<NavPoint>,
+
+    /// The auxiliary navigation lists of the NCX file, such as a list of figures
+    ///
+    /// Each `navList` is keyed by its `class` attribute, or by its label if it has no
+    /// class. This field is always empty for EPUB 3 publications that have a navigation
+    /// document, whose navigation sections are available through `nav_sections`.
+    pub nav_lists: HashMap<String, Vec<NavPoint>>,
+
+    /// The directory of the navigation document the catalog was read from,
+    /// used to resolve the relative links of the catalog
+    catalog_base: PathBuf,
+
     /// The index of the current reading spine
     current_spine_index: AtomicUsize,
 
     /// Whether the epub file contains encryption information
     has_encryption: bool,
+
+    /// How specification violations are handled during parsing
+    parse_mode: ParseMode,
+
+    /// How resources located outside of the container are retrieved
+    fetch_policy: FetchPolicy,
+
+    /// Whether spine navigation skips the navigation document
+    skip_nav: bool,
+
+    /// Problems that were repaired while parsing in lenient mode
+    warnings: Vec<String>,
+
+    /// Problems with the play orders of the NCX navigation points
+    play_order_issues: Vec<PlayOrderIssue>,
 }
 
 impl<R: Read + Seek> EpubDoc<R> {
@@ -166,8 +258,32 @@ impl<R: Read + Seek> EpubDoc<R> {
     ///
     /// ## Notes
     /// - This function assumes the EPUB file structure is valid
+    /// - The file is parsed in the default `ParseMode::Lenient` mode
+    /// - `epub_path` is used as-is and does not need to exist on disk, so readers over
+    ///   in-memory buffers can be given any synthetic path
+    pub fn from_reader<P: AsRef<Path>>(reader: R, epub_path: P) -> Result<Self, EpubError> {
+        Self::from_reader_with_mode(reader, epub_path, ParseMode::default())
+    }
+
+    /// Creates a new EPUB document instance from a reader with the given parse mode
+    ///
+    /// Behaves like `from_reader`, but lets the caller decide whether recoverable
+    /// specification violations (such as duplicate manifest ids) are rejected or repaired.
+    ///
+    /// ## Parameters
+    /// - `reader`: The data source that implements the `Read` and `Seek` traits
+    /// - `epub_path`: The path to the EPUB file, used for path resolution and validation
+    /// - `mode`: How specification violations are handled
+    ///
+    /// ## Return
+    /// - `Ok(EpubDoc<R>)`: The successfully parsed EPUB document object
+    /// - `Err(EpubError)`: Errors encountered during parsing
     // TODO: 增加对必需的 metadata 的检查
-    pub fn from_reader(reader: R, epub_path: PathBuf) -> Result<Self, EpubError> {
+    pub fn from_reader_with_mode<P: AsRef<Path>>(
+        reader: R,
+        epub_path: P,
+        mode: ParseMode,
+    ) -> Result<Self, EpubError> {
         // Parsing process
         // 1. Verify that the ZIP compression method conforms to the EPUB specification
         // 2. Parse `META-INF/container.xml` retrieves the location of the OPF file
@@ -178,20 +294,146 @@ impl<R: Read + Seek> EpubDoc<R> {
         // 7. Verifies and extracts the unique identifier
 
         let mut archive = ZipArchive::new(reader).map_err(EpubError::from)?;
-        let epub_path = fs::canonicalize(epub_path)?;
 
         compression_method_check(&mut archive)?;
 
         let container =
             get_file_in_zip_archive(&mut archive, "META-INF/container.xml")?.decode()?;
-        let package_path = Self::parse_container(container)?;
+        let mut container_warnings = vec![];
+        let renditions = Self::parse_container(container, &mut container_warnings)?;
+        let package_path = renditions[0].full_path.clone();
+
+        let has_encryption = archive
+            .by_path(Path::new("META-INF/encryption.xml"))
+            .is_ok();
+
+        let mut doc = Self::load_package(
+            Arc::new(Mutex::new(archive)),
+            epub_path.as_ref().to_path_buf(),
+            renditions,
+            package_path,
+            mode,
+            has_encryption,
+        )?;
+        for warning in container_warnings {
+            doc.push_warning(warning);
+        }
+
+        Ok(doc)
+    }
+
+    /// Reports the metadata items of an EPUB file one by one
+    ///
+    /// This function reads only the container file and the package document of the
+    /// default rendition, and invokes `f` for each metadata item in document order,
+    /// without building an `EpubDoc`. The package document is parsed only up to the
+    /// end of its `<metadata>` element, so the manifest, spine and navigation are not
+    /// parsed, and no collection of metadata items is kept.
+    ///
+    /// ## Parameters
+    /// - `reader`: The data source that implements the `Read` and `Seek` traits
+    /// - `f`: The callback invoked for each metadata item
+    ///
+    /// ## Return
+    /// - `Ok(())`: All metadata items were reported
+    /// - `Err(EpubError)`: The container or the package document could not be read or parsed
+    ///
+    /// ## Notes
+    /// - Refinements are attached to the items they refine before the items are reported,
+    ///   the same as in `get_metadata`. Metadata links are not reported.
+    /// - The package document is still decoded as a whole. If its `version` attribute
+    ///   is missing, it is parsed completely to determine the version.
+    pub fn stream_metadata<F: FnMut(&MetadataItem)>(reader: R, mut f: F) -> Result<(), EpubError> {
+        let mut archive = ZipArchive::new(reader).map_err(EpubError::from)?;
+
+        let container =
+            get_file_in_zip_archive(&mut archive, "META-INF/container.xml")?.decode()?;
+        let mut container_warnings = vec![];
+        let renditions = Self::parse_container(container, &mut container_warnings)?;
+        for warning in container_warnings {
+            log::warn!("{}", warning);
+        }
+        let package_path = renditions[0].full_path.to_string_lossy().to_string();
+
+        let opf_file = get_file_in_zip_archive(&mut archive, &package_path)?.decode()?;
+        let package = XmlReader::parse_until(&opf_file, "metadata")?;
+        let version = match Self::determine_epub_version(&package) {
+            Ok(version) => version,
+
+            // without a version attribute, the version is told by the manifest and spine
+            Err(_) => Self::determine_epub_version(&XmlReader::parse(&opf_file)?)?,
+        };
+
+        let metadata_element = package
+            .find_elements_by_name("metadata")
+            .next()
+            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "metadata".to_string() })?;
+
+        // refinements may follow the items they refine, so they are collected first
+        let mut refinements = HashMap::new();
+        for element in metadata_element.children() {
+            let is_refinement = element.namespace.as_deref() == Some(OPF_NAMESPACE)
+                && element.name == "meta"
+                && element.get_attr("refines").is_some();
+            if is_refinement {
+                Self::parse_meta_element(element, version, &mut vec![], &mut refinements)?;
+            }
+        }
+
+        let mut items = Vec::with_capacity(1);
+        for element in metadata_element.children() {
+            match element.namespace.as_deref() {
+                Some(DC_NAMESPACE) => Self::parse_dc_metadata(element, version, &mut items)?,
+
+                // refinements were collected in the first pass
+                Some(OPF_NAMESPACE)
+                    if element.name == "meta" && element.get_attr("refines").is_none() =>
+                {
+                    Self::parse_meta_element(element, version, &mut items, &mut HashMap::new())?
+                }
+
+                _ => {}
+            }
+
+            for mut item in items.drain(..) {
+                if let Some(refined) = item.id.as_ref().and_then(|id| refinements.remove(id)) {
+                    item.refined = refined;
+                }
+                f(&item);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads the package document of a rendition
+    ///
+    /// This function parses the package document at `package_path` in the already
+    /// opened container, and builds the document from its metadata, manifest, spine,
+    /// guide and navigation, together with the encryption information of the container.
+    ///
+    /// ## Parameters
+    /// - `archive`: The opened container
+    /// - `epub_path`: The path to the EPUB file
+    /// - `renditions`: The renditions declared in the container
+    /// - `package_path`: The path to the package document to load
+    /// - `mode`: How specification violations are handled
+    /// - `has_encryption`: Whether the container has a META-INF/encryption.xml file
+    fn load_package(
+        archive: Arc<Mutex<ZipArchive<R>>>,
+        epub_path: PathBuf,
+        renditions: Vec<RenditionInfo>,
+        package_path: PathBuf,
+        mode: ParseMode,
+        has_encryption: bool,
+    ) -> Result<Self, EpubError> {
         let base_path = package_path
             .parent()
             .expect("the parent directory of the opf file must exist")
             .to_path_buf();
 
         let opf_file = get_file_in_zip_archive(
-            &mut archive,
+            &mut *archive.lock()?,
             package_path
                 .to_str()
                 .expect("package_path should be valid UTF-8"),
@@ -200,31 +442,44 @@ impl<R: Read + Seek> EpubDoc<R> {
         let package = XmlReader::parse(&opf_file)?;
 
         let version = Self::determine_epub_version(&package)?;
-        let has_encryption = archive
-            .by_path(Path::new("META-INF/encryption.xml"))
-            .is_ok();
 
         let mut doc = Self {
-            archive: Arc::new(Mutex::new(archive)),
+            archive,
             epub_path,
+            renditions,
             package_path,
             base_path,
             version,
             unique_identifier: String::new(),
             metadata: vec![],
             metadata_link: vec![],
+            prefixes: package
+                .get_attr("prefix")
+                .map(|value| Self::parse_prefixes(&value))
+                .unwrap_or_default(),
+            resource_refinements: HashMap::new(),
 
             #[cfg(feature = "no-indexmap")]
             manifest: HashMap::new(),
             #[cfg(not(feature = "no-indexmap"))]
             manifest: IndexMap::new(),
+            manifest_order: vec![],
 
             spine: vec![],
             encryption: None,
+            guide: vec![],
             catalog: vec![],
             catalog_title: String::new(),
+            page_list: vec![],
+            nav_lists: HashMap::new(),
+            catalog_base: PathBuf::new(),
             current_spine_index: AtomicUsize::new(0),
             has_encryption,
+            parse_mode: mode,
+            fetch_policy: FetchPolicy::default(),
+            skip_nav: false,
+            warnings: vec![],
+            play_order_issues: vec![],
         };
 
         let metadata_element = package.find_elements_by_name("metadata").next().unwrap();
@@ -234,6 +489,9 @@ impl<R: Read + Seek> EpubDoc<R> {
         doc.parse_metadata(metadata_element)?;
         doc.parse_manifest(manifest_element)?;
         doc.parse_spine(spine_element)?;
+        if let Some(guide_element) = package.find_elements_by_name("guide").next() {
+            doc.parse_guide(guide_element)?;
+        }
         doc.parse_encryption()?;
         doc.parse_catalog()?;
 
@@ -256,34 +514,63 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// Parse the EPUB container file (META-INF/container.xml)
     ///
     /// This function parses the container information in the EPUB file 、
-    /// to extract the path to the OPF package file. According to the EPUB
+    /// to extract the renditions of the publication. According to the EPUB
     /// specification, the `container.xml` file must exist in the `META-INF`
     /// directory and contain at least one `rootfile` element pointing to
     /// the main OPF file. When multiple `rootfile` elements exist, the first
-    /// element pointing to the OPF file is used as the default.
+    /// element is used as the default rendition.
     ///
     /// ## Parameters
     /// - `data`: The content string of the container.xml
+    /// - `warnings`: Collects the messages of the rootfiles that are skipped
     ///
     /// ## Return
-    /// - `Ok(PathBuf)`: The path to the successfully parsed OPF file
+    /// - `Ok(Vec<RenditionInfo>)`: The renditions declared by the rootfiles, never empty
     /// - `Err(EpubError)`: Errors encountered during parsing
-    fn parse_container(data: String) -> Result<PathBuf, EpubError> {
+    ///
+    /// ## Notes
+    /// - Only the default rendition requires the `full-path` attribute. Other rootfiles
+    ///   without it are skipped, and a message is added to `warnings`.
+    fn parse_container(
+        data: String,
+        warnings: &mut Vec<String>,
+    ) -> Result<Vec<RenditionInfo>, EpubError> {
         let root = XmlReader::parse(&data)?;
-        let rootfile = root
-            .find_elements_by_name("rootfile")
-            .next()
-            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "rootfile".to_string() })?;
+        let mut renditions = Vec::new();
+        for (index, rootfile) in root.find_elements_by_name("rootfile").enumerate() {
+            let full_path = match rootfile.get_attr("full-path") {
+                Some(full_path) => full_path,
+                None if index == 0 => {
+                    return Err(EpubError::MissingRequiredAttribute {
+                        tag: "rootfile".to_string(),
+                        attribute: "full-path".to_string(),
+                    });
+                }
+                None => {
+                    warnings.push(format!(
+                        "Rootfile {} without \"full-path\" attribute is ignored",
+                        index + 1
+                    ));
+                    continue;
+                }
+            };
 
-        let attr =
-            rootfile
-                .get_attr("full-path")
-                .ok_or_else(|| EpubError::MissingRequiredAttribute {
-                    tag: "rootfile".to_string(),
-                    attribute: "full-path".to_string(),
-                })?;
+            renditions.push(RenditionInfo {
+                full_path: PathBuf::from(full_path),
+                media_type: rootfile.get_attr("media-type"),
+                media: rootfile.get_attr("rendition:media"),
+                layout: rootfile.get_attr("rendition:layout"),
+                language: rootfile.get_attr("rendition:language"),
+                access_mode: rootfile.get_attr("rendition:accessMode"),
+                label: rootfile.get_attr("rendition:label"),
+            });
+        }
 
-        Ok(PathBuf::from(attr))
+        if renditions.is_empty() {
+            return Err(EpubError::NonCanonicalFile { tag: "rootfile".to_string() });
+        }
+
+        Ok(renditions)
     }
 
     /// Parse the EPUB metadata section
@@ -297,24 +584,48 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// ## Parameters
     /// - `metadata_element`: A reference to the `<metadata>` element in the OPF file
     fn parse_metadata(&mut self, metadata_element: &XmlElement) -> Result<(), EpubError> {
-        const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
-        const OPF_NAMESPACE: &str = "http://www.idpf.org/2007/opf";
-
         let mut metadata = Vec::new();
         let mut metadata_link = Vec::new();
-        let mut refinements = HashMap::<String, Vec<MetadataRefinement>>::new();
+        let mut refinements = HashMap::new();
+        self.collect_metadata(
+            metadata_element,
+            &mut metadata,
+            &mut metadata_link,
+            &mut refinements,
+        )?;
+
+        self.metadata = metadata;
+        self.metadata_link = metadata_link;
+        self.resource_refinements = refinements;
+        Ok(())
+    }
 
+    /// Collects the metadata items and links of a metadata element
+    ///
+    /// Refinements are attached to the metadata items they refine. Refinements
+    /// of other elements, such as manifest items, are left in `refinements`.
+    ///
+    /// ## Parameters
+    /// - `metadata_element`: A reference to a `<metadata>` element
+    fn collect_metadata(
+        &self,
+        metadata_element: &XmlElement,
+        metadata: &mut Vec<MetadataItem>,
+        metadata_link: &mut Vec<MetadataLinkItem>,
+        refinements: &mut HashMap<String, Vec<MetadataRefinement>>,
+    ) -> Result<(), EpubError> {
         for element in metadata_element.children() {
             match &element.namespace {
                 Some(namespace) if namespace == DC_NAMESPACE => {
-                    self.parse_dc_metadata(element, &mut metadata)?
+                    Self::parse_dc_metadata(element, self.version, metadata)?
                 }
 
-                Some(namespace) if namespace == OPF_NAMESPACE => self.parse_opf_metadata(
+                Some(namespace) if namespace == OPF_NAMESPACE => Self::parse_opf_metadata(
                     element,
-                    &mut metadata,
-                    &mut metadata_link,
-                    &mut refinements,
+                    self.version,
+                    metadata,
+                    metadata_link,
+                    refinements,
                 )?,
 
                 _ => {}
@@ -329,8 +640,6 @@ impl<R: Read + Seek> EpubDoc<R> {
             }
         }
 
-        self.metadata = metadata;
-        self.metadata_link = metadata_link;
         Ok(())
     }
 
@@ -343,12 +652,17 @@ impl<R: Read + Seek> EpubDoc<R> {
     ///
     /// ## Parameters
     /// - `manifest_element`: A reference to the `<manifest>` element in the OPF file
+    ///
+    /// ## Notes
+    /// - When several items share an id, strict mode fails with `DuplicateManifestId`,
+    ///   while lenient mode keeps the first declaration and records a warning.
     fn parse_manifest(&mut self, manifest_element: &XmlElement) -> Result<(), EpubError> {
         let estimated_items = manifest_element.children().count();
         #[cfg(feature = "no-indexmap")]
         let mut resources = HashMap::with_capacity(estimated_items);
         #[cfg(not(feature = "no-indexmap"))]
         let mut resources = IndexMap::with_capacity(estimated_items);
+        let mut order = Vec::with_capacity(estimated_items);
 
         for element in manifest_element.children() {
             let id = element
@@ -375,6 +689,19 @@ impl<R: Read + Seek> EpubDoc<R> {
             let properties = element.get_attr("properties");
             let fallback = element.get_attr("fallback");
 
+            if resources.contains_key(&id) {
+                if self.parse_mode == ParseMode::Strict {
+                    return Err(EpubError::DuplicateManifestId { id });
+                }
+
+                self.push_warning(format!(
+                    "Duplicate manifest id \"{}\", the item pointing to \"{}\" is ignored",
+                    id, path
+                ));
+                continue;
+            }
+
+            order.push(id.clone());
             resources.insert(
                 id.clone(),
                 ManifestItem {
@@ -388,10 +715,57 @@ impl<R: Read + Seek> EpubDoc<R> {
         }
 
         self.manifest = resources;
+        self.manifest_order = order;
         self.validate_fallback_chains();
         Ok(())
     }
 
+    /// Parse the EPUB guide section
+    ///
+    /// This function parses the `<reference>` children of the `<guide>` element.
+    /// Since the guide is deprecated and only informative, references lacking the
+    /// required `type` or `href` attribute are skipped with a warning. References
+    /// whose `href` cannot be resolved are skipped the same way in `ParseMode::Lenient`,
+    /// while `ParseMode::Strict` returns the error.
+    ///
+    /// ## Parameters
+    /// - `guide_element`: A reference to the `<guide>` element in the OPF file
+    fn parse_guide(&mut self, guide_element: &XmlElement) -> Result<(), EpubError> {
+        let mut guide = Vec::new();
+        for element in guide_element.find_children_by_name("reference") {
+            let (Some(reference_type), Some(href)) =
+                (element.get_attr("type"), element.get_attr("href"))
+            else {
+                self.push_warning(
+                    "Guide reference without \"type\" or \"href\" attribute is ignored".to_string(),
+                );
+                continue;
+            };
+
+            let href = href.split('#').next().unwrap_or_default();
+            let href = match self.normalize_manifest_path(href) {
+                Ok(href) => href,
+                Err(err) if self.parse_mode == ParseMode::Lenient => {
+                    self.push_warning(format!(
+                        "Guide reference of type \"{}\" is ignored: {}",
+                        reference_type, err
+                    ));
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            guide.push(GuideReference {
+                reference_type,
+                title: element.get_attr("title"),
+                href,
+            });
+        }
+
+        self.guide = guide;
+        Ok(())
+    }
+
     /// Parse the EPUB spine section
     ///
     /// This function parses the `<spine>` elements in the OPF file to extract
@@ -472,13 +846,12 @@ impl<R: Read + Seek> EpubDoc<R> {
                         attribute: "Algorithm".to_string(),
                     })?
                     .to_string(),
-                data: reference
-                    .get_attr("URI")
-                    .ok_or_else(|| EpubError::MissingRequiredAttribute {
+                data: normalize_container_path(&reference.get_attr("URI").ok_or_else(|| {
+                    EpubError::MissingRequiredAttribute {
                         tag: "CipherReference".to_string(),
                         attribute: "URI".to_string(),
-                    })?
-                    .to_string(),
+                    }
+                })?),
             });
         }
 
@@ -494,92 +867,236 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// This function is responsible for parsing the navigation information of EPUB
     /// publications. Different parsing strategies are used depending on the EPUB version:
     /// - EPUB 2.0: Parses the NCX file to obtain directory information
-    /// - EPUB 3.0: Parses the Navigation Document (NAV) file to obtain directory information
+    /// - EPUB 3.0: Parses the Navigation Document (NAV) file to obtain directory information,
+    ///   or the NCX file referenced by `spine@toc` if the publication has no Navigation Document
     fn parse_catalog(&mut self) -> Result<(), EpubError> {
-        const HEAD_TAGS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+        match self.version {
+            EpubVersion::Version2_0 => self.parse_ncx_catalog(),
+            EpubVersion::Version3_0 => match self.nav_document_path() {
+                Ok(nav_path) => self.parse_nav_catalog(&nav_path),
+                Err(err) => match self.spine_toc_id()? {
+                    Some(toc_id) if self.manifest.contains_key(&toc_id) => {
+                        self.push_warning(
+                            "The Navigation Document is missing, the catalog is read from the NCX file instead."
+                                .to_string(),
+                        );
+                        self.parse_ncx_catalog()
+                    }
+                    _ => Err(err),
+                },
+            },
+        }
+    }
 
+    /// Retrieves the id of the NCX file referenced by the `toc` attribute of the spine
+    ///
+    /// ## Return
+    /// - `Ok(Option<String>)`: The id of the NCX manifest item, or `None` if the spine
+    ///   has no `toc` attribute
+    /// - `Err(EpubError)`: The package document could not be read, or it has no spine
+    fn spine_toc_id(&self) -> Result<Option<String>, EpubError> {
         let mut archive = self.archive.lock()?;
-        match self.version {
-            EpubVersion::Version2_0 => {
-                let opf_file =
-                    get_file_in_zip_archive(&mut archive, self.package_path.to_str().unwrap())?
-                        .decode()?;
-                let opf_element = XmlReader::parse(&opf_file)?;
+        let opf_file =
+            get_file_in_zip_archive(&mut archive, self.package_path.to_str().unwrap())?.decode()?;
+        let opf_element = XmlReader::parse(&opf_file)?;
 
-                let toc_id = opf_element
-                    .find_children_by_name("spine")
-                    .next()
-                    .ok_or_else(|| EpubError::NonCanonicalFile { tag: "spine".to_string() })?
-                    .get_attr("toc")
-                    .ok_or_else(|| EpubError::MissingRequiredAttribute {
-                        tag: "spine".to_string(),
-                        attribute: "toc".to_string(),
-                    })?
-                    .to_owned();
-                let toc_path = self
-                    .manifest
-                    .get(&toc_id)
-                    .ok_or(EpubError::ResourceIdNotExist { id: toc_id })?
-                    .path
-                    .to_str()
-                    .unwrap();
-
-                let ncx_file = get_file_in_zip_archive(&mut archive, toc_path)?.decode()?;
-                let ncx = XmlReader::parse(&ncx_file)?;
-
-                match ncx.find_elements_by_name("docTitle").next() {
-                    Some(element) => self.catalog_title = element.text(),
-                    None => log::warn!(
-                        "Expecting to get docTitle information from the ncx file, but it's missing."
-                    ),
-                };
+        Ok(opf_element
+            .find_children_by_name("spine")
+            .next()
+            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "spine".to_string() })?
+            .get_attr("toc"))
+    }
+
+    /// Parse the catalog from the NCX file referenced by the `toc` attribute of the spine
+    fn parse_ncx_catalog(&mut self) -> Result<(), EpubError> {
+        let toc_id = self
+            .spine_toc_id()?
+            .ok_or_else(|| EpubError::MissingRequiredAttribute {
+                tag: "spine".to_string(),
+                attribute: "toc".to_string(),
+            })?;
+        let toc_path = self
+            .manifest
+            .get(&toc_id)
+            .ok_or(EpubError::ResourceIdNotExist { id: toc_id })?
+            .path
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let mut archive = self.archive.lock()?;
+        let ncx_file = get_file_in_zip_archive(&mut archive, &toc_path)?.decode()?;
+        let ncx = XmlReader::parse(&ncx_file)?;
+        self.catalog_base = Path::new(&toc_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        match ncx.find_elements_by_name("docTitle").next() {
+            Some(element) => self.catalog_title = element.text(),
+            None => log::warn!(
+                "Expecting to get docTitle information from the ncx file, but it's missing."
+            ),
+        };
+
+        let nav_map = ncx
+            .find_elements_by_name("navMap")
+            .next()
+            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "navMap".to_string() })?;
 
-                let nav_map = ncx
-                    .find_elements_by_name("navMap")
+        let mut catalog = self.parse_nav_points(nav_map)?;
+
+        if let Some(page_list) = ncx.find_elements_by_name("pageList").next() {
+            self.page_list = Self::parse_ncx_targets(page_list, "pageTarget");
+        }
+        for nav_list in ncx.find_elements_by_name("navList") {
+            let key = nav_list.get_attr("class").or_else(|| {
+                nav_list
+                    .find_children_by_name("navLabel")
                     .next()
-                    .ok_or_else(|| EpubError::NonCanonicalFile { tag: "navMap".to_string() })?;
+                    .map(|label| label.text().trim().to_string())
+            });
 
-                self.catalog = self.parse_nav_points(nav_map)?;
+            match key {
+                Some(key) if !key.is_empty() => {
+                    let targets = Self::parse_ncx_targets(nav_list, "navTarget");
+                    self.nav_lists.entry(key).or_insert(targets);
+                }
+                _ => log::warn!("Skipping a navList without a class or label in the ncx file."),
+            }
+        }
+        drop(archive);
 
-                Ok(())
+        let issues = Self::validate_play_order(&catalog);
+        if let Some(issue) = issues.first() {
+            if self.parse_mode == ParseMode::Strict {
+                return Err(EpubError::InvalidPlayOrder { message: issue.to_string() });
             }
 
-            EpubVersion::Version3_0 => {
-                let nav_path = self
-                    .manifest
-                    .values()
-                    .find(|item| {
-                        if let Some(property) = &item.properties {
-                            return property.contains("nav");
-                        }
-                        false
-                    })
-                    .map(|item| item.path.clone())
-                    .ok_or_else(|| EpubError::NonCanonicalEpub {
-                        expected_file: "Navigation Document".to_string(),
-                    })?;
+            Self::reassign_play_order(&mut catalog, &mut 1);
+        }
+        for issue in issues.iter() {
+            self.push_warning(issue.to_string());
+        }
+        self.play_order_issues = issues;
 
-                let nav_file =
-                    get_file_in_zip_archive(&mut archive, nav_path.to_str().unwrap())?.decode()?;
-
-                let nav_element = XmlReader::parse(&nav_file)?;
-                let nav = nav_element
-                    .find_elements_by_name("nav")
-                    .find(|&element| element.get_attr("epub:type") == Some(String::from("toc")))
-                    .ok_or_else(|| EpubError::NonCanonicalFile { tag: "nav".to_string() })?;
-                let nav_title = nav.find_children_by_names(&HEAD_TAGS).next();
-                let nav_list = nav
-                    .find_children_by_name("ol")
-                    .next()
-                    .ok_or_else(|| EpubError::NonCanonicalFile { tag: "ol".to_string() })?;
+        Self::sort_nav_points(&mut catalog);
+        self.catalog = catalog;
 
-                self.catalog = self.parse_catalog_list(nav_list)?;
-                if let Some(nav_title) = nav_title {
-                    self.catalog_title = nav_title.text();
-                };
-                Ok(())
+        Ok(())
+    }
+
+    /// Parse the catalog from the `toc` navigation of the EPUB 3 navigation document
+    fn parse_nav_catalog(&mut self, nav_path: &Path) -> Result<(), EpubError> {
+        const HEAD_TAGS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+        let mut archive = self.archive.lock()?;
+        let nav_file =
+            get_file_in_zip_archive(&mut archive, nav_path.to_str().unwrap())?.decode()?;
+
+        let nav_element = XmlReader::parse(&nav_file)?;
+        self.catalog_base = nav_path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let nav = nav_element
+            .find_elements_by_name("nav")
+            .find(|&element| element.get_attr("epub:type") == Some(String::from("toc")))
+            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "nav".to_string() })?;
+        let nav_title = nav.find_children_by_names(&HEAD_TAGS).next();
+        let nav_list = nav
+            .find_children_by_name("ol")
+            .next()
+            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "ol".to_string() })?;
+
+        self.catalog = self.parse_catalog_list(nav_list)?;
+        if let Some(nav_title) = nav_title {
+            self.catalog_title = nav_title.text();
+        };
+
+        let page_list = nav_element
+            .find_elements_by_name("nav")
+            .find(|&element| element.get_attr("epub:type") == Some(String::from("page-list")))
+            .and_then(|element| element.find_children_by_name("ol").next());
+        if let Some(page_list) = page_list {
+            self.page_list = self.parse_catalog_list(page_list)?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the path of the EPUB 3 navigation document
+    ///
+    /// ## Return
+    /// - `Ok(PathBuf)`: The path of the manifest item with the `nav` property
+    /// - `Err(EpubError)`: No manifest item declares the `nav` property
+    fn nav_document_path(&self) -> Result<PathBuf, EpubError> {
+        self.first_item_with_property("nav")
+            .map(|item| item.path.clone())
+            .ok_or_else(|| EpubError::NonCanonicalEpub {
+                expected_file: "Navigation Document".to_string(),
+            })
+    }
+
+    /// Retrieves every navigation section of the navigation document
+    ///
+    /// Besides the table of contents, the EPUB 3 navigation document may contain other
+    /// `<nav>` elements, such as `page-list`, `landmarks`, `loi` (list of illustrations)
+    /// and `lot` (list of tables). This function parses each of them into a tree of
+    /// navigation points, in the same form as `catalog`.
+    ///
+    /// ## Return
+    /// - `Ok(HashMap<String, Vec<NavPoint>>)`: The navigation points of each section,
+    ///   keyed by the `epub:type` of its `<nav>` element
+    /// - `Err(EpubError)`: The navigation document is missing or malformed
+    ///
+    /// ## Notes
+    /// - `<nav>` elements without `epub:type` are skipped. When several elements share
+    ///   the same type, the first one is kept.
+    /// - The `content` of the navigation points is relative to the directory of the
+    ///   navigation document, just like that of `catalog`.
+    /// - When the catalog was read from the NCX file, that is, for EPUB 2 publications
+    ///   and EPUB 3 publications without a navigation document, the NCX navigation map
+    ///   is returned under `toc`, the page list under `page-list`, and each `navList`
+    ///   under its key in `nav_lists`.
+    pub fn nav_sections(&self) -> Result<HashMap<String, Vec<NavPoint>>, EpubError> {
+        let mut sections = HashMap::new();
+
+        // mirrors `parse_catalog`, which falls back to the NCX file when an EPUB 3
+        // publication has no navigation document
+        if self.version == EpubVersion::Version2_0 || self.nav_document_path().is_err() {
+            sections.insert("toc".to_string(), self.catalog.clone());
+            if !self.page_list.is_empty() {
+                sections.insert("page-list".to_string(), self.page_list.clone());
             }
+            for (key, nav_list) in self.nav_lists.iter() {
+                sections
+                    .entry(key.clone())
+                    .or_insert_with(|| nav_list.clone());
+            }
+            return Ok(sections);
+        }
+
+        let nav_path = self.nav_document_path()?;
+        let nav_file =
+            get_file_in_zip_archive(&mut *self.archive.lock()?, nav_path.to_str().unwrap())?
+                .decode()?;
+        let nav_element = XmlReader::parse(&nav_file)?;
+
+        for nav in nav_element.find_elements_by_name("nav") {
+            let nav_type = match nav.get_attr("epub:type") {
+                Some(nav_type) if !sections.contains_key(nav_type.trim()) => nav_type,
+                _ => continue,
+            };
+            let nav_list = nav
+                .find_children_by_name("ol")
+                .next()
+                .ok_or_else(|| EpubError::NonCanonicalFile { tag: "ol".to_string() })?;
+
+            sections.insert(
+                nav_type.trim().to_string(),
+                self.parse_catalog_list(nav_list)?,
+            );
         }
+
+        Ok(sections)
     }
 
     /// Check if the EPUB file contains `encryption.xml`
@@ -602,604 +1119,2388 @@ impl<R: Read + Seek> EpubDoc<R> {
         self.has_encryption
     }
 
-    /// Retrieves a list of metadata items
-    ///
-    /// This function retrieves all matching metadata items from the EPUB metadata
-    /// based on the specified attribute name (key). Metadata items may come from
-    /// the DC (Dublin Core) namespace or the OPF namespace and contain basic
-    /// information about the publication, such as title, author, identifier, etc.
+    /// Verifies the obfuscated fonts of the publication
     ///
-    /// ## Parameters
-    /// - `key`: The name of the metadata attribute to retrieve
+    /// This function de-obfuscates each font that the encryption data declares as
+    /// obfuscated with the IDPF or Adobe algorithm, using the key derived from the
+    /// unique identifier of the publication, and checks whether the result starts with
+    /// the signature of a font file. A font obfuscated with another identifier does not.
     ///
     /// ## Return
-    /// - `Some(Vec<MetadataItem>)`: A vector containing all matching metadata items
-    /// - `None`: If no matching metadata items are found
-    pub fn get_metadata(&self, key: &str) -> Option<Vec<MetadataItem>> {
-        let metadatas = self
-            .metadata
-            .iter()
-            .filter(|item| item.property == key)
-            .cloned()
-            .collect::<Vec<MetadataItem>>();
+    /// - `Ok(Vec<(String, bool)>)`: The path of each obfuscated font, and whether it
+    ///   is a valid font after de-obfuscation, in the order of the encryption data
+    /// - `Err(EpubError)`: An obfuscated font could not be read from the container
+    ///
+    /// ## Notes
+    /// - Resources encrypted with other methods are not included in the result.
+    pub fn verify_font_obfuscation(&self) -> Result<Vec<(String, bool)>, EpubError> {
+        let encryption = match &self.encryption {
+            Some(encryption) => encryption,
+            None => return Ok(vec![]),
+        };
 
-        (!metadatas.is_empty()).then_some(metadatas)
+        let mut archive = self.archive.lock()?;
+        let mut result = Vec::new();
+        for data in encryption {
+            let font = match data.method.as_str() {
+                "http://www.idpf.org/2008/embedding" => apply_idpf_obfuscation(
+                    &get_file_in_zip_archive(&mut archive, &data.data)?,
+                    &idpf_obfuscation_key(&self.unique_identifier),
+                ),
+                "http://ns.adobe.com/pdf/enc#RC" => apply_adobe_obfuscation(
+                    &get_file_in_zip_archive(&mut archive, &data.data)?,
+                    &adobe_obfuscation_key(&self.unique_identifier),
+                ),
+                _ => continue,
+            };
+
+            result.push((data.data.clone(), is_font_data(&font)));
+        }
+
+        Ok(result)
     }
 
-    /// Retrieves a list of values for specific metadata items
+    /// Retrieves the renditions declared in the container
     ///
-    /// This function retrieves the values ​​of all matching metadata items from
-    /// the EPUB metadata based on the given property name (key).
+    /// Each `rootfile` element of the META-INF/container.xml file declares a rendition.
+    /// Most publications have a single rendition, while multiple-rendition publications
+    /// may offer, for example, a reflowable and a fixed-layout rendition.
+    ///
+    /// ## Return
+    /// - `Vec<RenditionInfo>`: The renditions in declaration order, never empty
+    ///
+    /// ## Notes
+    /// - The first rendition is the default one, loaded when the document is opened.
+    pub fn available_renditions(&self) -> Vec<RenditionInfo> {
+        self.renditions.clone()
+    }
+
+    /// Opens another rendition of the publication
+    ///
+    /// This function parses the package document of the rendition at the specified
+    /// index, and replaces the metadata, manifest, spine, guide and navigation of the
+    /// document with those of the rendition. The reading position is reset to the
+    /// first spine item, while the fetch policy and the navigation options are kept.
     ///
     /// ## Parameters
-    /// - `key`: The name of the metadata attribute to retrieve
+    /// - `index`: The index of the rendition in `available_renditions`, starting from 0
     ///
     /// ## Return
-    /// - `Some(Vec<String>)`: A vector containing all matching metadata item values
-    /// - `None`: If no matching metadata items are found
-    pub fn get_metadata_value(&self, key: &str) -> Option<Vec<String>> {
-        let values = self
-            .metadata
-            .iter()
-            .filter(|item| item.property == key)
-            .map(|item| item.value.clone())
-            .collect::<Vec<String>>();
-
-        (!values.is_empty()).then_some(values)
+    /// - `Ok(())`: The rendition was opened
+    /// - `Err(EpubError)`: Index out of range, or the package document of the rendition
+    ///   could not be parsed, in which case the document is left unchanged
+    pub fn open_rendition(&mut self, index: usize) -> Result<(), EpubError> {
+        let rendition = self
+            .renditions
+            .get(index)
+            .ok_or(EpubError::RenditionIndexOutOfRange { index, len: self.renditions.len() })?;
+
+        let mut doc = Self::load_package(
+            Arc::clone(&self.archive),
+            self.epub_path.clone(),
+            self.renditions.clone(),
+            rendition.full_path.clone(),
+            self.parse_mode,
+            self.has_encryption,
+        )?;
+        doc.fetch_policy = self.fetch_policy.clone();
+        doc.skip_nav = self.skip_nav;
+
+        *self = doc;
+        Ok(())
     }
 
-    /// Retrieves the title of the publication
+    /// Retrieves the container-level metadata of the publication
     ///
-    /// This function retrieves all title information from the EPUB metadata.
-    /// According to the EPUB specification, a publication can have multiple titles,
-    /// which are returned in the order they appear in the metadata.
+    /// This function parses the `metadata.xml` file in the `META-INF` directory, which
+    /// carries metadata about the container rather than about a single rendition. Its
+    /// metadata elements are parsed the same way as those of the package document.
     ///
     /// ## Return
-    /// - `Result<Vec<String>, EpubError>`: A vector containing all title information
-    /// - `EpubError`: If and only if the OPF file does not contain `<dc:title>`
-    ///
-    /// ## Notes
-    /// - The EPUB specification requires each publication to have at least one title.
-    #[inline]
-    pub fn get_title(&self) -> Vec<String> {
-        self.get_metadata_value("title")
-            .expect("missing required 'title' metadata which is required by the EPUB specification")
+    /// - `Ok(Some(Vec<MetadataItem>))`: The metadata items of the container
+    /// - `Ok(None)`: The publication does not contain a `metadata.xml` file
+    /// - `Err(EpubError)`: The metadata file could not be read or is malformed
+    pub fn container_metadata(&self) -> Result<Option<Vec<MetadataItem>>, EpubError> {
+        let mut archive = self.archive.lock()?;
+        let metadata_file = match archive.by_name("META-INF/metadata.xml") {
+            Ok(mut file) => {
+                let mut entry = Vec::<u8>::new();
+                file.read_to_end(&mut entry)?;
+                entry.decode()?
+            }
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        drop(archive);
+
+        let root = XmlReader::parse(&metadata_file)?;
+        let mut metadata = Vec::new();
+        self.collect_metadata(&root, &mut metadata, &mut vec![], &mut HashMap::new())?;
+
+        Ok(Some(metadata))
     }
 
-    /// Retrieves the language used in the publication
+    /// Retrieves the digital signatures of the publication
     ///
-    /// This function retrieves the language information of a publication from the EPUB
-    /// metadata. According to the EPUB specification, language information identifies
-    /// the primary language of the publication and can have multiple language identifiers.
+    /// This function parses the `signatures.xml` file in the `META-INF` directory.
+    /// Each `<Reference>` of each `<Signature>` is returned as one item, describing
+    /// which resource is signed and with which algorithms.
     ///
     /// ## Return
-    /// - `Ok(Vec<String>)`: A vector containing all language identifiers
-    /// - `Err(EpubError)`: If and only if the OPF file does not contain `<dc:language>`
+    /// - `Ok(Vec<Signature>)`: The signed references, empty if the publication is not signed
+    /// - `Err(EpubError)`: The signatures file could not be read or is malformed
     ///
     /// ## Notes
-    /// - The EPUB specification requires that each publication specify at least one primary language.
-    /// - Language identifiers should conform to RFC 3066 or later standards.
-    #[inline]
-    pub fn get_language(&self) -> Vec<String> {
-        self.get_metadata_value("language").expect(
-            "missing required 'language' metadata which is required by the EPUB specification",
-        )
+    /// - This function only parses the signatures; it does not verify them.
+    pub fn signatures(&self) -> Result<Vec<Signature>, EpubError> {
+        let mut archive = self.archive.lock()?;
+        let signatures_file = match archive.by_name("META-INF/signatures.xml") {
+            Ok(mut file) => {
+                let mut entry = Vec::<u8>::new();
+                file.read_to_end(&mut entry)?;
+                entry.decode()?
+            }
+            Err(ZipError::FileNotFound) => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+        drop(archive);
+
+        let root = XmlReader::parse(&signatures_file)?;
+
+        let mut signatures = Vec::new();
+        for signature in root.children() {
+            if signature.name != "Signature" {
+                continue;
+            }
+
+            let signature_method = signature
+                .find_elements_by_name("SignatureMethod")
+                .next()
+                .ok_or_else(|| EpubError::NonCanonicalFile { tag: "SignatureMethod".to_string() })?
+                .get_attr("Algorithm")
+                .ok_or_else(|| EpubError::MissingRequiredAttribute {
+                    tag: "SignatureMethod".to_string(),
+                    attribute: "Algorithm".to_string(),
+                })?;
+
+            for reference in signature.find_elements_by_name("Reference") {
+                let digest_method = reference
+                    .find_elements_by_name("DigestMethod")
+                    .next()
+                    .ok_or_else(|| EpubError::NonCanonicalFile { tag: "DigestMethod".to_string() })?
+                    .get_attr("Algorithm")
+                    .ok_or_else(|| EpubError::MissingRequiredAttribute {
+                        tag: "DigestMethod".to_string(),
+                        attribute: "Algorithm".to_string(),
+                    })?;
+
+                signatures.push(Signature {
+                    reference_uri: reference.get_attr("URI").unwrap_or_default(),
+                    signature_method: signature_method.clone(),
+                    digest_method,
+                });
+            }
+        }
+
+        Ok(signatures)
     }
 
-    /// Retrieves the identifier of a publication
+    /// Checks whether a file exists in the EPUB container
     ///
-    /// This function retrieves the identifier information of a publication from
-    /// the EPUB metadata. According to the EPUB specification, each publication
-    /// must have a identifier, typically an ISBN, UUID, or other unique identifier.
+    /// Unlike the manifest, which lists the declared resources, this function checks
+    /// the archive itself. Together they distinguish a resource that is declared but
+    /// missing from one that is not declared at all.
     ///
-    /// ## Return
-    /// - `Ok(Vec<String>)`: A vector containing all identifier information
-    /// - `Err(EpubError)`: If and only if the OPF file does not contain `<dc:identifier>`
+    /// ## Parameters
+    /// - `path`: The path of the file relative to the root of the container,
+    ///   such as the `path` of a manifest item
     ///
-    /// ## Notes
-    /// - The EPUB specification requires each publication to have at least one identifier.
-    /// - In the OPF file, the `unique-identifier` attribute of the `<package>` element
-    ///   should point to a `<dc:identifier>` element used to uniquely identify the publication.
-    ///   This means that `unique-identifier` is not exactly equal to `<dc:identifier>`.
-    #[inline]
-    pub fn get_identifier(&self) -> Vec<String> {
-        self.get_metadata_value("identifier").expect(
-            "missing required 'identifier' metadata which is required by the EPUB specification",
-        )
+    /// ## Return
+    /// - `true` if the file exists in the container
+    /// - `false` if the file does not exist, or the archive could not be accessed
+    pub fn zip_contains(&self, path: &str) -> bool {
+        self.archive
+            .lock()
+            .is_ok_and(|archive| archive.index_for_name(path).is_some())
     }
 
-    /// Retrieves a unified metadata sheet from the EPUB publication
+    /// Retrieves the names of all entries in the container
     ///
-    /// This function consolidates all metadata from the EPUB into a single `MetadataSheet`
-    /// structure, providing a simplified interface for metadata access. It handles both
-    /// EPUB 2 and EPUB 3 metadata formats, including refinements from EPUB 3.
+    /// The names are listed in the order of the central directory of the archive,
+    /// including the `mimetype` file, the files in `META-INF` and the package
+    /// document, as well as directory entries.
     ///
     /// ## Return
-    /// - `MetadataSheet`: A populated metadata sheet containing all publication metadata
-    ///
-    /// ## Notes
-    /// - Multi-value metadata (title, creator, etc.) are stored in Vec fields in order
-    /// - Date metadata extracts event type from refinements (e.g., "publication", "modification")
-    /// - Identifier metadata uses item IDs as keys in the HashMap
-    pub fn get_metadata_sheet(&self) -> MetadataSheet {
-        let mut sheet = MetadataSheet::new();
-        for item in &self.metadata {
-            let value = item.value.clone();
+    /// - `Vec<String>`: The entry names, empty if the archive could not be accessed
+    pub fn archive_entry_names(&self) -> Vec<String> {
+        let Ok(archive) = self.archive.lock() else {
+            return vec![];
+        };
 
-            match item.property.as_str() {
-                "title" => {
-                    sheet.title.push(value);
-                }
-                "creator" => {
-                    sheet.creator.push(value);
-                }
-                "contributor" => {
-                    sheet.contributor.push(value);
-                }
-                "subject" => {
-                    sheet.subject.push(value);
-                }
-                "language" => {
-                    sheet.language.push(value);
-                }
-                "relation" => {
-                    sheet.relation.push(value);
-                }
-                "date" => {
-                    let event = item
-                        .refined
-                        .iter()
-                        .filter_map(|refine| {
-                            if refine.property.eq("event") {
-                                Some(refine.value.clone())
-                            } else {
-                                None
-                            }
-                        })
-                        .next()
-                        .unwrap_or_default();
-                    sheet.date.insert(value, event);
-                }
-                "identifier" => {
-                    let id = item.id.clone().unwrap_or_default();
-                    sheet.identifier.insert(id, value);
-                }
-                "description" => {
-                    sheet.description = value;
-                }
-                "format" => {
-                    sheet.format = value;
-                }
-                "publisher" => {
-                    sheet.publisher = value;
-                }
-                "rights" => {
-                    sheet.rights = value;
-                }
-                "source" => {
-                    sheet.source = value;
-                }
-                "ccoverage" => {
-                    sheet.coverage = value;
-                }
-                "type" => {
-                    sheet.epub_type = value;
-                }
-                _ => {}
-            };
-        }
+        (0..archive.len())
+            .filter_map(|index| archive.name_for_index(index).map(str::to_string))
+            .collect()
+    }
 
-        sheet
+    /// Sets how resources located outside of the container are retrieved
+    ///
+    /// By default, retrieving a remote resource fails with `EpubError::RemoteResource`.
+    ///
+    /// ## Parameters
+    /// - `policy`: The fetch policy to apply to subsequent resource retrievals
+    pub fn set_fetch_policy(&mut self, policy: FetchPolicy) -> &mut Self {
+        self.fetch_policy = policy;
+        self
     }
 
-    /// Retrieve resource data by resource ID
+    /// Sets whether spine navigation skips the navigation document
     ///
-    /// This function will find the resource with the specified ID in the manifest.
-    /// If the resource is encrypted, it will be automatically decrypted.
+    /// The EPUB 3 navigation document is often included in the spine, while many
+    /// reading systems do not present it as part of the page flow. When enabled,
+    /// `spine_prev` and `spine_next` pass over the spine items for which
+    /// `spine_item_is_nav` returns `true`.
     ///
     /// ## Parameters
-    /// - `id`: The ID of the resource to retrieve
+    /// - `skip`: Whether to skip the navigation document, `false` by default
+    pub fn set_skip_nav(&mut self, skip: bool) -> &mut Self {
+        self.skip_nav = skip;
+        self
+    }
+
+    /// Retrieves the manifest items that make use of remote resources
     ///
-    /// ## Return
-    /// - `Ok((Vec<u8>, String))`: Successfully retrieved and decrypted resource data and
-    ///   the MIME type
-    /// - `Err(EpubError)`: Errors that occurred during the retrieval process
+    /// This function returns the manifest items whose `properties` contain the
+    /// `remote-resources` token, that is, the publication resources which reference
+    /// resources located outside of the EPUB container.
+    pub fn remote_resources(&self) -> Vec<&ManifestItem> {
+        self.manifest_items_with_property("remote-resources")
+            .collect()
+    }
+
+    /// Checks whether the publication contains scripted content
     ///
-    /// ## Notes
-    /// - This function will automatically decrypt the resource if it is encrypted.
-    /// - For unsupported encryption methods, the corresponding error will be returned.
-    pub fn get_manifest_item(&self, id: &str) -> Result<(Vec<u8>, String), EpubError> {
-        let resource_item = self
-            .manifest
-            .get(id)
-            .ok_or_else(|| EpubError::ResourceIdNotExist { id: id.to_string() })?;
+    /// Returns `true` if any manifest item carries the `scripted` property.
+    pub fn uses_scripts(&self) -> bool {
+        self.manifest_items_with_property("scripted")
+            .next()
+            .is_some()
+    }
 
-        self.get_resource(resource_item)
+    /// Checks whether the publication contains MathML
+    ///
+    /// Returns `true` if any manifest item carries the `mathml` property.
+    pub fn uses_mathml(&self) -> bool {
+        self.manifest_items_with_property("mathml").next().is_some()
     }
 
-    /// Retrieves resource item data by resource path
+    /// Checks whether the publication references remote resources
     ///
-    /// This function retrieves resources from the manifest based on the input path.
-    /// The input path must be a relative path to the root directory of the EPUB container;
-    /// using an absolute path or a relative path to another location will result in an error.
+    /// Returns `true` if any manifest item carries the `remote-resources` property.
+    pub fn uses_remote_resources(&self) -> bool {
+        self.manifest_items_with_property("remote-resources")
+            .next()
+            .is_some()
+    }
+
+    /// Retrieves the manifest items with the given property
+    ///
+    /// The `properties` attribute of a manifest item is a space-separated list of
+    /// tokens, such as `nav`, `cover-image` or `mathml`. An item is returned when one
+    /// of its tokens is equal to `property`.
     ///
     /// ## Parameters
-    /// - `path`: The path of the resource to retrieve
+    /// - `property`: The property to look for, e.g. `nav`
     ///
     /// ## Return
-    /// - `Ok((Vec<u8>, String))`: Successfully retrieved and decrypted resource data and
-    ///   the MIME type
-    /// - `Err(EpubError)`: Errors that occurred during the retrieval process
-    ///
-    /// ## Notes
-    /// - This function will automatically decrypt the resource if it is encrypted.
-    /// - For unsupported encryption methods, the corresponding error will be returned.
-    /// - Relative paths other than the root directory of the Epub container are not supported.
-    pub fn get_manifest_item_by_path(&self, path: &str) -> Result<(Vec<u8>, String), EpubError> {
-        let manifest = self
-            .manifest
-            .iter()
-            .find(|(_, item)| item.path.to_str().unwrap() == path)
-            .map(|(_, manifest)| manifest)
-            .ok_or_else(|| EpubError::ResourceNotFound { resource: path.to_string() })?;
-
-        self.get_resource(manifest)
+    /// - `Vec<&ManifestItem>`: The manifest items with the property, in manifest order
+    ///   unless the `no-indexmap` feature is enabled
+    pub fn items_with_property(&self, property: &str) -> Vec<&ManifestItem> {
+        self.manifest_items_with_property(property).collect()
     }
 
-    /// Retrieves supported resource items by resource ID, with fallback mechanism supported
-    ///
-    /// This function attempts to retrieve the resource item with the specified ID and
-    /// checks if its MIME type is in the list of supported formats. If the current resource
-    /// format is not supported, it searches for a supported resource format along the
-    /// fallback chain according to the fallback mechanism defined in the EPUB specification.
+    /// Retrieves the first manifest item with the given property
     ///
     /// ## Parameters
-    /// - `id`: The ID of the resource to retrieve
-    /// - `supported_format`: A vector of supported MIME types
+    /// - `property`: The property to look for, e.g. `cover-image`
     ///
     /// ## Return
-    /// - `Ok((Vec<u8>, String))`: Successfully retrieved and decrypted resource data and
-    ///   the MIME type
-    /// - `Err(EpubError)`: Errors that occurred during the retrieval process
-    pub fn get_manifest_item_with_fallback(
-        &self,
-        id: &str,
-        supported_format: &[&str],
-    ) -> Result<(Vec<u8>, String), EpubError> {
-        let mut current_id = id;
-        let mut fallback_chain = Vec::<&str>::new();
-        'fallback: loop {
-            let manifest_item = self
-                .manifest
-                .get(current_id)
-                .ok_or_else(|| EpubError::ResourceIdNotExist { id: id.to_string() })?;
-
-            if supported_format.contains(&manifest_item.mime.as_str()) {
-                return self.get_resource(manifest_item);
-            }
-
-            let fallback_id = match &manifest_item.fallback {
-                // The loop ends when no fallback resource exists
-                None => break 'fallback,
-
-                // End the loop when the loop continues to fallback if a fallback resource exists
-                Some(id) if fallback_chain.contains(&id.as_str()) => break 'fallback,
-
-                Some(id) => {
-                    fallback_chain.push(id.as_str());
+    /// - `Some(&ManifestItem)`: The first manifest item with the property, see
+    ///   [`items_with_property`](Self::items_with_property)
+    /// - `None`: No manifest item has the property
+    pub fn first_item_with_property(&self, property: &str) -> Option<&ManifestItem> {
+        self.manifest_items_with_property(property).next()
+    }
 
-                    // Since only warnings are issued for fallback resource checks
-                    // during initialization, the issue of fallback resources possibly
-                    // not existing needs to be handled here.
-                    id.as_str()
-                }
-            };
+    /// Iterates over the manifest items whose `properties` contain the given token
+    fn manifest_items_with_property<'a>(
+        &'a self,
+        property: &str,
+    ) -> impl Iterator<Item = &'a ManifestItem> {
+        self.manifest.values().filter(move |item| {
+            item.properties.as_ref().is_some_and(|properties| {
+                properties.split_whitespace().any(|token| token == property)
+            })
+        })
+    }
 
-            current_id = fallback_id;
-        }
+    /// Retrieves the problems repaired while parsing
+    ///
+    /// In `ParseMode::Lenient`, recoverable specification violations do not interrupt
+    /// parsing. Each of them is described by a message in the returned list instead.
+    /// In `ParseMode::Strict` these violations are returned as errors, so the list is
+    /// usually empty.
+    #[inline]
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
 
-        Err(EpubError::NoSupportedFileFormat)
+    /// Retrieves the problems found in the play orders of the NCX navigation points
+    ///
+    /// The problems are listed in the order of the navigation points in the NCX
+    /// document, and are also reported by `warnings`. In `ParseMode::Strict` the first
+    /// problem is returned as `EpubError::InvalidPlayOrder` instead, so the list is
+    /// always empty.
+    #[inline]
+    pub fn play_order_issues(&self) -> &[PlayOrderIssue] {
+        &self.play_order_issues
     }
 
-    /// Retrieves the cover of the EPUB document
+    /// Retrieves the raw content of the package document
     ///
-    /// This function searches for the cover of the EPUB document by examining manifest
-    /// items in the manifest. It looks for manifest items whose ID or attribute contains
-    /// "cover" (case-insensitive) and attempts to retrieve the content of the first match.
+    /// This function reads the OPF file at `package_path` from the container as is,
+    /// so that consumers can process the package document with their own tools.
     ///
     /// ## Return
-    /// - `Some((Vec<u8>, String))`: Successfully retrieved and decrypted cover data and
-    ///   the MIME type
-    /// - `None`: No cover resource was found
-    ///
-    /// ## Notes
-    /// - This function only returns the first successfully retrieved cover resource,
-    ///   even if multiple matches exist
-    /// - The retrieved cover may not be an image resource; users need to pay attention
-    ///   to the resource's MIME type.
-    pub fn get_cover(&self) -> Option<(Vec<u8>, String)> {
-        self.manifest
-            .values()
-            .filter(|manifest| {
-                manifest.id.to_ascii_lowercase().contains("cover")
-                    || manifest
-                        .properties
-                        .as_ref()
-                        .map(|properties| properties.to_ascii_lowercase().contains("cover"))
-                        .unwrap_or(false)
-            })
-            .find_map(|manifest| {
-                self.get_resource(manifest)
-                    .map_err(|err| log::warn!("{err}"))
-                    .ok()
-            })
-    }
-
-    /// Retrieves resource data by manifest item
-    fn get_resource(&self, resource_item: &ManifestItem) -> Result<(Vec<u8>, String), EpubError> {
-        let path = resource_item
-            .path
-            .to_str()
-            .expect("manifest item path should be valid UTF-8");
-
+    /// - `Ok(Vec<u8>)`: The raw content of the package document
+    /// - `Err(EpubError)`: The package document could not be read from the archive
+    pub fn package_document_bytes(&self) -> Result<Vec<u8>, EpubError> {
         let mut archive = self.archive.lock()?;
-        let mut data = match archive.by_name(path) {
-            Ok(mut file) => {
-                let mut entry = Vec::<u8>::new();
-                file.read_to_end(&mut entry)?;
-                Ok(entry)
-            }
-            Err(ZipError::FileNotFound) => {
-                Err(EpubError::ResourceNotFound { resource: path.to_string() })
-            }
-            Err(err) => Err(EpubError::from(err)),
-        }?;
+        get_file_in_zip_archive(&mut archive, &self.package_path.to_string_lossy())
+    }
 
-        if let Some(method) = self.is_encryption_file(path) {
-            data = self.auto_dencrypt(&method, &mut data)?;
-        }
+    /// Retrieves a list of metadata items
+    ///
+    /// This function retrieves all matching metadata items from the EPUB metadata
+    /// based on the specified attribute name (key). Metadata items may come from
+    /// the DC (Dublin Core) namespace or the OPF namespace and contain basic
+    /// information about the publication, such as title, author, identifier, etc.
+    ///
+    /// ## Parameters
+    /// - `key`: The name of the metadata attribute to retrieve
+    ///
+    /// ## Return
+    /// - `Some(Vec<MetadataItem>)`: A vector containing all matching metadata items
+    /// - `None`: If no matching metadata items are found
+    pub fn get_metadata(&self, key: &str) -> Option<Vec<MetadataItem>> {
+        let metadatas = self
+            .metadata
+            .iter()
+            .filter(|item| item.property == key)
+            .cloned()
+            .collect::<Vec<MetadataItem>>();
 
-        Ok((data, resource_item.mime.clone()))
+        (!metadatas.is_empty()).then_some(metadatas)
     }
 
-    /// Navigate to a specified chapter using the spine index
+    /// Retrieves a list of metadata items by the full IRI of their property
     ///
-    /// This function retrieves the content data of the corresponding chapter based
-    /// on the index position in the EPUB spine. The spine defines the linear reading
-    /// order of the publication's content documents, and each spine item references
-    /// resources in the manifest.
+    /// Unlike `get_metadata`, which compares property names literally, this function
+    /// expands the prefix of each property with the prefixes declared in the package
+    /// and the prefixes reserved by the EPUB 3 specification. This allows properties
+    /// to be found regardless of the prefix the publication uses for them, e.g.
+    /// `http://purl.org/dc/terms/modified` matches both `dcterms:modified` and
+    /// `terms:modified` when `terms` is declared with the same IRI. Dublin Core
+    /// elements are expanded with the Dublin Core namespace.
     ///
     /// ## Parameters
-    /// - `index`: The index position in the spine, starting from 0
+    /// - `uri`: The full IRI of the metadata property to retrieve
     ///
     /// ## Return
-    /// - `Some((Vec<u8>, String))`: Successfully retrieved chapter content data and the MIME type
-    /// - `None`: Index out of range or data retrieval error
-    ///
-    /// ## Notes
-    /// - The index must be less than the total number of spine projects.
-    /// - If the resource is encrypted, it will be automatically decrypted before returning.
-    /// - It does not check whether the Spine project follows a linear reading order.
-    pub fn navigate_by_spine_index(&mut self, index: usize) -> Option<(Vec<u8>, String)> {
-        if index >= self.spine.len() {
-            return None;
-        }
+    /// - `Some(Vec<MetadataItem>)`: A vector containing all matching metadata items
+    /// - `None`: If no matching metadata items are found
+    pub fn get_metadata_by_uri(&self, uri: &str) -> Option<Vec<MetadataItem>> {
+        let metadatas = self
+            .metadata
+            .iter()
+            .filter(|item| {
+                self.expand_property(&item.property)
+                    .is_some_and(|iri| iri == uri)
+            })
+            .cloned()
+            .collect::<Vec<MetadataItem>>();
 
-        let manifest_id = self.spine[index].idref.as_ref();
-        self.current_spine_index.store(index, Ordering::SeqCst);
-        self.get_manifest_item(manifest_id)
-            .map_err(|err| log::warn!("{err}"))
-            .ok()
+        (!metadatas.is_empty()).then_some(metadatas)
     }
 
-    /// Navigate to the previous linear reading chapter
+    /// Expands a metadata property into its full IRI
     ///
-    /// This function searches backwards in the EPUB spine for the previous linear
-    /// reading chapter and returns the content data of that chapter. It only navigates
-    /// to chapters marked as linear reading.
+    /// ## Parameters
+    /// - `property`: The property of a metadata item, such as `dcterms:modified`
     ///
     /// ## Return
-    /// - `Some((Vec<u8>, String))`: Successfully retrieved previous chapter content data and
-    ///   the MIME type
-    /// - `None`: Already in the first chapter, the current chapter is not linear,
-    ///   or data retrieval failed
-    pub fn spine_prev(&self) -> Option<(Vec<u8>, String)> {
-        let current_index = self.current_spine_index.load(Ordering::SeqCst);
-        if current_index == 0 || !self.spine[current_index].linear {
-            return None;
+    /// - `Some(String)`: The full IRI of the property
+    /// - `None`: The prefix of the property is unknown, or the property of an EPUB 2
+    ///   meta element has no vocabulary
+    fn expand_property(&self, property: &str) -> Option<String> {
+        const DC_ELEMENTS: [&str; 15] = [
+            "contributor",
+            "coverage",
+            "creator",
+            "date",
+            "description",
+            "format",
+            "identifier",
+            "language",
+            "publisher",
+            "relation",
+            "rights",
+            "source",
+            "subject",
+            "title",
+            "type",
+        ];
+
+        match property.split_once(':') {
+            Some((prefix, reference)) => self
+                .prefixes
+                .get(prefix)
+                .map(String::as_str)
+                .or_else(|| {
+                    RESERVED_PREFIXES
+                        .iter()
+                        .find(|(name, _)| *name == prefix)
+                        .map(|(_, iri)| *iri)
+                })
+                .map(|iri| format!("{iri}{reference}")),
+            None if DC_ELEMENTS.contains(&property) => Some(format!("{DC_NAMESPACE}{property}")),
+            None if self.version == EpubVersion::Version3_0 => {
+                Some(format!("{META_VOCABULARY}{property}"))
+            }
+            None => None,
         }
-
-        let prev_index = (0..current_index)
-            .rev()
-            .find(|&index| self.spine[index].linear)?;
-
-        self.current_spine_index.store(prev_index, Ordering::SeqCst);
-        let manifest_id = self.spine[prev_index].idref.as_ref();
-        self.get_manifest_item(manifest_id)
-            .map_err(|err| log::warn!("{err}"))
-            .ok()
     }
 
-    /// Navigate to the next linear reading chapter
+    /// Parses the value of the `prefix` attribute of the package element
     ///
-    /// This function searches forwards in the EPUB spine for the next linear reading
-    /// chapter and returns the content data of that chapter. It only navigates to
-    /// chapters marked as linear reading.
+    /// The attribute is a whitespace-separated list of `prefix: IRI` pairs. Malformed
+    /// pairs are ignored, and the first declaration of a prefix wins.
+    ///
+    /// ## Parameters
+    /// - `value`: The value of the `prefix` attribute
     ///
     /// ## Return
-    /// - `Some((Vec<u8>, String))`: Successfully retrieved next chapter content data and
-    ///   the MIME type
-    /// - `None`: Already in the last chapter, the current chapter is not linear,
-    ///   or data retrieval failed
-    pub fn spine_next(&mut self) -> Option<(Vec<u8>, String)> {
-        let current_index = self.current_spine_index.load(Ordering::SeqCst);
-        if current_index >= self.spine.len() - 1 || !self.spine[current_index].linear {
-            return None;
-        }
+    /// - `HashMap<String, String>`: The declared prefixes, mapped to their IRIs
+    fn parse_prefixes(value: &str) -> HashMap<String, String> {
+        let mut prefixes = HashMap::new();
+        let mut tokens = value.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let Some(name) = token.strip_suffix(':').filter(|name| !name.is_empty()) else {
+                continue;
+            };
+            let Some(iri) = tokens.next() else {
+                break;
+            };
 
-        let next_index =
-            (current_index + 1..self.spine.len()).find(|&index| self.spine[index].linear)?;
+            prefixes
+                .entry(name.to_string())
+                .or_insert_with(|| iri.to_string());
+        }
 
-        self.current_spine_index.store(next_index, Ordering::SeqCst);
-        let manifest_id = self.spine[next_index].idref.as_ref();
-        self.get_manifest_item(manifest_id)
-            .map_err(|err| log::warn!("{err}"))
-            .ok()
+        prefixes
     }
 
-    /// Retrieves the content data of the current chapter
+    /// Retrieves a list of values for specific metadata items
     ///
-    /// This function returns the content data of the chapter at the current
-    /// index position in the EPUB spine.
+    /// This function retrieves the values ​​of all matching metadata items from
+    /// the EPUB metadata based on the given property name (key).
+    ///
+    /// ## Parameters
+    /// - `key`: The name of the metadata attribute to retrieve
     ///
     /// ## Return
-    /// - `Some((Vec<u8>, String))`: Successfully retrieved current chapter content data and
-    ///   the MIME type
-    /// - `None`: Data retrieval failed
-    pub fn spine_current(&self) -> Option<(Vec<u8>, String)> {
-        let manifest_id = self.spine[self.current_spine_index.load(Ordering::SeqCst)]
-            .idref
-            .as_ref();
-        self.get_manifest_item(manifest_id)
-            .map_err(|err| log::warn!("{err}"))
-            .ok()
+    /// - `Some(Vec<String>)`: A vector containing all matching metadata item values
+    /// - `None`: If no matching metadata items are found
+    pub fn get_metadata_value(&self, key: &str) -> Option<Vec<String>> {
+        let values = self
+            .metadata
+            .iter()
+            .filter(|item| item.property == key)
+            .map(|item| item.value.clone())
+            .collect::<Vec<String>>();
+
+        (!values.is_empty()).then_some(values)
     }
 
-    /// Determine the EPUB version from the OPF file
+    /// Retrieves the value of an EPUB 2 meta element
     ///
-    /// This function is used to detect the version of an epub file from an OPF file.
-    /// When the version attribute in the package is abnormal, version information will
-    /// be identified through some version characteristics of the epub file. An error is
-    /// returned when neither direct nor indirect methods can identify the version.
+    /// EPUB 2 meta elements are name and content pairs, such as `<meta name="cover"
+    /// content="cover-image"/>`. They are also accepted in EPUB 3 publications, which
+    /// often keep them for compatibility with older reading systems.
     ///
     /// ## Parameters
-    /// - `opf_element`: A reference to the OPF file element
-    fn determine_epub_version(opf_element: &XmlElement) -> Result<EpubVersion, EpubError> {
-        // Check the explicit version attribute
-        if let Some(version) = opf_element.get_attr("version") {
-            match version.as_str() {
-                "2.0" => return Ok(EpubVersion::Version2_0),
-                "3.0" => return Ok(EpubVersion::Version3_0),
-                _ => {}
-            }
-        }
+    /// - `name`: The name of the meta element
+    ///
+    /// ## Return
+    /// - `Some(String)`: The content of the first meta element with the given name
+    /// - `None`: No meta element has the given name
+    ///
+    /// ## Notes
+    /// - Meta elements are stored as ordinary metadata items, so an EPUB 3 metadata
+    ///   item whose property equals `name` is returned as well.
+    pub fn meta_name(&self, name: &str) -> Option<String> {
+        self.metadata
+            .iter()
+            .find(|item| item.property == name)
+            .map(|item| item.value.clone())
+    }
 
-        let spine_element = opf_element
-            .find_elements_by_name("spine")
-            .next()
-            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "spine".to_string() })?;
+    /// Retrieves the series information written by Calibre
+    ///
+    /// Calibre records the series of a publication in the `calibre:series` and
+    /// `calibre:series_index` meta elements.
+    ///
+    /// ## Return
+    /// - `Some((String, Option<f64>))`: The name of the series, and the position of the
+    ///   publication in it when present and numeric
+    /// - `None`: The publication carries no `calibre:series` meta element
+    pub fn calibre_series(&self) -> Option<(String, Option<f64>)> {
+        let series = self.meta_name("calibre:series")?;
+        let index = self
+            .meta_name("calibre:series_index")
+            .and_then(|index| index.parse::<f64>().ok());
+
+        Some((series, index))
+    }
 
-        // Look for EPUB 2.x specific features
-        if spine_element.get_attr("toc").is_some() {
-            return Ok(EpubVersion::Version2_0);
-        }
+    /// Retrieves the metadata items refined by a specific refinement
+    ///
+    /// This function retrieves the metadata items with the given property that carry
+    /// a refinement with the given property and value, e.g. the creators whose `role`
+    /// is `aut`.
+    ///
+    /// ## Parameters
+    /// - `property`: The name of the metadata attribute to retrieve
+    /// - `refinement_property`: The property of the refinement to match
+    /// - `refinement_value`: The value of the refinement to match
+    ///
+    /// ## Return
+    /// - `Vec<&MetadataItem>`: The matching metadata items in document order,
+    ///   empty if none matches
+    pub fn get_metadata_where(
+        &self,
+        property: &str,
+        refinement_property: &str,
+        refinement_value: &str,
+    ) -> Vec<&MetadataItem> {
+        self.metadata
+            .iter()
+            .filter(|item| item.property == property)
+            .filter(|item| {
+                item.refined.iter().any(|refinement| {
+                    refinement.property == refinement_property
+                        && refinement.value == refinement_value
+                })
+            })
+            .collect()
+    }
 
-        let manifest_element = opf_element
-            .find_elements_by_name("manifest")
-            .next()
-            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "manifest".to_string() })?;
+    /// Retrieves the values of a refinement across the metadata items of a property
+    ///
+    /// This function collects the values of the refinements with the given property
+    /// from all metadata items with the given property, e.g. the `file-as` values of
+    /// all creators, or the `title-type` values of all titles.
+    ///
+    /// ## Parameters
+    /// - `property`: The name of the metadata attribute whose items are inspected
+    /// - `refinement_property`: The property of the refinements to collect
+    ///
+    /// ## Return
+    /// - `Vec<String>`: The refinement values in document order, empty if none matches
+    pub fn get_refinement_values(&self, property: &str, refinement_property: &str) -> Vec<String> {
+        self.metadata
+            .iter()
+            .filter(|item| item.property == property)
+            .flat_map(|item| item.refined.iter())
+            .filter(|refinement| refinement.property == refinement_property)
+            .map(|refinement| refinement.value.clone())
+            .collect()
+    }
 
-        // Look for EPUB 3.x specific features
-        manifest_element
-            .children()
-            .find_map(|element| {
-                if let Some(id) = element.get_attr("id") {
-                    if id.eq("nav") {
-                        return Some(EpubVersion::Version3_0);
-                    }
-                }
+    /// Retrieves the total playback time of the media overlays
+    ///
+    /// The total duration is declared by the `media:duration` metadata item that
+    /// does not refine any element.
+    ///
+    /// ## Return
+    /// - `Some(Duration)`: The total duration of the publication
+    /// - `None`: The duration is not declared or is not a valid clock value
+    pub fn total_duration(&self) -> Option<Duration> {
+        self.metadata
+            .iter()
+            .find(|item| item.property == "media:duration")
+            .and_then(|item| parse_clock_value(&item.value))
+    }
 
-                None
-            })
-            .ok_or(EpubError::UnrecognizedEpubVersion)
+    /// Retrieves the playback time of a media overlay
+    ///
+    /// The duration of each media overlay is declared by a `media:duration`
+    /// metadata item refining the manifest item of the overlay.
+    ///
+    /// ## Parameters
+    /// - `content_id`: The manifest id of the media overlay document
+    ///
+    /// ## Return
+    /// - `Some(Duration)`: The duration of the media overlay
+    /// - `None`: The duration is not declared or is not a valid clock value
+    pub fn overlay_duration(&self, content_id: &str) -> Option<Duration> {
+        self.resource_refinements
+            .get(content_id)?
+            .iter()
+            .find(|refinement| refinement.property == "media:duration")
+            .and_then(|refinement| parse_clock_value(&refinement.value))
     }
 
-    /// Parse metadata elements under the Dublin Core namespace
+    /// Retrieves the title of the publication
     ///
-    /// This function handles the `<metadata>` Dublin Core element in the OPF file (namespace
-    /// is "http://purl.org/dc/elements/1.1/"). These elements usually contain the basic
-    /// information of the publication, such as title, author, publication date, etc.
+    /// This function retrieves all title information from the EPUB metadata.
+    /// According to the EPUB specification, a publication can have multiple titles,
+    /// which are returned in the order they appear in the metadata.
+    ///
+    /// ## Return
+    /// - `Result<Vec<String>, EpubError>`: A vector containing all title information
+    /// - `EpubError`: If and only if the OPF file does not contain `<dc:title>`
     ///
     /// ## Notes
-    /// - In EPUB 3.0, granular information is handled by separate '<meta>' elements and 'refines' attributes
-    /// - All text content is normalized by whitespace
+    /// - The EPUB specification requires each publication to have at least one title.
     #[inline]
-    fn parse_dc_metadata(
-        &self,
-        element: &XmlElement,
-        metadata: &mut Vec<MetadataItem>,
-        // refinements: &mut HashMap<String, Vec<MetadataRefinement>>,
-    ) -> Result<(), EpubError> {
-        let id = element.get_attr("id");
-        let lang = element.get_attr("lang");
-        let property = element.name.clone();
-        let value = element.text().normalize_whitespace();
+    pub fn get_title(&self) -> Vec<String> {
+        self.get_metadata_value("title")
+            .expect("missing required 'title' metadata which is required by the EPUB specification")
+    }
 
-        let refined = match self.version {
-            // In EPUB 2.0, supplementary metadata (refinements) are represented
-            // through other attribute data pairs of the tag.
-            EpubVersion::Version2_0 => element
-                .attributes
-                .iter()
-                .map(|(name, value)| {
-                    let property = name.to_string();
-                    let value = value.to_string().normalize_whitespace();
+    /// Retrieves the language used in the publication
+    ///
+    /// This function retrieves the language information of a publication from the EPUB
+    /// metadata. According to the EPUB specification, language information identifies
+    /// the primary language of the publication and can have multiple language identifiers.
+    ///
+    /// ## Return
+    /// - `Ok(Vec<String>)`: A vector containing all language identifiers
+    /// - `Err(EpubError)`: If and only if the OPF file does not contain `<dc:language>`
+    ///
+    /// ## Notes
+    /// - The EPUB specification requires that each publication specify at least one primary language.
+    /// - Language identifiers should conform to RFC 3066 or later standards.
+    #[inline]
+    pub fn get_language(&self) -> Vec<String> {
+        self.get_metadata_value("language").expect(
+            "missing required 'language' metadata which is required by the EPUB specification",
+        )
+    }
 
-                    MetadataRefinement {
-                        refines: id.clone().unwrap(),
-                        property,
-                        value,
-                        lang: None,
-                        scheme: None,
-                    }
-                })
-                .collect(),
-            EpubVersion::Version3_0 => vec![],
-        };
+    /// Guesses the language the publication is written in
+    ///
+    /// The first declared language is returned as long as it is a well-formed language
+    /// tag naming an actual language. Otherwise, the language is guessed from the text
+    /// of the content documents:
+    /// 1. The plain text of the linear spine items is sampled in reading order, until
+    ///    about 2000 characters are collected or the spine is exhausted. Content
+    ///    documents that cannot be read are skipped.
+    /// 2. The language is guessed from the script used by most of the letters of the
+    ///    sample, such as Japanese for kana and Han characters, or Russian for Cyrillic.
+    ///
+    /// ## Return
+    /// - `Some(String)`: The declared language tag, or the primary language subtag of
+    ///   the guessed language
+    /// - `None`: No valid language is declared and no language could be guessed
+    ///
+    /// ## Notes
+    /// - A declared language is trusted even if the content is written in another
+    ///   language, since mislabeled books cannot be told apart from multilingual ones.
+    /// - Text written in the Latin script is never guessed, because the script alone
+    ///   cannot tell the many languages that use it apart.
+    pub fn guess_reading_language(&self) -> Option<String> {
+        const SAMPLE_LENGTH: usize = 2000;
+
+        let declared = self
+            .metadata
+            .iter()
+            .find(|item| item.property == "language")
+            .map(|item| item.value.trim());
+        if let Some(language) = declared.filter(|language| is_valid_language_tag(language)) {
+            return Some(language.to_string());
+        }
 
-        metadata.push(MetadataItem { id, property, value, lang, refined });
+        let mut sample = String::new();
+        for (spine_index, _) in self
+            .spine
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.linear)
+        {
+            if let Ok(text) = self.get_chapter_text(spine_index) {
+                sample.push_str(&text);
+                sample.push('\n');
+            }
 
-        Ok(())
+            if sample.chars().count() >= SAMPLE_LENGTH {
+                break;
+            }
+        }
+
+        guess_language_by_script(&sample).map(str::to_string)
     }
 
-    /// Parse metadata elements under the OPF namespace
+    /// Retrieves the identifier of a publication
     ///
-    /// This function handles the `<metadata>` OPF element in the OPF file (namespace
-    /// is "http://www.idpf.org/2007/opf"). These elements include '<meta>' and '<link>',
-    /// which are used to provide extended metadata and links to external resources for EPUB publications.
+    /// This function retrieves the identifier information of a publication from
+    /// the EPUB metadata. According to the EPUB specification, each publication
+    /// must have a identifier, typically an ISBN, UUID, or other unique identifier.
+    ///
+    /// ## Return
+    /// - `Ok(Vec<String>)`: A vector containing all identifier information
+    /// - `Err(EpubError)`: If and only if the OPF file does not contain `<dc:identifier>`
     ///
     /// ## Notes
-    /// - The function is only responsible for distribution processing, and the
-    ///   specific parsing logic is implemented in the dedicated function
-    /// - All parsing results are added directly to the incoming collection and no new collection is returned
+    /// - The EPUB specification requires each publication to have at least one identifier.
+    /// - In the OPF file, the `unique-identifier` attribute of the `<package>` element
+    ///   should point to a `<dc:identifier>` element used to uniquely identify the publication.
+    ///   This means that `unique-identifier` is not exactly equal to `<dc:identifier>`.
     #[inline]
-    fn parse_opf_metadata(
-        &self,
-        element: &XmlElement,
-        metadata: &mut Vec<MetadataItem>,
-        metadata_link: &mut Vec<MetadataLinkItem>,
-        refinements: &mut HashMap<String, Vec<MetadataRefinement>>,
-    ) -> Result<(), EpubError> {
-        match element.name.as_str() {
-            "meta" => self.parse_meta_element(element, metadata, refinements),
-            "link" => self.parse_link_element(element, metadata_link),
-            _ => Ok(()),
+    pub fn get_identifier(&self) -> Vec<String> {
+        self.get_metadata_value("identifier").expect(
+            "missing required 'identifier' metadata which is required by the EPUB specification",
+        )
+    }
+
+    /// Retrieves a unified metadata sheet from the EPUB publication
+    ///
+    /// This function consolidates all metadata from the EPUB into a single `MetadataSheet`
+    /// structure, providing a simplified interface for metadata access. It handles both
+    /// EPUB 2 and EPUB 3 metadata formats, including refinements from EPUB 3.
+    ///
+    /// ## Return
+    /// - `MetadataSheet`: A populated metadata sheet containing all publication metadata
+    ///
+    /// ## Notes
+    /// - Multi-value metadata (title, creator, etc.) are stored in Vec fields in order
+    /// - Date metadata extracts event type from refinements (e.g., "publication", "modification")
+    /// - Identifier metadata uses item IDs as keys in the HashMap
+    pub fn get_metadata_sheet(&self) -> MetadataSheet {
+        let mut sheet = MetadataSheet::new();
+        for item in &self.metadata {
+            let value = item.value.clone();
+
+            match item.property.as_str() {
+                "title" => {
+                    sheet.title.push(value);
+                }
+                "creator" => {
+                    sheet.creator.push(value);
+                }
+                "contributor" => {
+                    sheet.contributor.push(value);
+                }
+                "subject" => {
+                    sheet.subject.push(value);
+                }
+                "language" => {
+                    sheet.language.push(value);
+                }
+                "relation" => {
+                    sheet.relation.push(value);
+                }
+                "date" => {
+                    let event = item
+                        .refined
+                        .iter()
+                        .filter_map(|refine| {
+                            if refine.property.eq("event") {
+                                Some(refine.value.clone())
+                            } else {
+                                None
+                            }
+                        })
+                        .next()
+                        .unwrap_or_default();
+                    sheet.date.insert(value, event);
+                }
+                "identifier" => {
+                    let id = item.id.clone().unwrap_or_default();
+                    sheet.identifier.insert(id, value);
+                }
+                "description" => {
+                    sheet.description = value;
+                }
+                "format" => {
+                    sheet.format = value;
+                }
+                "publisher" => {
+                    sheet.publisher = value;
+                }
+                "rights" => {
+                    sheet.rights = value;
+                }
+                "source" => {
+                    sheet.source = value;
+                }
+                "ccoverage" => {
+                    sheet.coverage = value;
+                }
+                "type" => {
+                    sheet.epub_type = value;
+                }
+                _ => {}
+            };
         }
+
+        sheet
     }
 
-    #[inline]
-    fn parse_meta_element(
+    /// Retrieves a summary of the publication
+    ///
+    /// This function gathers the title, authors, language, unique identifier, description,
+    /// publisher, cover and number of chapters of the publication in a single call.
+    ///
+    /// ## Return
+    /// - `Ok(BookSummary)`: The summary of the publication
+    /// - `Err(EpubError)`: The metadata does not contain a title
+    ///
+    /// ## Notes
+    /// - The cover is retrieved in the same way as `get_cover`.
+    /// - Every spine item is counted as a chapter, including non-linear ones.
+    pub fn summary(&self) -> Result<BookSummary, EpubError> {
+        let first_value = |property: &str| {
+            self.metadata
+                .iter()
+                .find(|item| item.property == property)
+                .map(|item| item.value.clone())
+        };
+
+        Ok(BookSummary {
+            title: first_value("title")
+                .ok_or_else(|| EpubError::NonCanonicalFile { tag: "dc:title".to_string() })?,
+            authors: self.get_metadata_value("creator").unwrap_or_default(),
+            language: first_value("language"),
+            identifier: self.unique_identifier.clone(),
+            description: first_value("description"),
+            publisher: first_value("publisher"),
+            cover: self.get_cover(),
+            chapter_count: self.spine.len(),
+        })
+    }
+
+    /// Retrieve resource data by resource ID
+    ///
+    /// This function will find the resource with the specified ID in the manifest.
+    /// If the resource is encrypted, it will be automatically decrypted.
+    ///
+    /// ## Parameters
+    /// - `id`: The ID of the resource to retrieve
+    ///
+    /// ## Return
+    /// - `Ok((Vec<u8>, String))`: Successfully retrieved and decrypted resource data and
+    ///   the MIME type
+    /// - `Err(EpubError)`: Errors that occurred during the retrieval process
+    ///
+    /// ## Notes
+    /// - This function will automatically decrypt the resource if it is encrypted.
+    /// - For unsupported encryption methods, the corresponding error will be returned.
+    pub fn get_manifest_item(&self, id: &str) -> Result<(Vec<u8>, String), EpubError> {
+        let resource_item = self
+            .manifest
+            .get(id)
+            .ok_or_else(|| EpubError::ResourceIdNotExist { id: id.to_string() })?;
+
+        self.get_resource(resource_item)
+    }
+
+    /// Retrieves the manifest items in document order
+    ///
+    /// This function returns the manifest items in the order they are declared in the
+    /// OPF file, regardless of whether `manifest` is backed by [`IndexMap`] or, with
+    /// the `no-indexmap` feature, by [`HashMap`].
+    ///
+    /// ## Return
+    /// - `Vec<&ManifestItem>`: The manifest items in declaration order
+    ///
+    /// ## Notes
+    /// - Items whose ids are removed from `manifest` after parsing are skipped, and
+    ///   items inserted into `manifest` after parsing are not included.
+    /// - When several items share an id, only the first declaration is kept.
+    pub fn manifest_in_order(&self) -> Vec<&ManifestItem> {
+        self.manifest_order
+            .iter()
+            .filter_map(|id| self.manifest.get(id))
+            .collect()
+    }
+
+    /// Retrieves resource item data by resource path
+    ///
+    /// This function retrieves resources from the manifest based on the input path.
+    /// The input path must be a relative path to the root directory of the EPUB container;
+    /// using an absolute path or a relative path to another location will result in an error.
+    ///
+    /// ## Parameters
+    /// - `path`: The path of the resource to retrieve
+    ///
+    /// ## Return
+    /// - `Ok((Vec<u8>, String))`: Successfully retrieved and decrypted resource data and
+    ///   the MIME type
+    /// - `Err(EpubError)`: Errors that occurred during the retrieval process
+    ///
+    /// ## Notes
+    /// - This function will automatically decrypt the resource if it is encrypted.
+    /// - For unsupported encryption methods, the corresponding error will be returned.
+    /// - Relative paths other than the root directory of the Epub container are not supported.
+    pub fn get_manifest_item_by_path<P: AsRef<Path>>(
         &self,
-        element: &XmlElement,
-        metadata: &mut Vec<MetadataItem>,
-        refinements: &mut HashMap<String, Vec<MetadataRefinement>>,
-    ) -> Result<(), EpubError> {
-        match self.version {
-            EpubVersion::Version2_0 => {
-                let property = element
-                    .get_attr("name")
-                    .ok_or_else(|| EpubError::NonCanonicalFile { tag: element.tag_name() })?;
-                let value = element
+        path: P,
+    ) -> Result<(Vec<u8>, String), EpubError> {
+        let path = path.as_ref();
+        let manifest = self
+            .manifest
+            .values()
+            .find(|item| item.path == path)
+            .ok_or_else(|| EpubError::ResourceNotFound {
+                resource: path.to_string_lossy().to_string(),
+            })?;
+
+        self.get_resource(manifest)
+    }
+
+    /// Retrieves supported resource items by resource ID, with fallback mechanism supported
+    ///
+    /// This function attempts to retrieve the resource item with the specified ID and
+    /// checks if its MIME type is in the list of supported formats. If the current resource
+    /// format is not supported, it searches for a supported resource format along the
+    /// fallback chain according to the fallback mechanism defined in the EPUB specification.
+    ///
+    /// ## Parameters
+    /// - `id`: The ID of the resource to retrieve
+    /// - `supported_format`: A vector of supported MIME types
+    ///
+    /// ## Return
+    /// - `Ok((Vec<u8>, String))`: Successfully retrieved and decrypted resource data and
+    ///   the MIME type
+    /// - `Err(EpubError)`: Errors that occurred during the retrieval process
+    pub fn get_manifest_item_with_fallback(
+        &self,
+        id: &str,
+        supported_format: &[&str],
+    ) -> Result<(Vec<u8>, String), EpubError> {
+        self.get_manifest_item_with_fallback_verbose(id, supported_format)
+            .map_err(EpubError::from)
+    }
+
+    /// Retrieves supported resource items by resource ID, reporting the fallback chain tried
+    ///
+    /// This function behaves like `get_manifest_item_with_fallback`, but when the
+    /// retrieval fails, the returned error also lists every manifest item that was
+    /// examined along the fallback chain together with its MIME type.
+    ///
+    /// ## Parameters
+    /// - `id`: The ID of the resource to retrieve
+    /// - `supported_format`: A vector of supported MIME types
+    ///
+    /// ## Return
+    /// - `Ok((Vec<u8>, String))`: Successfully retrieved and decrypted resource data and
+    ///   the MIME type
+    /// - `Err(FallbackError)`: The error that occurred and the `(id, mime)` steps tried
+    pub fn get_manifest_item_with_fallback_verbose(
+        &self,
+        id: &str,
+        supported_format: &[&str],
+    ) -> Result<(Vec<u8>, String), FallbackError> {
+        let mut current_id = id;
+        let mut visited = HashSet::<&str>::new();
+        let mut tried = Vec::new();
+        loop {
+            let manifest_item = match self.manifest.get(current_id) {
+                Some(item) => item,
+                None => {
+                    return Err(FallbackError {
+                        tried,
+                        source: EpubError::ResourceIdNotExist { id: id.to_string() },
+                    });
+                }
+            };
+            visited.insert(manifest_item.id.as_str());
+            tried.push((manifest_item.id.clone(), manifest_item.mime.clone()));
+
+            if supported_format.contains(&manifest_item.mime.as_str()) {
+                return self
+                    .get_resource(manifest_item)
+                    .map_err(|source| FallbackError { tried, source });
+            }
+
+            // The loop ends when no fallback resource exists, or when the fallback
+            // resource has already been examined, i.e. the chain is circular.
+            // Since only warnings are issued for fallback resource checks during
+            // initialization, a missing fallback resource is handled in the next round.
+            match manifest_item.fallback.as_deref() {
+                Some(fallback_id) if !visited.contains(fallback_id) => current_id = fallback_id,
+                _ => break,
+            }
+        }
+
+        Err(FallbackError {
+            tried,
+            source: EpubError::NoSupportedFileFormat,
+        })
+    }
+
+    /// Iterates over the data of every resource declared in the manifest
+    ///
+    /// Resources are retrieved lazily, in the order of the manifest, and encrypted
+    /// resources are automatically decrypted in the same way as `get_manifest_item`.
+    ///
+    /// ## Return
+    /// - `impl Iterator`: An iterator yielding, for each manifest item, either its id,
+    ///   path, data and MIME type, or the error that occurred while retrieving it
+    ///
+    /// ## Notes
+    /// - Remote resources are subject to the fetch policy, and yield an error
+    ///   when fetching is refused.
+    /// - A failure to retrieve one resource does not end the iteration.
+    pub fn iter_resources(
+        &self,
+    ) -> impl Iterator<Item = Result<(String, PathBuf, Vec<u8>, String), EpubError>> + '_ {
+        self.manifest.values().map(|item| {
+            let (data, mime) = self.get_resource(item)?;
+            Ok((item.id.clone(), item.path.clone(), data, mime))
+        })
+    }
+
+    /// Retrieves the cover of the EPUB document
+    ///
+    /// This function searches for the cover of the EPUB document by examining manifest
+    /// items in the manifest. It looks for manifest items whose ID or attribute contains
+    /// "cover" (case-insensitive) and attempts to retrieve the content of the first match.
+    ///
+    /// ## Return
+    /// - `Some((Vec<u8>, String))`: Successfully retrieved and decrypted cover data and
+    ///   the MIME type
+    /// - `None`: No cover resource was found
+    ///
+    /// ## Notes
+    /// - This function only returns the first successfully retrieved cover resource,
+    ///   even if multiple matches exist
+    /// - The retrieved cover may not be an image resource; users need to pay attention
+    ///   to the resource's MIME type.
+    pub fn get_cover(&self) -> Option<(Vec<u8>, String)> {
+        self.manifest
+            .values()
+            .filter(|manifest| {
+                manifest.id.to_ascii_lowercase().contains("cover")
+                    || manifest
+                        .properties
+                        .as_ref()
+                        .map(|properties| properties.to_ascii_lowercase().contains("cover"))
+                        .unwrap_or(false)
+            })
+            .find_map(|manifest| {
+                self.get_resource(manifest)
+                    .map_err(|err| log::warn!("{err}"))
+                    .ok()
+            })
+    }
+
+    /// Generates a scaled-down thumbnail of the cover
+    ///
+    /// The cover image declared by the `cover-image` property or by the EPUB 2 cover
+    /// declarations is used, falling back to `get_cover`. It is decoded, then resized so
+    /// that its largest side equals `max_dimension`, preserving the aspect ratio. PNG covers are
+    /// re-encoded as PNG, and other raster covers as JPEG.
+    ///
+    /// ## Parameters
+    /// - `max_dimension`: The maximum width and height of the thumbnail, in pixels
+    ///
+    /// ## Return
+    /// - `Ok((Vec<u8>, String))`: The thumbnail data and its MIME type
+    /// - `Err(EpubError)`: No cover was found, or the cover could not be decoded or encoded
+    ///
+    /// ## Notes
+    /// - SVG covers are returned as they are, since they scale without loss.
+    /// - Covers already fitting within `max_dimension` are returned without being re-encoded.
+    /// - Only JPEG and PNG covers can be decoded.
+    #[cfg(feature = "image")]
+    pub fn cover_thumbnail(&self, max_dimension: u32) -> Result<(Vec<u8>, String), EpubError> {
+        // The cover found by `get_cover` may be an XHTML cover page, so the
+        // declared cover images are tried first
+        let declared_cover = self
+            .first_item_with_property("cover-image")
+            .map(|item| item.id.clone())
+            .or_else(|| self.epub2_cover_id())
+            .filter(|id| {
+                self.manifest
+                    .get(id)
+                    .is_some_and(|item| item.mime.starts_with("image/"))
+            });
+
+        let cover = match declared_cover {
+            Some(id) => Some(self.get_manifest_item(&id)?),
+            None => self.get_cover(),
+        };
+        let (data, mime) =
+            cover.ok_or_else(|| EpubError::ResourceNotFound { resource: "cover".to_string() })?;
+        if mime == "image/svg+xml" {
+            return Ok((data, mime));
+        }
+
+        Self::make_thumbnail(data, mime, max_dimension)
+    }
+
+    /// Generates a thumbnail from the first image of the first linear chapter
+    ///
+    /// This is a fallback for publications without a cover. If a cover is found, its
+    /// thumbnail is returned as by `cover_thumbnail`. Otherwise, the first `img` or SVG
+    /// `image` element of the first linear content document is located, and the image
+    /// it links to is scaled down in the same way.
+    ///
+    /// ## Parameters
+    /// - `max_dimension`: The maximum width and height of the thumbnail, in pixels
+    ///
+    /// ## Return
+    /// - `Ok(Some((Vec<u8>, String)))`: The thumbnail data and its MIME type
+    /// - `Ok(None)`: There is no cover, and the first linear chapter contains no image
+    /// - `Err(EpubError)`: The cover, the chapter or the image could not be read, decoded
+    ///   or encoded
+    ///
+    /// ## Notes
+    /// - Only the first linear chapter is examined, later chapters are never searched.
+    /// - The image must be declared in the manifest.
+    #[cfg(feature = "image")]
+    pub fn first_page_thumbnail(
+        &self,
+        max_dimension: u32,
+    ) -> Result<Option<(Vec<u8>, String)>, EpubError> {
+        use crate::utils::extract_first_image_src;
+
+        match self.cover_thumbnail(max_dimension) {
+            Err(EpubError::ResourceNotFound { resource }) if resource == "cover" => {}
+            result => return result.map(Some),
+        }
+
+        let Some(spine_index) = self.spine.iter().position(|item| item.linear) else {
+            return Ok(None);
+        };
+        let (data, _) = self.get_manifest_item(&self.spine[spine_index].idref)?;
+        let Some(src) = extract_first_image_src(&data.decode()?)? else {
+            return Ok(None);
+        };
+
+        let path = self
+            .resolve_relative(spine_index, &src)
+            .ok_or(EpubError::ResourceNotFound { resource: src })?;
+        let (data, mime) = self.get_manifest_item_by_path(path)?;
+        if mime == "image/svg+xml" {
+            return Ok(Some((data, mime)));
+        }
+
+        Self::make_thumbnail(data, mime, max_dimension).map(Some)
+    }
+
+    /// Scales down a raster image so that its largest side equals `max_dimension`
+    ///
+    /// PNG images are re-encoded as PNG, and other images as JPEG. Images already
+    /// fitting within `max_dimension` are returned without being re-encoded.
+    #[cfg(feature = "image")]
+    fn make_thumbnail(
+        data: Vec<u8>,
+        mime: String,
+        max_dimension: u32,
+    ) -> Result<(Vec<u8>, String), EpubError> {
+        use image::{ImageFormat, imageops::FilterType};
+
+        let source = image::load_from_memory(&data)?;
+        let max_dimension = max_dimension.max(1);
+        if source.width() <= max_dimension && source.height() <= max_dimension {
+            return Ok((data, mime));
+        }
+
+        let thumbnail = source.resize(max_dimension, max_dimension, FilterType::Triangle);
+        let mut output = std::io::Cursor::new(Vec::new());
+        if mime == "image/png" {
+            thumbnail.write_to(&mut output, ImageFormat::Png)?;
+        } else {
+            // JPEG does not support an alpha channel
+            image::DynamicImage::ImageRgb8(thumbnail.to_rgb8())
+                .write_to(&mut output, ImageFormat::Jpeg)?;
+            return Ok((output.into_inner(), "image/jpeg".to_string()));
+        }
+
+        Ok((output.into_inner(), mime))
+    }
+
+    /// Retrieves the manifest id of the cover declared in the EPUB 2 way
+    ///
+    /// EPUB 2 publications declare their cover with two independent mechanisms, which are
+    /// frequently both present and may point to different resources:
+    /// - `<meta name="cover" content="...">`, whose content is the id of the cover image
+    /// - `<guide><reference type="cover" href="...">`, which points to the cover page
+    ///
+    /// The `meta` declaration takes precedence, as long as its id exists in the manifest.
+    /// Otherwise, the id of the manifest item referenced by the guide cover is returned.
+    ///
+    /// ## Return
+    /// - `Some(String)`: The manifest id of the cover resource
+    /// - `None`: Neither mechanism yields a resource declared in the manifest
+    pub fn epub2_cover_id(&self) -> Option<String> {
+        let meta_cover = self
+            .metadata
+            .iter()
+            .filter(|item| item.property == "cover")
+            .find(|item| self.manifest.contains_key(&item.value))
+            .map(|item| item.value.clone());
+        if meta_cover.is_some() {
+            return meta_cover;
+        }
+
+        let guide_cover = self
+            .guide
+            .iter()
+            .find(|reference| reference.reference_type.eq_ignore_ascii_case("cover"))?;
+        self.manifest
+            .values()
+            .find(|item| item.path == guide_cover.href)
+            .map(|item| item.id.clone())
+    }
+
+    /// Retrieves resource data by manifest item
+    fn get_resource(&self, resource_item: &ManifestItem) -> Result<(Vec<u8>, String), EpubError> {
+        let path = resource_item
+            .path
+            .to_str()
+            .expect("manifest item path should be valid UTF-8");
+
+        if Self::is_remote_url(path) {
+            return match &self.fetch_policy {
+                FetchPolicy::Refuse => Err(EpubError::RemoteResource { url: path.to_string() }),
+                FetchPolicy::Fetch(fetcher) => Ok((fetcher(path)?, resource_item.mime.clone())),
+            };
+        }
+
+        let mut archive = self.archive.lock()?;
+        let entry_name = find_file_in_zip_archive(&archive, path)
+            .ok_or_else(|| EpubError::ResourceNotFound { resource: path.to_string() })?;
+        let mut data = match archive.by_name(&entry_name) {
+            Ok(mut file) => {
+                let mut entry = Vec::<u8>::new();
+                file.read_to_end(&mut entry)?;
+                Ok(entry)
+            }
+            Err(ZipError::FileNotFound) => {
+                Err(EpubError::ResourceNotFound { resource: path.to_string() })
+            }
+            Err(err) => Err(EpubError::from(err)),
+        }?;
+
+        if let Some(method) = self.is_encryption_file(path) {
+            data = self.auto_dencrypt(&method, &mut data)?;
+        }
+
+        Ok((data, resource_item.mime.clone()))
+    }
+
+    /// Retrieves the flattened catalog together with the spine position of each entry
+    ///
+    /// This function walks the catalog tree in document order and resolves the
+    /// content link of each navigation point to the index of the spine item that
+    /// references the same resource.
+    ///
+    /// ## Return
+    /// - `Vec<(usize, &NavPoint, Option<usize>)>`: The depth of the entry (starting from 0),
+    ///   the entry itself, and the resolved spine index
+    ///
+    /// ## Notes
+    /// - Entries without content, with a fragment-only link, with an external link, or
+    ///   pointing to a resource that is not in the spine are resolved to `None`.
+    pub fn catalog_with_spine(&self) -> Vec<(usize, &NavPoint, Option<usize>)> {
+        let mut result = Vec::new();
+        self.flatten_catalog(&self.catalog, 0, &mut result);
+        result
+    }
+
+    /// Recursively flattens navigation points with their depth and spine index
+    fn flatten_catalog<'a>(
+        &self,
+        nav_points: &'a [NavPoint],
+        depth: usize,
+        result: &mut Vec<(usize, &'a NavPoint, Option<usize>)>,
+    ) {
+        for nav_point in nav_points {
+            let spine_index = nav_point
+                .content
+                .as_ref()
+                .and_then(|content| self.resolve_catalog_href(content))
+                .and_then(|path| self.spine_index_for_path(&path));
+
+            result.push((depth, nav_point, spine_index));
+            self.flatten_catalog(&nav_point.children, depth + 1, result);
+        }
+    }
+
+    /// Resolves a link of the catalog to a path relative to the EPUB root directory
+    ///
+    /// Links in the catalog are relative to the navigation document.
+    ///
+    /// ## Return
+    /// - `Some(PathBuf)`: The normalized path of the linked resource
+    /// - `None`: The link is empty, fragment-only, external or leaks out of the container
+    fn resolve_catalog_href(&self, href: &Path) -> Option<PathBuf> {
+        self.resolve_href(&self.catalog_base, &href.to_string_lossy())
+    }
+
+    /// Resolves a link relative to a directory to a path relative to the EPUB root directory
+    ///
+    /// The link may carry a fragment identifier or a query, which are removed here.
+    ///
+    /// ## Parameters
+    /// - `base_dir`: The directory the link is relative to, relative to the EPUB root directory
+    /// - `href`: The link to resolve
+    ///
+    /// ## Return
+    /// - `Some(PathBuf)`: The normalized path of the linked resource
+    /// - `None`: The link is empty, fragment-only, external or leaks out of the container
+    fn resolve_href(&self, base_dir: &Path, href: &str) -> Option<PathBuf> {
+        let href = normalize_container_path(href);
+        let href = href.split(['#', '?']).next().unwrap_or_default();
+        if href.is_empty() || href.contains(':') {
+            return None;
+        }
+
+        let path = if href.starts_with("../") {
+            check_realtive_link_leakage(PathBuf::new(), base_dir.to_path_buf(), href)?
+        } else if let Some(path) = href.strip_prefix('/') {
+            path.to_string()
+        } else {
+            base_dir.join(href).to_string_lossy().to_string()
+        };
+
+        Some(PathBuf::from(normalize_container_path(&path)))
+    }
+
+    /// Finds the index of the first spine item referencing the resource at `path`
+    fn spine_index_for_path(&self, path: &Path) -> Option<usize> {
+        let item = self.manifest.values().find(|item| item.path == path)?;
+        self.spine.iter().position(|spine| spine.idref == item.id)
+    }
+
+    /// Retrieves the spine item at the specified index
+    ///
+    /// ## Parameters
+    /// - `index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `Some(&SpineItem)`: The spine item at the index
+    /// - `None`: Index out of range
+    pub fn spine_item(&self, index: usize) -> Option<&SpineItem> {
+        self.spine.get(index)
+    }
+
+    /// Retrieves the manifest item referenced by the spine item at the specified index
+    ///
+    /// ## Parameters
+    /// - `index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `Some(&ManifestItem)`: The manifest item referenced by the spine item
+    /// - `None`: Index out of range, or the spine item references an id that does
+    ///   not exist in the manifest
+    pub fn manifest_item_for_spine(&self, index: usize) -> Option<&ManifestItem> {
+        let spine_item = self.spine.get(index)?;
+        self.manifest.get(&spine_item.idref)
+    }
+
+    /// Checks whether the spine item at the specified index is the navigation document
+    ///
+    /// ## Parameters
+    /// - `index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `true`: The manifest item referenced by the spine item has the `nav` property
+    /// - `false`: Otherwise, or the index is out of range
+    pub fn spine_item_is_nav(&self, index: usize) -> bool {
+        self.manifest_item_for_spine(index).is_some_and(|item| {
+            item.properties
+                .as_ref()
+                .is_some_and(|properties| properties.split_whitespace().any(|p| p == "nav"))
+        })
+    }
+
+    /// Checks whether spine navigation may stop at the spine item at the specified index
+    fn is_spine_stop(&self, index: usize) -> bool {
+        self.spine[index].linear && !(self.skip_nav && self.spine_item_is_nav(index))
+    }
+
+    /// Pairs every spine item with the manifest item it references
+    ///
+    /// This function returns the spine items in reading order, each together with the
+    /// manifest item its `idref` points to, so that the path and MIME type of the
+    /// resources in the reading order can be listed without repeated lookups.
+    ///
+    /// ## Return
+    /// - `Vec<(&SpineItem, Option<&ManifestItem>)>`: The spine items in reading order,
+    ///   with `None` for spine items whose `idref` does not exist in the manifest
+    pub fn spine_with_manifest(&self) -> Vec<(&SpineItem, Option<&ManifestItem>)> {
+        self.spine
+            .iter()
+            .map(|spine_item| (spine_item, self.manifest.get(&spine_item.idref)))
+            .collect()
+    }
+
+    /// Resolves a link of a content document to a path relative to the EPUB root directory
+    ///
+    /// Relative `src` and `href` values in a content document are relative to the
+    /// directory of the document. This function joins `relative` onto that directory,
+    /// and removes any fragment identifier or query from it.
+    ///
+    /// ## Parameters
+    /// - `spine_index`: The index position in the spine of the content document
+    /// - `relative`: The link found in the content document, such as `../images/cover.png`
+    ///
+    /// ## Return
+    /// - `Some(PathBuf)`: The path of the linked resource, relative to the EPUB root directory
+    /// - `None`: Index out of range, or the link is empty, fragment-only, an absolute URL,
+    ///   or leaks out of the container
+    ///
+    /// ## Notes
+    /// - The linked resource is not required to exist in the container.
+    pub fn resolve_relative(&self, spine_index: usize, relative: &str) -> Option<PathBuf> {
+        let item = self.manifest_item_for_spine(spine_index)?;
+        let base_dir = item
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        self.resolve_href(&base_dir, relative)
+    }
+
+    /// Retrieves the number of items in the spine
+    pub fn spine_len(&self) -> usize {
+        self.spine.len()
+    }
+
+    /// Retrieves the current position in the spine
+    ///
+    /// The position is the spine index of the chapter returned by `spine_current`,
+    /// and can be saved to restore the reading position later.
+    pub fn current_spine_index(&self) -> usize {
+        self.current_spine_index.load(Ordering::SeqCst)
+    }
+
+    /// Sets the current position in the spine
+    ///
+    /// Unlike `navigate_by_spine_index`, this function only moves the position and
+    /// does not retrieve the chapter, so it can be used to restore a saved reading position.
+    ///
+    /// ## Parameters
+    /// - `index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `Ok(())`: The position was updated
+    /// - `Err(EpubError)`: The index is out of range, in which case the position is unchanged
+    pub fn set_current_spine_index(&mut self, index: usize) -> Result<(), EpubError> {
+        if index >= self.spine.len() {
+            return Err(EpubError::SpineIndexOutOfRange { index, len: self.spine.len() });
+        }
+
+        self.current_spine_index.store(index, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Resolves how the spine item at the specified index is placed in a spread
+    ///
+    /// The `rendition:spread-*` property of the spine item overrides the global
+    /// `rendition:spread` metadata. When spreads are disabled, the page is not placed
+    /// in a spread. Otherwise, the `page-spread-left`, `page-spread-right` and
+    /// `rendition:page-spread-center` properties of the spine item decide its side.
+    ///
+    /// ## Parameters
+    /// - `spine_index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `PageSpread`: The placement of the page in the spread
+    ///
+    /// ## Notes
+    /// - The `rendition:page-spread-left` and `rendition:page-spread-right` aliases
+    ///   from EPUB 3.0 are also recognized.
+    /// - For an index out of range, only the global `rendition:spread` metadata is used.
+    pub fn page_spread(&self, spine_index: usize) -> PageSpread {
+        let properties = self
+            .spine
+            .get(spine_index)
+            .and_then(|item| item.properties.as_deref())
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<&str>>();
+
+        let spread = properties
+            .iter()
+            .find_map(|property| property.strip_prefix("rendition:spread-"))
+            .or_else(|| {
+                self.metadata
+                    .iter()
+                    .find(|item| item.property == "rendition:spread")
+                    .map(|item| item.value.trim())
+            });
+        if spread == Some("none") {
+            return PageSpread::None;
+        }
+
+        properties
+            .iter()
+            .find_map(|&property| match property {
+                "page-spread-left" | "rendition:page-spread-left" => Some(PageSpread::Left),
+                "page-spread-right" | "rendition:page-spread-right" => Some(PageSpread::Right),
+                "rendition:page-spread-center" => Some(PageSpread::Center),
+                _ => None,
+            })
+            .unwrap_or(PageSpread::Auto)
+    }
+
+    /// Retrieves the plain text of the spine item at the specified index
+    ///
+    /// The content document is decoded and stripped of its markup. Text inside `head`,
+    /// `script` and `style` elements is skipped, whitespace is collapsed, and block-level
+    /// elements such as paragraphs and headings are separated by line breaks.
+    ///
+    /// ## Parameters
+    /// - `spine_index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `Ok(String)`: The plain text of the content document
+    /// - `Err(EpubError)`: Index out of range, or the content document could not be
+    ///   read or parsed
+    pub fn get_chapter_text(&self, spine_index: usize) -> Result<String, EpubError> {
+        let spine_item = self
+            .spine
+            .get(spine_index)
+            .ok_or(EpubError::SpineIndexOutOfRange {
+                index: spine_index,
+                len: self.spine.len(),
+            })?;
+
+        let (data, _) = self.get_manifest_item(&spine_item.idref)?;
+        extract_plain_text(&data.decode()?)
+    }
+
+    /// Retrieves the title of the spine item at the specified index
+    ///
+    /// The label of the first catalog entry, in document order, that links to the
+    /// spine item is preferred. If there is none, the content document is parsed, and
+    /// the text of its first `h1` to `h6` heading, or else of its `title` element, is used.
+    ///
+    /// ## Parameters
+    /// - `spine_index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `Ok(Some(String))`: The title of the chapter
+    /// - `Ok(None)`: Neither the catalog nor the content document provides a title
+    /// - `Err(EpubError)`: Index out of range, or the content document could not be
+    ///   read or parsed
+    pub fn chapter_title(&self, spine_index: usize) -> Result<Option<String>, EpubError> {
+        let spine_item = self
+            .spine
+            .get(spine_index)
+            .ok_or(EpubError::SpineIndexOutOfRange {
+                index: spine_index,
+                len: self.spine.len(),
+            })?;
+
+        let label = self
+            .catalog_with_spine()
+            .into_iter()
+            .find(|(_, _, index)| *index == Some(spine_index))
+            .map(|(_, nav_point, _)| nav_point.label.trim().to_string())
+            .filter(|label| !label.is_empty());
+        if label.is_some() {
+            return Ok(label);
+        }
+
+        let (data, _) = self.get_manifest_item(&spine_item.idref)?;
+        extract_chapter_title(&data.decode()?)
+    }
+
+    /// Checks whether the catalog of the publication is empty
+    ///
+    /// ## Return
+    /// - `true`: The navigation document or NCX file declares no navigation points
+    /// - `false`: The catalog has at least one navigation point
+    pub fn toc_is_empty(&self) -> bool {
+        self.catalog.is_empty()
+    }
+
+    /// Builds a flat catalog from the spine when the catalog is empty
+    ///
+    /// One navigation point is created for each linear spine item, in reading order.
+    /// The label of each navigation point is the title resolved by
+    /// [`chapter_title`](Self::chapter_title), and its content is the path of the
+    /// spine item's manifest item.
+    ///
+    /// ## Notes
+    /// - The catalog is left untouched if it is not empty.
+    /// - If no title can be resolved for a spine item, or its content document cannot
+    ///   be read, the idref of the spine item is used as the label and a warning is
+    ///   recorded for the latter.
+    pub fn synthesize_catalog_from_spine(&mut self) {
+        if !self.toc_is_empty() {
+            return;
+        }
+
+        let mut catalog = Vec::new();
+        let mut warnings = Vec::new();
+        for (spine_index, spine_item) in self.spine.iter().enumerate() {
+            if !spine_item.linear {
+                continue;
+            }
+            let Some(item) = self.manifest.get(&spine_item.idref) else {
+                continue;
+            };
+
+            let label = match self.chapter_title(spine_index) {
+                Ok(title) => title.unwrap_or_else(|| spine_item.idref.clone()),
+                Err(err) => {
+                    warnings.push(format!(
+                        "Unable to resolve the title of spine item '{}': {}",
+                        spine_item.idref, err
+                    ));
+                    spine_item.idref.clone()
+                }
+            };
+
+            catalog.push(NavPoint {
+                label,
+                content: Some(item.path.clone()),
+                children: vec![],
+                play_order: Some(catalog.len() + 1),
+                doc_index: catalog.len(),
+            });
+        }
+
+        for warning in warnings {
+            self.push_warning(warning);
+        }
+
+        // the manifest paths are relative to the EPUB root directory
+        self.catalog_base = PathBuf::new();
+        self.catalog = catalog;
+    }
+
+    /// Iterates over the linear chapters of the publication
+    ///
+    /// Chapters are yielded in reading order, skipping non-linear spine items. The title
+    /// of each chapter is the label of the first catalog entry, in document order, that
+    /// links to its spine item.
+    ///
+    /// ## Return
+    /// - `impl Iterator`: An iterator yielding, for each linear spine item, either the
+    ///   chapter or the error that occurred while reading or parsing it
+    ///
+    /// ## Notes
+    /// - Content documents are retrieved lazily, and a failure to read one chapter does
+    ///   not end the iteration.
+    pub fn chapters(&self) -> impl Iterator<Item = Result<Chapter, EpubError>> + '_ {
+        let mut titles = HashMap::new();
+        for (_, nav_point, spine_index) in self.catalog_with_spine() {
+            if let Some(spine_index) = spine_index {
+                titles
+                    .entry(spine_index)
+                    .or_insert_with(|| nav_point.label.clone());
+            }
+        }
+
+        self.spine
+            .iter()
+            .enumerate()
+            .filter(|(_, spine_item)| spine_item.linear)
+            .map(move |(spine_index, spine_item)| {
+                let (html, _) = self.get_manifest_item(&spine_item.idref)?;
+                let text = extract_plain_text(&html.decode()?)?;
+
+                Ok(Chapter {
+                    spine_index,
+                    title: titles.get(&spine_index).cloned(),
+                    html,
+                    text,
+                })
+            })
+    }
+
+    /// Retrieves the reading order of the publication
+    ///
+    /// The linear spine items are walked in order, and each of them is annotated with
+    /// the label and depth of the first catalog entry, in document order, that links
+    /// to it.
+    ///
+    /// ## Return
+    /// - `ReadingOrder`: One reading unit for each linear spine item, in spine order
+    ///
+    /// ## Notes
+    /// - Unlike `chapters`, no content document is read, so the title of a unit is
+    ///   `None` when no catalog entry links to it. Use `chapter_title` to fall back
+    ///   to the headings of the content document.
+    pub fn reading_order(&self) -> ReadingOrder {
+        let mut entries = HashMap::new();
+        for (depth, nav_point, spine_index) in self.catalog_with_spine() {
+            if let Some(spine_index) = spine_index {
+                entries
+                    .entry(spine_index)
+                    .or_insert_with(|| (nav_point.label.clone(), depth));
+            }
+        }
+
+        let units = self
+            .spine
+            .iter()
+            .enumerate()
+            .filter(|(_, spine_item)| spine_item.linear)
+            .map(|(spine_index, spine_item)| {
+                let entry = entries.get(&spine_index);
+
+                ReadingUnit {
+                    spine_index,
+                    manifest_id: spine_item.idref.clone(),
+                    title: entry.map(|(label, _)| label.clone()),
+                    toc_depth: entry.map(|(_, depth)| *depth),
+                }
+            })
+            .collect();
+
+        ReadingOrder { units }
+    }
+
+    /// Searches the text of the publication
+    ///
+    /// All linear spine items that are XHTML or SVG content documents are searched in
+    /// reading order, using the plain text returned by `get_chapter_text`. Matches
+    /// within a spine item do not overlap.
+    ///
+    /// ## Parameters
+    /// - `query`: The text to search for
+    /// - `options`: Options controlling case sensitivity, word boundaries and the
+    ///   length of the snippets
+    ///
+    /// ## Return
+    /// - `Ok(Vec<SearchHit>)`: The matches in reading order, empty if `query` is empty
+    /// - `Err(EpubError)`: A content document could not be read or parsed
+    pub fn search(&self, query: &str, options: SearchOptions) -> Result<Vec<SearchHit>, EpubError> {
+        let query = if options.case_insensitive {
+            query
+                .chars()
+                .flat_map(char::to_lowercase)
+                .collect::<Vec<char>>()
+        } else {
+            query.chars().collect::<Vec<char>>()
+        };
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut hits = Vec::new();
+        for (spine_index, spine_item) in self.spine.iter().enumerate() {
+            if !spine_item.linear {
+                continue;
+            }
+
+            let is_content_document = self.manifest.get(&spine_item.idref).is_some_and(|item| {
+                item.mime == "application/xhtml+xml" || item.mime == "image/svg+xml"
+            });
+            if !is_content_document {
+                continue;
+            }
+
+            // the text is lowercased once, and every folded character keeps the byte offset
+            // of the original character it comes from, since lowercasing may change the length
+            let text = self.get_chapter_text(spine_index)?;
+            let (folded, origins): (Vec<char>, Vec<usize>) = if options.case_insensitive {
+                text.char_indices()
+                    .flat_map(|(index, ch)| ch.to_lowercase().map(move |lower| (lower, index)))
+                    .unzip()
+            } else {
+                text.char_indices().map(|(index, ch)| (ch, index)).unzip()
+            };
+
+            let (mut counted_bytes, mut counted_chars) = (0, 0);
+            let mut offset = 0;
+            while offset + query.len() <= folded.len() {
+                let end = offset + query.len();
+
+                // a match may not start or end inside the lowercase form of a character
+                let on_boundary = (offset == 0 || origins[offset - 1] != origins[offset])
+                    && (end == folded.len() || origins[end - 1] != origins[end]);
+                if !on_boundary || folded[offset..end] != query[..] {
+                    offset += 1;
+                    continue;
+                }
+
+                let start_byte = origins[offset];
+                let end_byte = origins.get(end).copied().unwrap_or(text.len());
+                let is_word_boundary = !text[..start_byte]
+                    .chars()
+                    .next_back()
+                    .is_some_and(char::is_alphanumeric)
+                    && !text[end_byte..]
+                        .chars()
+                        .next()
+                        .is_some_and(char::is_alphanumeric);
+                if options.whole_word && !is_word_boundary {
+                    offset += 1;
+                    continue;
+                }
+
+                let snippet_start = text[..start_byte]
+                    .char_indices()
+                    .rev()
+                    .take(options.context)
+                    .last()
+                    .map_or(start_byte, |(index, _)| index);
+                let snippet_end = text[end_byte..]
+                    .char_indices()
+                    .nth(options.context)
+                    .map_or(text.len(), |(index, _)| end_byte + index);
+
+                counted_chars += text[counted_bytes..start_byte].chars().count();
+                counted_bytes = start_byte;
+
+                hits.push(SearchHit {
+                    spine_index,
+                    char_offset: counted_chars,
+                    snippet: text[snippet_start..snippet_end]
+                        .replace('\n', " ")
+                        .trim()
+                        .to_string(),
+                });
+                offset = end;
+            }
+        }
+
+        Ok(hits)
+    }
+
+    /// Generates an EPUB CFI for a location in a spine item
+    ///
+    /// The package path of the CFI addresses the spine item, with an id assertion when
+    /// the itemref has an `id` attribute. The spine is always the third child element
+    /// of the package element, so it is addressed by the step `/6`.
+    ///
+    /// ## Parameters
+    /// - `spine_index`: The index position in the spine, starting from 0
+    /// - `element_path`: The steps of the path in the content document, such as
+    ///   `[4, 2, 1]` for `/4/2/1`
+    /// - `char_offset`: The character offset in the addressed text
+    ///
+    /// ## Return
+    /// - `Ok(String)`: The CFI, such as `epubcfi(/6/4[chap01ref]!/4/2/1:0)`
+    /// - `Err(EpubError)`: The spine index is out of range
+    ///
+    /// ## Notes
+    /// - The steps of `element_path` are not checked against the content document.
+    pub fn cfi_for(
+        &self,
+        spine_index: usize,
+        element_path: &[usize],
+        char_offset: usize,
+    ) -> Result<String, EpubError> {
+        let spine_item = self
+            .spine
+            .get(spine_index)
+            .ok_or(EpubError::SpineIndexOutOfRange {
+                index: spine_index,
+                len: self.spine.len(),
+            })?;
+
+        let assertion = spine_item
+            .id
+            .as_deref()
+            .map(|id| format!("[{}]", escape_cfi_assertion(id)))
+            .unwrap_or_default();
+        let content_path = element_path
+            .iter()
+            .map(|step| format!("/{step}"))
+            .collect::<String>();
+
+        Ok(format!(
+            "epubcfi(/6/{}{assertion}!{content_path}:{char_offset})",
+            (spine_index + 1) * 2
+        ))
+    }
+
+    /// Parses an EPUB CFI into a location in a spine item
+    ///
+    /// The CFI must consist of a package path addressing an itemref of the spine,
+    /// followed by a single indirection into the content document. Id assertions in
+    /// the content path are ignored.
+    ///
+    /// ## Parameters
+    /// - `cfi`: The CFI to parse, such as `epubcfi(/6/4[chap01ref]!/4/2/1:0)`
+    ///
+    /// ## Return
+    /// - `Ok(CfiLocation)`: The addressed spine item and the path in its content document
+    /// - `Err(EpubError)`: The CFI is malformed or not supported, addresses a spine item
+    ///   that does not exist, or its id assertion does not match the itemref
+    pub fn parse_cfi(&self, cfi: &str) -> Result<CfiLocation, EpubError> {
+        let invalid = |message: &str| EpubError::InvalidCfi {
+            cfi: cfi.to_string(),
+            message: message.to_string(),
+        };
+
+        let path = cfi
+            .strip_prefix("epubcfi(")
+            .and_then(|path| path.strip_suffix(')'))
+            .ok_or_else(|| invalid("it must be enclosed in \"epubcfi(...)\""))?;
+        let CfiPath { segments, char_offset } = parse_cfi_path(path).map_err(invalid)?;
+
+        let [package_steps, content_steps] = segments.as_slice() else {
+            return Err(invalid("it must contain exactly one indirection"));
+        };
+        let itemref = match package_steps.as_slice() {
+            [spine, itemref] if spine.index == 6 && itemref.index > 0 && itemref.index % 2 == 0 => {
+                itemref
+            }
+            _ => {
+                return Err(invalid(
+                    "its package path must address an itemref of the spine",
+                ));
+            }
+        };
+
+        let spine_index = itemref.index / 2 - 1;
+        let spine_item = self
+            .spine
+            .get(spine_index)
+            .ok_or(EpubError::SpineIndexOutOfRange {
+                index: spine_index,
+                len: self.spine.len(),
+            })?;
+
+        if let (Some(assertion), Some(id)) = (&itemref.assertion, &spine_item.id) {
+            if assertion != id {
+                return Err(invalid("its id assertion does not match the itemref"));
+            }
+        }
+
+        Ok(CfiLocation {
+            spine_index,
+            element_path: content_steps.iter().map(|step| step.index).collect(),
+            char_offset,
+        })
+    }
+
+    /// Navigate to a specified chapter using the spine index
+    ///
+    /// This function retrieves the content data of the corresponding chapter based
+    /// on the index position in the EPUB spine. The spine defines the linear reading
+    /// order of the publication's content documents, and each spine item references
+    /// resources in the manifest.
+    ///
+    /// ## Parameters
+    /// - `index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `Some((Vec<u8>, String))`: Successfully retrieved chapter content data and the MIME type
+    /// - `None`: Index out of range or data retrieval error
+    ///
+    /// ## Notes
+    /// - The index must be less than the total number of spine projects.
+    /// - If the resource is encrypted, it will be automatically decrypted before returning.
+    /// - It does not check whether the Spine project follows a linear reading order.
+    /// - Use `try_navigate_by_spine_index` to tell a retrieval error from an index out of range.
+    pub fn navigate_by_spine_index(&mut self, index: usize) -> Option<(Vec<u8>, String)> {
+        self.try_navigate_by_spine_index(index)
+            .map_err(|err| log::warn!("{err}"))
+            .ok()
+            .flatten()
+    }
+
+    /// Navigate to a specified chapter using the spine index, reporting retrieval errors
+    ///
+    /// This function behaves like `navigate_by_spine_index`, except that a failure to
+    /// retrieve the chapter, such as an unsupported encryption method, is returned as
+    /// an error instead of `None`.
+    ///
+    /// ## Parameters
+    /// - `index`: The index position in the spine, starting from 0
+    ///
+    /// ## Return
+    /// - `Ok(Some((Vec<u8>, String)))`: Successfully retrieved chapter content data and
+    ///   the MIME type
+    /// - `Ok(None)`: Index out of range
+    /// - `Err(EpubError)`: The chapter could not be retrieved
+    ///
+    /// ## Notes
+    /// - The current spine position is moved to `index` even if the retrieval fails.
+    pub fn try_navigate_by_spine_index(
+        &mut self,
+        index: usize,
+    ) -> Result<Option<(Vec<u8>, String)>, EpubError> {
+        if index >= self.spine.len() {
+            return Ok(None);
+        }
+
+        self.current_spine_index.store(index, Ordering::SeqCst);
+        self.get_manifest_item(&self.spine[index].idref).map(Some)
+    }
+
+    /// Navigate to the previous linear reading chapter
+    ///
+    /// This function searches backwards in the EPUB spine for the previous linear
+    /// reading chapter and returns the content data of that chapter. It only navigates
+    /// to chapters marked as linear reading, and passes over the navigation document
+    /// when `set_skip_nav` is enabled.
+    ///
+    /// ## Return
+    /// - `Some((Vec<u8>, String))`: Successfully retrieved previous chapter content data and
+    ///   the MIME type
+    /// - `None`: Already in the first chapter, the current chapter is not linear,
+    ///   or data retrieval failed
+    pub fn spine_prev(&self) -> Option<(Vec<u8>, String)> {
+        self.try_spine_prev()
+            .map_err(|err| log::warn!("{err}"))
+            .ok()
+            .flatten()
+    }
+
+    /// Navigate to the previous linear reading chapter, reporting retrieval errors
+    ///
+    /// This function behaves like `spine_prev`, except that a failure to retrieve the
+    /// chapter is returned as an error instead of `None`.
+    ///
+    /// ## Return
+    /// - `Ok(Some((Vec<u8>, String)))`: Successfully retrieved previous chapter content
+    ///   data and the MIME type
+    /// - `Ok(None)`: Already in the first chapter, or the current chapter is not linear
+    /// - `Err(EpubError)`: The previous chapter could not be retrieved
+    pub fn try_spine_prev(&self) -> Result<Option<(Vec<u8>, String)>, EpubError> {
+        let current_index = self.current_spine_index.load(Ordering::SeqCst);
+        if current_index == 0
+            || !self
+                .spine
+                .get(current_index)
+                .is_some_and(|item| item.linear)
+        {
+            return Ok(None);
+        }
+
+        let Some(prev_index) = (0..current_index)
+            .rev()
+            .find(|&index| self.is_spine_stop(index))
+        else {
+            return Ok(None);
+        };
+
+        self.current_spine_index.store(prev_index, Ordering::SeqCst);
+        self.get_manifest_item(&self.spine[prev_index].idref)
+            .map(Some)
+    }
+
+    /// Navigate to the next linear reading chapter
+    ///
+    /// This function searches forwards in the EPUB spine for the next linear reading
+    /// chapter and returns the content data of that chapter. It only navigates to
+    /// chapters marked as linear reading, and passes over the navigation document
+    /// when `set_skip_nav` is enabled.
+    ///
+    /// ## Return
+    /// - `Some((Vec<u8>, String))`: Successfully retrieved next chapter content data and
+    ///   the MIME type
+    /// - `None`: Already in the last chapter, the current chapter is not linear,
+    ///   or data retrieval failed
+    pub fn spine_next(&mut self) -> Option<(Vec<u8>, String)> {
+        self.try_spine_next()
+            .map_err(|err| log::warn!("{err}"))
+            .ok()
+            .flatten()
+    }
+
+    /// Navigate to the next linear reading chapter, reporting retrieval errors
+    ///
+    /// This function behaves like `spine_next`, except that a failure to retrieve the
+    /// chapter is returned as an error instead of `None`.
+    ///
+    /// ## Return
+    /// - `Ok(Some((Vec<u8>, String)))`: Successfully retrieved next chapter content data
+    ///   and the MIME type
+    /// - `Ok(None)`: Already in the last chapter, or the current chapter is not linear
+    /// - `Err(EpubError)`: The next chapter could not be retrieved
+    pub fn try_spine_next(&mut self) -> Result<Option<(Vec<u8>, String)>, EpubError> {
+        let current_index = self.current_spine_index.load(Ordering::SeqCst);
+        if current_index + 1 >= self.spine.len()
+            || !self
+                .spine
+                .get(current_index)
+                .is_some_and(|item| item.linear)
+        {
+            return Ok(None);
+        }
+
+        let Some(next_index) =
+            (current_index + 1..self.spine.len()).find(|&index| self.is_spine_stop(index))
+        else {
+            return Ok(None);
+        };
+
+        self.current_spine_index.store(next_index, Ordering::SeqCst);
+        self.get_manifest_item(&self.spine[next_index].idref)
+            .map(Some)
+    }
+
+    /// Retrieves the content data of the current chapter
+    ///
+    /// This function returns the content data of the chapter at the current
+    /// index position in the EPUB spine.
+    ///
+    /// ## Return
+    /// - `Some((Vec<u8>, String))`: Successfully retrieved current chapter content data and
+    ///   the MIME type
+    /// - `None`: Data retrieval failed, or the spine is empty
+    pub fn spine_current(&self) -> Option<(Vec<u8>, String)> {
+        self.try_spine_current()
+            .map_err(|err| log::warn!("{err}"))
+            .ok()
+    }
+
+    /// Retrieves the content data of the current chapter, reporting retrieval errors
+    ///
+    /// This function behaves like `spine_current`, except that a failure to retrieve
+    /// the chapter is returned as an error instead of `None`.
+    ///
+    /// ## Return
+    /// - `Ok((Vec<u8>, String))`: Successfully retrieved current chapter content data
+    ///   and the MIME type
+    /// - `Err(EpubError)`: The current chapter could not be retrieved, or the spine is empty
+    pub fn try_spine_current(&self) -> Result<(Vec<u8>, String), EpubError> {
+        let current_index = self.current_spine_index.load(Ordering::SeqCst);
+        let Some(spine_item) = self.spine.get(current_index) else {
+            return Err(EpubError::SpineIndexOutOfRange {
+                index: current_index,
+                len: self.spine.len(),
+            });
+        };
+
+        self.get_manifest_item(&spine_item.idref)
+    }
+
+    /// Retrieves the reading progress of the current spine position
+    ///
+    /// The progress is the number of linear spine items before the current position
+    /// divided by the total number of linear spine items, so the first item reports
+    /// 0.0 and the current item is not counted as read yet. Non-linear items are not
+    /// counted, so reading a non-linear item reports the progress of the linear items
+    /// before it.
+    ///
+    /// ## Return
+    /// - `f64`: The reading progress, in the range of 0.0 to 1.0
+    ///
+    /// ## Notes
+    /// - Returns 0.0 if the spine does not contain any linear item.
+    pub fn reading_progress(&self) -> f64 {
+        let total = self.spine.iter().filter(|item| item.linear).count();
+        if total == 0 {
+            return 0.0;
+        }
+
+        let current_index = self.current_spine_index.load(Ordering::SeqCst);
+        let read = self
+            .spine
+            .iter()
+            .take(current_index)
+            .filter(|item| item.linear)
+            .count();
+
+        (read as f64 / total as f64).clamp(0.0, 1.0)
+    }
+
+    /// Retrieves the reading progress of the current spine position weighted by size
+    ///
+    /// Works like [`reading_progress`](Self::reading_progress), but each linear spine
+    /// item is weighted by the uncompressed size of its resource in the container,
+    /// so that long chapters account for a larger share of the progress.
+    ///
+    /// ## Return
+    /// - `Ok(f64)`: The reading progress, in the range of 0.0 to 1.0
+    /// - `Err(EpubError)`: The archive could not be accessed
+    ///
+    /// ## Notes
+    /// - Spine items whose resource is missing or remote are weighted as zero.
+    /// - Falls back to the unweighted progress if all linear items weigh zero.
+    pub fn reading_progress_weighted(&self) -> Result<f64, EpubError> {
+        let current_index = self.current_spine_index.load(Ordering::SeqCst);
+
+        let mut archive = self.archive.lock()?;
+        let (mut read, mut total) = (0u64, 0u64);
+        for (index, spine) in self.spine.iter().enumerate() {
+            if !spine.linear {
+                continue;
+            }
+
+            let size = self
+                .manifest
+                .get(&spine.idref)
+                .and_then(|item| item.path.to_str())
+                .and_then(|path| archive.by_name(path).ok().map(|file| file.size()))
+                .unwrap_or(0);
+
+            total += size;
+            if index < current_index {
+                read += size;
+            }
+        }
+        drop(archive);
+
+        if total == 0 {
+            return Ok(self.reading_progress());
+        }
+
+        Ok((read as f64 / total as f64).clamp(0.0, 1.0))
+    }
+
+    /// Determine the EPUB version from the OPF file
+    ///
+    /// This function is used to detect the version of an epub file from an OPF file.
+    /// When the version attribute in the package is abnormal, version information will
+    /// be identified through some version characteristics of the epub file. An error is
+    /// returned when neither direct nor indirect methods can identify the version.
+    ///
+    /// ## Parameters
+    /// - `opf_element`: A reference to the OPF file element
+    fn determine_epub_version(opf_element: &XmlElement) -> Result<EpubVersion, EpubError> {
+        // Check the explicit version attribute
+        if let Some(version) = opf_element.get_attr("version") {
+            match version.as_str() {
+                "2.0" => return Ok(EpubVersion::Version2_0),
+                "3.0" => return Ok(EpubVersion::Version3_0),
+                _ => {}
+            }
+        }
+
+        let spine_element = opf_element
+            .find_elements_by_name("spine")
+            .next()
+            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "spine".to_string() })?;
+
+        // Look for EPUB 2.x specific features
+        if spine_element.get_attr("toc").is_some() {
+            return Ok(EpubVersion::Version2_0);
+        }
+
+        let manifest_element = opf_element
+            .find_elements_by_name("manifest")
+            .next()
+            .ok_or_else(|| EpubError::NonCanonicalFile { tag: "manifest".to_string() })?;
+
+        // Look for EPUB 3.x specific features
+        manifest_element
+            .children()
+            .find_map(|element| {
+                if let Some(id) = element.get_attr("id") {
+                    if id.eq("nav") {
+                        return Some(EpubVersion::Version3_0);
+                    }
+                }
+
+                None
+            })
+            .ok_or(EpubError::UnrecognizedEpubVersion)
+    }
+
+    /// Parse metadata elements under the Dublin Core namespace
+    ///
+    /// This function handles the `<metadata>` Dublin Core element in the OPF file (namespace
+    /// is "http://purl.org/dc/elements/1.1/"). These elements usually contain the basic
+    /// information of the publication, such as title, author, publication date, etc.
+    ///
+    /// ## Notes
+    /// - In EPUB 3.0, granular information is handled by separate '<meta>' elements and 'refines' attributes
+    /// - All text content is normalized by whitespace
+    #[inline]
+    fn parse_dc_metadata(
+        element: &XmlElement,
+        version: EpubVersion,
+        metadata: &mut Vec<MetadataItem>,
+        // refinements: &mut HashMap<String, Vec<MetadataRefinement>>,
+    ) -> Result<(), EpubError> {
+        let id = element.get_attr("id");
+        let lang = element.get_attr("lang");
+        let property = element.name.clone();
+        let value = element.text().normalize_whitespace();
+
+        let refined = match version {
+            // In EPUB 2.0, supplementary metadata (refinements) are represented
+            // through other attribute data pairs of the tag.
+            EpubVersion::Version2_0 => element
+                .attributes
+                .iter()
+                .map(|(name, value)| {
+                    let property = name.to_string();
+                    let value = value.to_string().normalize_whitespace();
+
+                    MetadataRefinement {
+                        refines: id.clone().unwrap(),
+                        property,
+                        value,
+                        lang: None,
+                        scheme: None,
+                    }
+                })
+                .collect(),
+            EpubVersion::Version3_0 => vec![],
+        };
+
+        metadata.push(MetadataItem { id, property, value, lang, refined });
+
+        Ok(())
+    }
+
+    /// Parse metadata elements under the OPF namespace
+    ///
+    /// This function handles the `<metadata>` OPF element in the OPF file (namespace
+    /// is "http://www.idpf.org/2007/opf"). These elements include '<meta>' and '<link>',
+    /// which are used to provide extended metadata and links to external resources for EPUB publications.
+    ///
+    /// ## Notes
+    /// - The function is only responsible for distribution processing, and the
+    ///   specific parsing logic is implemented in the dedicated function
+    /// - All parsing results are added directly to the incoming collection and no new collection is returned
+    #[inline]
+    fn parse_opf_metadata(
+        element: &XmlElement,
+        version: EpubVersion,
+        metadata: &mut Vec<MetadataItem>,
+        metadata_link: &mut Vec<MetadataLinkItem>,
+        refinements: &mut HashMap<String, Vec<MetadataRefinement>>,
+    ) -> Result<(), EpubError> {
+        match element.name.as_str() {
+            "meta" => Self::parse_meta_element(element, version, metadata, refinements),
+            "link" => Self::parse_link_element(element, metadata_link),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn parse_meta_element(
+        element: &XmlElement,
+        version: EpubVersion,
+        metadata: &mut Vec<MetadataItem>,
+        refinements: &mut HashMap<String, Vec<MetadataRefinement>>,
+    ) -> Result<(), EpubError> {
+        // EPUB 3 publications may keep EPUB 2 meta elements, such as the ones written
+        // by Calibre, for compatibility with older reading systems
+        let is_legacy_meta =
+            element.get_attr("property").is_none() && element.get_attr("name").is_some();
+        let version = if is_legacy_meta {
+            EpubVersion::Version2_0
+        } else {
+            version
+        };
+
+        match version {
+            EpubVersion::Version2_0 => {
+                let property = element
+                    .get_attr("name")
+                    .ok_or_else(|| EpubError::NonCanonicalFile { tag: element.tag_name() })?;
+                let value = element
                     .get_attr("content")
                     .ok_or_else(|| EpubError::MissingRequiredAttribute {
                         tag: element.tag_name(),
@@ -1207,1449 +3508,3427 @@ impl<R: Read + Seek> EpubDoc<R> {
                     })?
                     .normalize_whitespace();
 
-                metadata.push(MetadataItem {
-                    id: None,
-                    property,
-                    value,
-                    lang: None,
-                    refined: vec![],
-                });
-            }
+                metadata.push(MetadataItem {
+                    id: None,
+                    property,
+                    value,
+                    lang: None,
+                    refined: vec![],
+                });
+            }
+
+            EpubVersion::Version3_0 => {
+                let property = element.get_attr("property").ok_or_else(|| {
+                    EpubError::MissingRequiredAttribute {
+                        tag: element.tag_name(),
+                        attribute: "property".to_string(),
+                    }
+                })?;
+                let value = element.text().normalize_whitespace();
+                let lang = element.get_attr("lang");
+
+                if let Some(refines) = element.get_attr("refines") {
+                    let id = refines.strip_prefix("#").unwrap_or(&refines).to_string();
+                    let scheme = element.get_attr("scheme");
+                    let refinement = MetadataRefinement {
+                        refines: id.clone(),
+                        property,
+                        value,
+                        lang,
+                        scheme,
+                    };
+
+                    if let Some(refinements) = refinements.get_mut(&id) {
+                        refinements.push(refinement);
+                    } else {
+                        refinements.insert(id, vec![refinement]);
+                    }
+                } else {
+                    let id = element.get_attr("id");
+                    let item = MetadataItem {
+                        id,
+                        property,
+                        value,
+                        lang,
+                        refined: vec![],
+                    };
+
+                    metadata.push(item);
+                };
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn parse_link_element(
+        element: &XmlElement,
+        metadata_link: &mut Vec<MetadataLinkItem>,
+    ) -> Result<(), EpubError> {
+        let href = element
+            .get_attr("href")
+            .ok_or_else(|| EpubError::MissingRequiredAttribute {
+                tag: element.tag_name(),
+                attribute: "href".to_string(),
+            })?;
+        let rel = element
+            .get_attr("rel")
+            .ok_or_else(|| EpubError::MissingRequiredAttribute {
+                tag: element.tag_name(),
+                attribute: "rel".to_string(),
+            })?;
+        let hreflang = element.get_attr("hreflang");
+        let id = element.get_attr("id");
+        let mime = element.get_attr("media-type");
+        let properties = element.get_attr("properties");
+        let refines = element
+            .get_attr("refines")
+            .map(|refines| refines.strip_prefix("#").unwrap_or(&refines).to_string());
+
+        metadata_link.push(MetadataLinkItem {
+            href,
+            rel,
+            hreflang,
+            id,
+            mime,
+            properties,
+            refines,
+        });
+        Ok(())
+    }
+
+    /// Recursively parse NCX navigation points from navMap or nested navPoint elements
+    ///
+    /// This function parses the hierarchical navigation structure defined in NCX files
+    /// for EPUB 2.x documents. It handles nested navPoint elements to build a complete
+    /// tree representation of the publication's table of contents.
+    fn parse_nav_points(&self, parent_element: &XmlElement) -> Result<Vec<NavPoint>, EpubError> {
+        let mut nav_points = Vec::new();
+        for (doc_index, nav_point) in parent_element.find_children_by_name("navPoint").enumerate() {
+            let mut item = Self::parse_ncx_target(nav_point, doc_index);
+            item.children = self.parse_nav_points(nav_point)?;
+
+            nav_points.push(item);
+        }
+
+        Ok(nav_points)
+    }
+
+    /// Parse the NCX targets with the given tag name, such as `pageTarget` or `navTarget`
+    ///
+    /// Targets of `pageList` and `navList` elements cannot be nested, so the returned
+    /// navigation points have no children.
+    fn parse_ncx_targets(parent_element: &XmlElement, tag_name: &str) -> Vec<NavPoint> {
+        parent_element
+            .find_children_by_name(tag_name)
+            .enumerate()
+            .map(|(doc_index, target)| Self::parse_ncx_target(target, doc_index))
+            .collect()
+    }
+
+    /// Parse the label, content link and play order of a single NCX navigation element
+    fn parse_ncx_target(element: &XmlElement, doc_index: usize) -> NavPoint {
+        let label = match element.find_children_by_name("navLabel").next() {
+            Some(label) => label.text(),
+            None => String::new(),
+        };
+
+        let content = Self::parse_ncx_content(element);
+
+        let play_order = element
+            .get_attr("playOrder")
+            .and_then(|order| order.parse::<usize>().ok());
+
+        NavPoint {
+            label,
+            content,
+            children: vec![],
+            play_order,
+            doc_index,
+        }
+    }
+
+    /// Parse the link of a NCX navigation element
+    ///
+    /// The link is held by the `src` attribute of the empty `<content>` child,
+    /// the text of that element is not part of the NCX format.
+    ///
+    /// ## Return
+    /// - `Some(PathBuf)`: The link, relative to the directory of the NCX file
+    /// - `None`: The element has no `<content>` child, or it lacks the `src` attribute
+    fn parse_ncx_content(element: &XmlElement) -> Option<PathBuf> {
+        element
+            .find_children_by_name("content")
+            .next()
+            .and_then(|content| content.get_attr("src"))
+            .map(|src| PathBuf::from(normalize_container_path(&src)))
+    }
+
+    /// Validates the `playOrder` values of NCX navigation points
+    ///
+    /// This function walks the navigation points in document order and reports
+    /// every navigation point whose play order is missing, duplicated, or not
+    /// greater than the play orders that precede it in the document.
+    ///
+    /// ## Return
+    /// - `Vec<PlayOrderIssue>`: Each problem found, empty if the play orders are valid
+    fn validate_play_order(nav_points: &[NavPoint]) -> Vec<PlayOrderIssue> {
+        let mut findings = Vec::new();
+        let mut seen = HashSet::new();
+        let mut last_order: Option<usize> = None;
+
+        let mut stack: Vec<&NavPoint> = nav_points.iter().rev().collect();
+        while let Some(nav_point) = stack.pop() {
+            stack.extend(nav_point.children.iter().rev());
+
+            let label = nav_point.label.clone();
+            let Some(order) = nav_point.play_order else {
+                findings.push(PlayOrderIssue::Missing { label });
+                continue;
+            };
+
+            if !seen.insert(order) {
+                findings.push(PlayOrderIssue::Duplicate { label, play_order: order });
+            } else if let Some(last) = last_order.filter(|&last| last > order) {
+                findings.push(PlayOrderIssue::OutOfOrder {
+                    label,
+                    play_order: order,
+                    preceding: last,
+                });
+            }
+
+            last_order = last_order.max(Some(order));
+        }
+
+        findings
+    }
+
+    /// Reassigns the play orders of navigation points by document order
+    ///
+    /// Play orders are assigned depth-first, starting from `next_order`, so that
+    /// sorting the navigation tree restores the order in which the entries appear
+    /// in the NCX document.
+    fn reassign_play_order(nav_points: &mut [NavPoint], next_order: &mut usize) {
+        for nav_point in nav_points {
+            nav_point.play_order = Some(*next_order);
+            *next_order += 1;
+            Self::reassign_play_order(&mut nav_point.children, next_order);
+        }
+    }
+
+    /// Sorts each level of the navigation tree by play order
+    fn sort_nav_points(nav_points: &mut [NavPoint]) {
+        nav_points.sort();
+        for nav_point in nav_points {
+            Self::sort_nav_points(&mut nav_point.children);
+        }
+    }
+
+    /// Recursively parses directory list structures
+    ///
+    /// This function recursively parses HTML navigation list structures,
+    /// converting `<ol>` and `<li>` elements into NavPoint structures.
+    /// Multi-level nested directory structures are supported.
+    fn parse_catalog_list(&self, element: &XmlElement) -> Result<Vec<NavPoint>, EpubError> {
+        let mut catalog = Vec::new();
+        for (doc_index, item) in element.children().enumerate() {
+            if item.tag_name() != "li" {
+                return Err(EpubError::NonCanonicalFile { tag: "li".to_string() });
+            }
+
+            let title_element = item
+                .find_children_by_names(&["span", "a"])
+                .next()
+                .ok_or_else(|| EpubError::NonCanonicalFile { tag: "span/a".to_string() })?;
+            let content_href = title_element
+                .get_attr("href")
+                .map(|href| PathBuf::from(normalize_container_path(&href)));
+            let sub_list = if let Some(list) = item.find_children_by_name("ol").next() {
+                self.parse_catalog_list(list)?
+            } else {
+                vec![]
+            };
+
+            catalog.push(NavPoint {
+                label: title_element.text(),
+                content: content_href,
+                children: sub_list,
+                play_order: None,
+                doc_index,
+            });
+        }
+
+        Ok(catalog)
+    }
+
+    /// Converts relative paths in the manifest to normalized paths
+    /// relative to the EPUB root directory
+    ///
+    /// This function processes the href attribute of resources in the EPUB
+    /// manifest and converts it to a normalized path representation.
+    /// It handles four types of paths:
+    /// - Absolute URLs of remote resources (kept unchanged)
+    /// - Relative paths starting with `../` (checks if they exceed the EPUB package scope)
+    /// - Absolute paths starting with `/` (relative to the EPUB root directory)
+    /// - Other relative paths (relative to the directory containing the OPF file)
+    ///
+    /// ## Parameters
+    /// - `path`: The href attribute value of the resource in the manifest
+    ///
+    /// ## Return
+    /// - `Ok(PathBuf)`: The parsed normalized path
+    /// - `Err(EpubError)`: Relative link leakage
+    #[inline]
+    fn normalize_manifest_path(&self, path: &str) -> Result<PathBuf, EpubError> {
+        // remote resources are located outside of the container and are kept as is
+        if Self::is_remote_url(path) {
+            return Ok(PathBuf::from(path));
+        }
+
+        let href = normalize_container_path(path);
+        let path = if href.starts_with("../") {
+            // the check runs on container paths, so that it does not depend on
+            // whether `epub_path` is absolute, relative or synthetic
+            check_realtive_link_leakage(PathBuf::new(), self.base_path.clone(), &href)
+                .ok_or_else(|| EpubError::RelativeLinkLeakage { path: path.to_string() })?
+        } else if let Some(path) = href.strip_prefix("/") {
+            path.to_string()
+        } else {
+            self.base_path.join(&href).to_string_lossy().to_string()
+        };
+
+        Ok(PathBuf::from(normalize_container_path(&path)))
+    }
+
+    /// Verify the fallback chain of all manifest items
+    ///
+    /// This function iterates through all manifest items with the fallback
+    /// attribute and verifies the validity of their fallback chains, including checking:
+    /// - Whether circular references exist
+    /// - Whether the fallback resource exists in the manifest
+    ///
+    /// ## Notes
+    /// If an invalid fallback chain is found, a warning log will be logged
+    /// but the processing flow will not be interrupted.
+    // TODO: consider using BFS to validate fallback chains, to provide efficient
+    fn validate_fallback_chains(&self) {
+        for (id, item) in &self.manifest {
+            if item.fallback.is_none() {
+                continue;
+            }
+
+            let mut fallback_chain = Vec::new();
+            if let Err(msg) = self.validate_fallback_chain(id, &mut fallback_chain) {
+                log::warn!("Invalid fallback chain for item {}: {}", id, msg);
+            }
+        }
+    }
+
+    /// Checks whether a path is an absolute URL pointing outside of the container
+    #[inline]
+    fn is_remote_url(path: &str) -> bool {
+        path.contains("://")
+    }
+
+    /// Records a problem that was repaired during lenient parsing
+    fn push_warning(&mut self, message: String) {
+        log::warn!("{}", message);
+        self.warnings.push(message);
+    }
+
+    /// Recursively verify the validity of a single fallback chain
+    ///
+    /// This function recursively traces the fallback chain to check for the following issues:
+    /// - Circular reference
+    /// - The referenced fallback resource does not exist
+    ///
+    /// ## Parameters
+    /// - `manifest_id`: The id of the manifest item currently being verified
+    /// - `fallback_chain`: The visited fallback chain paths used to detect circular references
+    ///
+    /// ## Return
+    /// - `Ok(())`: The fallback chain is valid
+    /// - `Err(String)`: A string containing error information
+    fn validate_fallback_chain(
+        &self,
+        manifest_id: &str,
+        fallback_chain: &mut Vec<String>,
+    ) -> Result<(), String> {
+        if fallback_chain.contains(&manifest_id.to_string()) {
+            fallback_chain.push(manifest_id.to_string());
+
+            return Err(format!(
+                "Circular reference detected in fallback chain for {}",
+                fallback_chain.join("->")
+            ));
+        }
+
+        // Get the current item; its existence can be ensured based on the calling context.
+        let item = self.manifest.get(manifest_id).unwrap();
+
+        if let Some(fallback_id) = &item.fallback {
+            if !self.manifest.contains_key(fallback_id) {
+                return Err(format!(
+                    "Fallback resource {} does not exist in manifest",
+                    fallback_id
+                ));
+            }
+
+            fallback_chain.push(manifest_id.to_string());
+            self.validate_fallback_chain(fallback_id, fallback_chain)
+        } else {
+            // The end of the fallback chain
+            Ok(())
+        }
+    }
+
+    /// Checks if a resource at the specified path is an encrypted file
+    ///
+    /// This function queries whether a specific resource path is marked as an encrypted
+    /// file in the EPUB encryption information. It checks the encrypted data stored in
+    /// `self.encryption`, looking for an entry that matches the given path.
+    ///
+    /// ## Parameters
+    /// - `path`: The path of the resource to check
+    ///
+    /// ## Return
+    /// - `Some(String)`: The encryption method used for the resource
+    /// - `None`: The resource is not encrypted
+    fn is_encryption_file(&self, path: &str) -> Option<String> {
+        self.encryption.as_ref().and_then(|encryptions| {
+            encryptions
+                .iter()
+                .find(|encryption| encryption.data == path)
+                .map(|encryption| encryption.method.clone())
+        })
+    }
+
+    /// Automatically decrypts encrypted resource data
+    ///
+    /// Automatically decrypts data based on the provided encryption method.
+    /// This function supports various encryption methods defined by the EPUB
+    /// specification, including font obfuscation and the XML encryption standard.
+    ///
+    /// ## Parameters
+    /// - `method`: The encryption method used for the resource
+    /// - `data`: The encrypted resource data
+    ///
+    /// ## Return
+    /// - `Ok(Vec<u8>)`: The decrypted resource data
+    /// - `Err(EpubError)`: Unsupported encryption method
+    ///
+    /// ## Supported Encryption Methods
+    /// - IDPF font obfuscation: `http://www.idpf.org/2008/embedding`
+    /// - Adobe font obfuscation: `http://ns.adobe.com/pdf/enc#RC`
+    #[inline]
+    fn auto_dencrypt(&self, method: &str, data: &mut [u8]) -> Result<Vec<u8>, EpubError> {
+        match method {
+            "http://www.idpf.org/2008/embedding" => {
+                Ok(idpf_font_dencryption(data, &self.unique_identifier))
+            }
+            "http://ns.adobe.com/pdf/enc#RC" => {
+                Ok(adobe_font_dencryption(data, &self.unique_identifier))
+            }
+            _ => Err(EpubError::UnsupportedEncryptedMethod { method: method.to_string() }),
+        }
+    }
+}
+
+impl EpubDoc<BufReader<File>> {
+    /// Creates a new EPUB document instance
+    ///
+    /// This function is a convenience constructor for `EpubDoc`,
+    /// used to create an EPUB parser instance directly from a file path.
+    ///
+    /// ## Parameters
+    /// - `path`: The path to the EPUB file
+    ///
+    /// ## Return
+    /// - `Ok(EpubDoc)`: The created EPUB document instance
+    /// - `Err(EpubError)`: An error occurred during initialization
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, EpubError> {
+        Self::new_with_mode(path, ParseMode::default())
+    }
+
+    /// Opens an EPUB file
+    ///
+    /// This function is an alias of [`new`](Self::new), following the naming
+    /// of `File::open`.
+    ///
+    /// ## Parameters
+    /// - `path`: The path to the EPUB file
+    ///
+    /// ## Return
+    /// - `Ok(EpubDoc)`: The created EPUB document instance
+    /// - `Err(EpubError)`: An error occurred during initialization
+    #[inline]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, EpubError> {
+        Self::new(path)
+    }
+
+    /// Creates a new EPUB document instance with the given parse mode
+    ///
+    /// ## Parameters
+    /// - `path`: The path to the EPUB file
+    /// - `mode`: How specification violations are handled
+    ///
+    /// ## Return
+    /// - `Ok(EpubDoc)`: The created EPUB document instance
+    /// - `Err(EpubError)`: An error occurred during initialization
+    pub fn new_with_mode<P: AsRef<Path>>(path: P, mode: ParseMode) -> Result<Self, EpubError> {
+        let file = File::open(&path).map_err(EpubError::from)?;
+        let path = fs::canonicalize(path)?;
+
+        Self::from_reader_with_mode(BufReader::new(file), path, mode)
+    }
+
+    /// Validates whether a file is a valid EPUB document
+    ///
+    /// This function attempts to open and parse the given file as an EPUB document.
+    /// It performs basic validation to determine if the file conforms to the EPUB specification.
+    ///
+    /// ## Parameters
+    /// - `path`: The path to the file to validate
+    ///
+    /// ## Returns
+    /// - `Ok(true)`: The file is a valid EPUB document
+    /// - `Ok(false)`: The file exists but is not a valid EPUB (e.g., missing required files,
+    ///   invalid XML structure, unrecognized version)
+    /// - `Err(EpubError)`: A critical error occurred (e.g., IO error, ZIP archive error,
+    ///   encoding error, mutex poison)
+    pub fn is_valid_epub<P: AsRef<Path>>(path: P) -> Result<bool, EpubError> {
+        let result = EpubDoc::new(path);
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(err) if Self::is_outside_error(&err) => Err(err),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Determines if an error is a "critical" external error that should be propagated
+    ///
+    /// ## Error Classification
+    /// Outside errors (returned as `Err`):
+    /// - ArchiveError: ZIP archive corruption or read errors
+    /// - IOError: File system or read errors
+    /// - MutexError: Thread synchronization errors
+    /// - Utf8DecodeError: UTF-8 encoding errors
+    /// - Utf16DecodeError: UTF-16 encoding errors
+    /// - QuickXmlError: XML parser errors
+    /// - XmlParse: Malformed XML errors
+    ///
+    /// Irrelevant errors (returned as `Ok(false)`):
+    /// - these errors could not have occurred in this situation.
+    /// - EpubBuilderError
+    /// - WalkDirError
+    ///
+    /// Content errors (returned as `Ok(false)`):
+    /// - All other EpubError variants
+    fn is_outside_error(err: &EpubError) -> bool {
+        matches!(
+            err,
+            EpubError::ArchiveError { .. }
+                | EpubError::IOError { .. }
+                | EpubError::MutexError
+                | EpubError::Utf8DecodeError { .. }
+                | EpubError::Utf16DecodeError { .. }
+                | EpubError::QuickXmlError { .. }
+                | EpubError::XmlParse { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        io::BufReader,
+        path::{Path, PathBuf},
+        sync::{Arc, atomic::Ordering},
+        time::Duration,
+    };
+
+    use crate::{
+        epub::EpubDoc,
+        error::EpubError,
+        types::{
+            CfiLocation, EpubVersion, FetchPolicy, ManifestItem, PageSpread, ParseMode,
+            PlayOrderIssue, SearchOptions,
+        },
+        utils::XmlReader,
+    };
+
+    /// Section 3.3 package documents
+    mod package_documents_tests {
+        use std::{path::Path, sync::atomic::Ordering};
+
+        use crate::epub::{EpubDoc, EpubVersion};
+
+        /// ID: pkg-collections-unknown
+        ///
+        /// The package document contains a collection with an unknown role. The reading system must open the EPUB successfully.
+        #[test]
+        fn test_pkg_collections_unknown() {
+            let epub_file = Path::new("./test_case/pkg-collections-unknown.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+        }
+
+        /// ID: pkg-creator-order
+        ///
+        /// Several creators are listed in the package document. The reading system must not display them out of order (but it may display only the first).
+        #[test]
+        fn test_pkg_creator_order() {
+            let epub_file = Path::new("./test_case/pkg-creator-order.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let creators = doc.get_metadata_value("creator");
+            assert!(creators.is_some());
+
+            let creators = creators.unwrap();
+            assert_eq!(creators.len(), 5);
+            assert_eq!(
+                creators,
+                vec![
+                    "Dave Cramer",
+                    "Wendy Reid",
+                    "Dan Lazin",
+                    "Ivan Herman",
+                    "Brady Duga",
+                ]
+            );
+        }
+
+        /// ID: pkg-manifest-unknown
+        ///
+        /// The package document contains a manifest item with unknown properties. The reading system must open the EPUB successfully.
+        #[test]
+        fn test_pkg_manifest_order() {
+            let epub_file = Path::new("./test_case/pkg-manifest-unknown.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert_eq!(doc.manifest.len(), 2);
+            assert!(doc.get_manifest_item("nav").is_ok());
+            assert!(doc.get_manifest_item("content_001").is_ok());
+            assert!(doc.get_manifest_item("content_002").is_err());
+        }
+
+        /// ID: pkg-meta-unknown
+        ///
+        /// The package document contains a meta tag with an unknown property. The reading system must open the EPUB successfully.
+        #[test]
+        fn test_pkg_meta_unknown() {
+            let epub_file = Path::new("./test_case/pkg-meta-unknown.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let value = doc.get_metadata_value("dcterms:isReferencedBy");
+            assert!(value.is_some());
+            let value = value.unwrap();
+            assert_eq!(value.len(), 1);
+            assert_eq!(
+                value,
+                vec!["https://www.w3.org/TR/epub-rs/#confreq-rs-pkg-meta-unknown"]
+            );
+
+            let value = doc.get_metadata_value("dcterms:modified");
+            assert!(value.is_some());
+            let value = value.unwrap();
+            assert_eq!(value.len(), 1);
+            assert_eq!(value, vec!["2021-01-11T00:00:00Z"]);
+
+            let value = doc.get_metadata_value("dcterms:title");
+            assert!(value.is_none());
+        }
+
+        /// ID: pkg-meta-whitespace
+        ///
+        /// The package document's title and creator contain leading and trailing spaces along with excess internal whitespace. The reading system must render only a single space in all cases.
+        #[test]
+        fn test_pkg_meta_white_space() {
+            let epub_file = Path::new("./test_case/pkg-meta-whitespace.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let value = doc.get_metadata_value("creator");
+            assert!(value.is_some());
+            let value = value.unwrap();
+            assert_eq!(value.len(), 1);
+            assert_eq!(value, vec!["Dave Cramer"]);
+
+            let value = doc.get_metadata_value("description");
+            assert!(value.is_some());
+            let value = value.unwrap();
+            assert_eq!(value.len(), 1);
+            assert_eq!(
+                value,
+                vec![
+                    "The package document's title and creator contain leading and trailing spaces along with excess internal whitespace. The reading system must render only a single space in all cases."
+                ]
+            );
+        }
+
+        /// ID: pkg-spine-duplicate-item-hyperlink
+        ///
+        /// The spine contains several references to the same content document. The reading system must move to the position of the first duplicate in the reading order when following a hyperlink.
+        #[test]
+        fn test_pkg_spine_duplicate_item_hyperlink() {
+            let epub_file = Path::new("./test_case/pkg-spine-duplicate-item-hyperlink.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let mut doc = doc.unwrap();
+            assert_eq!(doc.spine.len(), 4);
+            assert_eq!(
+                doc.navigate_by_spine_index(0).unwrap(),
+                doc.get_manifest_item("content_001").unwrap()
+            );
+            assert_eq!(
+                doc.navigate_by_spine_index(1).unwrap(),
+                doc.get_manifest_item("content_002").unwrap()
+            );
+            assert_eq!(
+                doc.navigate_by_spine_index(2).unwrap(),
+                doc.get_manifest_item("content_002").unwrap()
+            );
+            assert_eq!(
+                doc.navigate_by_spine_index(3).unwrap(),
+                doc.get_manifest_item("content_002").unwrap()
+            );
+        }
+
+        /// ID: pkg-spine-duplicate-item-rendering
+        ///
+        /// The spine contains several references to the same content document. The reading system must not skip the duplicates when rendering the reading order.
+        #[test]
+        fn test_pkg_spine_duplicate_item_rendering() {
+            let epub_file = Path::new("./test_case/pkg-spine-duplicate-item-rendering.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let mut doc = doc.unwrap();
+            assert_eq!(doc.spine.len(), 4);
+
+            let result = doc.spine_prev();
+            assert!(result.is_none());
+
+            let result = doc.spine_next();
+            assert!(result.is_some());
+
+            doc.spine_next();
+            doc.spine_next();
+            let result = doc.spine_next();
+            assert!(result.is_none());
+        }
+
+        /// ID: pkg-spine-nonlinear-activation
+        ///
+        /// An itemref in the spine is marked as non-linear. Although it (possibly) cannot be accessed through the table of contents, it can be reached from a link in the XHTML content.
+        #[test]
+        fn test_pkg_spine_nonlinear_activation() {
+            let epub_file = Path::new("./test_case/pkg-spine-nonlinear-activation.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let mut doc = doc.unwrap();
+            assert!(doc.spine_prev().is_none());
+            assert!(doc.spine_next().is_none());
+
+            assert!(doc.navigate_by_spine_index(1).is_some());
+            assert!(doc.spine_prev().is_none());
+            assert!(doc.spine_next().is_none());
+        }
+
+        /// ID: pkg-spine-order
+        ///
+        /// Basic test of whether a reading system can display spine items in the correct order. The test fails if the reading system presents content in the order in which the file names sort, or if it presents files in manifest order rather than spine order.
+        #[test]
+        fn test_pkg_spine_order() {
+            let epub_file = Path::new("./test_case/pkg-spine-order.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert_eq!(doc.spine.len(), 4);
+            assert_eq!(
+                doc.spine
+                    .iter()
+                    .map(|item| item.idref.clone())
+                    .collect::<Vec<String>>(),
+                vec![
+                    "d-content_001",
+                    "c-content_002",
+                    "b-content_003",
+                    "a-content_004",
+                ]
+            );
+        }
+
+        /// ID: pkg-spine-order-svg
+        ///
+        /// Basic test of whether a reading system can display SVG spine items in the correct order.
+        #[test]
+        fn test_spine_order_svg() {
+            let epub_file = Path::new("./test_case/pkg-spine-order-svg.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let mut doc = doc.unwrap();
+            assert_eq!(doc.spine.len(), 4);
+
+            while let Some(spine) = doc.spine_next() {
+                let idref = doc.spine[doc.current_spine_index.load(Ordering::Relaxed)]
+                    .idref
+                    .clone();
+                let resource = doc.get_manifest_item(&idref);
+                assert!(resource.is_ok());
+
+                let resource = resource.unwrap();
+                assert_eq!(spine, resource);
+            }
+
+            assert_eq!(doc.current_spine_index.load(Ordering::Relaxed), 3);
+        }
+
+        /// ID: pkg-spine-unknown
+        ///
+        /// The package document contains a spine item with unknown properties. The reading system must open the EPUB successfully.
+        #[test]
+        fn test_pkg_spine_unknown() {
+            let epub_file = Path::new("./test_case/pkg-spine-unknown.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert_eq!(doc.spine.len(), 1);
+            assert_eq!(doc.spine[0].idref, "content_001");
+            assert_eq!(doc.spine[0].id, None);
+            assert!(doc.spine[0].linear);
+            assert_eq!(doc.spine[0].properties, Some("untrustworthy".to_string()));
+        }
+
+        /// ID: pkg-title-order
+        ///
+        /// Several titles are listed in the package document. The reading system must use the first title (and whether to use other titles is not defined).
+        #[test]
+        fn test_pkg_title_order() {
+            let epub_file = Path::new("./test_case/pkg-title-order.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let title_list = doc.get_title();
+            assert_eq!(title_list.len(), 6);
+            assert_eq!(
+                title_list,
+                vec![
+                    "pkg-title-order",
+                    "This title must not display first",
+                    "Also, this title must not display first",
+                    "This title also must not display first",
+                    "This title must also not display first",
+                    "This title must not display first, also",
+                ]
+            );
+        }
+
+        /// ID: pkg-unique-id
+        ///
+        /// The package document's dc:identifier is identical across two publications. The reading system should display both publications independently.
+        #[test]
+        fn test_pkg_unique_id() {
+            let epub_file = Path::new("./test_case/pkg-unique-id.epub");
+            let doc_1 = EpubDoc::new(epub_file);
+            assert!(doc_1.is_ok());
+
+            let epub_file = Path::new("./test_case/pkg-unique-id_duplicate.epub");
+            let doc_2 = EpubDoc::new(epub_file);
+            assert!(doc_2.is_ok());
+
+            let doc_1 = doc_1.unwrap();
+            let doc_2 = doc_2.unwrap();
+
+            assert_eq!(doc_1.get_identifier(), doc_2.get_identifier());
+            assert_eq!(doc_1.unique_identifier, "pkg-unique-id");
+            assert_eq!(doc_2.unique_identifier, "pkg-unique-id");
+        }
+
+        /// ID: pkg-version-backward
+        ///
+        /// “Reading Systems MUST attempt to process an EPUB Publication whose Package Document version attribute is less than "3.0"”. This is an EPUB with package version attribute set to "0", to see if a reading system will open it.
+        #[test]
+        fn test_pkg_version_backward() {
+            let epub_file = Path::new("./test_case/pkg-version-backward.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert_eq!(doc.version, EpubVersion::Version3_0);
+        }
+
+        /// ID: pkg-linked-records
+        ///
+        /// Reading System must process and display the title and creator metadata from the package document. An ONIX 3.0 format linked metadata record exists, but contains neither title nor creator metadata.
+        #[test]
+        fn test_pkg_linked_records() {
+            let epub_file = Path::new("./test_case/pkg-linked-records.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert_eq!(doc.metadata_link.len(), 3);
+
+            let item = doc.metadata_link.iter().find(|&item| {
+                if let Some(properties) = &item.properties {
+                    properties.eq("onix")
+                } else {
+                    false
+                }
+            });
+            assert!(item.is_some());
+        }
+
+        /// ID: pkg-manifest-unlisted-resource
+        ///
+        /// The XHTML content references an image that does not appear in the manifest. The image should not be shown.
+        #[test]
+        fn test_pkg_manifest_unlisted_resource() {
+            let epub_file = Path::new("./test_case/pkg-manifest-unlisted-resource.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert!(
+                doc.get_manifest_item_by_path("EPUB/content_001.xhtml")
+                    .is_ok()
+            );
+
+            assert!(doc.get_manifest_item_by_path("EPUB/red.png").is_err());
+            let err = doc.get_manifest_item_by_path("EPUB/red.png").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Resource not found: Unable to find resource from \"EPUB/red.png\"."
+            );
+        }
+    }
+
+    /// Section 3.4 manifest fallbacks
+    ///
+    /// The tests under this module seem to favor the reading system rather than the EPUB format itself
+    mod manifest_fallbacks_tests {
+        use std::path::Path;
+
+        use crate::{epub::EpubDoc, error::EpubError};
+
+        /// ID: pub-foreign_bad-fallback
+        ///
+        /// This is a test of manifest fallbacks where both the spine item and the fallback are likely to be unsupported. The spine item is a DMG, with a fallback to a PSD file. Reading systems may raise an error on the ingenstion workflow.
+        #[test]
+        fn test_pub_foreign_bad_fallback() {
+            let epub_file = Path::new("./test_case/pub-foreign_bad-fallback.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert!(doc.get_manifest_item("content_001").is_ok());
+            assert!(doc.get_manifest_item("bar").is_ok());
+
+            assert_eq!(
+                doc.get_manifest_item_with_fallback("content_001", &["application/xhtml+xml"])
+                    .unwrap_err()
+                    .to_string(),
+                "No supported file format: The fallback resource does not contain the file format you support."
+            );
+
+            let result = doc
+                .get_manifest_item_with_fallback_verbose("content_001", &["application/xhtml+xml"]);
+            assert!(result.is_err());
+
+            let err = result.unwrap_err();
+            assert_eq!(err.source, EpubError::NoSupportedFileFormat);
+            assert_eq!(
+                err.tried,
+                vec![
+                    (
+                        "content_001".to_string(),
+                        "application/octet-stream".to_string()
+                    ),
+                    ("bar".to_string(), "image/psd".to_string()),
+                ]
+            );
+        }
+
+        /// ID: pub-foreign_image
+        ///
+        /// An HTML content file contains a PSD image, with a manifest fallback to a PNG image. This tests fallbacks for resources that are not in the spine.
+        #[test]
+        fn test_pub_foreign_image() {
+            let epub_file = Path::new("./test_case/pub-foreign_image.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let result = doc.get_manifest_item_with_fallback(
+                "image-tiff",
+                &["image/png", "application/xhtml+xml"],
+            );
+            assert!(result.is_ok());
+
+            let (_, mime) = result.unwrap();
+            assert_eq!(mime, "image/png");
+        }
+
+        /// ID: pub-foreign_json-spine
+        ///
+        /// This EPUB uses a JSON content file in the spine, with a manifest fallback to an HTML document. If the reading system does not support JSON, it should display the HTML.
+        #[test]
+        fn test_pub_foreign_json_spine() {
+            let epub_file = Path::new("./test_case/pub-foreign_json-spine.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let result = doc.get_manifest_item_with_fallback(
+                "content_primary",
+                &["application/xhtml+xml", "application/json"],
+            );
+            assert!(result.is_ok());
+            let (_, mime) = result.unwrap();
+            assert_eq!(mime, "application/json");
+
+            let result =
+                doc.get_manifest_item_with_fallback("content_primary", &["application/xhtml+xml"]);
+            assert!(result.is_ok());
+            let (_, mime) = result.unwrap();
+            assert_eq!(mime, "application/xhtml+xml");
+        }
+
+        /// ID: pub-foreign_xml-spine
+        ///
+        /// This EPUB uses an ordinary XML content file with mimetype application/xml in the spine, with a manifest fallback to an HTML document. If the reading system does not support XML, it should display the HTML.
+        #[test]
+        fn test_pub_foreign_xml_spine() {
+            let epub_file = Path::new("./test_case/pub-foreign_xml-spine.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let result = doc.get_manifest_item_with_fallback(
+                "content_primary",
+                &["application/xhtml+xml", "application/xml"],
+            );
+            assert!(result.is_ok());
+            let (_, mime) = result.unwrap();
+            assert_eq!(mime, "application/xml");
+
+            let result =
+                doc.get_manifest_item_with_fallback("content_primary", &["application/xhtml+xml"]);
+            assert!(result.is_ok());
+            let (_, mime) = result.unwrap();
+            assert_eq!(mime, "application/xhtml+xml");
+        }
+
+        /// ID: pub-foreign_xml-suffix-spine
+        ///
+        /// This EPUB uses an custom XML content file with mimetype application/dtc+xml in the spine, with a manifest fallback to an HTML document. If the reading system does not support XML, it should display the HTML.
+        #[test]
+        fn test_pub_foreign_xml_suffix_spine() {
+            let epub_file = Path::new("./test_case/pub-foreign_xml-suffix-spine.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let result = doc.get_manifest_item_with_fallback(
+                "content_primary",
+                &["application/xhtml+xml", "application/dtc+xml"],
+            );
+            assert!(result.is_ok());
+            let (_, mime) = result.unwrap();
+            assert_eq!(mime, "application/dtc+xml");
+
+            let result =
+                doc.get_manifest_item_with_fallback("content_primary", &["application/xhtml+xml"]);
+            assert!(result.is_ok());
+            let (_, mime) = result.unwrap();
+            assert_eq!(mime, "application/xhtml+xml");
+        }
+    }
+
+    /// Section 3.9 open container format
+    mod open_container_format_tests {
+        use std::{io::Read, path::Path};
+
+        use crate::{
+            epub::EpubDoc,
+            utils::{apply_idpf_obfuscation, idpf_obfuscation_key, is_font_data},
+        };
+
+        /// ID: ocf-metainf-inc
+        ///
+        /// An extra configuration file, not in the reserved files' list, is added to the META-INF folder; this file must be ignored.
+        #[test]
+        fn test_ocf_metainf_inc() {
+            let epub_file = Path::new("./test_case/ocf-metainf-inc.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+        }
+
+        /// ID: ocf-metainf-manifest
+        ///
+        /// An ancillary manifest file, containing an extra spine item, is present in the META-INF directory; this extra item must be ignored by the reading system.
+        #[test]
+        fn test_ocf_metainf_manifest() {
+            let epub_file = Path::new("./test_case/ocf-metainf-manifest.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+        }
+
+        /// ID: ocf-package_arbitrary
+        ///
+        /// The EPUB contains three valid package files and three corresponding sets of content documents, but only one of the packages, in an unusual subdirectory, is referenced by the container.xml file. The reading system must use this package.
+        #[test]
+        fn test_ocf_package_arbitrary() {
+            let epub_file = Path::new("./test_case/ocf-package_arbitrary.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert_eq!(doc.package_path, Path::new("FOO/BAR/package.opf"));
+        }
+
+        /// ID: ocf-package_multiple
+        ///
+        /// The EPUB contains three valid package files and three corresponding sets of content documents, all referenced by the container.xml file. The reading system must use the first package.
+        #[test]
+        fn test_ocf_package_multiple() {
+            let epub_file = Path::new("./test_case/ocf-package_multiple.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert_eq!(doc.package_path, Path::new("FOO/BAR/package.opf"));
+            assert_eq!(doc.base_path, Path::new("FOO/BAR"));
+        }
+
+        /// ID: ocf-url_link-leaking-relative
+        ///
+        /// Use a relative link with several double-dot path segments from the content to a photograph. The folder hierarchy containing the photograph starts at the root level; the relative image reference exceeds depth of hierarchy.
+        #[test]
+        fn test_ocf_url_link_leaking_relative() {
+            let epub_file = Path::new("./test_case/ocf-url_link-leaking-relative.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_err());
+            assert_eq!(
+                doc.err().unwrap().to_string(),
+                String::from(
+                    "Relative link leakage: Path \"../../../../media/imgs/monastery.jpg\" is out of container range."
+                )
+            )
+        }
+
+        /// ID: ocf-url_link-path-absolute
+        ///
+        /// Use a path-absolute link, i.e., beginning with a leading slash, from the content to a photograph. The folder hierarchy containing the photograph starts at the root level.
+        #[test]
+        fn test_ocf_url_link_path_absolute() {
+            let epub_file = Path::new("./test_case/ocf-url_link-path-absolute.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let resource = doc.manifest.get("photo").unwrap();
+            assert_eq!(resource.path, Path::new("media/imgs/monastery.jpg"));
+        }
+
+        /// ID: ocf-url_link-relative
+        ///
+        /// A simple relative link from the content to a photograph. The folder hierarchy containing the photograph starts at the root level.
+        #[test]
+        fn test_ocf_url_link_relative() {
+            let epub_file = Path::new("./test_case/ocf-url_link-relative.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let resource = doc.manifest.get("photo").unwrap();
+            assert_eq!(resource.path, Path::new("media/imgs/monastery.jpg"));
+        }
+
+        /// ID: ocf-url_manifest
+        ///
+        /// The manifest refers to an XHTML file in an arbitrary subfolder. The reading system must be able to find the content.
+        #[test]
+        fn test_ocf_url_manifest() {
+            let epub_file = Path::new("./test_case/ocf-url_manifest.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert!(doc.get_manifest_item("nav").is_ok());
+            assert!(doc.get_manifest_item("content_001").is_ok());
+            assert!(doc.get_manifest_item("content_002").is_err());
+        }
+
+        /// ID: ocf-url_relative
+        ///
+        /// The manifest refers to an XHTML file in an arbitrary subfolder that is relative to the package's own arbitrary folder. The reading system must be able to find the content.
+        #[test]
+        fn test_ocf_url_relative() {
+            let epub_file = Path::new("./test_case/ocf-url_relative.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            assert_eq!(doc.package_path, Path::new("foo/BAR/baz.opf"));
+            assert_eq!(doc.base_path, Path::new("foo/BAR"));
+            assert_eq!(
+                doc.manifest.get("nav").unwrap().path,
+                Path::new("foo/BAR/nav.xhtml")
+            );
+            assert_eq!(
+                doc.manifest.get("content_001").unwrap().path,
+                Path::new("foo/BAR/qux/content_001.xhtml")
+            );
+            assert!(doc.get_manifest_item("nav").is_ok());
+            assert!(doc.get_manifest_item("content_001").is_ok());
+        }
+
+        /// ID: ocf-zip-comp
+        ///
+        /// MUST treat any OCF ZIP container that uses compression techniques other than Deflate as in error.
+        /// This test case does not use compression methods other than Deflate and cannot detect whether it is effective.
+        #[test]
+        fn test_ocf_zip_comp() {
+            let epub_file = Path::new("./test_case/ocf-zip-comp.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+        }
+
+        /// ID: ocf-zip-mult
+        ///
+        /// MUST treat any OCF ZIP container that splits the content into segments as in error.
+        /// This test case is not a segmented OCF ZIP container and cannot be tested to see if it is valid.
+        #[test]
+        fn test_ocf_zip_mult() {
+            let epub_file = Path::new("./test_case/ocf-zip-mult.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+        }
+
+        /// ID: ocf-font_obfuscation
+        ///
+        /// An obfuscated (TrueType) font should be displayed after de-obfuscation.
+        #[test]
+        fn test_ocf_font_obfuscation() {
+            let epub_file = Path::new("./test_case/ocf-font_obfuscation.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let unique_id = doc.unique_identifier.clone();
+
+            let key = idpf_obfuscation_key(&unique_id);
+
+            assert!(doc.encryption.is_some());
+            assert_eq!(doc.encryption.as_ref().unwrap().len(), 1);
+
+            let data = &doc.encryption.unwrap()[0];
+            assert_eq!(data.method, "http://www.idpf.org/2008/embedding");
+
+            let mut font_file = Vec::new();
+            let font_file = doc
+                .archive
+                .lock()
+                .unwrap()
+                .by_name(&data.data)
+                .unwrap()
+                .read_to_end(&mut font_file)
+                .map(|_| font_file);
+            assert!(font_file.is_ok());
+            let font_file = font_file.unwrap();
+
+            // 根据EPUB规范，字体混淆是直接对字体文件进行的，不需要解压步骤，直接进行去混淆处理
+            let deobfuscated = apply_idpf_obfuscation(&font_file, &key);
+
+            assert!(is_font_data(&deobfuscated));
+        }
+
+        /// ID: ocf-font_obfuscation-bis
+        ///
+        /// An obfuscated (TrueType) font should not be displayed after de-obfuscation, because the obfuscation used a different publication id.
+        #[test]
+        fn test_ocf_font_obfuscation_bis() {
+            let epub_file = Path::new("./test_case/ocf-font_obfuscation_bis.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+
+            let wrong_unique_id = "wrong-publication-id";
+            let wrong_key = idpf_obfuscation_key(wrong_unique_id);
+
+            assert!(doc.encryption.is_some());
+            assert_eq!(doc.encryption.as_ref().unwrap().len(), 1);
+
+            let data = &doc.encryption.unwrap()[0];
+            assert_eq!(data.method, "http://www.idpf.org/2008/embedding");
+
+            let mut font_file = Vec::new();
+            let font_file = doc
+                .archive
+                .lock()
+                .unwrap()
+                .by_name(&data.data)
+                .unwrap()
+                .read_to_end(&mut font_file)
+                .map(|_| font_file);
+            assert!(font_file.is_ok());
+            let font_file = font_file.unwrap();
+
+            // 使用错误的密钥进行去混淆
+            let deobfuscated_with_wrong_key = apply_idpf_obfuscation(&font_file, &wrong_key);
+
+            assert!(!is_font_data(&deobfuscated_with_wrong_key));
+        }
+
+        #[test]
+        fn test_verify_font_obfuscation() {
+            let epub_file = Path::new("./test_case/ocf-font_obfuscation.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let result = doc.verify_font_obfuscation();
+            assert!(result.is_ok());
+            assert_eq!(
+                result.unwrap(),
+                vec![("EPUB/fonts/Lobster.ttf".to_string(), true)]
+            );
+        }
+
+        #[test]
+        fn test_verify_font_obfuscation_with_wrong_id() {
+            let epub_file = Path::new("./test_case/ocf-font_obfuscation_bis.epub");
+            let doc = EpubDoc::new(epub_file);
+            assert!(doc.is_ok());
+
+            let doc = doc.unwrap();
+            let result = doc.verify_font_obfuscation();
+            assert!(result.is_ok());
+
+            let result = result.unwrap();
+            assert_eq!(result.len(), 1);
+            assert!(!result[0].1);
+
+            let epub_file = Path::new("./test_case/epub-33.epub");
+            let doc = EpubDoc::new(epub_file).unwrap();
+            assert!(doc.verify_font_obfuscation().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_parse_container() {
+        let epub_file = Path::new("./test_case/ocf-zip-mult.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        // let doc = doc.unwrap();
+        let mut warnings = vec![];
+        let container = r#"
+        <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+            <rootfiles></rootfiles>
+        </container>
+        "#
+        .to_string();
+
+        let result = EpubDoc::<BufReader<File>>::parse_container(container, &mut warnings);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            EpubError::NonCanonicalFile { tag: "rootfile".to_string() }
+        );
+
+        let container = r#"
+        <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+            <rootfiles>
+                <rootfile media-type="application/oebps-package+xml"/>
+            </rootfiles>
+        </container>
+        "#
+        .to_string();
+
+        let result = EpubDoc::<BufReader<File>>::parse_container(container, &mut warnings);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            EpubError::MissingRequiredAttribute {
+                tag: "rootfile".to_string(),
+                attribute: "full-path".to_string(),
+            }
+        );
+
+        let container = r#"
+        <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+            <rootfiles>
+                <rootfile media-type="application/oebps-package+xml" full-path="EPUB/content.opf"/>
+            </rootfiles>
+        </container>
+        "#
+        .to_string();
+
+        let result = EpubDoc::<BufReader<File>>::parse_container(container, &mut warnings);
+        assert!(result.is_ok());
+
+        let renditions = result.unwrap();
+        assert_eq!(renditions.len(), 1);
+        assert_eq!(renditions[0].full_path, PathBuf::from("EPUB/content.opf"));
+        assert_eq!(
+            renditions[0].media_type,
+            Some("application/oebps-package+xml".to_string())
+        );
+        assert_eq!(renditions[0].label, None);
+        assert!(warnings.is_empty());
+
+        let container = r#"
+        <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+            <rootfiles>
+                <rootfile media-type="application/oebps-package+xml" full-path="EPUB/content.opf"/>
+                <rootfile media-type="application/oebps-package+xml"/>
+                <rootfile media-type="application/oebps-package+xml" full-path="FXL/content.opf"/>
+            </rootfiles>
+        </container>
+        "#
+        .to_string();
+
+        let mut warnings = vec![];
+        let result = EpubDoc::<BufReader<File>>::parse_container(container, &mut warnings);
+        assert!(result.is_ok());
+
+        let renditions = result.unwrap();
+        assert_eq!(renditions.len(), 2);
+        assert_eq!(renditions[1].full_path, PathBuf::from("FXL/content.opf"));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Rootfile 2 "));
+    }
+
+    #[test]
+    fn test_rootfile_without_full_path_is_skipped() {
+        let epub_file = Path::new("./test_case/ocf-rootfile-without-full-path.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        assert_eq!(doc.available_renditions().len(), 1);
+        assert_eq!(doc.warnings().len(), 1);
+        assert!(doc.warnings()[0].contains("full-path"));
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let epub_file = Path::new("./test_case/ocf-package_multiple.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let manifest = r#"
+        <manifest>
+            <item href="content_001.xhtml" media-type="application/xhtml+xml"/>
+            <item properties="nav" href="nav.xhtml" media-type="application/xhtml+xml"/>
+        </manifest>
+        "#;
+        let mut doc = doc.unwrap();
+        let element = XmlReader::parse(manifest);
+        assert!(element.is_ok());
+
+        let element = element.unwrap();
+        let result = doc.parse_manifest(&element);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            EpubError::MissingRequiredAttribute {
+                tag: "item".to_string(),
+                attribute: "id".to_string(),
+            },
+        );
+
+        let manifest = r#"
+        <manifest>
+            <item id="content_001" media-type="application/xhtml+xml"/>
+            <item id="nav" properties="nav" media-type="application/xhtml+xml"/>
+        </manifest>
+        "#;
+        let element = XmlReader::parse(manifest);
+        assert!(element.is_ok());
+
+        let element = element.unwrap();
+        let result = doc.parse_manifest(&element);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            EpubError::MissingRequiredAttribute {
+                tag: "item".to_string(),
+                attribute: "href".to_string(),
+            },
+        );
+
+        let manifest = r#"
+        <manifest>
+            <item id="content_001" href="content_001.xhtml"/>
+            <item id="nav" properties="nav" href="nav.xhtml"/>
+        </manifest>
+        "#;
+        let element = XmlReader::parse(manifest);
+        assert!(element.is_ok());
+
+        let element = element.unwrap();
+        let result = doc.parse_manifest(&element);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            EpubError::MissingRequiredAttribute {
+                tag: "item".to_string(),
+                attribute: "media-type".to_string(),
+            },
+        );
+
+        let manifest = r#"
+        <manifest>
+            <item id="content_001" href="content_001.xhtml" media-type="application/xhtml+xml"/>
+            <item id="nav" properties="nav" href="nav.xhtml" media-type="application/xhtml+xml"/>
+        </manifest>
+        "#;
+        let element = XmlReader::parse(manifest);
+        assert!(element.is_ok());
+
+        let element = element.unwrap();
+        let result = doc.parse_manifest(&element);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_parse_manifest_duplicate_id_strict() {
+        let epub_file = Path::new("./test_case/pkg-manifest-duplicate-id.epub");
+        let doc = EpubDoc::new_with_mode(epub_file, ParseMode::Strict);
+        assert!(doc.is_err());
+        assert_eq!(
+            doc.err().unwrap(),
+            EpubError::DuplicateManifestId { id: "content_001".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_parse_manifest_duplicate_id_lenient() {
+        let epub_file = Path::new("./test_case/pkg-manifest-duplicate-id.epub");
+        let doc = EpubDoc::new_with_mode(epub_file, ParseMode::Lenient);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        assert_eq!(doc.manifest.len(), 2);
+        assert_eq!(
+            doc.manifest.get("content_001").unwrap().path,
+            PathBuf::from("EPUB/content_001.xhtml")
+        );
+        assert_eq!(doc.warnings().len(), 1);
+        assert!(doc.warnings()[0].contains("content_001"));
+    }
+
+    #[test]
+    fn test_parse_guide_leaking_href_strict() {
+        let epub_file = Path::new("./test_case/pkg-guide-leaking-href.epub");
+        let doc = EpubDoc::new_with_mode(epub_file, ParseMode::Strict);
+        assert!(doc.is_err());
+        assert_eq!(
+            doc.err().unwrap(),
+            EpubError::RelativeLinkLeakage { path: "../../cover.xhtml".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_parse_guide_leaking_href_lenient() {
+        let epub_file = Path::new("./test_case/pkg-guide-leaking-href.epub");
+        let doc = EpubDoc::new_with_mode(epub_file, ParseMode::Lenient);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        assert_eq!(doc.guide.len(), 1);
+        assert_eq!(doc.guide[0].reference_type, "text");
+        assert_eq!(doc.guide[0].href, PathBuf::from("EPUB/content_001.xhtml"));
+        assert_eq!(doc.warnings().len(), 1);
+        assert!(doc.warnings()[0].contains("cover"));
+    }
+
+    #[test]
+    fn test_get_manifest_item_with_sloppy_entry_names() {
+        let epub_file = Path::new("./test_case/ocf-zip-entry-dot-slash.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+
+        // stored as "./EPUB/content_001.xhtml"
+        let content = doc.get_manifest_item("content_001");
+        assert!(content.is_ok());
+        assert_eq!(content.unwrap().1, "application/xhtml+xml");
+        assert!(doc.spine_current().is_some());
+
+        // stored as "EPUB\notes\notes.xhtml"
+        let notes = doc.get_manifest_item("notes");
+        assert!(notes.is_ok());
+
+        // stored as "EPUB/Style.CSS"
+        let style = doc.get_manifest_item("style");
+        assert!(style.is_ok());
+        assert_eq!(style.unwrap().0, b"body { margin: 0; }\n");
+
+        let mut doc = doc;
+        doc.manifest.get_mut("style").unwrap().path = PathBuf::from("EPUB/missing.css");
+        assert_eq!(
+            doc.get_manifest_item("style").err().unwrap(),
+            EpubError::ResourceNotFound { resource: "EPUB/missing.css".to_string() }
+        );
+    }
+
+    /// Test for function `has_encryption`
+    #[test]
+    fn test_fn_has_encryption() {
+        let epub_file = Path::new("./test_case/ocf-font_obfuscation.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        assert!(doc.has_encryption());
+    }
+
+    /// This test is used to detect whether the "META-INF/encryption.xml" file is parsed correctly
+    #[test]
+    fn test_fn_parse_encryption() {
+        let epub_file = Path::new("./test_case/ocf-font_obfuscation.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        assert!(doc.encryption.is_some());
+
+        let encryption = doc.encryption.unwrap();
+        assert_eq!(encryption.len(), 1);
+        assert_eq!(encryption[0].method, "http://www.idpf.org/2008/embedding");
+        assert_eq!(encryption[0].data, "EPUB/fonts/Lobster.ttf");
+    }
+
+    #[test]
+    fn test_get_metadata_existing_key() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+
+        let titles = doc.get_metadata("title");
+        assert!(titles.is_some());
+
+        let titles = titles.unwrap();
+        assert_eq!(titles.len(), 1);
+        assert_eq!(titles[0].property, "title");
+        assert_eq!(titles[0].value, "EPUB 3.3");
+
+        let languages = doc.get_metadata("language");
+        assert!(languages.is_some());
+
+        let languages = languages.unwrap();
+        assert_eq!(languages.len(), 1);
+        assert_eq!(languages[0].property, "language");
+        assert_eq!(languages[0].value, "en-us");
+
+        let language = doc.get_language();
+        assert_eq!(language, vec!["en-us"]);
+    }
+
+    #[test]
+    fn test_guess_reading_language() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let mut doc = EpubDoc::new(epub_file).unwrap();
+        assert_eq!(doc.guess_reading_language(), Some("en-us".to_string()));
+
+        // a valid declared language is returned without reading the content
+        doc.manifest.get_mut("main").unwrap().path = PathBuf::from("missing.xhtml");
+        assert_eq!(doc.guess_reading_language(), Some("en-us".to_string()));
+
+        // the content is sniffed when the declared language is undetermined
+        let epub_file = Path::new("./test_case/pkg-lang-undetermined.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
+        assert_eq!(doc.get_language(), vec!["und"]);
+        assert_eq!(doc.guess_reading_language(), Some("ja".to_string()));
+
+        // Latin text cannot be guessed
+        let epub_file = Path::new("./test_case/pkg-creator-order.epub");
+        let mut doc = EpubDoc::new(epub_file).unwrap();
+        doc.metadata
+            .iter_mut()
+            .filter(|item| item.property == "language")
+            .for_each(|item| item.value = "english".to_string());
+        assert_eq!(doc.guess_reading_language(), None);
+    }
+
+    #[test]
+    fn test_get_metadata_nonexistent_key() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        let metadata = doc.get_metadata("nonexistent");
+        assert!(metadata.is_none());
+    }
+
+    #[test]
+    fn test_get_metadata_multiple_items_same_type() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+
+        let creators = doc.get_metadata("creator");
+        assert!(creators.is_some());
+
+        let creators = creators.unwrap();
+        assert_eq!(creators.len(), 3);
+
+        assert_eq!(creators[0].id, Some("creator_id_0".to_string()));
+        assert_eq!(creators[0].property, "creator");
+        assert_eq!(creators[0].value, "Matt Garrish, DAISY Consortium");
+
+        assert_eq!(creators[1].id, Some("creator_id_1".to_string()));
+        assert_eq!(creators[1].property, "creator");
+        assert_eq!(creators[1].value, "Ivan Herman, W3C");
+
+        assert_eq!(creators[2].id, Some("creator_id_2".to_string()));
+        assert_eq!(creators[2].property, "creator");
+        assert_eq!(creators[2].value, "Dave Cramer, Invited Expert");
+    }
+
+    #[test]
+    fn test_get_metadata_with_refinement() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
 
-            EpubVersion::Version3_0 => {
-                let property = element.get_attr("property").ok_or_else(|| {
-                    EpubError::MissingRequiredAttribute {
-                        tag: element.tag_name(),
-                        attribute: "property".to_string(),
-                    }
-                })?;
-                let value = element.text().normalize_whitespace();
-                let lang = element.get_attr("lang");
+        let title = doc.get_metadata("title");
+        assert!(title.is_some());
 
-                if let Some(refines) = element.get_attr("refines") {
-                    let id = refines.strip_prefix("#").unwrap_or(&refines).to_string();
-                    let scheme = element.get_attr("scheme");
-                    let refinement = MetadataRefinement {
-                        refines: id.clone(),
-                        property,
-                        value,
-                        lang,
-                        scheme,
-                    };
+        let title = title.unwrap();
+        assert_eq!(title.len(), 1);
+        assert_eq!(title[0].refined.len(), 1);
+        assert_eq!(title[0].refined[0].property, "title-type");
+        assert_eq!(title[0].refined[0].value, "main");
+    }
 
-                    if let Some(refinements) = refinements.get_mut(&id) {
-                        refinements.push(refinement);
-                    } else {
-                        refinements.insert(id, vec![refinement]);
-                    }
-                } else {
-                    let id = element.get_attr("id");
-                    let item = MetadataItem {
-                        id,
-                        property,
-                        value,
-                        lang,
-                        refined: vec![],
-                    };
+    #[test]
+    fn test_stream_metadata() {
+        let file = std::fs::File::open("./test_case/epub-33.epub").unwrap();
+
+        let mut titles = 0;
+        let mut title_types = vec![];
+        let mut properties = vec![];
+        let result = EpubDoc::stream_metadata(file, |item| {
+            if item.property == "title" {
+                titles += 1;
+                title_types.extend(item.refined.iter().map(|r| r.value.clone()));
+            }
+            properties.push(item.property.clone());
+        });
+        assert!(result.is_ok());
+        assert_eq!(titles, 1);
+        assert_eq!(title_types, vec!["main"]);
 
-                    metadata.push(item);
-                };
+        let doc = EpubDoc::new("./test_case/epub-33.epub").unwrap();
+        let expected = doc
+            .metadata
+            .iter()
+            .map(|item| item.property.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(properties, expected);
+
+        let file = std::fs::File::open("./test_case/epub-2.epub").unwrap();
+        let mut identifiers = vec![];
+        let result = EpubDoc::stream_metadata(file, |item| {
+            if item.property == "identifier" {
+                identifiers.push(item.value.clone());
             }
-        }
-        Ok(())
-    }
+        });
+        assert!(result.is_ok());
 
-    #[inline]
-    fn parse_link_element(
-        &self,
-        element: &XmlElement,
-        metadata_link: &mut Vec<MetadataLinkItem>,
-    ) -> Result<(), EpubError> {
-        let href = element
-            .get_attr("href")
-            .ok_or_else(|| EpubError::MissingRequiredAttribute {
-                tag: element.tag_name(),
-                attribute: "href".to_string(),
-            })?;
-        let rel = element
-            .get_attr("rel")
-            .ok_or_else(|| EpubError::MissingRequiredAttribute {
-                tag: element.tag_name(),
-                attribute: "rel".to_string(),
-            })?;
-        let hreflang = element.get_attr("hreflang");
-        let id = element.get_attr("id");
-        let mime = element.get_attr("media-type");
-        let properties = element.get_attr("properties");
+        let doc = EpubDoc::new("./test_case/epub-2.epub").unwrap();
+        assert_eq!(identifiers, doc.get_identifier());
+    }
 
-        metadata_link.push(MetadataLinkItem {
-            href,
-            rel,
-            hreflang,
-            id,
-            mime,
-            properties,
-            refines: None,
+    #[test]
+    fn test_stream_metadata_stops_after_metadata() {
+        // the spine of the package document is not closed properly
+        let epub_file = "./test_case/pkg-malformed-after-metadata.epub";
+        assert!(EpubDoc::new(epub_file).is_err());
+
+        let file = std::fs::File::open(epub_file).unwrap();
+        let mut creators = vec![];
+        let result = EpubDoc::stream_metadata(file, |item| {
+            if item.property == "creator" {
+                creators.push(item.value.clone());
+            }
         });
-        Ok(())
+        assert!(result.is_ok());
+        assert_eq!(creators.len(), 5);
+        assert_eq!(creators[0], "Dave Cramer");
     }
 
-    /// Recursively parse NCX navigation points from navMap or nested navPoint elements
-    ///
-    /// This function parses the hierarchical navigation structure defined in NCX files
-    /// for EPUB 2.x documents. It handles nested navPoint elements to build a complete
-    /// tree representation of the publication's table of contents.
-    fn parse_nav_points(&self, parent_element: &XmlElement) -> Result<Vec<NavPoint>, EpubError> {
-        let mut nav_points = Vec::new();
-        for nav_point in parent_element.find_children_by_name("navPoint") {
-            let label = match nav_point.find_children_by_name("navLabel").next() {
-                Some(element) => element.text(),
-                None => String::new(),
-            };
+    #[test]
+    fn test_parse_link_element_refines() {
+        let metadata = XmlReader::parse(
+            r##"<metadata>
+                <link refines="#pub-id" rel="record" href="https://example.org/onix.xml" media-type="application/xml" properties="onix"/>
+                <link rel="record" href="onix.xml"/>
+            </metadata>"##,
+        )
+        .unwrap();
 
-            let content = nav_point
-                .find_children_by_name("content")
-                .next()
-                .map(|element| PathBuf::from(element.text()));
+        let mut metadata_link = vec![];
+        for element in metadata.find_children_by_name("link") {
+            EpubDoc::<BufReader<File>>::parse_link_element(element, &mut metadata_link).unwrap();
+        }
+        assert_eq!(metadata_link.len(), 2);
+        assert_eq!(metadata_link[0].refines, Some("pub-id".to_string()));
+        assert_eq!(metadata_link[0].rel, "record");
+        assert_eq!(metadata_link[1].refines, None);
+    }
 
-            let play_order = nav_point
-                .get_attr("playOrder")
-                .and_then(|order| order.parse::<usize>().ok());
+    #[test]
+    fn test_get_metadata_by_uri() {
+        let epub_file = Path::new("./test_case/pkg-custom-prefix.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
 
-            let children = self.parse_nav_points(nav_point)?;
+        assert_eq!(doc.prefixes.len(), 1);
+        assert_eq!(doc.prefixes["terms"], "http://purl.org/dc/terms/");
 
-            nav_points.push(NavPoint { label, content, play_order, children });
-        }
+        // the literal lookup does not know that `terms` is an alias of `dcterms`
+        assert!(doc.get_metadata("dcterms:modified").is_none());
 
-        nav_points.sort();
-        Ok(nav_points)
+        let modified = doc
+            .get_metadata_by_uri("http://purl.org/dc/terms/modified")
+            .unwrap();
+        assert_eq!(modified.len(), 1);
+        assert_eq!(modified[0].property, "terms:modified");
+        assert_eq!(modified[0].value, "2021-01-11T00:00:00Z");
+
+        // reserved prefixes do not need to be declared
+        let referenced = doc.get_metadata_by_uri("http://purl.org/dc/terms/isReferencedBy");
+        assert_eq!(referenced.unwrap()[0].property, "dcterms:isReferencedBy");
+
+        let creators = doc.get_metadata_by_uri("http://purl.org/dc/elements/1.1/creator");
+        assert_eq!(creators.unwrap().len(), 5);
+
+        assert!(
+            doc.get_metadata_by_uri("http://purl.org/dc/terms/unknown")
+                .is_none()
+        );
     }
 
-    /// Recursively parses directory list structures
-    ///
-    /// This function recursively parses HTML navigation list structures,
-    /// converting `<ol>` and `<li>` elements into NavPoint structures.
-    /// Multi-level nested directory structures are supported.
-    fn parse_catalog_list(&self, element: &XmlElement) -> Result<Vec<NavPoint>, EpubError> {
-        let mut catalog = Vec::new();
-        for item in element.children() {
-            if item.tag_name() != "li" {
-                return Err(EpubError::NonCanonicalFile { tag: "li".to_string() });
-            }
+    #[test]
+    fn test_parse_prefixes() {
+        let prefixes = EpubDoc::<BufReader<File>>::parse_prefixes(
+            "foaf: http://xmlns.com/foaf/spec/\n  dbp: http://dbpedia.org/ontology/ broken foaf: http://example.org/",
+        );
 
-            let title_element = item
-                .find_children_by_names(&["span", "a"])
-                .next()
-                .ok_or_else(|| EpubError::NonCanonicalFile { tag: "span/a".to_string() })?;
-            let content_href = title_element.get_attr("href").map(PathBuf::from);
-            let sub_list = if let Some(list) = item.find_children_by_name("ol").next() {
-                self.parse_catalog_list(list)?
-            } else {
-                vec![]
-            };
+        assert_eq!(prefixes.len(), 2);
+        assert_eq!(prefixes["foaf"], "http://xmlns.com/foaf/spec/");
+        assert_eq!(prefixes["dbp"], "http://dbpedia.org/ontology/");
+    }
 
-            catalog.push(NavPoint {
-                label: title_element.text(),
-                content: content_href,
-                children: sub_list,
-                play_order: None,
-            });
-        }
+    #[test]
+    fn test_get_metadata_where() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        Ok(catalog)
+        let doc = doc.unwrap();
+
+        let editors = doc.get_metadata_where("creator", "role", "edt");
+        assert_eq!(editors.len(), 3);
+        assert_eq!(editors[0].value, "Matt Garrish, DAISY Consortium");
+        assert_eq!(editors[2].value, "Dave Cramer, Invited Expert");
+
+        assert!(doc.get_metadata_where("creator", "role", "aut").is_empty());
+        assert!(doc.get_metadata_where("title", "role", "edt").is_empty());
+
+        let title = doc.get_metadata_where("title", "title-type", "main");
+        assert_eq!(title.len(), 1);
     }
 
-    /// Converts relative paths in the manifest to normalized paths
-    /// relative to the EPUB root directory
-    ///
-    /// This function processes the href attribute of resources in the EPUB
-    /// manifest and converts it to a normalized path representation.
-    /// It handles three types of paths:
-    /// - Relative paths starting with `../` (checks if they exceed the EPUB package scope)
-    /// - Absolute paths starting with `/` (relative to the EPUB root directory)
-    /// - Other relative paths (relative to the directory containing the OPF file)
-    ///
-    /// ## Parameters
-    /// - `path`: The href attribute value of the resource in the manifest
-    ///
-    /// ## Return
-    /// - `Ok(PathBuf)`: The parsed normalized path
-    /// - `Err(EpubError)`: Relative link leakage
-    #[inline]
-    fn normalize_manifest_path(&self, path: &str) -> Result<PathBuf, EpubError> {
-        let mut path = if path.starts_with("../") {
-            let mut current_dir = self.epub_path.join(&self.package_path);
-            current_dir.pop();
+    #[test]
+    fn test_get_refinement_values() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            check_realtive_link_leakage(self.epub_path.clone(), current_dir, path)
-                .map(PathBuf::from)
-                .ok_or_else(|| EpubError::RelativeLinkLeakage { path: path.to_string() })?
-        } else if let Some(path) = path.strip_prefix("/") {
-            PathBuf::from(path.to_string())
-        } else {
-            self.base_path.join(path)
-        };
+        let doc = doc.unwrap();
+        assert_eq!(
+            doc.get_refinement_values("title", "title-type"),
+            vec!["main"]
+        );
+        assert_eq!(doc.get_refinement_values("creator", "role"), vec!["edt"; 3]);
+        assert_eq!(
+            doc.get_refinement_values("subject", "term"),
+            vec!["10002951.10003260", "10002944.10011122.10003459"]
+        );
 
-        #[cfg(windows)]
-        {
-            path = PathBuf::from(path.to_string_lossy().replace('\\', "/"));
-        }
+        assert!(doc.get_refinement_values("creator", "file-as").is_empty());
+        assert!(doc.get_refinement_values("publisher", "role").is_empty());
+    }
+
+    #[test]
+    fn test_media_overlay_duration() {
+        let epub_file = Path::new("./test_case/mo-duration.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        assert_eq!(doc.total_duration(), Some(Duration::from_millis(5_025_678)));
+        assert_eq!(
+            doc.overlay_duration("overlay_001"),
+            Some(Duration::from_millis(600_500))
+        );
+        assert_eq!(doc.overlay_duration("content_001"), None);
 
-        Ok(path)
+        let doc = EpubDoc::new("./test_case/pkg-spine-unknown.epub").unwrap();
+        assert_eq!(doc.total_duration(), None);
     }
 
-    /// Verify the fallback chain of all manifest items
-    ///
-    /// This function iterates through all manifest items with the fallback
-    /// attribute and verifies the validity of their fallback chains, including checking:
-    /// - Whether circular references exist
-    /// - Whether the fallback resource exists in the manifest
-    ///
-    /// ## Notes
-    /// If an invalid fallback chain is found, a warning log will be logged
-    /// but the processing flow will not be interrupted.
-    // TODO: consider using BFS to validate fallback chains, to provide efficient
-    fn validate_fallback_chains(&self) {
-        for (id, item) in &self.manifest {
-            if item.fallback.is_none() {
-                continue;
-            }
+    #[test]
+    fn test_uses_capabilities() {
+        let doc = EpubDoc::new("./test_case/ocf-url_origin.epub").unwrap();
+        assert!(doc.uses_scripts());
+        assert!(!doc.uses_mathml());
+        assert!(!doc.uses_remote_resources());
+
+        let doc = EpubDoc::new("./test_case/pub-remote-resources.epub").unwrap();
+        assert!(!doc.uses_scripts());
+        assert!(doc.uses_remote_resources());
+    }
 
-            let mut fallback_chain = Vec::new();
-            if let Err(msg) = self.validate_fallback_chain(id, &mut fallback_chain) {
-                log::warn!("Invalid fallback chain for item {}: {}", id, msg);
-            }
-        }
+    #[test]
+    fn test_archive_entry_names() {
+        let epub_file = Path::new("./test_case/nav-toc-spine.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        let names = doc.archive_entry_names();
+        assert_eq!(names[0], "mimetype");
+        assert!(names.contains(&"META-INF/container.xml".to_string()));
+        assert!(names.contains(&doc.package_path.to_string_lossy().to_string()));
+        assert_eq!(names.len(), 10);
     }
 
-    /// Recursively verify the validity of a single fallback chain
-    ///
-    /// This function recursively traces the fallback chain to check for the following issues:
-    /// - Circular reference
-    /// - The referenced fallback resource does not exist
-    ///
-    /// ## Parameters
-    /// - `manifest_id`: The id of the manifest item currently being verified
-    /// - `fallback_chain`: The visited fallback chain paths used to detect circular references
-    ///
-    /// ## Return
-    /// - `Ok(())`: The fallback chain is valid
-    /// - `Err(String)`: A string containing error information
-    fn validate_fallback_chain(
-        &self,
-        manifest_id: &str,
-        fallback_chain: &mut Vec<String>,
-    ) -> Result<(), String> {
-        if fallback_chain.contains(&manifest_id.to_string()) {
-            fallback_chain.push(manifest_id.to_string());
+    #[test]
+    fn test_zip_contains() {
+        let epub_file = Path::new("./test_case/pkg-manifest-missing-resource.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            return Err(format!(
-                "Circular reference detected in fallback chain for {}",
-                fallback_chain.join("->")
-            ));
-        }
+        let doc = doc.unwrap();
+        assert!(doc.zip_contains("META-INF/container.xml"));
 
-        // Get the current item; its existence can be ensured based on the calling context.
-        let item = self.manifest.get(manifest_id).unwrap();
+        let present = doc.manifest.get("content_001").unwrap();
+        assert!(doc.zip_contains(&present.path.to_string_lossy()));
 
-        if let Some(fallback_id) = &item.fallback {
-            if !self.manifest.contains_key(fallback_id) {
-                return Err(format!(
-                    "Fallback resource {} does not exist in manifest",
-                    fallback_id
-                ));
-            }
+        // declared in the manifest, but missing from the container
+        let missing = doc.manifest.get("content_002").unwrap();
+        assert!(!doc.zip_contains(&missing.path.to_string_lossy()));
+        assert!(doc.get_manifest_item("content_002").is_err());
 
-            fallback_chain.push(manifest_id.to_string());
-            self.validate_fallback_chain(fallback_id, fallback_chain)
-        } else {
-            // The end of the fallback chain
-            Ok(())
-        }
+        // neither declared nor contained
+        assert!(!doc.zip_contains("EPUB/content_003.xhtml"));
     }
 
-    /// Checks if a resource at the specified path is an encrypted file
-    ///
-    /// This function queries whether a specific resource path is marked as an encrypted
-    /// file in the EPUB encryption information. It checks the encrypted data stored in
-    /// `self.encryption`, looking for an entry that matches the given path.
-    ///
-    /// ## Parameters
-    /// - `path`: The path of the resource to check
-    ///
-    /// ## Return
-    /// - `Some(String)`: The encryption method used for the resource
-    /// - `None`: The resource is not encrypted
-    fn is_encryption_file(&self, path: &str) -> Option<String> {
-        self.encryption.as_ref().and_then(|encryptions| {
-            encryptions
-                .iter()
-                .find(|encryption| encryption.data == path)
-                .map(|encryption| encryption.method.clone())
-        })
+    #[test]
+    fn test_missing_resource_errors() {
+        let epub_file = Path::new("./test_case/pkg-manifest-unknown.epub");
+        let mut doc = EpubDoc::new(epub_file).unwrap();
+
+        let err = doc.get_manifest_item("content_002").err().unwrap();
+        assert!(err.is_missing_id());
+        assert!(!err.is_missing_file());
+
+        doc.manifest.get_mut("content_001").unwrap().path = PathBuf::from("EPUB/missing.xhtml");
+        let err = doc.get_manifest_item("content_001").err().unwrap();
+        assert!(err.is_missing_file());
+        assert!(!err.is_missing_id());
+
+        let err = EpubError::from(zip::result::ZipError::FileNotFound);
+        assert!(err.is_missing_file());
+        assert!(!EpubError::EmptyDataError.is_missing_file());
     }
 
-    /// Automatically decrypts encrypted resource data
-    ///
-    /// Automatically decrypts data based on the provided encryption method.
-    /// This function supports various encryption methods defined by the EPUB
-    /// specification, including font obfuscation and the XML encryption standard.
-    ///
-    /// ## Parameters
-    /// - `method`: The encryption method used for the resource
-    /// - `data`: The encrypted resource data
-    ///
-    /// ## Return
-    /// - `Ok(Vec<u8>)`: The decrypted resource data
-    /// - `Err(EpubError)`: Unsupported encryption method
-    ///
-    /// ## Supported Encryption Methods
-    /// - IDPF font obfuscation: `http://www.idpf.org/2008/embedding`
-    /// - Adobe font obfuscation: `http://ns.adobe.com/pdf/enc#RC`
-    #[inline]
-    fn auto_dencrypt(&self, method: &str, data: &mut [u8]) -> Result<Vec<u8>, EpubError> {
-        match method {
-            "http://www.idpf.org/2008/embedding" => {
-                Ok(idpf_font_dencryption(data, &self.unique_identifier))
-            }
-            "http://ns.adobe.com/pdf/enc#RC" => {
-                Ok(adobe_font_dencryption(data, &self.unique_identifier))
+    #[test]
+    fn test_get_manifest_item_by_path() {
+        let doc = EpubDoc::open("./test_case/nav-toc-spine.epub");
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        let path = doc.manifest.get("nav").unwrap().path.clone();
+
+        let (by_str, mime) = doc
+            .get_manifest_item_by_path(path.to_str().unwrap())
+            .unwrap();
+        assert_eq!(mime, "application/xhtml+xml");
+
+        let (by_path, _) = doc.get_manifest_item_by_path(path.as_path()).unwrap();
+        assert_eq!(by_str, by_path);
+
+        let result = doc.get_manifest_item_by_path(Path::new("EPUB/missing.xhtml"));
+        assert_eq!(
+            result.err().unwrap(),
+            EpubError::ResourceNotFound {
+                resource: "EPUB/missing.xhtml".to_string()
             }
-            _ => Err(EpubError::UnsupportedEncryptedMethod { method: method.to_string() }),
-        }
+        );
     }
-}
 
-impl EpubDoc<BufReader<File>> {
-    /// Creates a new EPUB document instance
-    ///
-    /// This function is a convenience constructor for `EpubDoc`,
-    /// used to create an EPUB parser instance directly from a file path.
-    ///
-    /// ## Parameters
-    /// - `path`: The path to the EPUB file
-    ///
-    /// ## Return
-    /// - `Ok(EpubDoc)`: The created EPUB document instance
-    /// - `Err(EpubError)`: An error occurred during initialization
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, EpubError> {
-        let file = File::open(&path).map_err(EpubError::from)?;
-        let path = fs::canonicalize(path)?;
+    #[test]
+    fn test_get_manifest_item_with_fallback() {
+        let epub_file = Path::new("./test_case/pub-foreign_bad-fallback.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        assert!(doc.get_manifest_item("content_001").is_ok());
+        assert!(doc.get_manifest_item("bar").is_ok());
+
+        // 当回退链上存在可回退资源时能获取资源
+        if let Ok((_, mime)) = doc.get_manifest_item_with_fallback("content_001", &["image/psd"]) {
+            assert_eq!(mime, "image/psd");
+        } else {
+            panic!("get_manifest_item_with_fallback failed");
+        }
 
-        Self::from_reader(BufReader::new(file), path)
+        // 当回退链上不存在可回退资源时无法获取资源
+        assert_eq!(
+            doc.get_manifest_item_with_fallback("content_001", &["application/xhtml+xml"])
+                .unwrap_err()
+                .to_string(),
+            "No supported file format: The fallback resource does not contain the file format you support."
+        );
     }
 
-    /// Validates whether a file is a valid EPUB document
-    ///
-    /// This function attempts to open and parse the given file as an EPUB document.
-    /// It performs basic validation to determine if the file conforms to the EPUB specification.
-    ///
-    /// ## Parameters
-    /// - `path`: The path to the file to validate
-    ///
-    /// ## Returns
-    /// - `Ok(true)`: The file is a valid EPUB document
-    /// - `Ok(false)`: The file exists but is not a valid EPUB (e.g., missing required files,
-    ///   invalid XML structure, unrecognized version)
-    /// - `Err(EpubError)`: A critical error occurred (e.g., IO error, ZIP archive error,
-    ///   encoding error, mutex poison)
-    pub fn is_valid_epub<P: AsRef<Path>>(path: P) -> Result<bool, EpubError> {
-        let result = EpubDoc::new(path);
+    #[test]
+    fn test_get_manifest_item_with_fallback_cycle() {
+        let epub_file = Path::new("./test_case/pub-foreign_bad-fallback.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        match result {
-            Ok(_) => Ok(true),
-            Err(err) if Self::is_outside_error(&err) => Err(err),
-            Err(_) => Ok(false),
+        let mut doc = doc.unwrap();
+        for (id, fallback) in [("a", "b"), ("b", "a")] {
+            doc.manifest.insert(
+                id.to_string(),
+                ManifestItem {
+                    id: id.to_string(),
+                    path: PathBuf::from(format!("EPUB/{}.bin", id)),
+                    mime: "application/octet-stream".to_string(),
+                    properties: None,
+                    fallback: Some(fallback.to_string()),
+                },
+            );
         }
-    }
 
-    /// Determines if an error is a "critical" external error that should be propagated
-    ///
-    /// ## Error Classification
-    /// Outside errors (returned as `Err`):
-    /// - ArchiveError: ZIP archive corruption or read errors
-    /// - IOError: File system or read errors
-    /// - MutexError: Thread synchronization errors
-    /// - Utf8DecodeError: UTF-8 encoding errors
-    /// - Utf16DecodeError: UTF-16 encoding errors
-    /// - QuickXmlError: XML parser errors
-    ///
-    /// Irrelevant errors (returned as `Ok(false)`):
-    /// - these errors could not have occurred in this situation.
-    /// - EpubBuilderError
-    /// - WalkDirError
-    ///
-    /// Content errors (returned as `Ok(false)`):
-    /// - All other EpubError variants
-    fn is_outside_error(err: &EpubError) -> bool {
-        matches!(
-            err,
-            EpubError::ArchiveError { .. }
-                | EpubError::IOError { .. }
-                | EpubError::MutexError
-                | EpubError::Utf8DecodeError { .. }
-                | EpubError::Utf16DecodeError { .. }
-                | EpubError::QuickXmlError { .. }
-        )
+        let result = doc.get_manifest_item_with_fallback_verbose("a", &["image/png"]);
+        assert!(result.is_err());
+
+        let err = result.unwrap_err();
+        assert_eq!(err.source, EpubError::NoSupportedFileFormat);
+        assert_eq!(
+            err.tried
+                .iter()
+                .map(|(id, _)| id.as_str())
+                .collect::<Vec<&str>>(),
+            ["a", "b"]
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{
-        fs::File,
-        io::BufReader,
-        path::{Path, PathBuf},
-    };
+    #[test]
+    fn test_iter_resources() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-    use crate::{epub::EpubDoc, error::EpubError, utils::XmlReader};
+        let doc = doc.unwrap();
+        let resources = doc.iter_resources().collect::<Result<Vec<_>, _>>();
+        assert!(resources.is_ok());
 
-    /// Section 3.3 package documents
-    mod package_documents_tests {
-        use std::{path::Path, sync::atomic::Ordering};
+        let resources = resources.unwrap();
+        assert_eq!(resources.len(), doc.manifest.len());
 
-        use crate::epub::{EpubDoc, EpubVersion};
+        for (id, path, data, mime) in resources {
+            let item = doc.manifest.get(&id).unwrap();
+            assert_eq!(path, item.path);
+            assert_eq!(mime, item.mime);
+            assert_eq!(data, doc.get_manifest_item(&id).unwrap().0);
+        }
 
-        /// ID: pkg-collections-unknown
-        ///
-        /// The package document contains a collection with an unknown role. The reading system must open the EPUB successfully.
-        #[test]
-        fn test_pkg_collections_unknown() {
-            let epub_file = Path::new("./test_case/pkg-collections-unknown.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        // a missing resource does not end the iteration
+        let epub_file = Path::new("./test_case/pkg-manifest-missing-resource.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
+        let results = doc.iter_resources().collect::<Vec<_>>();
+        assert_eq!(results.len(), doc.manifest.len());
+        assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+    }
+
+    #[test]
+    fn test_get_cover() {
+        let epub_file = Path::new("./test_case/pkg-cover-image.epub");
+        let doc = EpubDoc::new(epub_file);
+        if let Err(err) = &doc {
+            println!("{}", err);
         }
+        assert!(doc.is_ok());
 
-        /// ID: pkg-creator-order
-        ///
-        /// Several creators are listed in the package document. The reading system must not display them out of order (but it may display only the first).
-        #[test]
-        fn test_pkg_creator_order() {
-            let epub_file = Path::new("./test_case/pkg-creator-order.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let doc = doc.unwrap();
+        let result = doc.get_cover();
+        assert!(result.is_some());
 
-            let doc = doc.unwrap();
-            let creators = doc.get_metadata_value("creator");
-            assert!(creators.is_some());
+        let (data, mime) = result.unwrap();
+        assert_eq!(data.len(), 5785);
+        assert_eq!(mime, "image/jpeg");
+    }
 
-            let creators = creators.unwrap();
-            assert_eq!(creators.len(), 5);
-            assert_eq!(
-                creators,
-                vec![
-                    "Dave Cramer",
-                    "Wendy Reid",
-                    "Dan Lazin",
-                    "Ivan Herman",
-                    "Brady Duga",
-                ]
-            );
-        }
+    #[test]
+    fn test_manifest_in_order() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        /// ID: pkg-manifest-unknown
-        ///
-        /// The package document contains a manifest item with unknown properties. The reading system must open the EPUB successfully.
-        #[test]
-        fn test_pkg_manifest_order() {
-            let epub_file = Path::new("./test_case/pkg-manifest-unknown.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let doc = doc.unwrap();
+        let ids = doc
+            .manifest_in_order()
+            .into_iter()
+            .map(|item| item.id.clone())
+            .collect::<Vec<_>>();
+
+        let mut expected = vec!["main", "nav", "cover", "title_page"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        expected.extend((5..=20).map(|index| format!("res_id{}", index)));
+        assert_eq!(ids, expected);
+    }
 
-            let doc = doc.unwrap();
-            assert_eq!(doc.manifest.len(), 2);
-            assert!(doc.get_manifest_item("nav").is_ok());
-            assert!(doc.get_manifest_item("content_001").is_ok());
-            assert!(doc.get_manifest_item("content_002").is_err());
-        }
+    #[test]
+    fn test_from_reader_with_synthetic_path() {
+        let bytes = std::fs::read("./test_case/nav-toc-spine.epub").unwrap();
+        let doc = EpubDoc::from_reader(
+            std::io::Cursor::new(bytes),
+            PathBuf::from("/in-memory/does-not-exist.epub"),
+        );
+        assert!(doc.is_ok());
 
-        /// ID: pkg-meta-unknown
-        ///
-        /// The package document contains a meta tag with an unknown property. The reading system must open the EPUB successfully.
-        #[test]
-        fn test_pkg_meta_unknown() {
-            let epub_file = Path::new("./test_case/pkg-meta-unknown.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let doc = doc.unwrap();
+        assert_eq!(doc.catalog_with_spine()[0].2, Some(0));
+        assert!(doc.spine_current().is_some());
+    }
 
-            let doc = doc.unwrap();
-            let value = doc.get_metadata_value("dcterms:isReferencedBy");
-            assert!(value.is_some());
-            let value = value.unwrap();
-            assert_eq!(value.len(), 1);
+    #[test]
+    fn test_from_reader_with_relative_path_rejects_leaking_href() {
+        let bytes = std::fs::read("./test_case/pkg-manifest-leaking-href.epub").unwrap();
+        for epub_path in ["book.epub", "dir/book.epub"] {
+            let doc = EpubDoc::from_reader(std::io::Cursor::new(bytes.clone()), epub_path);
             assert_eq!(
-                value,
-                vec!["https://www.w3.org/TR/epub-rs/#confreq-rs-pkg-meta-unknown"]
+                doc.err().unwrap(),
+                EpubError::RelativeLinkLeakage { path: "../../leak.png".to_string() }
             );
+        }
 
-            let value = doc.get_metadata_value("dcterms:modified");
-            assert!(value.is_some());
-            let value = value.unwrap();
-            assert_eq!(value.len(), 1);
-            assert_eq!(value, vec!["2021-01-11T00:00:00Z"]);
+        let doc = EpubDoc::new("./test_case/pkg-manifest-leaking-href.epub");
+        assert!(doc.is_err());
+    }
 
-            let value = doc.get_metadata_value("dcterms:title");
-            assert!(value.is_none());
-        }
+    #[test]
+    fn test_nav_sections() {
+        let epub_file = Path::new("./test_case/nav-sections.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        /// ID: pkg-meta-whitespace
-        ///
-        /// The package document's title and creator contain leading and trailing spaces along with excess internal whitespace. The reading system must render only a single space in all cases.
-        #[test]
-        fn test_pkg_meta_white_space() {
-            let epub_file = Path::new("./test_case/pkg-meta-whitespace.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let doc = doc.unwrap();
+        let sections = doc.nav_sections();
+        assert!(sections.is_ok());
 
-            let doc = doc.unwrap();
-            let value = doc.get_metadata_value("creator");
-            assert!(value.is_some());
-            let value = value.unwrap();
-            assert_eq!(value.len(), 1);
-            assert_eq!(value, vec!["Dave Cramer"]);
+        let sections = sections.unwrap();
+        assert_eq!(sections.len(), 2);
 
-            let value = doc.get_metadata_value("description");
-            assert!(value.is_some());
-            let value = value.unwrap();
-            assert_eq!(value.len(), 1);
-            assert_eq!(
-                value,
-                vec![
-                    "The package document's title and creator contain leading and trailing spaces along with excess internal whitespace. The reading system must render only a single space in all cases."
-                ]
-            );
-        }
+        let toc = &sections["toc"];
+        assert_eq!(toc, &doc.catalog);
+        assert_eq!(toc[0].label, "Chapter 1");
+        assert_eq!(toc[0].children[0].label, "Section 1.1");
 
-        /// ID: pkg-spine-duplicate-item-hyperlink
-        ///
-        /// The spine contains several references to the same content document. The reading system must move to the position of the first duplicate in the reading order when following a hyperlink.
-        #[test]
-        fn test_pkg_spine_duplicate_item_hyperlink() {
-            let epub_file = Path::new("./test_case/pkg-spine-duplicate-item-hyperlink.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let loi = sections["loi"]
+            .iter()
+            .map(|nav_point| (nav_point.label.as_str(), nav_point.content.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            loi,
+            vec![
+                (
+                    "Figure 1",
+                    Some(PathBuf::from("content_001.xhtml#figure-1"))
+                ),
+                (
+                    "Figure 2",
+                    Some(PathBuf::from("content_001.xhtml#figure-2"))
+                ),
+            ]
+        );
 
-            let mut doc = doc.unwrap();
-            assert_eq!(doc.spine.len(), 4);
-            assert_eq!(
-                doc.navigate_by_spine_index(0).unwrap(),
-                doc.get_manifest_item("content_001").unwrap()
-            );
-            assert_eq!(
-                doc.navigate_by_spine_index(1).unwrap(),
-                doc.get_manifest_item("content_002").unwrap()
-            );
-            assert_eq!(
-                doc.navigate_by_spine_index(2).unwrap(),
-                doc.get_manifest_item("content_002").unwrap()
-            );
-            assert_eq!(
-                doc.navigate_by_spine_index(3).unwrap(),
-                doc.get_manifest_item("content_002").unwrap()
-            );
-        }
+        let epub_file = Path::new("./test_case/epub2-cover.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
+        let sections = doc.nav_sections().unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections["toc"], doc.catalog);
+    }
 
-        /// ID: pkg-spine-duplicate-item-rendering
-        ///
-        /// The spine contains several references to the same content document. The reading system must not skip the duplicates when rendering the reading order.
-        #[test]
-        fn test_pkg_spine_duplicate_item_rendering() {
-            let epub_file = Path::new("./test_case/pkg-spine-duplicate-item-rendering.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+    #[test]
+    fn test_catalog_with_spine() {
+        let epub_file = Path::new("./test_case/nav-toc-spine.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let mut doc = doc.unwrap();
-            assert_eq!(doc.spine.len(), 4);
+        let doc = doc.unwrap();
+        let entries = doc
+            .catalog_with_spine()
+            .into_iter()
+            .map(|(depth, nav_point, index)| (depth, nav_point.label.as_str(), index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                (0, "Chapter 1", Some(0)),
+                (1, "Section 1.1", Some(0)),
+                (1, "Fragment only", None),
+                (0, "Chapter 2", Some(1)),
+                (0, "Chapter 3", Some(2)),
+                (0, "Chapter 4", Some(3)),
+                (0, "External link", None),
+            ]
+        );
 
-            let result = doc.spine_prev();
-            assert!(result.is_none());
+        let top_level = entries
+            .iter()
+            .filter(|(depth, _, _)| *depth == 0)
+            .filter_map(|(_, _, index)| *index)
+            .collect::<Vec<usize>>();
+        assert!(top_level.windows(2).all(|pair| pair[0] < pair[1]));
+    }
 
-            let result = doc.spine_next();
-            assert!(result.is_some());
+    #[test]
+    fn test_calibre_series() {
+        let epub_file = Path::new("./test_case/epub2-calibre-series.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            doc.spine_next();
-            doc.spine_next();
-            let result = doc.spine_next();
-            assert!(result.is_none());
-        }
+        let doc = doc.unwrap();
+        assert_eq!(
+            doc.calibre_series(),
+            Some(("Cover Declarations".to_string(), Some(2.5)))
+        );
+        assert_eq!(doc.meta_name("cover"), Some("cover-image".to_string()));
+        assert_eq!(doc.meta_name("calibre:rating"), None);
 
-        /// ID: pkg-spine-nonlinear-activation
-        ///
-        /// An itemref in the spine is marked as non-linear. Although it (possibly) cannot be accessed through the table of contents, it can be reached from a link in the XHTML content.
-        #[test]
-        fn test_pkg_spine_nonlinear_activation() {
-            let epub_file = Path::new("./test_case/pkg-spine-nonlinear-activation.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        // EPUB 2 meta elements are accepted in EPUB 3 publications
+        let epub_file = Path::new("./test_case/epub3-calibre-series.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let mut doc = doc.unwrap();
-            assert!(doc.spine_prev().is_none());
-            assert!(doc.spine_next().is_none());
+        let doc = doc.unwrap();
+        assert_eq!(
+            doc.calibre_series(),
+            Some(("Spine Progression".to_string(), None))
+        );
+        assert_eq!(
+            doc.meta_name("calibre:series_index"),
+            Some("not a number".to_string())
+        );
 
-            assert!(doc.navigate_by_spine_index(1).is_some());
-            assert!(doc.spine_prev().is_none());
-            assert!(doc.spine_next().is_none());
-        }
+        let epub_file = Path::new("./test_case/epub2-cover.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
+        assert_eq!(doc.calibre_series(), None);
+    }
 
-        /// ID: pkg-spine-order
-        ///
-        /// Basic test of whether a reading system can display spine items in the correct order. The test fails if the reading system presents content in the order in which the file names sort, or if it presents files in manifest order rather than spine order.
-        #[test]
-        fn test_pkg_spine_order() {
-            let epub_file = Path::new("./test_case/pkg-spine-order.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+    #[test]
+    fn test_epub2_cover_id() {
+        let epub_file = Path::new("./test_case/epub2-cover.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let doc = doc.unwrap();
-            assert_eq!(doc.spine.len(), 4);
-            assert_eq!(
-                doc.spine
-                    .iter()
-                    .map(|item| item.idref.clone())
-                    .collect::<Vec<String>>(),
-                vec![
-                    "d-content_001",
-                    "c-content_002",
-                    "b-content_003",
-                    "a-content_004",
-                ]
-            );
-        }
+        let mut doc = doc.unwrap();
+        assert_eq!(doc.guide.len(), 2);
+        assert_eq!(doc.guide[1].href, PathBuf::from("OPS/content_001.xhtml"));
 
-        /// ID: pkg-spine-order-svg
-        ///
-        /// Basic test of whether a reading system can display SVG spine items in the correct order.
-        #[test]
-        fn test_spine_order_svg() {
-            let epub_file = Path::new("./test_case/pkg-spine-order-svg.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        // the meta declaration wins over the guide reference
+        assert_eq!(doc.epub2_cover_id(), Some("cover-image".to_string()));
 
-            let mut doc = doc.unwrap();
-            assert_eq!(doc.spine.len(), 4);
+        // a meta declaration pointing to an unknown id falls back to the guide
+        doc.metadata
+            .iter_mut()
+            .filter(|item| item.property == "cover")
+            .for_each(|item| item.value = "missing-id".to_string());
+        assert_eq!(doc.epub2_cover_id(), Some("cover-page".to_string()));
 
-            loop {
-                if let Some(spine) = doc.spine_next() {
-                    let idref = doc.spine[doc.current_spine_index.load(Ordering::Relaxed)]
-                        .idref
-                        .clone();
-                    let resource = doc.get_manifest_item(&idref);
-                    assert!(resource.is_ok());
+        doc.guide.clear();
+        assert_eq!(doc.epub2_cover_id(), None);
+    }
 
-                    let resource = resource.unwrap();
-                    assert_eq!(spine, resource);
-                } else {
-                    break;
-                }
-            }
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_cover_thumbnail() {
+        let epub_file = Path::new("./test_case/epub2-cover.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            assert_eq!(doc.current_spine_index.load(Ordering::Relaxed), 3);
-        }
+        // the cover is 300 pixels wide and 134 pixels high
+        let doc = doc.unwrap();
+        let thumbnail = doc.cover_thumbnail(100);
+        assert!(thumbnail.is_ok());
 
-        /// ID: pkg-spine-unknown
-        ///
-        /// The package document contains a spine item with unknown properties. The reading system must open the EPUB successfully.
-        #[test]
-        fn test_pkg_spine_unknown() {
-            let epub_file = Path::new("./test_case/pkg-spine-unknown.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let (data, mime) = thumbnail.unwrap();
+        assert_eq!(mime, "image/jpeg");
 
-            let doc = doc.unwrap();
-            assert_eq!(doc.spine.len(), 1);
-            assert_eq!(doc.spine[0].idref, "content_001");
-            assert_eq!(doc.spine[0].id, None);
-            assert_eq!(doc.spine[0].linear, true);
-            assert_eq!(doc.spine[0].properties, Some("untrustworthy".to_string()));
-        }
+        let thumbnail = image::load_from_memory(&data).unwrap();
+        assert_eq!(thumbnail.width(), 100);
+        assert!(thumbnail.height() <= 100);
 
-        /// ID: pkg-title-order
-        ///
-        /// Several titles are listed in the package document. The reading system must use the first title (and whether to use other titles is not defined).
-        #[test]
-        fn test_pkg_title_order() {
-            let epub_file = Path::new("./test_case/pkg-title-order.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        // the cover page found by `get_cover` is not used
+        let (data, _) = doc.cover_thumbnail(500).unwrap();
+        assert!(data == doc.get_manifest_item("cover-image").unwrap().0);
 
-            let doc = doc.unwrap();
-            let title_list = doc.get_title();
-            assert_eq!(title_list.len(), 6);
-            assert_eq!(
-                title_list,
-                vec![
-                    "pkg-title-order",
-                    "This title must not display first",
-                    "Also, this title must not display first",
-                    "This title also must not display first",
-                    "This title must also not display first",
-                    "This title must not display first, also",
-                ]
-            );
-        }
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
+        let (_, mime) = doc.cover_thumbnail(100).unwrap();
+        assert_eq!(mime, "image/svg+xml");
+    }
 
-        /// ID: pkg-unique-id
-        ///
-        /// The package document's dc:identifier is identical across two publications. The reading system should display both publications independently.
-        #[test]
-        fn test_pkg_unique_id() {
-            let epub_file = Path::new("./test_case/pkg-unique-id.epub");
-            let doc_1 = EpubDoc::new(epub_file);
-            assert!(doc_1.is_ok());
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_first_page_thumbnail() {
+        let epub_file = Path::new("./test_case/pkg-first-page-image.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
+        assert!(doc.get_cover().is_none());
+
+        // the image of the first page is 300 pixels wide and 134 pixels high
+        let (data, mime) = doc.first_page_thumbnail(100).unwrap().unwrap();
+        assert_eq!(mime, "image/jpeg");
+
+        let thumbnail = image::load_from_memory(&data).unwrap();
+        assert_eq!(thumbnail.width(), 100);
+        assert!(thumbnail.height() <= 100);
+
+        // the cover is preferred when there is one
+        let epub_file = Path::new("./test_case/epub2-cover.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
+        assert!(doc.first_page_thumbnail(100).unwrap() == Some(doc.cover_thumbnail(100).unwrap()));
+
+        let epub_file = Path::new("./test_case/pkg-creator-order.epub");
+        let doc = EpubDoc::new(epub_file).unwrap();
+        assert!(doc.first_page_thumbnail(100).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_remote_resources() {
+        let epub_file = Path::new("./test_case/pub-remote-resources.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let mut doc = doc.unwrap();
+        let remote_resources = doc.remote_resources();
+        assert_eq!(remote_resources.len(), 1);
+        assert_eq!(remote_resources[0].id, "content_001");
+        assert_eq!(
+            doc.manifest.get("remote-audio").unwrap().path,
+            PathBuf::from("https://www.example.org/audio.mp3")
+        );
+
+        // fetching is refused by default
+        assert_eq!(
+            doc.get_manifest_item("remote-audio").unwrap_err(),
+            EpubError::RemoteResource {
+                url: "https://www.example.org/audio.mp3".to_string()
+            }
+        );
 
-            let epub_file = Path::new("./test_case/pkg-unique-id_duplicate.epub");
-            let doc_2 = EpubDoc::new(epub_file);
-            assert!(doc_2.is_ok());
+        doc.set_fetch_policy(FetchPolicy::Fetch(Arc::new(|url| {
+            Ok(url.as_bytes().to_vec())
+        })));
+        let result = doc.get_manifest_item("remote-audio");
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            (
+                b"https://www.example.org/audio.mp3".to_vec(),
+                "audio/mpeg".to_string()
+            )
+        );
+    }
 
-            let doc_1 = doc_1.unwrap();
-            let doc_2 = doc_2.unwrap();
+    #[test]
+    fn test_reading_progress() {
+        let epub_file = Path::new("./test_case/pkg-spine-progression-nonlinear.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            assert_eq!(doc_1.get_identifier(), doc_2.get_identifier());
-            assert_eq!(doc_1.unique_identifier, "pkg-unique-id");
-            assert_eq!(doc_2.unique_identifier, "pkg-unique-id");
-        }
+        let mut doc = doc.unwrap();
+        assert!(!doc.spine[1].linear);
 
-        /// ID: pkg-version-backward
-        ///
-        /// “Reading Systems MUST attempt to process an EPUB Publication whose Package Document version attribute is less than "3.0"”. This is an EPUB with package version attribute set to "0", to see if a reading system will open it.
-        #[test]
-        fn test_pkg_version_backward() {
-            let epub_file = Path::new("./test_case/pkg-version-backward.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        // the current item is not counted as read
+        assert_eq!(doc.reading_progress(), 0.0);
+        assert_eq!(doc.reading_progress_weighted().unwrap(), 0.0);
 
-            let doc = doc.unwrap();
-            assert_eq!(doc.version, EpubVersion::Version3_0);
-        }
+        doc.navigate_by_spine_index(1);
+        assert!((doc.reading_progress() - 1.0 / 3.0).abs() < f64::EPSILON);
 
-        /// ID: pkg-linked-records
-        ///
-        /// Reading System must process and display the title and creator metadata from the package document. An ONIX 3.0 format linked metadata record exists, but contains neither title nor creator metadata.
-        #[test]
-        fn test_pkg_linked_records() {
-            let epub_file = Path::new("./test_case/pkg-linked-records.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        // the non-linear item does not advance the progress
+        doc.navigate_by_spine_index(2);
+        assert!((doc.reading_progress() - 1.0 / 3.0).abs() < f64::EPSILON);
 
-            let doc = doc.unwrap();
-            assert_eq!(doc.metadata_link.len(), 3);
+        doc.navigate_by_spine_index(3);
+        assert!((doc.reading_progress() - 2.0 / 3.0).abs() < f64::EPSILON);
+        let weighted = doc.reading_progress_weighted().unwrap();
+        assert!((weighted - 800.0 / 1293.0).abs() < f64::EPSILON);
+    }
 
-            let item = doc.metadata_link.iter().find(|&item| {
-                if let Some(properties) = &item.properties {
-                    properties.eq("onix")
-                } else {
-                    false
-                }
-            });
-            assert!(item.is_some());
+    #[test]
+    fn test_normalize_backslash_paths() {
+        let epub_file = Path::new("./test_case/pkg-spine-progression-nonlinear.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        for (href, expected) in [
+            ("content_001.xhtml", "EPUB/content_001.xhtml"),
+            ("text\\content_001.xhtml", "EPUB/text/content_001.xhtml"),
+            (".\\text/./content_001.xhtml", "EPUB/text/content_001.xhtml"),
+            ("..\\fonts\\font.otf", "fonts/font.otf"),
+            ("\\EPUB\\content_001.xhtml", "EPUB/content_001.xhtml"),
+        ] {
+            let path = doc.normalize_manifest_path(href);
+            assert!(path.is_ok());
+            assert_eq!(path.unwrap(), PathBuf::from(expected));
         }
 
-        /// ID: pkg-manifest-unlisted-resource
-        ///
-        /// The XHTML content references an image that does not appear in the manifest. The image should not be shown.
-        #[test]
-        fn test_pkg_manifest_unlisted_resource() {
-            let epub_file = Path::new("./test_case/pkg-manifest-unlisted-resource.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        assert!(doc.normalize_manifest_path("..\\..\\font.otf").is_err());
 
-            let doc = doc.unwrap();
-            assert!(
-                doc.get_manifest_item_by_path("EPUB/content_001.xhtml")
-                    .is_ok()
-            );
+        // catalog links with backslashes match the manifest paths
+        let path = doc.resolve_catalog_href(Path::new(".\\content_003.xhtml#page"));
+        assert_eq!(path, Some(PathBuf::from("EPUB/content_003.xhtml")));
+        assert_eq!(doc.spine_index_for_path(&path.unwrap()), Some(2));
+    }
 
-            assert!(doc.get_manifest_item_by_path("EPUB/red.png").is_err());
-            let err = doc.get_manifest_item_by_path("EPUB/red.png").unwrap_err();
+    #[test]
+    fn test_resolve_relative() {
+        let epub_file = Path::new("./test_case/ocf-url_manifest.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        for (relative, expected) in [
+            ("../img/x.png", "EPUB/img/x.png"),
+            ("./y.css", "EPUB/foo/y.css"),
+            ("y.css?v=2", "EPUB/foo/y.css"),
+            ("content_002.xhtml#note", "EPUB/foo/content_002.xhtml"),
+            ("../../META-INF/container.xml", "META-INF/container.xml"),
+            ("/root.css", "root.css"),
+        ] {
             assert_eq!(
-                err.to_string(),
-                "Resource not found: Unable to find resource from \"EPUB/red.png\"."
+                doc.resolve_relative(0, relative),
+                Some(PathBuf::from(expected))
             );
         }
+
+        assert!(doc.resolve_relative(0, "../../../x.png").is_none());
+        assert!(
+            doc.resolve_relative(0, "https://example.com/x.png")
+                .is_none()
+        );
+        assert!(doc.resolve_relative(0, "#note").is_none());
+        assert!(doc.resolve_relative(1, "./y.css").is_none());
     }
 
-    /// Section 3.4 manifest fallbacks
-    ///
-    /// The tests under this module seem to favor the reading system rather than the EPUB format itself
-    mod manifest_fallbacks_tests {
-        use std::path::Path;
+    #[test]
+    fn test_spine_navigation_errors() {
+        let epub_file = Path::new("./test_case/ocf-unsupported-encryption.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        use crate::epub::EpubDoc;
+        // the third spine item is encrypted with an unsupported method
+        let mut doc = doc.unwrap();
+        assert!(doc.navigate_by_spine_index(2).is_none());
 
-        /// ID: pub-foreign_bad-fallback
-        ///
-        /// This is a test of manifest fallbacks where both the spine item and the fallback are likely to be unsupported. The spine item is a DMG, with a fallback to a PSD file. Reading systems may raise an error on the ingenstion workflow.
-        #[test]
-        fn test_pub_foreign_bad_fallback() {
-            let epub_file = Path::new("./test_case/pub-foreign_bad-fallback.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let result = doc.try_navigate_by_spine_index(2);
+        assert!(result.is_err());
+        assert_eq!(
+            result.err().unwrap(),
+            EpubError::UnsupportedEncryptedMethod {
+                method: "http://www.w3.org/2001/04/xmlenc#aes256-cbc".to_string()
+            }
+        );
+        assert!(doc.try_spine_current().is_err());
 
-            let doc = doc.unwrap();
-            assert!(doc.get_manifest_item("content_001").is_ok());
-            assert!(doc.get_manifest_item("bar").is_ok());
+        let result = doc.try_navigate_by_spine_index(4);
+        assert!(matches!(result, Ok(None)));
 
-            assert_eq!(
-                doc.get_manifest_item_with_fallback("content_001", &vec!["application/xhtml+xml"])
-                    .unwrap_err()
-                    .to_string(),
-                "No supported file format: The fallback resource does not contain the file format you support."
-            );
-        }
+        assert!(matches!(doc.try_navigate_by_spine_index(0), Ok(Some(_))));
+        assert!(doc.try_spine_next().is_err());
+        assert_eq!(doc.current_spine_index.load(Ordering::SeqCst), 2);
 
-        /// ID: pub-foreign_image
-        ///
-        /// An HTML content file contains a PSD image, with a manifest fallback to a PNG image. This tests fallbacks for resources that are not in the spine.
-        #[test]
-        fn test_pub_foreign_image() {
-            let epub_file = Path::new("./test_case/pub-foreign_image.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        assert!(matches!(doc.try_spine_next(), Ok(Some(_))));
+        assert!(doc.try_spine_prev().is_err());
+        assert!(matches!(doc.try_spine_prev(), Ok(Some(_))));
+        assert!(matches!(doc.try_spine_prev(), Ok(None)));
+    }
 
-            let doc = doc.unwrap();
-            let result = doc.get_manifest_item_with_fallback(
-                "image-tiff",
-                &vec!["image/png", "application/xhtml+xml"],
-            );
-            assert!(result.is_ok());
+    #[test]
+    fn test_spine_item() {
+        let epub_file = Path::new("./test_case/pkg-spine-unknown.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let (_, mime) = result.unwrap();
-            assert_eq!(mime, "image/png");
-        }
+        let doc = doc.unwrap();
+        assert_eq!(doc.spine_len(), 1);
 
-        /// ID: pub-foreign_json-spine
-        ///
-        /// This EPUB uses a JSON content file in the spine, with a manifest fallback to an HTML document. If the reading system does not support JSON, it should display the HTML.
-        #[test]
-        fn test_pub_foreign_json_spine() {
-            let epub_file = Path::new("./test_case/pub-foreign_json-spine.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let item = doc.spine_item(0);
+        assert!(item.is_some());
 
-            let doc = doc.unwrap();
-            let result = doc.get_manifest_item_with_fallback(
-                "content_primary",
-                &vec!["application/xhtml+xml", "application/json"],
-            );
-            assert!(result.is_ok());
-            let (_, mime) = result.unwrap();
-            assert_eq!(mime, "application/json");
+        let item = item.unwrap();
+        assert_eq!(item.idref, "content_001");
+        assert_eq!(item.properties, Some("untrustworthy".to_string()));
+        assert!(item.linear);
 
-            let result = doc
-                .get_manifest_item_with_fallback("content_primary", &vec!["application/xhtml+xml"]);
-            assert!(result.is_ok());
-            let (_, mime) = result.unwrap();
-            assert_eq!(mime, "application/xhtml+xml");
-        }
+        assert!(doc.spine_item(1).is_none());
+    }
 
-        /// ID: pub-foreign_xml-spine
-        ///
-        /// This EPUB uses an ordinary XML content file with mimetype application/xml in the spine, with a manifest fallback to an HTML document. If the reading system does not support XML, it should display the HTML.
-        #[test]
-        fn test_pub_foreign_xml_spine() {
-            let epub_file = Path::new("./test_case/pub-foreign_xml-spine.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+    #[test]
+    fn test_manifest_item_for_spine() {
+        let epub_file = Path::new("./test_case/pkg-spine-unknown.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let doc = doc.unwrap();
-            let result = doc.get_manifest_item_with_fallback(
-                "content_primary",
-                &vec!["application/xhtml+xml", "application/xml"],
-            );
-            assert!(result.is_ok());
-            let (_, mime) = result.unwrap();
-            assert_eq!(mime, "application/xml");
+        let mut doc = doc.unwrap();
+        let item = doc.manifest_item_for_spine(0);
+        assert!(item.is_some());
 
-            let result = doc
-                .get_manifest_item_with_fallback("content_primary", &vec!["application/xhtml+xml"]);
-            assert!(result.is_ok());
-            let (_, mime) = result.unwrap();
-            assert_eq!(mime, "application/xhtml+xml");
-        }
+        let item = item.unwrap();
+        assert_eq!(item.id, "content_001");
+        assert_eq!(item.path, PathBuf::from("EPUB/content_001.xhtml"));
 
-        /// ID: pub-foreign_xml-suffix-spine
-        ///
-        /// This EPUB uses an custom XML content file with mimetype application/dtc+xml in the spine, with a manifest fallback to an HTML document. If the reading system does not support XML, it should display the HTML.
-        #[test]
-        fn test_pub_foreign_xml_suffix_spine() {
-            let epub_file = Path::new("./test_case/pub-foreign_xml-suffix-spine.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        assert!(doc.manifest_item_for_spine(1).is_none());
 
-            let doc = doc.unwrap();
-            let result = doc.get_manifest_item_with_fallback(
-                "content_primary",
-                &vec!["application/xhtml+xml", "application/dtc+xml"],
-            );
-            assert!(result.is_ok());
-            let (_, mime) = result.unwrap();
-            assert_eq!(mime, "application/dtc+xml");
+        // dangling idref
+        doc.spine[0].idref = "missing".to_string();
+        assert!(doc.manifest_item_for_spine(0).is_none());
+    }
 
-            let result = doc
-                .get_manifest_item_with_fallback("content_primary", &vec!["application/xhtml+xml"]);
-            assert!(result.is_ok());
-            let (_, mime) = result.unwrap();
-            assert_eq!(mime, "application/xhtml+xml");
+    #[test]
+    fn test_spine_with_manifest() {
+        let epub_file = Path::new("./test_case/pkg-spine-order.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        let pairs = doc.spine_with_manifest();
+        assert_eq!(pairs.len(), doc.spine_len());
+        for (spine_item, manifest_item) in pairs {
+            assert!(manifest_item.is_some());
+            assert_eq!(manifest_item.unwrap().id, spine_item.idref);
         }
-    }
 
-    /// Section 3.9 open container format
-    mod open_container_format_tests {
-        use std::{cmp::min, io::Read, path::Path};
+        let epub_file = Path::new("./test_case/pkg-spine-dangling-idref.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        use sha1::{Digest, Sha1};
+        let doc = doc.unwrap();
+        let pairs = doc
+            .spine_with_manifest()
+            .into_iter()
+            .map(|(spine_item, manifest_item)| {
+                (
+                    spine_item.idref.as_str(),
+                    manifest_item.map(|item| item.path.clone()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            vec![
+                ("content_001", Some(PathBuf::from("EPUB/content_001.xhtml"))),
+                ("content_missing", None),
+            ]
+        );
+    }
 
-        use crate::epub::EpubDoc;
+    #[test]
+    fn test_spine_item_is_nav() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        /// ID: ocf-metainf-inc
-        ///
-        /// An extra configuration file, not in the reserved files' list, is added to the META-INF folder; this file must be ignored.
-        #[test]
-        fn test_ocf_metainf_inc() {
-            let epub_file = Path::new("./test_case/ocf-metainf-inc.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
-        }
+        let mut doc = doc.unwrap();
+        assert_eq!(doc.spine[1].idref, "nav");
+        assert!(!doc.spine_item_is_nav(0));
+        assert!(doc.spine_item_is_nav(1));
+        assert!(!doc.spine_item_is_nav(2));
+        assert!(!doc.spine_item_is_nav(3));
+
+        assert!(doc.spine_next().is_some());
+        assert_eq!(doc.current_spine_index.load(Ordering::SeqCst), 1);
+        assert!(doc.spine_prev().is_some());
+        assert_eq!(doc.current_spine_index.load(Ordering::SeqCst), 0);
+
+        doc.set_skip_nav(true);
+        assert!(doc.spine_next().is_some());
+        assert_eq!(doc.current_spine_index.load(Ordering::SeqCst), 2);
+        assert!(doc.spine_prev().is_some());
+        assert_eq!(doc.current_spine_index.load(Ordering::SeqCst), 0);
+    }
 
-        /// ID: ocf-metainf-manifest
-        ///
-        /// An ancillary manifest file, containing an extra spine item, is present in the META-INF directory; this extra item must be ignored by the reading system.
-        #[test]
-        fn test_ocf_metainf_manifest() {
-            let epub_file = Path::new("./test_case/ocf-metainf-manifest.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
-        }
+    #[test]
+    fn test_spine_navigation_with_empty_spine() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        /// ID: ocf-package_arbitrary
-        ///
-        /// The EPUB contains three valid package files and three corresponding sets of content documents, but only one of the packages, in an unusual subdirectory, is referenced by the container.xml file. The reading system must use this package.
-        #[test]
-        fn test_ocf_package_arbitrary() {
-            let epub_file = Path::new("./test_case/ocf-package_arbitrary.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let mut doc = doc.unwrap();
+        doc.spine.clear();
+
+        assert!(doc.spine_current().is_none());
+        assert!(doc.spine_next().is_none());
+        assert!(doc.spine_prev().is_none());
+        assert!(matches!(doc.try_spine_next(), Ok(None)));
+        assert!(matches!(doc.try_spine_prev(), Ok(None)));
+        assert!(matches!(
+            doc.try_spine_current(),
+            Err(EpubError::SpineIndexOutOfRange { index: 0, len: 0 })
+        ));
+        assert_eq!(doc.current_spine_index.load(Ordering::SeqCst), 0);
 
-            let doc = doc.unwrap();
-            assert_eq!(doc.package_path, Path::new("FOO/BAR/package.opf"));
-        }
+        // a position left behind by a longer spine is out of range as well
+        doc.current_spine_index.store(2, Ordering::SeqCst);
+        assert!(doc.spine_current().is_none());
+        assert!(doc.spine_next().is_none());
+        assert!(doc.spine_prev().is_none());
+    }
 
-        /// ID: ocf-package_multiple
-        ///
-        /// The EPUB contains three valid package files and three corresponding sets of content documents, all referenced by the container.xml file. The reading system must use the first package.
-        #[test]
-        fn test_ocf_package_multiple() {
-            let epub_file = Path::new("./test_case/ocf-package_multiple.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+    #[test]
+    fn test_spine_current_with_out_of_range_index() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let doc = doc.unwrap();
-            assert_eq!(doc.package_path, Path::new("FOO/BAR/package.opf"));
-            assert_eq!(doc.base_path, Path::new("FOO/BAR"));
-        }
+        let doc = doc.unwrap();
+        let len = doc.spine_len();
+        doc.current_spine_index.store(len + 5, Ordering::SeqCst);
+
+        assert!(doc.spine_current().is_none());
+        assert!(matches!(
+            doc.try_spine_current(),
+            Err(EpubError::SpineIndexOutOfRange { index, len: spine_len })
+                if index == len + 5 && spine_len == len
+        ));
+        assert!(doc.spine_prev().is_none());
 
-        /// ID: ocf-url_link-leaking-relative
-        ///
-        /// Use a relative link with several double-dot path segments from the content to a photograph. The folder hierarchy containing the photograph starts at the root level; the relative image reference exceeds depth of hierarchy.
-        #[test]
-        fn test_ocf_url_link_leaking_relative() {
-            let epub_file = Path::new("./test_case/ocf-url_link-leaking-relative.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_err());
-            assert_eq!(
-                doc.err().unwrap().to_string(),
-                String::from(
-                    "Relative link leakage: Path \"../../../../media/imgs/monastery.jpg\" is out of container range."
-                )
-            )
-        }
+        doc.current_spine_index.store(len - 1, Ordering::SeqCst);
+        assert!(doc.spine_current().is_some());
+    }
 
-        /// ID: ocf-url_link-path-absolute
-        ///
-        /// Use a path-absolute link, i.e., beginning with a leading slash, from the content to a photograph. The folder hierarchy containing the photograph starts at the root level.
-        #[test]
-        fn test_ocf_url_link_path_absolute() {
-            let epub_file = Path::new("./test_case/ocf-url_link-path-absolute.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+    #[test]
+    fn test_set_current_spine_index() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let doc = doc.unwrap();
-            let resource = doc.manifest.get("photo").unwrap();
-            assert_eq!(resource.path, Path::new("media/imgs/monastery.jpg"));
-        }
+        let mut doc = doc.unwrap();
+        assert_eq!(doc.current_spine_index(), 0);
+
+        assert!(doc.set_current_spine_index(2).is_ok());
+        assert_eq!(doc.current_spine_index(), 2);
+        let (_, mime) = doc.spine_current().unwrap();
+        assert_eq!(mime, "application/xhtml+xml");
+
+        let len = doc.spine_len();
+        assert!(matches!(
+            doc.set_current_spine_index(len),
+            Err(EpubError::SpineIndexOutOfRange { index, len: spine_len })
+                if index == len && spine_len == len
+        ));
+        assert_eq!(doc.current_spine_index(), 2);
+    }
 
-        /// ID: ocf-url_link-relative
-        ///
-        /// A simple relative link from the content to a photograph. The folder hierarchy containing the photograph starts at the root level.
-        #[test]
-        fn test_ocf_url_link_relative() {
-            let epub_file = Path::new("./test_case/ocf-url_link-relative.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+    #[test]
+    fn test_page_spread() {
+        let epub_file = Path::new("./test_case/fxl-page-spread.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let doc = doc.unwrap();
-            let resource = doc.manifest.get("photo").unwrap();
-            assert_eq!(resource.path, Path::new("media/imgs/monastery.jpg"));
-        }
+        let mut doc = doc.unwrap();
+        let spreads = (0..doc.spine_len())
+            .map(|index| doc.page_spread(index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spreads,
+            vec![
+                PageSpread::Center,
+                PageSpread::Left,
+                PageSpread::Right,
+                PageSpread::Left,
+            ]
+        );
+        assert_eq!(doc.page_spread(4), PageSpread::Auto);
 
-        /// ID: ocf-url_manifest
-        ///
-        /// The manifest refers to an XHTML file in an arbitrary subfolder. The reading system must be able to find the content.
-        #[test]
-        fn test_ocf_url_manifest() {
-            let epub_file = Path::new("./test_case/ocf-url_manifest.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        doc.spine[3].properties = None;
+        assert_eq!(doc.page_spread(3), PageSpread::Auto);
 
-            let doc = doc.unwrap();
-            assert!(doc.get_manifest_item("nav").is_ok());
-            assert!(doc.get_manifest_item("content_001").is_ok());
-            assert!(doc.get_manifest_item("content_002").is_err());
-        }
+        // the spine item overrides the global setting
+        doc.spine[2].properties = Some("page-spread-right rendition:spread-none".to_string());
+        assert_eq!(doc.page_spread(2), PageSpread::None);
 
-        /// ID: ocf-url_relative
-        ///
-        /// The manifest refers to an XHTML file in an arbitrary subfolder that is relative to the package's own arbitrary folder. The reading system must be able to find the content.
-        #[test]
-        fn test_ocf_url_relative() {
-            let epub_file = Path::new("./test_case/ocf-url_relative.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        doc.metadata
+            .iter_mut()
+            .filter(|item| item.property == "rendition:spread")
+            .for_each(|item| item.value = "none".to_string());
+        assert_eq!(doc.page_spread(1), PageSpread::None);
+        assert_eq!(doc.page_spread(4), PageSpread::None);
 
-            let doc = doc.unwrap();
-            assert_eq!(doc.package_path, Path::new("foo/BAR/baz.opf"));
-            assert_eq!(doc.base_path, Path::new("foo/BAR"));
-            assert_eq!(
-                doc.manifest.get("nav").unwrap().path,
-                Path::new("foo/BAR/nav.xhtml")
-            );
-            assert_eq!(
-                doc.manifest.get("content_001").unwrap().path,
-                Path::new("foo/BAR/qux/content_001.xhtml")
-            );
-            assert!(doc.get_manifest_item("nav").is_ok());
-            assert!(doc.get_manifest_item("content_001").is_ok());
-        }
+        doc.spine[1].properties = Some("page-spread-left rendition:spread-auto".to_string());
+        assert_eq!(doc.page_spread(1), PageSpread::Left);
+    }
 
-        /// ID: ocf-zip-comp
-        ///
-        /// MUST treat any OCF ZIP container that uses compression techniques other than Deflate as in error.
-        /// This test case does not use compression methods other than Deflate and cannot detect whether it is effective.
-        #[test]
-        fn test_ocf_zip_comp() {
-            let epub_file = Path::new("./test_case/ocf-zip-comp.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
-        }
+    #[test]
+    fn test_get_chapter_text() {
+        let epub_file = Path::new("./test_case/pkg-spine-progression-nonlinear.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        /// ID: ocf-zip-mult
-        ///
-        /// MUST treat any OCF ZIP container that splits the content into segments as in error.
-        /// This test case is not a segmented OCF ZIP container and cannot be tested to see if it is valid.
-        #[test]
-        fn test_ocf_zip_mult() {
-            let epub_file = Path::new("./test_case/ocf-zip-mult.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
-        }
+        let doc = doc.unwrap();
+        let text = doc.get_chapter_text(0);
+        assert!(text.is_ok());
+        assert_eq!(
+            text.unwrap(),
+            "This is a multi-page test.\n\
+             This page must appear first.\n\
+             The next page should be to the left."
+        );
 
-        /// ID: ocf-font_obfuscation
-        ///
-        /// An obfuscated (TrueType) font should be displayed after de-obfuscation.
-        #[test]
-        fn test_ocf_font_obfuscation() {
-            let epub_file = Path::new("./test_case/ocf-font_obfuscation.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        // non-linear items are still accessible by index
+        assert!(doc.get_chapter_text(1).is_ok());
 
-            let doc = doc.unwrap();
-            let unique_id = doc.unique_identifier.clone();
+        let result = doc.get_chapter_text(4);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            EpubError::SpineIndexOutOfRange { index: 4, len: 4 }
+        );
+    }
 
-            let mut hasher = Sha1::new();
-            hasher.update(unique_id.as_bytes());
-            let hash = hasher.finalize();
-            let mut key = vec![0u8; 1040];
-            for i in 0..1040 {
-                key[i] = hash[i % hash.len()];
-            }
+    #[test]
+    fn test_chapter_title() {
+        let epub_file = Path::new("./test_case/nav-chapter-title.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            assert!(doc.encryption.is_some());
-            assert_eq!(doc.encryption.as_ref().unwrap().len(), 1);
+        let doc = doc.unwrap();
+        assert_eq!(doc.chapter_title(0).unwrap(), Some("Chapter 1".to_string()));
+        assert_eq!(
+            doc.chapter_title(1).unwrap(),
+            Some("The Second Chapter".to_string())
+        );
+        assert_eq!(
+            doc.chapter_title(2).unwrap(),
+            Some("Third Page".to_string())
+        );
+        assert_eq!(doc.chapter_title(3).unwrap(), None);
 
-            let data = &doc.encryption.unwrap()[0];
-            assert_eq!(data.method, "http://www.idpf.org/2008/embedding");
+        assert!(matches!(
+            doc.chapter_title(4),
+            Err(EpubError::SpineIndexOutOfRange { index: 4, len: 4 })
+        ));
+    }
 
-            let font_file = doc
-                .archive
-                .lock()
-                .unwrap()
-                .by_name(&data.data)
-                .unwrap()
-                .bytes()
-                .collect::<Result<Vec<u8>, _>>();
-            assert!(font_file.is_ok());
-            let font_file = font_file.unwrap();
+    #[test]
+    fn test_chapters() {
+        let epub_file = Path::new("./test_case/pkg-spine-progression-nonlinear.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            // 根据EPUB规范，字体混淆是直接对字体文件进行的，不需要解压步骤，直接进行去混淆处理
-            let mut deobfuscated = font_file.clone();
-            for i in 0..min(1040, deobfuscated.len()) {
-                deobfuscated[i] ^= key[i];
-            }
+        let doc = doc.unwrap();
+        let chapters = doc.chapters().collect::<Result<Vec<_>, _>>();
+        assert!(chapters.is_ok());
 
-            assert!(is_valid_font(&deobfuscated));
-        }
+        let chapters = chapters.unwrap();
+        let linear_len = doc.spine.iter().filter(|item| item.linear).count();
+        assert_eq!(chapters.len(), linear_len);
 
-        /// ID: ocf-font_obfuscation-bis
-        ///
-        /// An obfuscated (TrueType) font should not be displayed after de-obfuscation, because the obfuscation used a different publication id.
-        #[test]
-        fn test_ocf_font_obfuscation_bis() {
-            let epub_file = Path::new("./test_case/ocf-font_obfuscation_bis.epub");
-            let doc = EpubDoc::new(epub_file);
-            assert!(doc.is_ok());
+        let spine_indexes = chapters
+            .iter()
+            .map(|chapter| chapter.spine_index)
+            .collect::<Vec<_>>();
+        assert_eq!(spine_indexes, vec![0, 2, 3]);
 
-            let doc = doc.unwrap();
+        let titles = chapters
+            .iter()
+            .map(|chapter| chapter.title.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec![Some("Page 1"), Some("Page 3"), Some("Page 4")]);
 
-            let wrong_unique_id = "wrong-publication-id";
-            let mut hasher = Sha1::new();
-            hasher.update(wrong_unique_id.as_bytes());
-            let hash = hasher.finalize();
-            let mut wrong_key = vec![0u8; 1040];
-            for i in 0..1040 {
-                wrong_key[i] = hash[i % hash.len()];
-            }
+        let chapter = &chapters[1];
+        assert_eq!(chapter.text, doc.get_chapter_text(2).unwrap());
+        assert_eq!(
+            chapter.html,
+            doc.get_manifest_item(&doc.spine[2].idref).unwrap().0
+        );
+    }
 
-            assert!(doc.encryption.is_some());
-            assert_eq!(doc.encryption.as_ref().unwrap().len(), 1);
+    #[test]
+    fn test_search_case_insensitive_with_length_changing_lowercase() {
+        let epub_file = Path::new("./test_case/search-lowercase-expansion.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-            let data = &doc.encryption.unwrap()[0];
-            assert_eq!(data.method, "http://www.idpf.org/2008/embedding");
+        // 'İ' lowercases to "i̇", which is one character longer
+        let doc = doc.unwrap();
+        let options = SearchOptions {
+            case_insensitive: true,
+            context: 3,
+            ..Default::default()
+        };
+        let hits = doc.search("İstanbul", options).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].char_offset, 0);
+        assert_eq!(hits[0].snippet, "İstanbul is");
+
+        let hits = doc.search("istanbul", options).unwrap();
+        let offsets = hits.iter().map(|hit| hit.char_offset).collect::<Vec<_>>();
+        assert_eq!(offsets, vec![29, 42]);
+        assert_eq!(hits[1].snippet, "or ISTANBUL, l");
+
+        // offsets after the expanded character still count original characters
+        let hits = doc.search("BIG", options).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].char_offset, 12);
+        assert_eq!(hits[0].snippet, "is big. T");
+
+        // a match cannot start inside the lowercase form of a character
+        assert!(doc.search("\u{307}stanbul", options).unwrap().is_empty());
+    }
 
-            let font_file = doc
-                .archive
-                .lock()
-                .unwrap()
-                .by_name(&data.data)
+    #[test]
+    fn test_search() {
+        let epub_file = Path::new("./test_case/pkg-spine-progression-nonlinear.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        let hits = doc.search("appear", SearchOptions::default());
+        assert!(hits.is_ok());
+
+        // the non-linear second item is skipped
+        let hits = hits.unwrap();
+        let spine_indexes = hits.iter().map(|hit| hit.spine_index).collect::<Vec<_>>();
+        assert_eq!(spine_indexes, vec![0, 2, 3]);
+
+        let options = SearchOptions { context: 10, ..Default::default() };
+        let hits = doc.search("appear first", options).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].char_offset, 42);
+        assert_eq!(hits[0].snippet, "page must appear first. The next");
+
+        assert!(
+            doc.search("please proceed", SearchOptions::default())
                 .unwrap()
-                .bytes()
-                .collect::<Result<Vec<u8>, _>>();
-            assert!(font_file.is_ok());
-            let font_file = font_file.unwrap();
+                .is_empty()
+        );
 
-            // 使用错误的密钥进行去混淆
-            let mut deobfuscated_with_wrong_key = font_file.clone();
-            for i in 0..std::cmp::min(1040, deobfuscated_with_wrong_key.len()) {
-                deobfuscated_with_wrong_key[i] ^= wrong_key[i];
-            }
+        let options = SearchOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        let hits = doc.search("please proceed", options).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].spine_index, 2);
 
-            assert!(!is_valid_font(&deobfuscated_with_wrong_key));
-        }
+        let options = SearchOptions { whole_word: true, ..Default::default() };
+        assert!(doc.search("pag", options).unwrap().is_empty());
 
-        fn is_valid_font(data: &[u8]) -> bool {
-            if data.len() < 4 {
-                return false;
-            }
-            let sig = &data[0..4];
-            // OTF: "OTTO"
-            // TTF: 0x00010000, 0x00020000, "true", "typ1"
-            sig == b"OTTO"
-                || sig == b"\x00\x01\x00\x00"
-                || sig == b"\x00\x02\x00\x00"
-                || sig == b"true"
-                || sig == b"typ1"
-        }
+        let hits = doc.search("page", options).unwrap();
+        assert_eq!(hits.iter().filter(|hit| hit.spine_index == 0).count(), 3);
+        assert!(
+            !doc.search("pag", SearchOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(doc.search("", SearchOptions::default()).unwrap().is_empty());
     }
 
     #[test]
-    fn test_parse_container() {
-        let epub_file = Path::new("./test_case/ocf-zip-mult.epub");
+    fn test_cfi() {
+        let epub_file = Path::new("./test_case/pkg-spine-progression-nonlinear.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
-        // let doc = doc.unwrap();
-        let container = r#"
-        <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
-            <rootfiles></rootfiles>
-        </container>
-        "#
-        .to_string();
+        let mut doc = doc.unwrap();
+        doc.spine[1].id = Some("chap01ref".to_string());
 
-        let result = EpubDoc::<BufReader<File>>::parse_container(container);
-        assert!(result.is_err());
+        let cfi = doc.cfi_for(1, &[4, 2, 1], 0).unwrap();
+        assert_eq!(cfi, "epubcfi(/6/4[chap01ref]!/4/2/1:0)");
+
+        let location = doc.parse_cfi(&cfi);
+        assert!(location.is_ok());
         assert_eq!(
-            result.unwrap_err(),
-            EpubError::NonCanonicalFile { tag: "rootfile".to_string() }
+            location.unwrap(),
+            CfiLocation {
+                spine_index: 1,
+                element_path: vec![4, 2, 1],
+                char_offset: Some(0),
+            }
         );
 
-        let container = r#"
-        <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
-            <rootfiles>
-                <rootfile media-type="application/oebps-package+xml"/>
-            </rootfiles>
-        </container>
-        "#
-        .to_string();
+        let cfi = doc.cfi_for(3, &[4, 10], 12).unwrap();
+        assert_eq!(cfi, "epubcfi(/6/8!/4/10:12)");
+        assert_eq!(doc.parse_cfi(&cfi).unwrap().spine_index, 3);
+
+        assert_eq!(
+            doc.cfi_for(4, &[4], 0).unwrap_err(),
+            EpubError::SpineIndexOutOfRange { index: 4, len: 4 }
+        );
+
+        // assertions in the content path and text location assertions are ignored
+        let location = doc.parse_cfi("epubcfi(/6/2!/4[body01]/10[para05]/3:10[yyy])");
+        assert!(location.is_ok());
+        assert_eq!(
+            location.unwrap(),
+            CfiLocation {
+                spine_index: 0,
+                element_path: vec![4, 10, 3],
+                char_offset: Some(10),
+            }
+        );
 
-        let result = EpubDoc::<BufReader<File>>::parse_container(container);
+        let result = doc.parse_cfi("epubcfi(/6/10!/4)");
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err(),
-            EpubError::MissingRequiredAttribute {
-                tag: "rootfile".to_string(),
-                attribute: "full-path".to_string(),
-            }
+            EpubError::SpineIndexOutOfRange { index: 4, len: 4 }
         );
 
-        let container = r#"
-        <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
-            <rootfiles>
-                <rootfile media-type="application/oebps-package+xml" full-path="EPUB/content.opf"/>
-            </rootfiles>
-        </container>
-        "#
-        .to_string();
-
-        let result = EpubDoc::<BufReader<File>>::parse_container(container);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), PathBuf::from("EPUB/content.opf"))
+        for cfi in [
+            "/6/4!/4/2/1:0",
+            "epubcfi(/6/4[chap02ref]!/4/2/1:0)",
+            "epubcfi(/6/3!/4)",
+            "epubcfi(/4/4!/4)",
+            "epubcfi(/6/4)",
+            "epubcfi(/6/4!/4,/2/1:0,/2/1:5)",
+        ] {
+            assert!(matches!(
+                doc.parse_cfi(cfi),
+                Err(EpubError::InvalidCfi { .. })
+            ));
+        }
     }
 
     #[test]
-    fn test_parse_manifest() {
-        let epub_file = Path::new("./test_case/ocf-package_multiple.epub");
+    fn test_summary() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
-        let manifest = r#"
-        <manifest>
-            <item href="content_001.xhtml" media-type="application/xhtml+xml"/>
-            <item properties="nav" href="nav.xhtml" media-type="application/xhtml+xml"/>
-        </manifest>
-        "#;
-        let mut doc = doc.unwrap();
-        let element = XmlReader::parse(manifest);
-        assert!(element.is_ok());
+        let doc = doc.unwrap();
+        let summary = doc.summary();
+        assert!(summary.is_ok());
 
-        let element = element.unwrap();
-        let result = doc.parse_manifest(&element);
-        assert!(result.is_err());
+        let summary = summary.unwrap();
+        assert_eq!(summary.title, "EPUB 3.3");
         assert_eq!(
-            result.unwrap_err(),
-            EpubError::MissingRequiredAttribute {
-                tag: "item".to_string(),
-                attribute: "id".to_string(),
-            },
+            summary.authors,
+            vec![
+                "Matt Garrish, DAISY Consortium",
+                "Ivan Herman, W3C",
+                "Dave Cramer, Invited Expert",
+            ]
+        );
+        assert_eq!(summary.language, Some("en-us".to_string()));
+        assert_eq!(summary.identifier, "https://www.w3.org/TR/epub-33/");
+        assert_eq!(summary.description, None);
+        assert_eq!(
+            summary.publisher,
+            Some("World Wide Web Consortium".to_string())
         );
+        assert_eq!(summary.chapter_count, 3);
 
-        let manifest = r#"
-        <manifest>
-            <item id="content_001" media-type="application/xhtml+xml"/>
-            <item id="nav" properties="nav" media-type="application/xhtml+xml"/>
-        </manifest>
-        "#;
-        let element = XmlReader::parse(manifest);
-        assert!(element.is_ok());
+        assert!(summary.cover.is_some());
+        let (data, mime) = summary.cover.unwrap();
+        assert!(!data.is_empty());
+        assert_eq!(mime, "image/svg+xml");
+    }
 
-        let element = element.unwrap();
-        let result = doc.parse_manifest(&element);
-        assert!(result.is_err());
+    #[test]
+    fn test_package_document_bytes() {
+        let epub_file = Path::new("./test_case/epub-33.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let doc = doc.unwrap();
+        let bytes = doc.package_document_bytes();
+        assert!(bytes.is_ok());
+
+        let bytes = bytes.unwrap();
+        assert!(bytes.starts_with(b"<?xml"));
+        assert!(String::from_utf8_lossy(&bytes).contains("<package"));
+    }
+
+    #[test]
+    fn test_renditions() {
+        let epub_file = Path::new("./test_case/ocf-multiple-renditions.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
+
+        let mut doc = doc.unwrap();
+        let renditions = doc.available_renditions();
+        assert_eq!(renditions.len(), 2);
+        assert_eq!(renditions[0].full_path, PathBuf::from("EPUB/package.opf"));
+        assert_eq!(renditions[0].label, Some("Standard".to_string()));
+        assert_eq!(renditions[1].full_path, PathBuf::from("LARGE/package.opf"));
         assert_eq!(
-            result.unwrap_err(),
-            EpubError::MissingRequiredAttribute {
-                tag: "item".to_string(),
-                attribute: "href".to_string(),
-            },
+            renditions[1].media_type,
+            Some("application/oebps-package+xml".to_string())
         );
+        assert_eq!(renditions[1].media, Some("(min-width: 1024px)".to_string()));
+        assert_eq!(renditions[1].layout, Some("reflowable".to_string()));
+        assert_eq!(renditions[1].language, Some("ar".to_string()));
+        assert_eq!(renditions[1].access_mode, Some("visual".to_string()));
+        assert_eq!(renditions[1].label, Some("Large print".to_string()));
 
-        let manifest = r#"
-        <manifest>
-            <item id="content_001" href="content_001.xhtml"/>
-            <item id="nav" properties="nav" href="nav.xhtml"/>
-        </manifest>
-        "#;
-        let element = XmlReader::parse(manifest);
-        assert!(element.is_ok());
+        assert_eq!(doc.package_path, PathBuf::from("EPUB/package.opf"));
+        assert_eq!(doc.spine_len(), 4);
+        assert!(doc.spine_next().is_some());
 
-        let element = element.unwrap();
-        let result = doc.parse_manifest(&element);
+        let result = doc.open_rendition(1);
+        assert!(result.is_ok());
+        assert_eq!(doc.package_path, PathBuf::from("LARGE/package.opf"));
+        assert_eq!(doc.base_path, PathBuf::from("LARGE"));
+        assert_eq!(
+            doc.get_title(),
+            vec!["ocf-multiple-renditions (Large print)".to_string()]
+        );
+        assert_eq!(doc.spine_len(), 2);
+        assert_eq!(doc.catalog.len(), 2);
+        assert_eq!(doc.available_renditions(), renditions);
+        assert!(doc.spine_prev().is_none());
+
+        let (data, _) = doc.spine_current().unwrap();
+        assert!(
+            String::from_utf8(data)
+                .unwrap()
+                .contains("This page must appear third")
+        );
+
+        let result = doc.open_rendition(2);
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err(),
-            EpubError::MissingRequiredAttribute {
-                tag: "item".to_string(),
-                attribute: "media-type".to_string(),
-            },
+            EpubError::RenditionIndexOutOfRange { index: 2, len: 2 }
         );
+        assert_eq!(doc.package_path, PathBuf::from("LARGE/package.opf"));
 
-        let manifest = r#"
-        <manifest>
-            <item id="content_001" href="content_001.xhtml" media-type="application/xhtml+xml"/>
-            <item id="nav" properties="nav" href="nav.xhtml" media-type="application/xhtml+xml"/>
-        </manifest>
-        "#;
-        let element = XmlReader::parse(manifest);
-        assert!(element.is_ok());
-
-        let element = element.unwrap();
-        let result = doc.parse_manifest(&element);
-        assert!(result.is_ok());
+        assert!(doc.open_rendition(0).is_ok());
+        assert_eq!(doc.spine_len(), 4);
     }
 
-    /// Test for function `has_encryption`
     #[test]
-    fn test_fn_has_encryption() {
-        let epub_file = Path::new("./test_case/ocf-font_obfuscation.epub");
+    fn test_container_metadata() {
+        let epub_file = Path::new("./test_case/ocf-metainf-metadata.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
         let doc = doc.unwrap();
-        assert!(doc.has_encryption());
+        let metadata = doc.container_metadata();
+        assert!(metadata.is_ok());
+
+        let metadata = metadata.unwrap();
+        assert!(metadata.is_some());
+
+        let metadata = metadata.unwrap();
+        assert_eq!(metadata.len(), 3);
+        assert_eq!(metadata[0].property, "identifier");
+        assert_eq!(
+            metadata[0].value,
+            "urn:uuid:0f8ec6a1-7a4d-4d7e-9b6f-3a1c2d5e8f90"
+        );
+        assert_eq!(metadata[0].refined.len(), 1);
+        assert_eq!(metadata[0].refined[0].property, "identifier-type");
+        assert_eq!(metadata[1].property, "title");
+        assert_eq!(metadata[2].property, "dcterms:modified");
+
+        // the package metadata is not affected
+        assert_eq!(doc.get_title(), vec!["pkg-spine-unknown".to_string()]);
+
+        let doc = EpubDoc::new("./test_case/pkg-spine-unknown.epub").unwrap();
+        assert!(doc.container_metadata().unwrap().is_none());
     }
 
-    /// This test is used to detect whether the "META-INF/encryption.xml" file is parsed correctly
     #[test]
-    fn test_fn_parse_encryption() {
-        let epub_file = Path::new("./test_case/ocf-font_obfuscation.epub");
+    fn test_signatures() {
+        let epub_file = Path::new("./test_case/ocf-signatures.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
         let doc = doc.unwrap();
-        assert!(doc.encryption.is_some());
+        let signatures = doc.signatures();
+        assert!(signatures.is_ok());
 
-        let encryption = doc.encryption.unwrap();
-        assert_eq!(encryption.len(), 1);
-        assert_eq!(encryption[0].method, "http://www.idpf.org/2008/embedding");
-        assert_eq!(encryption[0].data, "EPUB/fonts/Lobster.ttf");
+        let signatures = signatures.unwrap();
+        assert_eq!(signatures.len(), 3);
+        assert_eq!(
+            signatures
+                .iter()
+                .map(|signature| signature.reference_uri.as_str())
+                .collect::<Vec<&str>>(),
+            ["#Manifest", "EPUB/package.opf", "EPUB/nav.xhtml"]
+        );
+        assert!(
+            signatures.iter().all(|signature| signature.signature_method
+                == "http://www.w3.org/2000/09/xmldsig#rsa-sha1")
+        );
+        assert_eq!(
+            signatures[1].digest_method,
+            "http://www.w3.org/2001/04/xmlenc#sha256"
+        );
+
+        // unsigned publication
+        let doc = EpubDoc::new("./test_case/pkg-spine-unknown.epub").unwrap();
+        assert_eq!(doc.signatures().unwrap(), vec![]);
     }
 
     #[test]
-    fn test_get_metadata_existing_key() {
-        let epub_file = Path::new("./test_case/epub-33.epub");
+    fn test_ncx_play_order_lenient() {
+        let epub_file = Path::new("./test_case/epub2-ncx-play-order.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
         let doc = doc.unwrap();
+        let labels = doc
+            .catalog
+            .iter()
+            .map(|nav_point| nav_point.label.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(labels, ["Chapter 1", "Chapter 2", "Chapter 3", "Chapter 4"]);
 
-        let titles = doc.get_metadata("title");
-        assert!(titles.is_some());
+        let orders = doc
+            .catalog
+            .iter()
+            .map(|nav_point| nav_point.play_order)
+            .collect::<Vec<Option<usize>>>();
+        assert_eq!(orders, [Some(1), Some(2), Some(3), Some(5)]);
+        assert_eq!(doc.catalog[2].children[0].label, "Section 3.1");
+        assert_eq!(doc.catalog[2].children[0].play_order, Some(4));
+        assert_eq!(doc.catalog[3].doc_index(), 3);
+
+        assert_eq!(doc.warnings().len(), 4);
+        assert!(doc.warnings()[0].contains("'Chapter 2' is missing"));
+        assert!(doc.warnings()[1].contains("'Chapter 3' has a duplicate playOrder 2"));
+        assert!(doc.warnings()[2].contains("'Section 3.1' is missing"));
+        assert!(doc.warnings()[3].contains("'Chapter 4' has playOrder 1"));
 
-        let titles = titles.unwrap();
-        assert_eq!(titles.len(), 1);
-        assert_eq!(titles[0].property, "title");
-        assert_eq!(titles[0].value, "EPUB 3.3");
+        assert_eq!(
+            doc.play_order_issues(),
+            [
+                PlayOrderIssue::Missing { label: "Chapter 2".to_string() },
+                PlayOrderIssue::Duplicate {
+                    label: "Chapter 3".to_string(),
+                    play_order: 2
+                },
+                PlayOrderIssue::Missing { label: "Section 3.1".to_string() },
+                PlayOrderIssue::OutOfOrder {
+                    label: "Chapter 4".to_string(),
+                    play_order: 1,
+                    preceding: 2,
+                },
+            ]
+        );
+    }
 
-        let languages = doc.get_metadata("language");
-        assert!(languages.is_some());
+    #[test]
+    fn test_items_with_property() {
+        let epub_file = Path::new("./test_case/nav-toc-spine.epub");
+        let doc = EpubDoc::new(epub_file);
+        assert!(doc.is_ok());
 
-        let languages = languages.unwrap();
-        assert_eq!(languages.len(), 1);
-        assert_eq!(languages[0].property, "language");
-        assert_eq!(languages[0].value, "en-us");
+        let doc = doc.unwrap();
+        let items = doc.items_with_property("nav");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, "nav");
+        assert_eq!(doc.first_item_with_property("nav").unwrap().id, "nav");
 
-        let language = doc.get_language();
-        assert_eq!(language, vec!["en-us"]);
+        assert!(doc.items_with_property("na").is_empty());
+        assert!(doc.first_item_with_property("cover-image").is_none());
     }
 
     #[test]
-    fn test_get_metadata_nonexistent_key() {
-        let epub_file = Path::new("./test_case/epub-33.epub");
+    fn test_reading_order() {
+        let epub_file = Path::new("./test_case/nav-toc-spine.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
         let doc = doc.unwrap();
-        let metadata = doc.get_metadata("nonexistent");
-        assert!(metadata.is_none());
+        let reading_order = doc.reading_order();
+        assert_eq!(reading_order.len(), 4);
+
+        let units = reading_order
+            .iter()
+            .map(|unit| {
+                (
+                    unit.spine_index,
+                    unit.manifest_id.as_str(),
+                    unit.title.as_deref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            units,
+            vec![
+                (0, "d-content_001", Some("Chapter 1")),
+                (1, "c-content_002", Some("Chapter 2")),
+                (2, "b-content_003", Some("Chapter 3")),
+                (3, "a-content_004", Some("Chapter 4")),
+            ]
+        );
+        assert!(reading_order.iter().all(|unit| unit.toc_depth == Some(0)));
+
+        // the non-linear spine item is skipped, and units without a catalog entry have no title
+        let doc = EpubDoc::new("./test_case/nav-empty-toc.epub").unwrap();
+        let reading_order = doc.reading_order();
+        let spine_indexes = reading_order
+            .iter()
+            .map(|unit| unit.spine_index)
+            .collect::<Vec<_>>();
+        assert_eq!(spine_indexes, [0, 1, 3]);
+        assert!(reading_order.iter().all(|unit| unit.title.is_none()));
+        assert!(reading_order.iter().all(|unit| unit.toc_depth.is_none()));
+        assert_eq!(reading_order.position(3), Some(2));
+        assert_eq!(reading_order.position(2), None);
     }
 
     #[test]
-    fn test_get_metadata_multiple_items_same_type() {
-        let epub_file = Path::new("./test_case/epub-33.epub");
+    fn test_synthesize_catalog_from_spine() {
+        let epub_file = Path::new("./test_case/nav-empty-toc.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
-        let doc = doc.unwrap();
-
-        let creators = doc.get_metadata("creator");
-        assert!(creators.is_some());
+        let mut doc = doc.unwrap();
+        assert!(doc.toc_is_empty());
 
-        let creators = creators.unwrap();
-        assert_eq!(creators.len(), 3);
+        doc.synthesize_catalog_from_spine();
+        assert!(!doc.toc_is_empty());
 
-        assert_eq!(creators[0].id, Some("creator_id_0".to_string()));
-        assert_eq!(creators[0].property, "creator");
-        assert_eq!(creators[0].value, "Matt Garrish, DAISY Consortium");
+        let linear_count = doc.spine.iter().filter(|item| item.linear).count();
+        assert_eq!(linear_count, 3);
+        assert_eq!(doc.catalog.len(), linear_count);
 
-        assert_eq!(creators[1].id, Some("creator_id_1".to_string()));
-        assert_eq!(creators[1].property, "creator");
-        assert_eq!(creators[1].value, "Ivan Herman, W3C");
+        let labels = doc
+            .catalog
+            .iter()
+            .map(|nav_point| nav_point.label.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            labels,
+            [
+                "Spine items in order",
+                "The Second Chapter",
+                "a-content_004"
+            ]
+        );
+        assert_eq!(
+            doc.catalog[0].content,
+            Some(PathBuf::from("EPUB/d-content_001.xhtml"))
+        );
 
-        assert_eq!(creators[2].id, Some("creator_id_2".to_string()));
-        assert_eq!(creators[2].property, "creator");
-        assert_eq!(creators[2].value, "Dave Cramer, Invited Expert");
+        let spine_indexes = doc
+            .catalog_with_spine()
+            .into_iter()
+            .map(|(_, _, index)| index)
+            .collect::<Vec<Option<usize>>>();
+        assert_eq!(spine_indexes, [Some(0), Some(1), Some(3)]);
+
+        // a catalog that is not empty is kept
+        let mut doc = EpubDoc::new("./test_case/nav-chapter-title.epub").unwrap();
+        assert!(!doc.toc_is_empty());
+        let catalog = doc.catalog.clone();
+        doc.synthesize_catalog_from_spine();
+        assert_eq!(doc.catalog, catalog);
     }
 
     #[test]
-    fn test_get_metadata_with_refinement() {
-        let epub_file = Path::new("./test_case/epub-33.epub");
+    fn test_epub3_ncx_fallback() {
+        let epub_file = Path::new("./test_case/epub3-ncx-only.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
         let doc = doc.unwrap();
-
-        let title = doc.get_metadata("title");
-        assert!(title.is_some());
-
-        let title = title.unwrap();
-        assert_eq!(title.len(), 1);
-        assert_eq!(title[0].refined.len(), 1);
-        assert_eq!(title[0].refined[0].property, "title-type");
-        assert_eq!(title[0].refined[0].value, "main");
+        assert_eq!(doc.version, EpubVersion::Version3_0);
+        assert_eq!(doc.catalog_base, PathBuf::from("OPS"));
+        assert_eq!(doc.catalog_title, "NCX Only");
+        assert_eq!(doc.catalog.len(), 2);
+        assert_eq!(doc.catalog[0].label, "Chapter 1");
+        assert_eq!(doc.catalog[1].children[0].label, "Section 2.1");
+
+        assert_eq!(doc.warnings().len(), 1);
+        assert!(doc.warnings()[0].contains("Navigation Document is missing"));
+
+        let sections = doc.nav_sections();
+        assert!(sections.is_ok());
+        let sections = sections.unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections["toc"], doc.catalog);
     }
 
     #[test]
-    fn test_get_manifest_item_with_fallback() {
-        let epub_file = Path::new("./test_case/pub-foreign_bad-fallback.epub");
+    fn test_ncx_page_list_and_nav_lists() {
+        let epub_file = Path::new("./test_case/epub2-ncx-page-list.epub");
         let doc = EpubDoc::new(epub_file);
         assert!(doc.is_ok());
 
         let doc = doc.unwrap();
-        assert!(doc.get_manifest_item("content_001").is_ok());
-        assert!(doc.get_manifest_item("bar").is_ok());
+        assert_eq!(doc.catalog.len(), 1);
+        assert_eq!(
+            doc.catalog[0].content,
+            Some(PathBuf::from("content_001.xhtml"))
+        );
 
-        // 当回退链上存在可回退资源时能获取资源
-        if let Ok((_, mime)) =
-            doc.get_manifest_item_with_fallback("content_001", &vec!["image/psd"])
-        {
-            assert_eq!(mime, "image/psd");
-        } else {
-            assert!(false, "get_manifest_item_with_fallback failed");
-        }
+        let pages = doc
+            .page_list
+            .iter()
+            .map(|page| (page.label.as_str(), page.content.clone(), page.play_order))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pages,
+            vec![
+                (
+                    "1",
+                    Some(PathBuf::from("content_001.xhtml#page-1")),
+                    Some(2)
+                ),
+                (
+                    "2",
+                    Some(PathBuf::from("content_001.xhtml#page-2")),
+                    Some(3)
+                ),
+                (
+                    "3",
+                    Some(PathBuf::from("content_001.xhtml#page-3")),
+                    Some(4)
+                ),
+            ]
+        );
 
-        // 当回退链上不存在可回退资源时无法获取资源
+        assert_eq!(doc.nav_lists.len(), 2);
+        assert_eq!(doc.nav_lists["lof"][0].label, "Figure 1");
         assert_eq!(
-            doc.get_manifest_item_with_fallback("content_001", &vec!["application/xhtml+xml"])
-                .unwrap_err()
-                .to_string(),
-            "No supported file format: The fallback resource does not contain the file format you support."
+            doc.nav_lists["lof"][0].content,
+            Some(PathBuf::from("content_001.xhtml#figure-1"))
         );
+        assert_eq!(doc.nav_lists["List of Tables"][0].label, "Table 1");
+
+        let sections = doc.nav_sections().unwrap();
+        assert_eq!(sections.len(), 4);
+        assert_eq!(sections["page-list"], doc.page_list);
+        assert_eq!(sections["lof"], doc.nav_lists["lof"]);
     }
 
     #[test]
-    fn test_get_cover() {
-        let epub_file = Path::new("./test_case/pkg-cover-image.epub");
+    fn test_parse_ncx_content() {
+        let nav_point = XmlReader::parse(
+            r#"<navPoint id="n1" playOrder="1">
+                <navLabel><text>Chapter 1</text></navLabel>
+                <content src="./text//chapter_001.xhtml#start"/>
+            </navPoint>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            EpubDoc::<BufReader<File>>::parse_ncx_content(&nav_point),
+            Some(PathBuf::from("text/chapter_001.xhtml#start"))
+        );
+
+        let item = EpubDoc::<BufReader<File>>::parse_ncx_target(&nav_point, 0);
+        assert_eq!(item.label, "Chapter 1");
+        assert_eq!(
+            item.content,
+            Some(PathBuf::from("text/chapter_001.xhtml#start"))
+        );
+
+        // the text of the content element is not a link
+        let nav_point = XmlReader::parse(
+            r#"<navPoint id="n2"><content>chapter_002.xhtml</content></navPoint>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            EpubDoc::<BufReader<File>>::parse_ncx_content(&nav_point),
+            None
+        );
+    }
+
+    #[test]
+    fn test_nav_page_list() {
+        let epub_file = Path::new("./test_case/nav-page-list.epub");
         let doc = EpubDoc::new(epub_file);
-        if let Err(err) = &doc {
-            println!("{}", err);
-        }
         assert!(doc.is_ok());
 
         let doc = doc.unwrap();
-        let result = doc.get_cover();
-        assert!(result.is_some());
+        let pages = doc
+            .page_list
+            .iter()
+            .map(|page| (page.label.as_str(), page.content.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pages,
+            vec![
+                ("1", Some(PathBuf::from("content_001.xhtml#page-1"))),
+                ("2", Some(PathBuf::from("content_001.xhtml#page-2"))),
+            ]
+        );
+        assert!(doc.nav_lists.is_empty());
+        assert_eq!(doc.nav_sections().unwrap()["page-list"], doc.page_list);
 
-        let (data, mime) = result.unwrap();
-        assert_eq!(data.len(), 5785);
-        assert_eq!(mime, "image/jpeg");
+        let doc = EpubDoc::new("./test_case/nav-sections.epub").unwrap();
+        assert!(doc.page_list.is_empty());
+    }
+
+    #[test]
+    fn test_ncx_play_order_strict() {
+        let epub_file = Path::new("./test_case/epub2-ncx-play-order.epub");
+        let doc = EpubDoc::new_with_mode(epub_file, ParseMode::Strict);
+        assert!(doc.is_err());
+        assert_eq!(
+            doc.err().unwrap(),
+            EpubError::InvalidPlayOrder {
+                message: "NavPoint 'Chapter 2' is missing the playOrder attribute.".to_string()
+            }
+        );
+
+        let epub_file = Path::new("./test_case/epub-2.epub");
+        let doc = EpubDoc::new_with_mode(epub_file, ParseMode::Strict);
+        assert!(doc.is_ok());
+        let doc = doc.unwrap();
+        assert!(doc.warnings().is_empty());
+        assert!(doc.play_order_issues().is_empty());
     }
 
     #[test]
@@ -2668,7 +6947,7 @@ mod tests {
     fn test_is_valid_epub_valid_file() {
         let result = EpubDoc::is_valid_epub("./test_case/epub-2.epub");
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
     }
 
     #[test]
@@ -2697,16 +6976,13 @@ mod tests {
     fn test_is_valid_epub_valid_epub_3() {
         let result = EpubDoc::is_valid_epub("./test_case/epub-33.epub");
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
     }
 
     #[test]
     fn test_is_outside_error() {
         let archive_error = EpubError::ArchiveError {
-            source: zip::result::ZipError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "test",
-            )),
+            source: zip::result::ZipError::Io(std::io::Error::other("test")),
         };
         assert!(EpubDoc::<BufReader<File>>::is_outside_error(&archive_error));
 
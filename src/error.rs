@@ -4,6 +4,8 @@
 //! EPUB file parsing and processing. All errors are uniformly wrapped in the
 //! `EpubError` enumeration for convenient error handling by the caller.
 
+use std::{cmp, fmt};
+
 use thiserror::Error;
 
 /// Types of errors that can occur during EPUB processing
@@ -11,6 +13,10 @@ use thiserror::Error;
 /// This enumeration defines the various error cases that can be encountered
 /// when parsing and processing EPUB files, including file format errors,
 /// missing resources, compression issues, etc.
+///
+/// Variants wrapping an underlying error, such as IO, archive, decoding and XML
+/// errors, expose it through `std::error::Error::source`, so that error chains are
+/// preserved when reporting.
 #[derive(Debug, Error)]
 pub enum EpubError {
     /// ZIP archive related errors
@@ -20,6 +26,13 @@ pub enum EpubError {
     #[error("Archive error: {source}")]
     ArchiveError { source: zip::result::ZipError },
 
+    /// Duplicate manifest id error
+    ///
+    /// This error occurs in strict parse mode when more than one manifest item
+    /// declares the same id, which the EPUB specification forbids.
+    #[error("Duplicate manifest id: More than one manifest item uses the id \"{id}\".")]
+    DuplicateManifestId { id: String },
+
     /// Data Decoding Error - Null data
     ///
     /// This error occurs when trying to decode an empty stream or when the data
@@ -44,6 +57,27 @@ pub enum EpubError {
     #[error("IO error: {source}")]
     IOError { source: std::io::Error },
 
+    /// Image error
+    ///
+    /// This error occurs when decoding, resizing or encoding an image using the image library.
+    #[cfg(feature = "image")]
+    #[error("Image error: {source}")]
+    ImageError { source: image::ImageError },
+
+    /// Invalid canonical fragment identifier error
+    ///
+    /// This error occurs when an EPUB CFI is malformed, uses features that are not
+    /// supported such as ranges or spatial offsets, or does not point into the spine.
+    #[error("Invalid CFI: \"{cfi}\" is not a supported CFI, {message}.")]
+    InvalidCfi { cfi: String, message: String },
+
+    /// Invalid NCX play order error
+    ///
+    /// This error occurs in strict parse mode when a navigation point of the NCX
+    /// document has a missing, duplicate, or out-of-order `playOrder` attribute.
+    #[error("Invalid play order: {message}")]
+    InvalidPlayOrder { message: String },
+
     /// Missing required attribute error
     ///
     /// Triggered when an XML element in an EPUB file lacks the required
@@ -90,6 +124,22 @@ pub enum EpubError {
     #[error("Relative link leakage: Path \"{path}\" is out of container range.")]
     RelativeLinkLeakage { path: String },
 
+    /// Remote resource error
+    ///
+    /// This error occurs when trying to get a resource located outside of the EPUB
+    /// container while the fetch policy of the document refuses to retrieve it.
+    #[error("Remote resource: Fetching \"{url}\" is refused by the fetch policy.")]
+    RemoteResource { url: String },
+
+    /// Rendition index out of range error
+    ///
+    /// This error occurs when trying to open a rendition by an index that is not
+    /// less than the number of rootfiles declared in the container.
+    #[error(
+        "Rendition index out of range: The index {index} is out of range for {len} renditions."
+    )]
+    RenditionIndexOutOfRange { index: usize, len: usize },
+
     /// Unable to find the resource id error
     ///
     /// This error occurs when trying to get a resource by id but that id doesn't exist in the manifest.
@@ -103,6 +153,13 @@ pub enum EpubError {
     #[error("Resource not found: Unable to find resource from \"{resource}\".")]
     ResourceNotFound { resource: String },
 
+    /// Spine index out of range error
+    ///
+    /// This error occurs when trying to access a spine item by an index
+    /// that is not less than the number of spine items.
+    #[error("Spine index out of range: The index {index} is out of range for {len} spine items.")]
+    SpineIndexOutOfRange { index: usize, len: usize },
+
     /// Unrecognized EPUB version error
     ///
     /// This error occurs when parsing epub files, the library cannot
@@ -157,6 +214,117 @@ pub enum EpubError {
     /// This error occurs when parsing XML data using the QuickXml library.
     #[error("QuickXml error: {source}")]
     QuickXmlError { source: quick_xml::Error },
+
+    /// XML syntax error
+    ///
+    /// This error occurs when `XmlReader` encounters malformed XML, such as mismatched
+    /// or unclosed tags. The position points at the place in the source document
+    /// where the parser gave up, so that authoring tools can report it to the user.
+    /// `message` is the rendered parser message; the underlying quick-xml error is
+    /// kept as `source` so it stays reachable through `Error::source`.
+    #[error("XML parse error at {position}: {message}")]
+    XmlParse {
+        message: String,
+        position: XmlPosition,
+        source: quick_xml::Error,
+    },
+}
+
+impl EpubError {
+    /// Checks whether the error reports a manifest id that does not exist
+    ///
+    /// This is the case when a resource is requested by an id that the package
+    /// document does not declare, which usually means the caller asked for the
+    /// wrong id rather than that the publication is broken.
+    pub fn is_missing_id(&self) -> bool {
+        matches!(self, Self::ResourceIdNotExist { .. })
+    }
+
+    /// Checks whether the error reports a resource that could not be found
+    ///
+    /// This is the case when a file cannot be found in the container, such as a
+    /// resource declared in the manifest, which means the publication is broken. It is
+    /// also the case when the caller looks up a resource that does not exist, such as
+    /// a path passed to `get_manifest_item_by_path` that no manifest item uses, or a
+    /// cover that the publication does not declare.
+    pub fn is_missing_file(&self) -> bool {
+        matches!(
+            self,
+            Self::ResourceNotFound { .. }
+                | Self::ArchiveError {
+                    source: zip::result::ZipError::FileNotFound
+                }
+        )
+    }
+}
+
+/// Error returned when resolving a resource along its fallback chain fails
+///
+/// Besides the underlying error, this error records every manifest item that was
+/// examined, in the order the fallback chain was followed, so that callers can tell
+/// which formats were attempted.
+#[derive(Debug, Error)]
+#[error("{source} Tried: [{}]", format_fallback_chain(.tried))]
+pub struct FallbackError {
+    /// The `(id, mime)` pair of each examined manifest item
+    pub tried: Vec<(String, String)>,
+
+    /// The error that ended the fallback resolution
+    pub source: EpubError,
+}
+
+fn format_fallback_chain(tried: &[(String, String)]) -> String {
+    tried
+        .iter()
+        .map(|(id, mime)| format!("{} ({})", id, mime))
+        .collect::<Vec<String>>()
+        .join(" -> ")
+}
+
+impl From<FallbackError> for EpubError {
+    fn from(value: FallbackError) -> Self {
+        value.source
+    }
+}
+
+/// Location of an error inside an XML document
+///
+/// `offset` is the byte offset from the beginning of the document, while `line`
+/// and `column` are 1-based and count characters rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XmlPosition {
+    pub offset: u64,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl XmlPosition {
+    /// Resolves a byte offset in `content` to a line and column position
+    ///
+    /// Offsets beyond the end of the content are clamped to the end of the content.
+    pub(crate) fn from_offset(content: &str, offset: u64) -> Self {
+        let mut end = cmp::min(offset as usize, content.len());
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let consumed = &content[..end];
+        let line = consumed.matches('\n').count() + 1;
+        let line_start = consumed.rfind('\n').map(|index| index + 1).unwrap_or(0);
+        let column = consumed[line_start..].chars().count() + 1;
+
+        Self { offset, line, column }
+    }
+}
+
+impl fmt::Display for XmlPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (byte {})",
+            self.line, self.column, self.offset
+        )
+    }
 }
 
 impl From<zip::result::ZipError> for EpubError {
@@ -209,10 +377,58 @@ impl From<walkdir::Error> for EpubError {
     }
 }
 
-#[cfg(test)]
+#[cfg(feature = "image")]
+impl From<image::ImageError> for EpubError {
+    fn from(value: image::ImageError) -> Self {
+        EpubError::ImageError { source: value }
+    }
+}
+
+/// Compares two errors
+///
+/// Variants carrying plain data, such as ids, paths and messages, compare by value.
+/// Variants wrapping an underlying error that cannot be compared, namely `ArchiveError`,
+/// `IOError`, `ImageError`, `QuickXmlError`, `Utf16DecodeError` and `WalkDirError`,
+/// compare by the message of the wrapped error, and `IOError` additionally by its
+/// `std::io::ErrorKind`. `XmlParse` compares by its message and position, that is by
+/// the message of the wrapped quick-xml error rather than the error itself. Errors of
+/// different variants never compare equal.
 impl PartialEq for EpubError {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (Self::ArchiveError { source: l_source }, Self::ArchiveError { source: r_source }) => {
+                l_source.to_string() == r_source.to_string()
+            }
+
+            (Self::DuplicateManifestId { id: l_id }, Self::DuplicateManifestId { id: r_id }) => {
+                l_id == r_id
+            }
+
+            #[cfg(feature = "builder")]
+            (
+                Self::EpubBuilderError { source: l_source },
+                Self::EpubBuilderError { source: r_source },
+            ) => l_source == r_source,
+
+            (Self::IOError { source: l_source }, Self::IOError { source: r_source }) => {
+                l_source.kind() == r_source.kind() && l_source.to_string() == r_source.to_string()
+            }
+
+            #[cfg(feature = "image")]
+            (Self::ImageError { source: l_source }, Self::ImageError { source: r_source }) => {
+                l_source.to_string() == r_source.to_string()
+            }
+
+            (
+                Self::InvalidCfi { cfi: l_cfi, message: l_message },
+                Self::InvalidCfi { cfi: r_cfi, message: r_message },
+            ) => l_cfi == r_cfi && l_message == r_message,
+
+            (
+                Self::InvalidPlayOrder { message: l_message },
+                Self::InvalidPlayOrder { message: r_message },
+            ) => l_message == r_message,
+
             (
                 Self::MissingRequiredAttribute { tag: l_tag, attribute: l_attribute },
                 Self::MissingRequiredAttribute { tag: r_tag, attribute: r_attribute },
@@ -232,6 +448,15 @@ impl PartialEq for EpubError {
                 Self::RelativeLinkLeakage { path: r_path },
             ) => l_path == r_path,
 
+            (Self::RemoteResource { url: l_url }, Self::RemoteResource { url: r_url }) => {
+                l_url == r_url
+            }
+
+            (
+                Self::RenditionIndexOutOfRange { index: l_index, len: l_len },
+                Self::RenditionIndexOutOfRange { index: r_index, len: r_len },
+            ) => l_index == r_index && l_len == r_len,
+
             (Self::ResourceIdNotExist { id: l_id }, Self::ResourceIdNotExist { id: r_id }) => {
                 l_id == r_id
             }
@@ -241,6 +466,11 @@ impl PartialEq for EpubError {
                 Self::ResourceNotFound { resource: r_resource },
             ) => l_resource == r_resource,
 
+            (
+                Self::SpineIndexOutOfRange { index: l_index, len: l_len },
+                Self::SpineIndexOutOfRange { index: r_index, len: r_len },
+            ) => l_index == r_index && l_len == r_len,
+
             (
                 Self::UnsupportedEncryptedMethod { method: l_method },
                 Self::UnsupportedEncryptedMethod { method: r_method },
@@ -256,13 +486,72 @@ impl PartialEq for EpubError {
                 Self::Utf8DecodeError { source: r_source },
             ) => l_source == r_source,
 
+            (
+                Self::Utf16DecodeError { source: l_source },
+                Self::Utf16DecodeError { source: r_source },
+            ) => l_source.to_string() == r_source.to_string(),
+
             #[cfg(feature = "builder")]
+            (Self::WalkDirError { source: l_source }, Self::WalkDirError { source: r_source }) => {
+                l_source.to_string() == r_source.to_string()
+            }
+
             (
-                Self::EpubBuilderError { source: l_source },
-                Self::EpubBuilderError { source: r_source },
-            ) => l_source == r_source,
+                Self::QuickXmlError { source: l_source },
+                Self::QuickXmlError { source: r_source },
+            ) => l_source.to_string() == r_source.to_string(),
+
+            (
+                Self::XmlParse {
+                    message: l_message, position: l_position, ..
+                },
+                Self::XmlParse {
+                    message: r_message, position: r_position, ..
+                },
+            ) => l_message == r_message && l_position == r_position,
+
+            (Self::EmptyDataError, Self::EmptyDataError)
+            | (Self::FailedParsingXml, Self::FailedParsingXml)
+            | (Self::MutexError, Self::MutexError)
+            | (Self::NoSupportedFileFormat, Self::NoSupportedFileFormat)
+            | (Self::UnrecognizedEpubVersion, Self::UnrecognizedEpubVersion) => true,
+
+            // listing every variant makes a new variant fail to compile until it is
+            // compared above, instead of silently comparing unequal to itself
+            #[cfg(feature = "builder")]
+            (Self::EpubBuilderError { .. } | Self::WalkDirError { .. }, _) => false,
 
-            _ => core::mem::discriminant(self) == core::mem::discriminant(other),
+            #[cfg(feature = "image")]
+            (Self::ImageError { .. }, _) => false,
+
+            (
+                Self::ArchiveError { .. }
+                | Self::DuplicateManifestId { .. }
+                | Self::EmptyDataError
+                | Self::FailedParsingXml
+                | Self::IOError { .. }
+                | Self::InvalidCfi { .. }
+                | Self::InvalidPlayOrder { .. }
+                | Self::MissingRequiredAttribute { .. }
+                | Self::MutexError
+                | Self::NonCanonicalEpub { .. }
+                | Self::NonCanonicalFile { .. }
+                | Self::NoSupportedFileFormat
+                | Self::RelativeLinkLeakage { .. }
+                | Self::RemoteResource { .. }
+                | Self::RenditionIndexOutOfRange { .. }
+                | Self::ResourceIdNotExist { .. }
+                | Self::ResourceNotFound { .. }
+                | Self::SpineIndexOutOfRange { .. }
+                | Self::UnrecognizedEpubVersion
+                | Self::UnsupportedEncryptedMethod { .. }
+                | Self::UnusableCompressionMethod { .. }
+                | Self::Utf8DecodeError { .. }
+                | Self::Utf16DecodeError { .. }
+                | Self::QuickXmlError { .. }
+                | Self::XmlParse { .. },
+                _,
+            ) => false,
         }
     }
 }
@@ -274,9 +563,15 @@ impl PartialEq for EpubError {
 /// are typically related to EPUB specification requirements or validation
 /// rules during the build process.
 #[cfg(feature = "builder")]
-#[derive(Debug, Error)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Error, PartialEq)]
 pub enum EpubBuilderError {
+    /// Block index out of range error
+    ///
+    /// This error is triggered when a block is accessed by an index that is
+    /// out of range of the blocks in a content document.
+    #[error("The block index {index} is out of range for {len} blocks.")]
+    BlockIndexOutOfRange { index: usize, len: usize },
+
     /// Illegal manifest path error
     ///
     /// This error is triggered when the path corresponding to a resource ID
@@ -288,6 +583,13 @@ pub enum EpubBuilderError {
     )]
     IllegalManifestPath { manifest_id: String },
 
+    /// Generated malformed XML error
+    ///
+    /// This error is triggered when a generated document is not well-formed XML,
+    /// which usually happens when raw markup such as MathML is unbalanced.
+    #[error("The generated document is not well-formed XML: {error}")]
+    GeneratedMalformedXml { error: String },
+
     /// Invalid rootfile path error
     ///
     /// This error is triggered when the rootfile path in the container.xml is invalid.
@@ -296,11 +598,26 @@ pub enum EpubBuilderError {
     #[error("A rootfile path should be a relative path and not start with '../'.")]
     IllegalRootfilePath,
 
+    /// Invalid footnote byte offset error
+    ///
+    /// This error is triggered when the byte offset of a footnote anchor
+    /// does not fall on a character boundary of the text.
+    #[error("The footnote byte offset {offset} is not on a character boundary.")]
+    InvalidFootnoteByteOffset { offset: usize },
+
     /// Invalid footnote locate error
     ///
-    /// This error is triggered when the footnote locate is out of range.
-    #[error("The footnote locate must be in the range of [0, {max_locate}].")]
-    InvalidFootnoteLocate { max_locate: usize },
+    /// This error is triggered when the footnote locate is out of range. `max_locate`
+    /// is counted in the same `unit` as the rejected locate, such as `characters`
+    /// or `words`.
+    #[error("The footnote locate must be in the range of [0, {max_locate}] {unit}.")]
+    InvalidFootnoteLocate { max_locate: usize, unit: String },
+
+    /// Invalid html fragment error
+    ///
+    /// This error is triggered when a raw html fragment is not well-formed.
+    #[error("The html fragment is not well-formed: {error}")]
+    InvalidHtmlFragment { error: String },
 
     /// Invalid mathml format error
     ///
@@ -308,6 +625,13 @@ pub enum EpubBuilderError {
     #[error("{error}")]
     InvalidMathMLFormat { error: String },
 
+    /// Invalid modification time error
+    ///
+    /// This error is triggered when a modification time cannot be stored
+    /// in a zip archive, which only supports the years 1980 to 2107.
+    #[error("The modification time '{time}' is out of the range supported by zip archives.")]
+    InvalidModificationTime { time: String },
+
     /// Invalid target path error
     ///
     /// This error is triggered when the target path terminates in a root or prefix,
@@ -486,6 +810,102 @@ mod from_trait_tests {
         }
     }
 
+    #[test]
+    fn test_error_source() {
+        use std::error::Error as _;
+
+        let io_err = io::Error::new(io::ErrorKind::NotFound, "file not found");
+        let epub_err: EpubError = io_err.into();
+        let source = epub_err.source();
+        assert!(source.is_some());
+        assert_eq!(source.unwrap().to_string(), "file not found");
+
+        let epub_err: EpubError = ZipError::FileNotFound.into();
+        assert_eq!(
+            epub_err.source().unwrap().to_string(),
+            ZipError::FileNotFound.to_string()
+        );
+
+        let utf8_err = String::from_utf8(vec![0x80, 0x81]).unwrap_err();
+        let message = utf8_err.to_string();
+        let epub_err: EpubError = utf8_err.into();
+        assert_eq!(epub_err.source().unwrap().to_string(), message);
+
+        let utf16_err = String::from_utf16(&[0xD800]).unwrap_err();
+        let message = utf16_err.to_string();
+        let epub_err: EpubError = utf16_err.into();
+        assert_eq!(epub_err.source().unwrap().to_string(), message);
+
+        let io_err = io::Error::new(io::ErrorKind::InvalidData, "xml parse error");
+        let epub_err: EpubError = quick_xml::Error::Io(io_err.into()).into();
+        assert!(
+            epub_err
+                .source()
+                .unwrap()
+                .to_string()
+                .contains("xml parse error")
+        );
+
+        // the chain is followed through the fallback error
+        let fallback_err = FallbackError {
+            tried: vec![],
+            source: io::Error::new(io::ErrorKind::NotFound, "file not found").into(),
+        };
+        let source = fallback_err.source().unwrap();
+        assert!(source.to_string().contains("file not found"));
+        assert_eq!(source.source().unwrap().to_string(), "file not found");
+
+        assert!(EpubError::EmptyDataError.source().is_none());
+    }
+
+    #[test]
+    fn test_partial_eq() {
+        let l_error = EpubError::from(io::Error::new(io::ErrorKind::NotFound, "missing file"));
+        let r_error = EpubError::from(io::Error::new(io::ErrorKind::NotFound, "missing file"));
+        assert_eq!(l_error, r_error);
+
+        let other_kind = EpubError::from(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "missing file",
+        ));
+        assert_ne!(l_error, other_kind);
+
+        let other_message = EpubError::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_ne!(l_error, other_message);
+
+        assert_eq!(
+            EpubError::from(ZipError::FileNotFound),
+            EpubError::from(ZipError::FileNotFound)
+        );
+        assert_ne!(
+            EpubError::from(ZipError::FileNotFound),
+            EpubError::from(ZipError::InvalidArchive("bad".into()))
+        );
+
+        assert_eq!(EpubError::EmptyDataError, EpubError::EmptyDataError);
+        assert_ne!(EpubError::EmptyDataError, EpubError::MutexError);
+        assert_ne!(
+            EpubError::ResourceIdNotExist { id: "a".to_string() },
+            EpubError::ResourceIdNotExist { id: "b".to_string() }
+        );
+        assert_ne!(l_error, EpubError::from(ZipError::FileNotFound));
+    }
+
+    #[test]
+    fn test_xml_position_from_offset() {
+        let content = "<a>\n  <b>\n</a>";
+
+        let position = XmlPosition::from_offset(content, 0);
+        assert_eq!((position.line, position.column), (1, 1));
+
+        let position = XmlPosition::from_offset(content, 6);
+        assert_eq!((position.line, position.column), (2, 3));
+
+        let position = XmlPosition::from_offset(content, 100);
+        assert_eq!((position.line, position.column), (3, 5));
+        assert_eq!(position.to_string(), "line 3, column 5 (byte 100)");
+    }
+
     #[cfg(feature = "builder")]
     #[test]
     fn test_from_epub_builder_error() {
@@ -51,6 +51,8 @@
 //! - `no-indexmap`: Remove the dependency on the external crate `IndexMap`. This dependency
 //!   is primarily used to ensure the order of resources in the manifest, as recommended
 //!   by the EPUB specification.
+//! - `image`: Enable `EpubDoc::cover_thumbnail`, which decodes and scales the cover
+//!   with the external crate `image`.
 
 pub(crate) mod utils;
 
@@ -60,4 +62,7 @@ pub mod epub;
 pub mod error;
 pub mod types;
 
-pub use utils::DecodeBytes;
+pub use utils::{
+    DecodeBytes, adobe_obfuscation_key, apply_adobe_obfuscation, apply_idpf_obfuscation,
+    idpf_obfuscation_key,
+};
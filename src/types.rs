@@ -14,7 +14,7 @@
 //! Many of these types implement a builder pattern for easier construction when the
 //! `builder` feature is enabled. See individual type documentation for details.
 
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 #[cfg(feature = "builder")]
 use crate::{
@@ -25,12 +25,80 @@ use crate::{
 /// Represents the EPUB version
 ///
 /// This enum is used to distinguish between different versions of the EPUB specification.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EpubVersion {
     Version2_0,
     Version3_0,
 }
 
+/// Represents how strictly an EPUB file is parsed
+///
+/// Real-world EPUB files frequently contain small specification violations that
+/// reading systems silently tolerate. This enum decides whether `EpubDoc` rejects
+/// such files or recovers from them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Recoverable violations are reported as errors
+    Strict,
+
+    /// Recoverable violations are repaired the way reading systems do,
+    /// and a warning is recorded in `EpubDoc::warnings`
+    #[default]
+    Lenient,
+}
+
+/// Represents how a page of a fixed-layout publication is placed in a spread
+///
+/// Resolved by `EpubDoc::page_spread` from the `rendition:spread` setting and the
+/// `page-spread-*` properties of the spine item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSpread {
+    /// The reading system places the page in the spread
+    Auto,
+
+    /// The page is placed on the left side of the spread
+    Left,
+
+    /// The page is placed on the right side of the spread
+    Right,
+
+    /// The page is centered across the spread
+    Center,
+
+    /// The page is not placed in a spread
+    None,
+}
+
+/// Callback used to retrieve the content of a remote resource by its URL
+pub type RemoteFetcher =
+    Arc<dyn Fn(&str) -> Result<Vec<u8>, crate::error::EpubError> + Send + Sync>;
+
+/// Represents how resources located outside of the EPUB container are retrieved
+///
+/// EPUB 3 allows some resources, typically audio and video, to be hosted remotely.
+/// Such manifest items have an absolute URL as their `href`, and content documents
+/// that use them carry the `remote-resources` property. This library performs no
+/// network access on its own, so fetching remote resources is refused unless the
+/// caller provides a fetcher.
+#[derive(Clone, Default)]
+pub enum FetchPolicy {
+    /// Retrieving a remote resource fails with `EpubError::RemoteResource`
+    #[default]
+    Refuse,
+
+    /// Remote resources are retrieved by calling the given fetcher with their URL
+    Fetch(RemoteFetcher),
+}
+
+impl std::fmt::Debug for FetchPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchPolicy::Refuse => write!(f, "Refuse"),
+            FetchPolicy::Fetch(_) => write!(f, "Fetch(..)"),
+        }
+    }
+}
+
 /// Represents a metadata item in the EPUB publication
 ///
 /// The `MetadataItem` structure represents a single piece of metadata from the EPUB publication.
@@ -86,6 +154,60 @@ pub struct MetadataItem {
     pub refined: Vec<MetadataRefinement>,
 }
 
+impl MetadataItem {
+    /// Returns the value of the metadata item to display in the preferred language
+    ///
+    /// When the preferred language differs from the language of the item, the value of
+    /// an `alternate-script` refinement in the preferred language is returned, such as
+    /// the Japanese script of a title given in romaji.
+    ///
+    /// ## Parameters
+    /// - `preferred_lang` - The preferred language code (e.g., "ja", "en-US"), or `None`
+    ///
+    /// ## Return
+    /// - `&str` - The value of the matching `alternate-script` refinement, or the value
+    ///   of the item itself if there is none
+    ///
+    /// ## Notes
+    /// - Language codes are compared case-insensitively. An exact match is preferred,
+    ///   otherwise codes with the same primary subtag match, e.g. "ja" and "ja-Jpan".
+    pub fn display_value(&self, preferred_lang: Option<&str>) -> &str {
+        let Some(preferred_lang) = preferred_lang else {
+            return &self.value;
+        };
+        if self
+            .lang
+            .as_deref()
+            .is_some_and(|lang| lang.eq_ignore_ascii_case(preferred_lang))
+        {
+            return &self.value;
+        }
+
+        fn primary_subtag(lang: &str) -> &str {
+            lang.split('-').next().unwrap_or(lang)
+        }
+
+        let alternates = self
+            .refined
+            .iter()
+            .filter(|refinement| refinement.property == "alternate-script")
+            .filter_map(|refinement| Some((refinement.lang.as_deref()?, &refinement.value)))
+            .collect::<Vec<_>>();
+
+        alternates
+            .iter()
+            .find(|(lang, _)| lang.eq_ignore_ascii_case(preferred_lang))
+            .or_else(|| {
+                let preferred = primary_subtag(preferred_lang);
+                alternates
+                    .iter()
+                    .find(|(lang, _)| primary_subtag(lang).eq_ignore_ascii_case(preferred))
+            })
+            .map(|(_, value)| value.as_str())
+            .unwrap_or(&self.value)
+    }
+}
+
 #[cfg(feature = "builder")]
 impl MetadataItem {
     /// Creates a new metadata item with the given property and value
@@ -105,6 +227,38 @@ impl MetadataItem {
         }
     }
 
+    /// Creates a new package-level `<meta>` metadata item
+    ///
+    /// Unlike `new`, which creates a Dublin Core element for the DC element names, this
+    /// function always creates an item that is serialized as a `<meta property="...">`
+    /// element, such as `dcterms:modified` or `schema:accessMode`.
+    ///
+    /// Requires the `builder` feature.
+    ///
+    /// ## Parameters
+    /// - `property` - The meta property, usually with a prefix (e.g., "schema:accessMode")
+    /// - `value` - The metadata value
+    ///
+    /// ## Notes
+    /// - A bare Dublin Core element name (e.g., "title") is placed in the reserved
+    ///   `dcterms:` prefix, because EPUB 3 only expresses the DC elements themselves
+    ///   as `dc:` elements.
+    pub fn meta(property: &str, value: &str) -> Self {
+        let property = if ELEMENT_IN_DC_NAMESPACE.contains(&property) {
+            format!("dcterms:{}", property)
+        } else {
+            property.to_string()
+        };
+
+        Self {
+            id: None,
+            property,
+            value: value.to_string(),
+            lang: None,
+            refined: vec![],
+        }
+    }
+
     /// Sets the ID of the metadata item
     ///
     /// Requires the `builder` feature.
@@ -295,7 +449,22 @@ impl MetadataRefinement {
 ///
 /// Link metadata items are defined in the OPF file using `<link>` elements in the metadata
 /// section and follow the EPUB 3.0 metadata link specification.
-#[derive(Debug)]
+///
+/// ## Builder Methods
+///
+/// When the `builder` feature is enabled, this struct provides convenient builder methods:
+///
+/// ```rust
+/// # #[cfg(feature = "builder")] {
+/// use lib_epub::types::MetadataLinkItem;
+///
+/// let link = MetadataLinkItem::new("https://example.org/onix.xml", "record")
+///     .with_media_type("application/xml")
+///     .with_properties("onix")
+///     .build();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
 pub struct MetadataLinkItem {
     /// The URI of the linked resource
     pub href: String,
@@ -323,10 +492,147 @@ pub struct MetadataLinkItem {
     /// Optional reference to another metadata item
     ///
     /// In EPUB 3.0, links can refine other metadata items. This field contains the ID
-    /// of the metadata item that this link refines, prefixed with "#".
+    /// of the metadata item that this link refines, without the leading "#" of the
+    /// `refines` attribute.
     pub refines: Option<String>,
 }
 
+#[cfg(feature = "builder")]
+impl MetadataLinkItem {
+    /// Creates a new metadata link item
+    ///
+    /// Requires the `builder` feature.
+    ///
+    /// ## Parameters
+    /// - `href` - The URI of the linked resource
+    /// - `rel` - The relationship of the linked resource, such as "record"
+    pub fn new(href: &str, rel: &str) -> Self {
+        Self {
+            href: href.to_string(),
+            rel: rel.to_string(),
+            hreflang: None,
+            id: None,
+            mime: None,
+            properties: None,
+            refines: None,
+        }
+    }
+
+    /// Sets the ID of the link
+    ///
+    /// Requires the `builder` feature.
+    ///
+    /// ## Parameters
+    /// - `id` - The ID to assign to this link
+    pub fn with_id(&mut self, id: &str) -> &mut Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Sets the MIME type of the linked resource
+    ///
+    /// Requires the `builder` feature.
+    ///
+    /// ## Parameters
+    /// - `media_type` - The MIME type, such as "application/xml"
+    pub fn with_media_type(&mut self, media_type: &str) -> &mut Self {
+        self.mime = Some(media_type.to_string());
+        self
+    }
+
+    /// Sets the properties of the link
+    ///
+    /// Requires the `builder` feature.
+    ///
+    /// ## Parameters
+    /// - `properties` - Space-separated properties, such as "onix"
+    pub fn with_properties(&mut self, properties: &str) -> &mut Self {
+        self.properties = Some(properties.to_string());
+        self
+    }
+
+    /// Sets the language of the linked resource
+    ///
+    /// Requires the `builder` feature.
+    ///
+    /// ## Parameters
+    /// - `hreflang` - The language code (e.g., "en", "fr", "zh-CN")
+    pub fn with_hreflang(&mut self, hreflang: &str) -> &mut Self {
+        self.hreflang = Some(hreflang.to_string());
+        self
+    }
+
+    /// Sets the metadata item refined by the link
+    ///
+    /// Requires the `builder` feature.
+    ///
+    /// ## Parameters
+    /// - `refines` - The ID of the refined metadata item, with or without a leading "#"
+    pub fn with_refines(&mut self, refines: &str) -> &mut Self {
+        self.refines = Some(refines.strip_prefix('#').unwrap_or(refines).to_string());
+        self
+    }
+
+    /// Builds the final metadata link item
+    ///
+    /// Requires the `builder` feature.
+    pub fn build(&self) -> Self {
+        Self { ..self.clone() }
+    }
+
+    /// Gets the XML attributes for this link
+    ///
+    /// The `refines` attribute is not included, since it must be written with a
+    /// leading "#" that the `refines` field does not store.
+    pub(crate) fn attributes(&self) -> Vec<(&str, &str)> {
+        let mut attributes = vec![("href", self.href.as_str()), ("rel", self.rel.as_str())];
+
+        if let Some(hreflang) = &self.hreflang {
+            attributes.push(("hreflang", hreflang.as_str()));
+        }
+
+        if let Some(id) = &self.id {
+            attributes.push(("id", id.as_str()));
+        }
+
+        if let Some(mime) = &self.mime {
+            attributes.push(("media-type", mime.as_str()));
+        }
+
+        if let Some(properties) = &self.properties {
+            attributes.push(("properties", properties.as_str()));
+        }
+
+        attributes
+    }
+}
+
+/// A summary of the information commonly displayed for a publication
+///
+/// This struct gathers, in a single value, the metadata that reading systems usually
+/// show in a list of publications, together with the cover and the number of chapters.
+/// Where the metadata allows several values, only the first one is kept, except
+/// for the authors.
+#[derive(Debug, Clone)]
+pub struct BookSummary {
+    /// The first title of the publication
+    pub title: String,
+    /// The creators of the publication, in the order they appear in the metadata
+    pub authors: Vec<String>,
+    /// The first language of the publication
+    pub language: Option<String>,
+    /// The unique identifier of the publication
+    pub identifier: String,
+    /// The first description of the publication
+    pub description: Option<String>,
+    /// The first publisher of the publication
+    pub publisher: Option<String>,
+    /// The cover data and its MIME type
+    pub cover: Option<(Vec<u8>, String)>,
+    /// The number of spine items
+    pub chapter_count: usize,
+}
+
 /// A unified metadata sheet for EPUB publications
 ///
 /// This struct provides a simplified, high-level interface for accessing EPUB metadata.
@@ -930,6 +1236,224 @@ pub struct EncryptionData {
     pub data: String,
 }
 
+/// Represents a rendition of the publication declared in the container
+///
+/// Each `rootfile` element of the META-INF/container.xml file declares a rendition,
+/// that is a package document. Multiple-rendition publications describe how their
+/// renditions differ with the `rendition:*` attributes of the rootfiles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenditionInfo {
+    /// The path to the package document of the rendition within the EPUB container
+    pub full_path: PathBuf,
+
+    /// The media type of the package document, normally `application/oebps-package+xml`
+    pub media_type: Option<String>,
+
+    /// The media query describing the devices the rendition is intended for,
+    /// from the `rendition:media` attribute
+    pub media: Option<String>,
+
+    /// The layout of the rendition, `reflowable` or `pre-paginated`,
+    /// from the `rendition:layout` attribute
+    pub layout: Option<String>,
+
+    /// The language of the rendition, from the `rendition:language` attribute
+    pub language: Option<String>,
+
+    /// The access mode of the rendition, such as `textual` or `visual`,
+    /// from the `rendition:accessMode` attribute
+    pub access_mode: Option<String>,
+
+    /// The human-readable name of the rendition, from the `rendition:label` attribute
+    pub label: Option<String>,
+}
+
+/// Represents a resource reference of a digital signature
+///
+/// This structure holds one `<Reference>` of a signature in the META-INF/signatures.xml
+/// file, as defined by the OCF specification, together with the algorithms used by the
+/// signature it belongs to. The signature itself is not verified.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    /// The URI of the signed resource
+    ///
+    /// This field contains the path/URI to the signed resource within the EPUB container,
+    /// or a same-document reference such as `#manifest` to an element of signatures.xml.
+    pub reference_uri: String,
+
+    /// The signature algorithm URI, such as <http://www.w3.org/2000/09/xmldsig#rsa-sha1>
+    pub signature_method: String,
+
+    /// The digest algorithm URI, such as <http://www.w3.org/2000/09/xmldsig#sha1>
+    pub digest_method: String,
+}
+
+/// Options controlling a full-text search of a publication
+///
+/// Used by `EpubDoc::search`. By default, the search is case-sensitive, matches
+/// substrings anywhere in words, and keeps 30 characters of context on each side
+/// of a match in the snippet.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    /// Whether letters are compared without regard to case
+    pub case_insensitive: bool,
+
+    /// Whether a match must not be preceded or followed by a letter or digit
+    pub whole_word: bool,
+
+    /// The number of characters kept on each side of a match in the snippet
+    pub context: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            case_insensitive: false,
+            whole_word: false,
+            context: 30,
+        }
+    }
+}
+
+/// Represents a match found by a full-text search of a publication
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    /// The index of the spine item containing the match
+    pub spine_index: usize,
+
+    /// The offset of the match, in characters, within the plain text of the spine item
+    ///
+    /// The plain text is the one returned by `EpubDoc::get_chapter_text`.
+    pub char_offset: usize,
+
+    /// The matched text together with its surrounding context, on a single line
+    pub snippet: String,
+}
+
+/// Represents a linear chapter of a publication together with its text
+///
+/// Chapters are produced by `EpubDoc::chapters`, one for each linear spine item.
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    /// The index of the spine item, starting from 0
+    pub spine_index: usize,
+
+    /// The label of the first catalog entry linking to the spine item, if any
+    pub title: Option<String>,
+
+    /// The raw data of the content document
+    pub html: Vec<u8>,
+
+    /// The plain text of the content document
+    ///
+    /// The plain text is the same as the one returned by `EpubDoc::get_chapter_text`.
+    pub text: String,
+}
+
+/// Represents a linear spine item in the reading order of a publication
+///
+/// Reading units are produced by `EpubDoc::reading_order`, and link the physical
+/// reading order of the spine to the logical structure of the catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadingUnit {
+    /// The index of the spine item, starting from 0
+    pub spine_index: usize,
+
+    /// The id of the manifest item referenced by the spine item
+    pub manifest_id: String,
+
+    /// The label of the first catalog entry linking to the spine item, if any
+    pub title: Option<String>,
+
+    /// The depth of the first catalog entry linking to the spine item, starting from 0
+    ///
+    /// It is `None` if no catalog entry links to the spine item.
+    pub toc_depth: Option<usize>,
+}
+
+/// Represents the reading order of a publication
+///
+/// The reading order walks the linear spine items in order, annotating each of them
+/// with the catalog entry that links to it, so that a continuous reading flow and a
+/// table of contents synced with it can be rendered from the same structure.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReadingOrder {
+    /// The reading units, in spine order
+    pub units: Vec<ReadingUnit>,
+}
+
+impl ReadingOrder {
+    /// Returns the number of reading units
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    /// Checks whether the reading order has no reading units
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+
+    /// Iterates over the reading units in spine order
+    pub fn iter(&self) -> std::slice::Iter<'_, ReadingUnit> {
+        self.units.iter()
+    }
+
+    /// Retrieves the position of the reading unit of a spine item
+    ///
+    /// ## Parameters
+    /// - `spine_index`: The index of the spine item, starting from 0
+    ///
+    /// ## Return
+    /// - `Some(usize)`: The position of the reading unit in `units`
+    /// - `None`: The spine item is not linear, or the index is out of range
+    pub fn position(&self, spine_index: usize) -> Option<usize> {
+        self.units
+            .iter()
+            .position(|unit| unit.spine_index == spine_index)
+    }
+}
+
+/// Represents a location in a publication addressed by an EPUB CFI
+///
+/// An EPUB Canonical Fragment Identifier, such as `epubcfi(/6/4[chap01ref]!/4/2/1:0)`,
+/// consists of a path through the package document to a spine item, followed by a
+/// path through the content document referenced by that spine item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CfiLocation {
+    /// The index of the referenced spine item, starting from 0
+    pub spine_index: usize,
+
+    /// The steps of the path in the content document
+    ///
+    /// The steps are kept as they appear in the CFI: even steps address elements,
+    /// and odd steps address the text between them. For example, `/4/2/1` is
+    /// represented as `[4, 2, 1]`.
+    pub element_path: Vec<usize>,
+
+    /// The character offset in the addressed text, if one is present
+    pub char_offset: Option<usize>,
+}
+
+/// Represents a reference in the `<guide>` element of an EPUB 2 package document
+///
+/// The guide identifies fundamental structural components of the publication, such as
+/// the cover page or the table of contents. It is deprecated since EPUB 3, but is still
+/// widely present in EPUB 2 publications and in EPUB 3 publications kept compatible with them.
+#[derive(Debug, Clone)]
+pub struct GuideReference {
+    /// The type of the referenced component, such as `cover`, `toc` or `text`
+    pub reference_type: String,
+
+    /// Optional human-readable title of the reference
+    pub title: Option<String>,
+
+    /// The path of the referenced resource
+    ///
+    /// The path is relative to the root of the EPUB container, and any fragment
+    /// identifier of the original `href` is removed.
+    pub href: PathBuf,
+}
+
 /// Represents a navigation point in an EPUB document's table of contents
 ///
 /// The `NavPoint` structure represents a single entry in the hierarchical table of contents
@@ -975,6 +1499,24 @@ pub struct NavPoint {
     /// It can be `None` for navigation points that no relevant information was
     /// provided in the original data.
     pub play_order: Option<usize>,
+
+    /// The position of this navigation point among its siblings in the source document
+    ///
+    /// Navigation points with the same play order are sorted by this index, so that
+    /// the original document order is preserved when play orders are missing.
+    pub(crate) doc_index: usize,
+}
+
+impl NavPoint {
+    /// Retrieves the position of this navigation point among its siblings
+    ///
+    /// The position is counted in the source document, or in the order the children
+    /// were added when the navigation point is built. Navigation points with the same
+    /// play order are sorted by this position.
+    #[inline]
+    pub fn doc_index(&self) -> usize {
+        self.doc_index
+    }
 }
 
 #[cfg(feature = "builder")]
@@ -991,6 +1533,7 @@ impl NavPoint {
             content: None,
             children: vec![],
             play_order: None,
+            doc_index: 0,
         }
     }
 
@@ -1011,7 +1554,11 @@ impl NavPoint {
     ///
     /// ## Parameters
     /// - `child` - The child navigation point to add
-    pub fn append_child(&mut self, child: NavPoint) -> &mut Self {
+    ///
+    /// ## Notes
+    /// - The `doc_index` of the child is set to its position among the children.
+    pub fn append_child(&mut self, mut child: NavPoint) -> &mut Self {
+        child.doc_index = self.children.len();
         self.children.push(child);
         self
     }
@@ -1022,7 +1569,13 @@ impl NavPoint {
     ///
     /// ## Parameters
     /// - `children` - Vector of child navigation points
-    pub fn set_children(&mut self, children: Vec<NavPoint>) -> &mut Self {
+    ///
+    /// ## Notes
+    /// - The `doc_index` of each child is set to its position in `children`.
+    pub fn set_children(&mut self, mut children: Vec<NavPoint>) -> &mut Self {
+        for (doc_index, child) in children.iter_mut().enumerate() {
+            child.doc_index = doc_index;
+        }
         self.children = children;
         self
     }
@@ -1037,7 +1590,7 @@ impl NavPoint {
 
 impl Ord for NavPoint {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.play_order.cmp(&other.play_order)
+        (self.play_order, self.doc_index).cmp(&(other.play_order, other.doc_index))
     }
 }
 
@@ -1049,7 +1602,92 @@ impl PartialOrd for NavPoint {
 
 impl PartialEq for NavPoint {
     fn eq(&self, other: &Self) -> bool {
-        self.play_order == other.play_order
+        self.play_order == other.play_order && self.doc_index == other.doc_index
+    }
+}
+
+/// Represents a problem with the `playOrder` values of NCX navigation points
+///
+/// Each issue names the navigation point by its label. In `ParseMode::Lenient` the
+/// play orders are reassigned by document order when any issue is found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PlayOrderIssue {
+    /// The navigation point has no `playOrder` attribute
+    Missing { label: String },
+
+    /// The play order is already used by a preceding navigation point
+    Duplicate { label: String, play_order: usize },
+
+    /// The play order is lower than the play order of a preceding navigation point
+    OutOfOrder {
+        label: String,
+        play_order: usize,
+        preceding: usize,
+    },
+}
+
+impl std::fmt::Display for PlayOrderIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayOrderIssue::Missing { label } => {
+                write!(
+                    f,
+                    "NavPoint '{}' is missing the playOrder attribute.",
+                    label
+                )
+            }
+            PlayOrderIssue::Duplicate { label, play_order } => write!(
+                f,
+                "NavPoint '{}' has a duplicate playOrder {}.",
+                label, play_order
+            ),
+            PlayOrderIssue::OutOfOrder { label, play_order, preceding } => write!(
+                f,
+                "NavPoint '{}' has playOrder {}, which is lower than the preceding playOrder {}.",
+                label, play_order, preceding
+            ),
+        }
+    }
+}
+
+/// Represents a problem found when validating a publication being built
+///
+/// Unlike build errors, these issues do not prevent the publication from being built,
+/// but the resulting publication does not conform to the EPUB specification.
+#[cfg(feature = "builder")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationIssue {
+    /// A manifest item uses a foreign media type without a fallback
+    ///
+    /// Resources whose media type is not an EPUB core media type should provide a
+    /// fallback, so that reading systems can always render them.
+    ForeignResourceWithoutFallback { manifest_id: String, mime: String },
+
+    /// A spine item is not a content document, and has no fallback to one
+    ///
+    /// Resources referenced from the spine that are not XHTML or SVG content documents,
+    /// such as images, must provide a fallback chain that reaches a content document.
+    /// This applies to core media types as well.
+    SpineItemNotContentDocument { manifest_id: String, mime: String },
+}
+
+#[cfg(feature = "builder")]
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::ForeignResourceWithoutFallback { manifest_id, mime } => write!(
+                f,
+                "The manifest item \"{}\" uses the foreign media type \"{}\" without a fallback.",
+                manifest_id, mime
+            ),
+            ValidationIssue::SpineItemNotContentDocument { manifest_id, mime } => write!(
+                f,
+                "The spine item \"{}\" uses the media type \"{}\" without a fallback to a content document.",
+                manifest_id, mime
+            ),
+        }
     }
 }
 
@@ -1061,7 +1699,7 @@ impl PartialEq for NavPoint {
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Footnote {
     /// The position/location of the footnote reference in the content
-    pub locate: usize,
+    pub locate: FootnoteAnchor,
 
     /// The text content of the footnote
     pub content: String,
@@ -1081,6 +1719,78 @@ impl PartialOrd for Footnote {
     }
 }
 
+/// Represents the position of a footnote reference in a piece of text
+///
+/// The footnote reference is inserted right after the position described by the
+/// anchor. All anchors are resolved to a character position when the content
+/// document is built, so positions reported by different tools can be used as is.
+/// The resolved position is always on a grapheme cluster boundary.
+#[cfg(feature = "content-builder")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum FootnoteAnchor {
+    /// Number of grapheme clusters before the footnote reference, starting from 1
+    ///
+    /// This is the anchor created from a plain `usize`. Since a grapheme cluster is
+    /// what a reader perceives as a single character, the footnote reference never
+    /// breaks up combining marks or emoji sequences.
+    GraphemeIndex(usize),
+
+    /// Number of characters before the footnote reference, starting from 1
+    ///
+    /// Unlike `GraphemeIndex`, this anchor counts Unicode scalar values. A position
+    /// inside a grapheme cluster is moved to the end of that cluster.
+    CharIndex(usize),
+
+    /// Number of bytes of the UTF-8 text before the footnote reference, starting from 1
+    ///
+    /// The offset must be on a character boundary. An offset inside a grapheme
+    /// cluster is moved to the end of that cluster.
+    ByteOffset(usize),
+
+    /// Number of words before the footnote reference, starting from 1
+    ///
+    /// Words are separated by whitespace, and the footnote reference is placed right
+    /// after the last character of the word.
+    WordIndex(usize),
+}
+
+#[cfg(feature = "content-builder")]
+impl FootnoteAnchor {
+    /// Returns the name of the unit the anchor counts in
+    ///
+    /// The unit is used to report the valid range of an anchor, such as `characters`
+    /// for `FootnoteAnchor::CharIndex`.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            FootnoteAnchor::GraphemeIndex(_) => "graphemes",
+            FootnoteAnchor::CharIndex(_) => "characters",
+            FootnoteAnchor::ByteOffset(_) => "bytes",
+            FootnoteAnchor::WordIndex(_) => "words",
+        }
+    }
+}
+
+#[cfg(feature = "content-builder")]
+impl From<usize> for FootnoteAnchor {
+    fn from(value: usize) -> Self {
+        FootnoteAnchor::GraphemeIndex(value)
+    }
+}
+
+/// Represents a run of text in a ruby annotated paragraph
+///
+/// A run with an annotation is rendered as `<ruby>base<rt>annotation</rt></ruby>`,
+/// while a run without an annotation is rendered as plain text.
+#[cfg(feature = "content-builder")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RubyRun {
+    /// The base text of the run
+    pub base: String,
+
+    /// The ruby annotation displayed alongside the base text
+    pub annotation: Option<String>,
+}
+
 /// Represents the type of a block element in the content document
 #[cfg(feature = "content-builder")]
 #[derive(Debug, Copy, Clone)]
@@ -1096,6 +1806,18 @@ pub enum BlockType {
     /// with visual distinction from regular paragraphs.
     Quote,
 
+    /// An aside block
+    ///
+    /// Represents content that is tangentially related to the main text,
+    /// such as sidebars and pull-quotes.
+    Aside,
+
+    /// A ruby annotated paragraph block
+    ///
+    /// Contains text runs where some runs carry ruby annotations, such as
+    /// furigana for Japanese or pinyin for Chinese text.
+    Ruby,
+
     /// A title or heading block
     ///
     /// Represents chapter or section titles with appropriate heading styling.
@@ -1129,6 +1851,8 @@ impl std::fmt::Display for BlockType {
         match self {
             BlockType::Text => write!(f, "Text"),
             BlockType::Quote => write!(f, "Quote"),
+            BlockType::Aside => write!(f, "Aside"),
+            BlockType::Ruby => write!(f, "Ruby"),
             BlockType::Title => write!(f, "Title"),
             BlockType::Image => write!(f, "Image"),
             BlockType::Audio => write!(f, "Audio"),
@@ -1138,6 +1862,33 @@ impl std::fmt::Display for BlockType {
     }
 }
 
+/// Represents the level of a heading, from `h1` to `h6`
+#[cfg(feature = "content-builder")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeadingLevel {
+    H1,
+    H2,
+    H3,
+    H4,
+    H5,
+    H6,
+}
+
+#[cfg(feature = "content-builder")]
+impl HeadingLevel {
+    /// Returns the numeric level of the heading, from 1 to 6
+    pub fn level(&self) -> usize {
+        match self {
+            HeadingLevel::H1 => 1,
+            HeadingLevel::H2 => 2,
+            HeadingLevel::H3 => 3,
+            HeadingLevel::H4 => 4,
+            HeadingLevel::H5 => 5,
+            HeadingLevel::H6 => 6,
+        }
+    }
+}
+
 /// Configuration options for document styling
 ///
 /// This struct aggregates all style-related configuration for an EPUB document,
@@ -1191,6 +1942,26 @@ impl StyleOptions {
     }
 }
 
+/// Represents a font embedded in a content document
+///
+/// Each font face is written to the generated stylesheet as an `@font-face` rule,
+/// and the font file is copied next to the content document when it is built.
+#[cfg(feature = "content-builder")]
+#[derive(Debug, Clone)]
+pub struct FontFace {
+    /// The font family name used to reference the font in CSS
+    pub family: String,
+
+    /// The path to the font file
+    pub src: PathBuf,
+
+    /// The font weight this font file provides, such as "bold" or "700"
+    pub weight: Option<String>,
+
+    /// The font style this font file provides, such as "italic"
+    pub style: Option<String>,
+}
+
 /// Text styling configuration
 ///
 /// Defines the visual appearance of text content in the document,
@@ -1198,19 +1969,29 @@ impl StyleOptions {
 #[cfg(feature = "content-builder")]
 #[derive(Debug, Clone)]
 pub struct TextStyle {
-    /// The base font size (default: 1.0, unit: rem)
+    /// The base font size (default: 1.0)
     ///
-    /// Relative to the root element, providing consistent sizing
-    /// across different viewing contexts.
+    /// The value is expressed in `font_size_unit`. With the default `rem` unit, it is
+    /// relative to the root element, providing consistent sizing across different
+    /// viewing contexts.
     pub font_size: f32,
 
-    /// The line height (default: 1.6, unit: em)
+    /// The unit of the font size (default: SizeUnit::Rem)
+    pub font_size_unit: SizeUnit,
+
+    /// The line height (default: 1.6)
     ///
     /// Controls the vertical spacing between lines of text.
     /// Values greater than 1.0 increase spacing, while values
     /// less than 1.0 compress the text.
     pub line_height: f32,
 
+    /// The unit of the line height (default: Some(SizeUnit::Em))
+    ///
+    /// `None` writes a unitless line height, which is multiplied by the font size of
+    /// each element instead of being computed once and inherited.
+    pub line_height_unit: Option<SizeUnit>,
+
     /// The font family stack (default: "-apple-system, Roboto, sans-serif")
     ///
     /// A comma-separated list of font families to use, with
@@ -1247,7 +2028,9 @@ impl Default for TextStyle {
     fn default() -> Self {
         Self {
             font_size: 1.0,
+            font_size_unit: SizeUnit::Rem,
             line_height: 1.6,
+            line_height_unit: Some(SizeUnit::Em),
             font_family: "-apple-system, Roboto, sans-serif".to_string(),
             font_weight: "normal".to_string(),
             font_style: "normal".to_string(),
@@ -1270,12 +2053,24 @@ impl TextStyle {
         self
     }
 
+    /// Sets the unit of the font size
+    pub fn with_font_size_unit(&mut self, unit: SizeUnit) -> &mut Self {
+        self.font_size_unit = unit;
+        self
+    }
+
     /// Sets the line height
     pub fn with_line_height(&mut self, line_height: f32) -> &mut Self {
         self.line_height = line_height;
         self
     }
 
+    /// Sets the unit of the line height, `None` for a unitless line height
+    pub fn with_line_height_unit(&mut self, unit: Option<SizeUnit>) -> &mut Self {
+        self.line_height_unit = unit;
+        self
+    }
+
     /// Sets the font family
     pub fn with_font_family(&mut self, font_family: &str) -> &mut Self {
         self.font_family = font_family.to_string();
@@ -1445,6 +2240,27 @@ impl PageLayout {
     }
 }
 
+/// Footnote rendering modes
+///
+/// Defines how the footnotes of a content document are marked up.
+#[cfg(feature = "content-builder")]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FootnoteMode {
+    /// Footnotes are collected in a list
+    ///
+    /// All footnotes are written as items of a single list at the end of the document,
+    /// and the references link to the list items.
+    #[default]
+    List,
+
+    /// Footnotes are written as popup notes
+    ///
+    /// References are marked with `epub:type="noteref"`, and each footnote is written
+    /// as its own `<aside epub:type="footnote">` element, which lets reading systems
+    /// show the note in a popup instead of jumping to it.
+    Popup,
+}
+
 /// Text alignment options
 ///
 /// Defines the available text alignment modes for content in the document.
@@ -1486,6 +2302,132 @@ impl std::fmt::Display for TextAlign {
     }
 }
 
+/// Units of the lengths written to the generated stylesheet
+#[cfg(feature = "content-builder")]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SizeUnit {
+    /// Pixels
+    Px,
+
+    /// Points, 1/72 of an inch
+    Pt,
+
+    /// Relative to the font size of the element
+    Em,
+
+    /// Relative to the font size of the root element
+    #[default]
+    Rem,
+
+    /// Percentage, relative to the font size of the element for font sizes and line heights
+    Percent,
+}
+
+#[cfg(feature = "content-builder")]
+impl std::fmt::Display for SizeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SizeUnit::Px => write!(f, "px"),
+            SizeUnit::Pt => write!(f, "pt"),
+            SizeUnit::Em => write!(f, "em"),
+            SizeUnit::Rem => write!(f, "rem"),
+            SizeUnit::Percent => write!(f, "%"),
+        }
+    }
+}
+
+/// XML declaration of a content document
+///
+/// Controls the `<?xml ... ?>` declaration written at the top of a generated
+/// content document. The default value produces `<?xml version="1.0" encoding="UTF-8"?>`.
+#[cfg(feature = "content-builder")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlDecl {
+    /// The XML version (default: "1.0")
+    pub version: String,
+
+    /// The encoding declaration (default: "UTF-8")
+    pub encoding: Option<String>,
+
+    /// The standalone declaration, either "yes" or "no" (default: none)
+    pub standalone: Option<String>,
+}
+
+#[cfg(feature = "content-builder")]
+impl Default for XmlDecl {
+    fn default() -> Self {
+        Self {
+            version: "1.0".to_string(),
+            encoding: Some("UTF-8".to_string()),
+            standalone: None,
+        }
+    }
+}
+
+/// Names of the directories holding the resources of a content document
+///
+/// The directories are created next to the generated content document, and the
+/// resources are referenced from the document through them. The default value uses
+/// the `img`, `audio`, `video` and `css` directories.
+#[cfg(feature = "content-builder")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceDirs {
+    /// The directory of images, including the fallback images of MathML blocks
+    pub image: String,
+
+    /// The directory of audio files
+    pub audio: String,
+
+    /// The directory of video files
+    pub video: String,
+
+    /// The directory of CSS files
+    pub css: String,
+}
+
+#[cfg(feature = "content-builder")]
+impl Default for ResourceDirs {
+    fn default() -> Self {
+        Self {
+            image: "img".to_string(),
+            audio: "audio".to_string(),
+            video: "video".to_string(),
+            css: "css".to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "content-builder")]
+impl XmlDecl {
+    /// Creates a new XML declaration with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the XML version
+    pub fn with_version(&mut self, version: &str) -> &mut Self {
+        self.version = version.to_string();
+        self
+    }
+
+    /// Sets the encoding declaration, `None` omits it
+    pub fn with_encoding(&mut self, encoding: Option<&str>) -> &mut Self {
+        self.encoding = encoding.map(str::to_string);
+        self
+    }
+
+    /// Sets the standalone declaration, `None` omits it
+    pub fn with_standalone(&mut self, standalone: Option<&str>) -> &mut Self {
+        self.standalone = standalone.map(str::to_string);
+        self
+    }
+
+    /// Builds the final XML declaration
+    pub fn build(&self) -> Self {
+        Self { ..self.clone() }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     mod navpoint_tests {
@@ -1501,6 +2443,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter1.html")),
                 children: vec![],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             let nav2 = NavPoint {
@@ -1508,6 +2451,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter2.html")),
                 children: vec![],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             let nav3 = NavPoint {
@@ -1515,6 +2459,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter1.html")),
                 children: vec![],
                 play_order: Some(2),
+                doc_index: 0,
             };
 
             assert_eq!(nav1, nav2); // Same play_order, different contents, should be equal
@@ -1529,6 +2474,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter1.html")),
                 children: vec![],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             let nav2 = NavPoint {
@@ -1536,6 +2482,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter2.html")),
                 children: vec![],
                 play_order: Some(2),
+                doc_index: 0,
             };
 
             let nav3 = NavPoint {
@@ -1543,6 +2490,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter3.html")),
                 children: vec![],
                 play_order: Some(3),
+                doc_index: 0,
             };
 
             // Test function cmp
@@ -1569,6 +2517,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter1.html")),
                 children: vec![],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             let nav_without_order = NavPoint {
@@ -1576,6 +2525,7 @@ mod tests {
                 content: Some(PathBuf::from("preface.html")),
                 children: vec![],
                 play_order: None,
+                doc_index: 0,
             };
 
             assert!(nav_without_order < nav_with_order);
@@ -1586,11 +2536,43 @@ mod tests {
                 content: Some(PathBuf::from("intro.html")),
                 children: vec![],
                 play_order: None,
+                doc_index: 0,
             };
 
             assert!(nav_without_order == nav_without_order2);
         }
 
+        /// Test that sorting keeps the document order of siblings with equal play orders
+        #[test]
+        fn test_navpoint_sort_preserves_document_order() {
+            let nav_points = ["Cover", "Preface", "Introduction", "Chapter 1"]
+                .iter()
+                .enumerate()
+                .map(|(doc_index, label)| NavPoint {
+                    label: label.to_string(),
+                    content: None,
+                    children: vec![],
+                    play_order: None,
+                    doc_index,
+                })
+                .collect::<Vec<NavPoint>>();
+
+            let mut sorted = [
+                nav_points[2].clone(),
+                nav_points[0].clone(),
+                nav_points[3].clone(),
+                nav_points[1].clone(),
+            ];
+            sorted.sort();
+
+            let labels = sorted
+                .iter()
+                .map(|nav| nav.label.as_str())
+                .collect::<Vec<&str>>();
+            assert_eq!(labels, ["Cover", "Preface", "Introduction", "Chapter 1"]);
+            assert!(nav_points[0] != nav_points[1]);
+        }
+
         /// Test NavPoint containing child nodes
         #[test]
         fn test_navpoint_with_children() {
@@ -1599,6 +2581,7 @@ mod tests {
                 content: Some(PathBuf::from("section1_1.html")),
                 children: vec![],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             let child2 = NavPoint {
@@ -1606,6 +2589,7 @@ mod tests {
                 content: Some(PathBuf::from("section1_2.html")),
                 children: vec![],
                 play_order: Some(2),
+                doc_index: 0,
             };
 
             let parent1 = NavPoint {
@@ -1613,6 +2597,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter1.html")),
                 children: vec![child1.clone(), child2.clone()],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             let parent2 = NavPoint {
@@ -1620,6 +2605,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter1.html")),
                 children: vec![child1.clone(), child2.clone()],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             assert!(parent1 == parent2);
@@ -1629,6 +2615,7 @@ mod tests {
                 content: Some(PathBuf::from("chapter2.html")),
                 children: vec![child1.clone(), child2.clone()],
                 play_order: Some(2),
+                doc_index: 0,
             };
 
             assert!(parent1 != parent3);
@@ -1643,6 +2630,7 @@ mod tests {
                 content: None,
                 children: vec![],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             let nav2 = NavPoint {
@@ -1650,12 +2638,49 @@ mod tests {
                 content: None,
                 children: vec![],
                 play_order: Some(1),
+                doc_index: 0,
             };
 
             assert!(nav1 == nav2);
         }
     }
 
+    mod metadata_item_tests {
+        use crate::types::{MetadataItem, MetadataRefinement};
+
+        fn refinement(property: &str, value: &str, lang: Option<&str>) -> MetadataRefinement {
+            MetadataRefinement {
+                refines: "title".to_string(),
+                property: property.to_string(),
+                value: value.to_string(),
+                lang: lang.map(str::to_string),
+                scheme: None,
+            }
+        }
+
+        #[test]
+        fn test_display_value() {
+            let item = MetadataItem {
+                id: Some("title".to_string()),
+                property: "title".to_string(),
+                value: "Shinjuku no Neko".to_string(),
+                lang: Some("ja-Latn".to_string()),
+                refined: vec![
+                    refinement("file-as", "Neko, Shinjuku no", None),
+                    refinement("alternate-script", "新宿の猫", Some("ja")),
+                    refinement("alternate-script", "The Cat of Shinjuku", Some("en")),
+                ],
+            };
+
+            assert_eq!(item.display_value(Some("ja")), "新宿の猫");
+            assert_eq!(item.display_value(Some("JA-JP")), "新宿の猫");
+            assert_eq!(item.display_value(Some("en-US")), "The Cat of Shinjuku");
+            assert_eq!(item.display_value(Some("ja-Latn")), "Shinjuku no Neko");
+            assert_eq!(item.display_value(Some("fr")), "Shinjuku no Neko");
+            assert_eq!(item.display_value(None), "Shinjuku no Neko");
+        }
+    }
+
     #[cfg(feature = "builder")]
     mod builder_tests {
         mod metadata_item {
@@ -1785,6 +2810,24 @@ mod tests {
                 );
             }
 
+            #[test]
+            fn test_metadata_item_meta() {
+                let metadata_item = MetadataItem::meta("schema:accessMode", "textual");
+                assert_eq!(metadata_item.property, "schema:accessMode");
+                assert_eq!(metadata_item.value, "textual");
+                assert_eq!(
+                    metadata_item.attributes(),
+                    vec![("property", "schema:accessMode")]
+                );
+
+                let metadata_item = MetadataItem::meta("title", "Test Book");
+                assert_eq!(metadata_item.property, "dcterms:title");
+                assert_eq!(
+                    metadata_item.attributes(),
+                    vec![("property", "dcterms:title")]
+                );
+            }
+
             #[test]
             fn test_metadata_item_attributes_with_lang() {
                 let mut metadata_item = MetadataItem::new("title", "Test Book");
@@ -2097,7 +3140,7 @@ mod tests {
                 assert_eq!(spine_item.idref, "content_001");
                 assert_eq!(spine_item.id, None);
                 assert_eq!(spine_item.properties, None);
-                assert_eq!(spine_item.linear, true);
+                assert!(spine_item.linear);
             }
 
             #[test]
@@ -2108,7 +3151,7 @@ mod tests {
                 assert_eq!(spine_item.idref, "content_001");
                 assert_eq!(spine_item.id, Some("spine1".to_string()));
                 assert_eq!(spine_item.properties, None);
-                assert_eq!(spine_item.linear, true);
+                assert!(spine_item.linear);
             }
 
             #[test]
@@ -2119,7 +3162,7 @@ mod tests {
                 assert_eq!(spine_item.idref, "content_001");
                 assert_eq!(spine_item.id, None);
                 assert_eq!(spine_item.properties, Some("page-spread-left".to_string()));
-                assert_eq!(spine_item.linear, true);
+                assert!(spine_item.linear);
             }
 
             #[test]
@@ -2143,7 +3186,7 @@ mod tests {
                 assert_eq!(spine_item.idref, "content_001");
                 assert_eq!(spine_item.id, None);
                 assert_eq!(spine_item.properties, None);
-                assert_eq!(spine_item.linear, false);
+                assert!(!spine_item.linear);
             }
 
             #[test]
@@ -2159,7 +3202,7 @@ mod tests {
                 assert_eq!(built.idref, "content_001");
                 assert_eq!(built.id, Some("spine1".to_string()));
                 assert_eq!(built.properties, Some("page-spread-left".to_string()));
-                assert_eq!(built.linear, false);
+                assert!(!built.linear);
             }
 
             #[test]
@@ -2175,7 +3218,7 @@ mod tests {
                 assert_eq!(built.idref, "content_001");
                 assert_eq!(built.id, Some("spine1".to_string()));
                 assert_eq!(built.properties, Some("page-spread-left".to_string()));
-                assert_eq!(built.linear, false);
+                assert!(!built.linear);
             }
 
             #[test]
@@ -2592,38 +3635,38 @@ mod tests {
 
     #[cfg(feature = "content-builder")]
     mod footnote_tests {
-        use crate::types::Footnote;
+        use crate::types::{Footnote, FootnoteAnchor};
 
         #[test]
         fn test_footnote_basic_creation() {
             let footnote = Footnote {
-                locate: 100,
+                locate: FootnoteAnchor::CharIndex(100),
                 content: "Sample footnote".to_string(),
             };
 
-            assert_eq!(footnote.locate, 100);
+            assert_eq!(footnote.locate, FootnoteAnchor::CharIndex(100));
             assert_eq!(footnote.content, "Sample footnote");
         }
 
         #[test]
         fn test_footnote_equality() {
             let footnote1 = Footnote {
-                locate: 100,
+                locate: FootnoteAnchor::CharIndex(100),
                 content: "First note".to_string(),
             };
 
             let footnote2 = Footnote {
-                locate: 100,
+                locate: FootnoteAnchor::CharIndex(100),
                 content: "First note".to_string(),
             };
 
             let footnote3 = Footnote {
-                locate: 100,
+                locate: FootnoteAnchor::CharIndex(100),
                 content: "Different note".to_string(),
             };
 
             let footnote4 = Footnote {
-                locate: 200,
+                locate: FootnoteAnchor::CharIndex(200),
                 content: "First note".to_string(),
             };
 
@@ -2635,17 +3678,17 @@ mod tests {
         #[test]
         fn test_footnote_ordering() {
             let footnote1 = Footnote {
-                locate: 100,
+                locate: FootnoteAnchor::CharIndex(100),
                 content: "First".to_string(),
             };
 
             let footnote2 = Footnote {
-                locate: 200,
+                locate: FootnoteAnchor::CharIndex(200),
                 content: "Second".to_string(),
             };
 
             let footnote3 = Footnote {
-                locate: 150,
+                locate: FootnoteAnchor::CharIndex(150),
                 content: "Middle".to_string(),
             };
 
@@ -2658,26 +3701,26 @@ mod tests {
 
         #[test]
         fn test_footnote_sorting() {
-            let mut footnotes = vec![
+            let mut footnotes = [
                 Footnote {
-                    locate: 300,
+                    locate: FootnoteAnchor::CharIndex(300),
                     content: "Third note".to_string(),
                 },
                 Footnote {
-                    locate: 100,
+                    locate: FootnoteAnchor::CharIndex(100),
                     content: "First note".to_string(),
                 },
                 Footnote {
-                    locate: 200,
+                    locate: FootnoteAnchor::CharIndex(200),
                     content: "Second note".to_string(),
                 },
             ];
 
             footnotes.sort();
 
-            assert_eq!(footnotes[0].locate, 100);
-            assert_eq!(footnotes[1].locate, 200);
-            assert_eq!(footnotes[2].locate, 300);
+            assert_eq!(footnotes[0].locate, FootnoteAnchor::CharIndex(100));
+            assert_eq!(footnotes[1].locate, FootnoteAnchor::CharIndex(200));
+            assert_eq!(footnotes[2].locate, FootnoteAnchor::CharIndex(300));
 
             assert_eq!(footnotes[0].content, "First note");
             assert_eq!(footnotes[1].content, "Second note");
@@ -2715,7 +3758,7 @@ mod tests {
 
     #[cfg(feature = "content-builder")]
     mod style_options_tests {
-        use crate::types::{ColorScheme, PageLayout, StyleOptions, TextAlign, TextStyle};
+        use crate::types::{ColorScheme, PageLayout, SizeUnit, StyleOptions, TextAlign, TextStyle};
 
         #[test]
         fn test_style_options_default() {
@@ -2745,7 +3788,9 @@ mod tests {
         fn test_style_options_custom_values() {
             let text = TextStyle {
                 font_size: 1.5,
+                font_size_unit: SizeUnit::Rem,
                 line_height: 2.0,
+                line_height_unit: Some(SizeUnit::Em),
                 font_family: "Georgia, serif".to_string(),
                 font_weight: "bold".to_string(),
                 font_style: "italic".to_string(),
@@ -2778,7 +3823,9 @@ mod tests {
             let style = TextStyle::default();
 
             assert_eq!(style.font_size, 1.0);
+            assert_eq!(style.font_size_unit, SizeUnit::Rem);
             assert_eq!(style.line_height, 1.6);
+            assert_eq!(style.line_height_unit, Some(SizeUnit::Em));
             assert_eq!(style.font_family, "-apple-system, Roboto, sans-serif");
             assert_eq!(style.font_weight, "normal");
             assert_eq!(style.font_style, "normal");
@@ -2790,7 +3837,9 @@ mod tests {
         fn test_text_style_custom_values() {
             let style = TextStyle {
                 font_size: 2.0,
+                font_size_unit: SizeUnit::Px,
                 line_height: 1.8,
+                line_height_unit: None,
                 font_family: "Times New Roman".to_string(),
                 font_weight: "bold".to_string(),
                 font_style: "italic".to_string(),
@@ -2799,7 +3848,9 @@ mod tests {
             };
 
             assert_eq!(style.font_size, 2.0);
+            assert_eq!(style.font_size_unit, SizeUnit::Px);
             assert_eq!(style.line_height, 1.8);
+            assert_eq!(style.line_height_unit, None);
             assert_eq!(style.font_family, "Times New Roman");
             assert_eq!(style.font_weight, "bold");
             assert_eq!(style.font_style, "italic");
@@ -1,17 +1,23 @@
 use std::{
-    cmp,
     collections::HashMap,
     io::{Read, Seek},
+    iter::Peekable,
     path::PathBuf,
+    str::Chars,
+    time::Duration,
 };
 
 #[cfg(feature = "builder")]
 use chrono::Local;
-use quick_xml::{NsReader, events::Event};
+use quick_xml::{
+    NsReader, Reader,
+    escape::resolve_predefined_entity,
+    events::{BytesRef, Event},
+};
 use sha1::{Digest, Sha1};
 use zip::{CompressionMethod, ZipArchive};
 
-use crate::error::EpubError;
+use crate::error::{EpubError, XmlPosition};
 
 #[cfg(feature = "builder")]
 pub static ELEMENT_IN_DC_NAMESPACE: std::sync::LazyLock<Vec<&str>> =
@@ -35,12 +41,79 @@ pub static ELEMENT_IN_DC_NAMESPACE: std::sync::LazyLock<Vec<&str>> =
         ]
     });
 
+#[cfg(feature = "builder")]
+/// The core media types of EPUB 3
+///
+/// Reading systems must support these media types, any other media type is a
+/// foreign resource which may need a fallback.
+pub const CORE_MEDIA_TYPES: &[&str] = &[
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/svg+xml",
+    "image/webp",
+    "audio/mpeg",
+    "audio/mp4",
+    "audio/ogg",
+    "text/css",
+    "font/ttf",
+    "application/font-sfnt",
+    "font/otf",
+    "application/vnd.ms-opentype",
+    "font/woff",
+    "application/font-woff",
+    "font/woff2",
+    "application/xhtml+xml",
+    "application/javascript",
+    "application/ecmascript",
+    "text/javascript",
+    "application/x-dtbncx+xml",
+    "application/smil+xml",
+    "application/pls+xml",
+];
+
+#[cfg(feature = "builder")]
+/// Checks whether a media type is one of the EPUB core media types
+///
+/// Media type parameters, such as `codecs`, are ignored and the comparison is
+/// case-insensitive.
+pub fn is_core_media_type(mime: &str) -> bool {
+    let essence = mime.split(';').next().unwrap_or_default().trim();
+    CORE_MEDIA_TYPES
+        .iter()
+        .any(|core| core.eq_ignore_ascii_case(essence))
+}
+
+#[cfg(feature = "builder")]
+/// Checks whether a media type is the media type of an EPUB content document
+///
+/// EPUB content documents are XHTML and SVG documents. Media type parameters are
+/// ignored and the comparison is case-insensitive.
+pub fn is_content_document_type(mime: &str) -> bool {
+    let essence = mime.split(';').next().unwrap_or_default().trim();
+    essence.eq_ignore_ascii_case("application/xhtml+xml")
+        || essence.eq_ignore_ascii_case("image/svg+xml")
+}
+
 #[cfg(feature = "builder")]
 /// Returns the current time with custom format
 pub fn local_time() -> String {
     Local::now().format("%Y-%m-%dT%H-%M-%S.%fU%z").to_string()
 }
 
+#[cfg(feature = "builder")]
+/// Returns a directory name that is unique within the process
+///
+/// The name consists of the current time, the process id and a process-wide
+/// counter, so that directories created in a tight loop or on multiple threads
+/// never collide.
+pub fn unique_dir_name() -> String {
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    let count = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    format!("{}-{}-{}", local_time(), std::process::id(), count)
+}
+
 /// Extracts the contents of a specified file from a ZIP archive
 ///
 /// This function reads the raw byte data of a specified file from an EPUB file (which
@@ -74,6 +147,60 @@ pub fn get_file_in_zip_archive<R: Read + Seek>(
     }
 }
 
+/// Finds the name under which a file is stored in the ZIP archive
+///
+/// Container paths always use forward slashes, but some tools store entries with
+/// backslashes, with a leading `./`, or in a different case. When no entry has
+/// exactly the given name, this function retries with those alternate forms, and
+/// finally scans the entry names case-insensitively.
+///
+/// ## Parameters
+/// - `zip_file`: The ZIP archive to search
+/// - `file_name`: The normalized container path of the file
+///
+/// ## Return
+/// - `Some(String)`: The name of the matching entry
+/// - `None`: No entry matches the file name
+///
+/// ## Notes
+/// - A warning is logged whenever an entry other than the exact name is used.
+pub fn find_file_in_zip_archive<R: Read + Seek>(
+    zip_file: &ZipArchive<R>,
+    file_name: &str,
+) -> Option<String> {
+    if zip_file.index_for_name(file_name).is_some() {
+        return Some(file_name.to_string());
+    }
+
+    let backslashed = file_name.replace('/', "\\");
+    let candidates = [
+        format!("./{}", file_name),
+        backslashed.clone(),
+        format!(".\\{}", backslashed),
+    ];
+    let entry_name = candidates
+        .into_iter()
+        .find(|candidate| zip_file.index_for_name(candidate).is_some())
+        .or_else(|| {
+            zip_file
+                .file_names()
+                .find(|name| {
+                    let name = name.replace('\\', "/");
+                    name.strip_prefix("./")
+                        .unwrap_or(&name)
+                        .eq_ignore_ascii_case(file_name)
+                })
+                .map(String::from)
+        })?;
+
+    log::warn!(
+        "The file \"{}\" is stored in the archive as \"{}\"",
+        file_name,
+        entry_name
+    );
+    Some(entry_name)
+}
+
 /// Checks if the compression method of all entries in the EPUB file
 /// conforms to the specification requirements.
 ///
@@ -163,6 +290,34 @@ pub fn check_realtive_link_leakage(
     Some(path)
 }
 
+/// Normalizes the separators of a path inside the EPUB container
+///
+/// Paths in the container always use forward slashes, but backslashes appear in
+/// hand-authored files and in paths joined on Windows. This function replaces them
+/// with forward slashes, and removes empty and `.` segments, so that paths obtained
+/// from the manifest, the navigation document and META-INF files can be compared.
+/// A leading slash and `..` segments are kept.
+///
+/// ## Parameters
+/// - `path`: The path to normalize
+///
+/// ## Return
+/// - `String`: The normalized path, such as `EPUB/text/ch1.xhtml` for `EPUB\.\text//ch1.xhtml`
+pub fn normalize_container_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<&str>>()
+        .join("/");
+
+    if path.starts_with('/') {
+        format!("/{segments}")
+    } else {
+        segments
+    }
+}
+
 /// Removes leading slash from a path
 ///
 /// This function removes the leading slash from a path if it exists.
@@ -175,6 +330,1013 @@ pub fn remove_leading_slash<P: AsRef<std::path::Path>>(path: P) -> PathBuf {
     }
 }
 
+/// Minifies a CSS stylesheet
+///
+/// This function removes comments and collapses whitespace in a stylesheet.
+/// Whitespace next to `{`, `}`, `;`, `,` and `>`, and after `:`, is removed
+/// entirely, while quoted strings are kept unchanged.
+///
+/// ## Parameters
+/// - `css`: The stylesheet to minify
+///
+/// ## Return
+/// - `String`: The minified stylesheet
+#[cfg(feature = "content-builder")]
+pub fn minify_css(css: &str) -> String {
+    const DELIMITERS: [char; 5] = ['{', '}', ';', ',', '>'];
+
+    let mut result = String::with_capacity(css.len());
+    let mut pending_space = false;
+    let mut chars = css.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for current in chars.by_ref() {
+                    if prev == '*' && current == '/' {
+                        break;
+                    }
+                    prev = current;
+                }
+                pending_space = true;
+            }
+
+            ch if ch.is_whitespace() => pending_space = true,
+
+            _ => {
+                // a space before `:` is kept, since `a :hover` and `a:hover` differ
+                if pending_space
+                    && !DELIMITERS.contains(&ch)
+                    && result
+                        .chars()
+                        .last()
+                        .is_some_and(|last| !DELIMITERS.contains(&last) && last != ':')
+                {
+                    result.push(' ');
+                }
+                pending_space = false;
+                result.push(ch);
+
+                if ch == '"' || ch == '\'' {
+                    for current in chars.by_ref() {
+                        result.push(current);
+                        if current == ch {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Sanitizes a file name to a safe ASCII subset
+///
+/// Accented Latin letters are first folded to their base letters, e.g. `é` to `e`.
+/// ASCII letters, digits, `-` and `_` in the file stem are kept, and every run of
+/// other characters is replaced by a single `-`, with leading and trailing `-`
+/// removed. Only ASCII letters and digits are kept in the extension.
+///
+/// ## Parameters
+/// - `file_name`: The file name to sanitize, e.g. `my photo (1).png`
+///
+/// ## Return
+/// - `String`: The sanitized file name, e.g. `my-photo-1.png`. A stem without any
+///   safe character is replaced by `resource`.
+#[cfg(feature = "content-builder")]
+pub fn sanitize_file_name(file_name: &str) -> String {
+    let (stem, extension) = match file_name.rfind('.') {
+        Some(index) if index > 0 => (&file_name[..index], Some(&file_name[index + 1..])),
+        _ => (file_name, None),
+    };
+
+    let mut result = String::new();
+    for char in stem.chars().flat_map(fold_latin_letter) {
+        if char.is_ascii_alphanumeric() || char == '_' {
+            result.push(char);
+        } else if !result.is_empty() && !result.ends_with('-') {
+            result.push('-');
+        }
+    }
+
+    let mut result = result.trim_end_matches('-').to_string();
+    if result.is_empty() {
+        result.push_str("resource");
+    }
+
+    let extension = extension
+        .map(|extension| {
+            extension
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+    if !extension.is_empty() {
+        result.push('.');
+        result.push_str(&extension);
+    }
+
+    result
+}
+
+/// Folds an accented Latin letter to its base letters
+///
+/// Letters of the Latin-1 Supplement and Latin Extended-A blocks are folded,
+/// such as `é` to `e` and `ß` to `ss`. Other characters are returned unchanged.
+#[cfg(feature = "content-builder")]
+fn fold_latin_letter(char: char) -> Vec<char> {
+    let folded = match char {
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'Ď' | 'Đ' | 'Ð' => "D",
+        'ď' | 'đ' | 'ð' => "d",
+        'È'..='Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'Ĥ' | 'Ħ' => "H",
+        'ĥ' | 'ħ' => "h",
+        'Ì'..='Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'Ĳ' => "IJ",
+        'ĳ' => "ij",
+        'Ĵ' => "J",
+        'ĵ' => "j",
+        'Ķ' => "K",
+        'ķ' | 'ĸ' => "k",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' | 'Ŋ' => "N",
+        'ñ' | 'ń' | 'ņ' | 'ň' | 'ŉ' | 'ŋ' => "n",
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ŕ' | 'Ŗ' | 'Ř' => "R",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ſ' => "s",
+        'ß' => "ss",
+        'Ţ' | 'Ť' | 'Ŧ' => "T",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'Þ' => "TH",
+        'þ' => "th",
+        'Ù'..='Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'Ŵ' => "W",
+        'ŵ' => "w",
+        'Ý' | 'Ŷ' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return vec![char],
+    };
+
+    folded.chars().collect()
+}
+
+/// Extracts the plain text of an XHTML content document
+///
+/// Text inside `head`, `script` and `style` elements is skipped. Runs of whitespace
+/// are collapsed into a single space, and block-level elements are separated by a
+/// line break, so that words of adjacent paragraphs are not joined.
+///
+/// ## Parameters
+/// - `content`: The XHTML content document
+///
+/// ## Return
+/// - `Ok(String)`: The plain text of the document
+/// - `Err(EpubError)`: The document is not well-formed XML
+pub fn extract_plain_text(content: &str) -> Result<String, EpubError> {
+    const SKIPPED_ELEMENTS: [&[u8]; 3] = [b"head", b"script", b"style"];
+    const BLOCK_ELEMENTS: [&[u8]; 28] = [
+        b"address",
+        b"article",
+        b"aside",
+        b"blockquote",
+        b"br",
+        b"dd",
+        b"div",
+        b"dl",
+        b"dt",
+        b"figcaption",
+        b"figure",
+        b"footer",
+        b"h1",
+        b"h2",
+        b"h3",
+        b"h4",
+        b"h5",
+        b"h6",
+        b"header",
+        b"hr",
+        b"li",
+        b"main",
+        b"nav",
+        b"ol",
+        b"p",
+        b"pre",
+        b"section",
+        b"tr",
+    ];
+
+    let mut reader = Reader::from_str(content);
+    let mut result = String::new();
+    let mut pending_space = false;
+    let mut skip_depth = 0usize;
+
+    let mut push_text = |result: &mut String, text: &str| {
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                pending_space = true;
+                continue;
+            }
+
+            if pending_space && !result.is_empty() && !result.ends_with('\n') {
+                result.push(' ');
+            }
+            pending_space = false;
+            result.push(ch);
+        }
+    };
+    let push_break = |result: &mut String| {
+        if !result.is_empty() && !result.ends_with('\n') {
+            result.push('\n');
+        }
+    };
+
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+
+            Event::Start(e) => {
+                let name = e.local_name();
+                if skip_depth > 0 || SKIPPED_ELEMENTS.contains(&name.as_ref()) {
+                    skip_depth += 1;
+                } else if BLOCK_ELEMENTS.contains(&name.as_ref()) {
+                    push_break(&mut result);
+                }
+            }
+
+            Event::End(e) => {
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                } else if BLOCK_ELEMENTS.contains(&e.local_name().as_ref()) {
+                    push_break(&mut result);
+                }
+            }
+
+            Event::Empty(e) => {
+                if skip_depth == 0 && BLOCK_ELEMENTS.contains(&e.local_name().as_ref()) {
+                    push_break(&mut result);
+                }
+            }
+
+            Event::Text(e) if skip_depth == 0 => {
+                push_text(&mut result, &e.decode().map_err(quick_xml::Error::from)?);
+            }
+
+            Event::CData(e) if skip_depth == 0 => {
+                push_text(&mut result, &e.decode().map_err(quick_xml::Error::from)?);
+            }
+
+            Event::GeneralRef(e) if skip_depth == 0 => {
+                push_text(&mut result, &resolve_general_ref(&e)?);
+            }
+
+            _ => continue,
+        }
+    }
+
+    Ok(result.trim_end().to_string())
+}
+
+/// Extracts the title of an XHTML content document
+///
+/// The title is the text of the first `h1` to `h6` heading in document order, or
+/// the text of the `title` element if the document has no heading with text.
+/// Runs of whitespace are collapsed into a single space.
+///
+/// ## Parameters
+/// - `content`: The XHTML content document
+///
+/// ## Return
+/// - `Ok(Some(String))`: The title of the document
+/// - `Ok(None)`: The document has neither a heading nor a title with text
+/// - `Err(EpubError)`: The document is not well-formed XML
+pub fn extract_chapter_title(content: &str) -> Result<Option<String>, EpubError> {
+    const HEADING_ELEMENTS: [&[u8]; 6] = [b"h1", b"h2", b"h3", b"h4", b"h5", b"h6"];
+
+    let mut reader = Reader::from_str(content);
+    let mut title = None;
+    let mut depth = 0usize;
+
+    // the depth of the element whose text is collected, and whether it is a heading
+    let mut capture: Option<(usize, bool)> = None;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+
+            Event::Start(e) => {
+                depth += 1;
+                if capture.is_some() {
+                    continue;
+                }
+
+                let name = e.local_name();
+                if HEADING_ELEMENTS.contains(&name.as_ref()) {
+                    capture = Some((depth, true));
+                } else if name.as_ref() == b"title" && title.is_none() {
+                    capture = Some((depth, false));
+                }
+            }
+
+            Event::End(_) => {
+                match capture {
+                    Some((capture_depth, is_heading)) if capture_depth == depth => {
+                        let collected = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                        text.clear();
+                        capture = None;
+
+                        if !collected.is_empty() {
+                            if is_heading {
+                                return Ok(Some(collected));
+                            }
+                            title = Some(collected);
+                        }
+                    }
+                    _ => {}
+                }
+                depth = depth.saturating_sub(1);
+            }
+
+            Event::Text(e) if capture.is_some() => {
+                text.push_str(&e.decode().map_err(quick_xml::Error::from)?);
+            }
+
+            Event::CData(e) if capture.is_some() => {
+                text.push_str(&e.decode().map_err(quick_xml::Error::from)?);
+            }
+
+            Event::GeneralRef(e) if capture.is_some() => {
+                text.push_str(&resolve_general_ref(&e)?);
+            }
+
+            _ => continue,
+        }
+    }
+
+    Ok(title)
+}
+
+/// Extracts the link of the first image of an XHTML content document
+///
+/// The link is the `src` attribute of the first `img` element, or the `href` or
+/// `xlink:href` attribute of the first SVG `image` element, whichever comes first
+/// in document order. Images with an empty link are skipped.
+///
+/// ## Parameters
+/// - `content`: The XHTML content document
+///
+/// ## Return
+/// - `Ok(Some(String))`: The link of the first image, as written in the document
+/// - `Ok(None)`: The document contains no image
+/// - `Err(EpubError)`: The document is not well-formed XML
+#[cfg(feature = "image")]
+pub fn extract_first_image_src(content: &str) -> Result<Option<String>, EpubError> {
+    let mut reader = Reader::from_str(content);
+
+    loop {
+        match reader.read_event()? {
+            Event::Eof => return Ok(None),
+
+            Event::Start(e) | Event::Empty(e) => {
+                let keys: &[&str] = match e.local_name().as_ref() {
+                    b"img" => &["src"],
+                    b"image" => &["href", "xlink:href"],
+                    _ => continue,
+                };
+
+                for key in keys {
+                    let Some(attr) = e.try_get_attribute(*key).map_err(quick_xml::Error::from)?
+                    else {
+                        continue;
+                    };
+
+                    let src = attr.unescape_value()?;
+                    if !src.trim().is_empty() {
+                        return Ok(Some(src.trim().to_string()));
+                    }
+                }
+            }
+
+            _ => continue,
+        }
+    }
+}
+
+/// Checks whether a language tag is well-formed and names an actual language
+///
+/// The tag must consist of subtags of one to eight ASCII letters or digits separated
+/// by `-`, and start with a primary language subtag of two or three letters. The
+/// special codes `und` (undetermined), `mul` (multiple languages), `mis` (uncoded
+/// languages) and `zxx` (no linguistic content) are rejected.
+///
+/// ## Parameters
+/// - `tag`: The language tag, such as `en-US`
+pub fn is_valid_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    let is_language = (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && !["und", "mul", "mis", "zxx"].contains(&primary.to_ascii_lowercase().as_str());
+
+    is_language
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+/// Guesses the language of a text from the script of its letters
+///
+/// Each script is mapped to the language it is most commonly used for, e.g. Cyrillic
+/// to Russian. Han characters are attributed to Japanese when kana make up at least
+/// a tenth of them, and to Chinese otherwise. A language is only returned when its
+/// script is used by more than half of the letters of the text.
+///
+/// ## Parameters
+/// - `text`: The text to examine
+///
+/// ## Return
+/// - `Some(&str)`: The primary language subtag of the guessed language
+/// - `None`: No script dominates the text, or the dominant script is shared by too
+///   many languages to guess one, such as Latin
+pub fn guess_language_by_script(text: &str) -> Option<&'static str> {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    let mut letters = 0usize;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        let language = match c as u32 {
+            0x3040..=0x30FF => "ja",
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF => "zh",
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => "ko",
+            0x0370..=0x03FF => "el",
+            0x0400..=0x04FF => "ru",
+            0x0590..=0x05FF => "he",
+            0x0600..=0x06FF => "ar",
+            0x0900..=0x097F => "hi",
+            0x0E00..=0x0E7F => "th",
+            _ => continue,
+        };
+        *counts.entry(language).or_default() += 1;
+    }
+
+    // Japanese is written with both kana and Han characters
+    let kana = counts.get("ja").copied().unwrap_or_default();
+    let han = counts.get("zh").copied().unwrap_or_default();
+    if kana > 0 && kana * 10 >= han {
+        counts.remove("zh");
+        counts.insert("ja", kana + han);
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .filter(|(_, count)| count * 2 > letters)
+        .map(|(language, _)| language)
+}
+
+/// Represents a step of an EPUB CFI path
+#[derive(Debug, PartialEq)]
+pub struct CfiStep {
+    /// The index of the step, even for elements and odd for the text between them
+    pub index: usize,
+
+    /// The unescaped id assertion of the step, such as `chap01ref` in `/4[chap01ref]`
+    pub assertion: Option<String>,
+}
+
+/// Represents a parsed EPUB CFI path
+#[derive(Debug, PartialEq)]
+pub struct CfiPath {
+    /// The steps of the path, split into segments at each `!` indirection
+    pub segments: Vec<Vec<CfiStep>>,
+
+    /// The character offset terminating the path, if one is present
+    pub char_offset: Option<usize>,
+}
+
+/// Escapes the characters of an EPUB CFI assertion that have a special meaning
+pub fn escape_cfi_assertion(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '^' | '[' | ']' | '(' | ')' | ',' | ';' | '=') {
+            escaped.push('^');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Parses the path of an EPUB CFI, without the surrounding `epubcfi(...)`
+///
+/// Only paths to a single location are supported. A character offset may terminate
+/// the path, optionally followed by a text location assertion, which is ignored.
+///
+/// ## Parameters
+/// - `path`: The path to parse, such as `/6/4[chap01ref]!/4/2/1:0`
+///
+/// ## Return
+/// - `Ok(CfiPath)`: The parsed path
+/// - `Err(&str)`: A description of why the path is malformed or not supported
+pub fn parse_cfi_path(path: &str) -> Result<CfiPath, &'static str> {
+    let mut chars = path.chars().peekable();
+    let mut segments: Vec<Vec<CfiStep>> = vec![vec![]];
+    let mut char_offset = None;
+
+    let read_number = |chars: &mut Peekable<Chars>| -> Option<usize> {
+        let mut digits = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            digits.push(digit);
+        }
+        digits.parse().ok()
+    };
+
+    while let Some(ch) = chars.next() {
+        if char_offset.is_some() && ch != '[' {
+            return Err("the character offset must terminate the path");
+        }
+
+        match ch {
+            '/' => {
+                let index = read_number(&mut chars).ok_or("a step must be a number")?;
+                if let Some(segment) = segments.last_mut() {
+                    segment.push(CfiStep { index, assertion: None });
+                }
+            }
+
+            '!' => {
+                if segments.last().is_none_or(|segment| segment.is_empty()) {
+                    return Err("an indirection must follow a step");
+                }
+                segments.push(vec![]);
+            }
+
+            '[' => {
+                let mut assertion = String::new();
+                loop {
+                    match chars.next() {
+                        Some('^') => {
+                            assertion.push(chars.next().ok_or("an assertion is not closed")?)
+                        }
+                        Some(']') => break,
+                        Some(ch) => assertion.push(ch),
+                        None => return Err("an assertion is not closed"),
+                    }
+                }
+
+                if char_offset.is_none() {
+                    let step = segments
+                        .last_mut()
+                        .and_then(|segment| segment.last_mut())
+                        .filter(|step| step.assertion.is_none())
+                        .ok_or("an assertion must follow a step")?;
+                    step.assertion = Some(assertion);
+                }
+            }
+
+            ':' => {
+                char_offset =
+                    Some(read_number(&mut chars).ok_or("a character offset must be a number")?);
+            }
+
+            ',' => return Err("ranges are not supported"),
+            '~' | '@' => return Err("temporal and spatial offsets are not supported"),
+            _ => return Err("the path contains an unexpected character"),
+        }
+    }
+
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return Err("a path must contain at least one step");
+    }
+
+    Ok(CfiPath { segments, char_offset })
+}
+
+/// Resolves a general entity reference found in the text of a content document
+///
+/// Character references, the predefined XML entities and the common named character
+/// references of HTML, such as `&nbsp;`, `&rsquo;` and `&mdash;`, are resolved.
+/// Other entities are declared by a DTD, which is not read.
+///
+/// ## Return
+/// - `Ok(String)`: The text the reference stands for, or the reference itself, such
+///   as `&name;`, if the entity is unknown, so that no text is lost
+/// - `Err(EpubError)`: The reference is malformed
+pub fn resolve_general_ref(reference: &BytesRef) -> Result<String, EpubError> {
+    if let Some(ch) = reference.resolve_char_ref()? {
+        return Ok(ch.to_string());
+    }
+
+    let name = reference.decode().map_err(quick_xml::Error::from)?;
+    if let Some(text) = resolve_predefined_entity(&name) {
+        return Ok(text.to_string());
+    }
+
+    Ok(match resolve_html_entity(&name) {
+        Some(ch) => ch.to_string(),
+        None => {
+            log::warn!("Unknown entity \"&{};\" is kept as it is.", name);
+            format!("&{};", name)
+        }
+    })
+}
+
+/// Resolves a named character reference of HTML
+///
+/// The common named character references of HTML are supported: those of HTML 4,
+/// covering Latin-1 letters and symbols, typographic punctuation, Greek letters,
+/// arrows and a few mathematical symbols.
+fn resolve_html_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "nbsp" => '\u{a0}',
+        "iexcl" => '\u{a1}',
+        "cent" => '\u{a2}',
+        "pound" => '\u{a3}',
+        "curren" => '\u{a4}',
+        "yen" => '\u{a5}',
+        "brvbar" => '\u{a6}',
+        "sect" => '\u{a7}',
+        "uml" => '\u{a8}',
+        "copy" => '\u{a9}',
+        "ordf" => '\u{aa}',
+        "laquo" => '\u{ab}',
+        "not" => '\u{ac}',
+        "shy" => '\u{ad}',
+        "reg" => '\u{ae}',
+        "macr" => '\u{af}',
+        "deg" => '\u{b0}',
+        "plusmn" => '\u{b1}',
+        "sup2" => '\u{b2}',
+        "sup3" => '\u{b3}',
+        "acute" => '\u{b4}',
+        "micro" => '\u{b5}',
+        "para" => '\u{b6}',
+        "middot" => '\u{b7}',
+        "cedil" => '\u{b8}',
+        "sup1" => '\u{b9}',
+        "ordm" => '\u{ba}',
+        "raquo" => '\u{bb}',
+        "frac14" => '\u{bc}',
+        "frac12" => '\u{bd}',
+        "frac34" => '\u{be}',
+        "iquest" => '\u{bf}',
+        "Agrave" => '\u{c0}',
+        "Aacute" => '\u{c1}',
+        "Acirc" => '\u{c2}',
+        "Atilde" => '\u{c3}',
+        "Auml" => '\u{c4}',
+        "Aring" => '\u{c5}',
+        "AElig" => '\u{c6}',
+        "Ccedil" => '\u{c7}',
+        "Egrave" => '\u{c8}',
+        "Eacute" => '\u{c9}',
+        "Ecirc" => '\u{ca}',
+        "Euml" => '\u{cb}',
+        "Igrave" => '\u{cc}',
+        "Iacute" => '\u{cd}',
+        "Icirc" => '\u{ce}',
+        "Iuml" => '\u{cf}',
+        "ETH" => '\u{d0}',
+        "Ntilde" => '\u{d1}',
+        "Ograve" => '\u{d2}',
+        "Oacute" => '\u{d3}',
+        "Ocirc" => '\u{d4}',
+        "Otilde" => '\u{d5}',
+        "Ouml" => '\u{d6}',
+        "times" => '\u{d7}',
+        "Oslash" => '\u{d8}',
+        "Ugrave" => '\u{d9}',
+        "Uacute" => '\u{da}',
+        "Ucirc" => '\u{db}',
+        "Uuml" => '\u{dc}',
+        "Yacute" => '\u{dd}',
+        "THORN" => '\u{de}',
+        "szlig" => '\u{df}',
+        "agrave" => '\u{e0}',
+        "aacute" => '\u{e1}',
+        "acirc" => '\u{e2}',
+        "atilde" => '\u{e3}',
+        "auml" => '\u{e4}',
+        "aring" => '\u{e5}',
+        "aelig" => '\u{e6}',
+        "ccedil" => '\u{e7}',
+        "egrave" => '\u{e8}',
+        "eacute" => '\u{e9}',
+        "ecirc" => '\u{ea}',
+        "euml" => '\u{eb}',
+        "igrave" => '\u{ec}',
+        "iacute" => '\u{ed}',
+        "icirc" => '\u{ee}',
+        "iuml" => '\u{ef}',
+        "eth" => '\u{f0}',
+        "ntilde" => '\u{f1}',
+        "ograve" => '\u{f2}',
+        "oacute" => '\u{f3}',
+        "ocirc" => '\u{f4}',
+        "otilde" => '\u{f5}',
+        "ouml" => '\u{f6}',
+        "divide" => '\u{f7}',
+        "oslash" => '\u{f8}',
+        "ugrave" => '\u{f9}',
+        "uacute" => '\u{fa}',
+        "ucirc" => '\u{fb}',
+        "uuml" => '\u{fc}',
+        "yacute" => '\u{fd}',
+        "thorn" => '\u{fe}',
+        "yuml" => '\u{ff}',
+        "OElig" => '\u{152}',
+        "oelig" => '\u{153}',
+        "Scaron" => '\u{160}',
+        "scaron" => '\u{161}',
+        "Yuml" => '\u{178}',
+        "fnof" => '\u{192}',
+        "circ" => '\u{2c6}',
+        "tilde" => '\u{2dc}',
+        "Alpha" => '\u{391}',
+        "Beta" => '\u{392}',
+        "Gamma" => '\u{393}',
+        "Delta" => '\u{394}',
+        "Omega" => '\u{3a9}',
+        "alpha" => '\u{3b1}',
+        "beta" => '\u{3b2}',
+        "gamma" => '\u{3b3}',
+        "delta" => '\u{3b4}',
+        "epsilon" => '\u{3b5}',
+        "lambda" => '\u{3bb}',
+        "mu" => '\u{3bc}',
+        "pi" => '\u{3c0}',
+        "sigma" => '\u{3c3}',
+        "omega" => '\u{3c9}',
+        "ensp" => '\u{2002}',
+        "emsp" => '\u{2003}',
+        "thinsp" => '\u{2009}',
+        "zwnj" => '\u{200c}',
+        "zwj" => '\u{200d}',
+        "lrm" => '\u{200e}',
+        "rlm" => '\u{200f}',
+        "ndash" => '\u{2013}',
+        "mdash" => '\u{2014}',
+        "lsquo" => '\u{2018}',
+        "rsquo" => '\u{2019}',
+        "sbquo" => '\u{201a}',
+        "ldquo" => '\u{201c}',
+        "rdquo" => '\u{201d}',
+        "bdquo" => '\u{201e}',
+        "dagger" => '\u{2020}',
+        "Dagger" => '\u{2021}',
+        "bull" => '\u{2022}',
+        "hellip" => '\u{2026}',
+        "permil" => '\u{2030}',
+        "prime" => '\u{2032}',
+        "Prime" => '\u{2033}',
+        "lsaquo" => '\u{2039}',
+        "rsaquo" => '\u{203a}',
+        "oline" => '\u{203e}',
+        "frasl" => '\u{2044}',
+        "euro" => '\u{20ac}',
+        "trade" => '\u{2122}',
+        "larr" => '\u{2190}',
+        "uarr" => '\u{2191}',
+        "rarr" => '\u{2192}',
+        "darr" => '\u{2193}',
+        "harr" => '\u{2194}',
+        "minus" => '\u{2212}',
+        "infin" => '\u{221e}',
+        "ne" => '\u{2260}',
+        "le" => '\u{2264}',
+        "ge" => '\u{2265}',
+        "asymp" => '\u{2248}',
+        "loz" => '\u{25ca}',
+        "spades" => '\u{2660}',
+        "clubs" => '\u{2663}',
+        "hearts" => '\u{2665}',
+        "diams" => '\u{2666}',
+        _ => return None,
+    })
+}
+
+/// Parses a SMIL clock value
+///
+/// Clock values are used by media overlays, e.g. in `media:duration` metadata.
+/// The following forms are supported, fractional seconds being optional:
+/// - Full clock values, such as `01:23:45.678`
+/// - Partial clock values, such as `23:45.678`
+/// - Timecount values, such as `600.5s`, `1.5h`, `90min`, `250ms` or `600`
+///
+/// ## Parameters
+/// - `value`: The clock value to parse
+///
+/// ## Return
+/// - `Some(Duration)`: The parsed duration
+/// - `None`: The value is not a valid clock value
+pub fn parse_clock_value(value: &str) -> Option<Duration> {
+    const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+    let value = value.trim();
+    let nanos = if value.contains(':') {
+        let parts = value.split(':').collect::<Vec<&str>>();
+        let (hours, minutes, seconds) = match parts.as_slice() {
+            [hours, minutes, seconds] => (parse_integer(hours)?, *minutes, *seconds),
+            [minutes, seconds] => (0, *minutes, *seconds),
+            _ => return None,
+        };
+
+        // minutes and seconds are always written with two digits
+        if minutes.len() != 2 || seconds.split('.').next()?.len() != 2 {
+            return None;
+        }
+
+        let minutes = parse_integer(minutes)?;
+        let seconds = parse_decimal_nanos(seconds)?;
+        if minutes >= 60 || seconds >= 60 * NANOS_PER_SEC {
+            return None;
+        }
+
+        (hours * 3600 + minutes * 60) * NANOS_PER_SEC + seconds
+    } else {
+        let split = value
+            .find(|ch: char| ch.is_ascii_alphabetic())
+            .unwrap_or(value.len());
+        let (number, metric) = value.split_at(split);
+        let number = parse_decimal_nanos(number)?;
+
+        match metric {
+            "h" => number * 3600,
+            "min" => number * 60,
+            "s" | "" => number,
+            "ms" => number / 1000,
+            _ => return None,
+        }
+    };
+
+    let nanos = u64::try_from(nanos).ok()?;
+    Some(Duration::from_nanos(nanos))
+}
+
+/// Parses a string of ASCII digits
+fn parse_integer(value: &str) -> Option<u128> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    value.parse().ok()
+}
+
+/// Parses a decimal number of seconds into nanoseconds
+///
+/// Digits beyond nanosecond precision are truncated.
+fn parse_decimal_nanos(value: &str) -> Option<u128> {
+    let (integer, fraction) = match value.split_once('.') {
+        Some((integer, fraction)) => (parse_integer(integer)?, Some(fraction)),
+        None => (parse_integer(value)?, None),
+    };
+
+    let fraction = match fraction {
+        Some(fraction) => {
+            // all digits are validated, even those beyond the precision
+            parse_integer(fraction)?;
+
+            let digits = &fraction[..fraction.len().min(9)];
+            parse_integer(digits)? * 10u128.pow(9 - digits.len() as u32)
+        }
+        None => 0,
+    };
+
+    integer
+        .checked_mul(1_000_000_000)
+        .and_then(|nanos| nanos.checked_add(fraction))
+}
+
+/// Derives the IDPF font obfuscation key from the unique identifier of a publication
+///
+/// The key is the SHA-1 digest of the unique identifier, after all whitespace
+/// characters (U+0020, U+0009, U+000D and U+000A) are removed from it.
+///
+/// ## Parameters
+/// - `unique_id`: The unique identifier of the EPUB publication
+///
+/// ## Return
+/// - `Vec<u8>`: The 20-byte obfuscation key
+pub fn idpf_obfuscation_key(unique_id: &str) -> Vec<u8> {
+    let unique_id = unique_id
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+        .collect::<String>();
+
+    let mut hasher = Sha1::new();
+    hasher.update(unique_id.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Derives the Adobe font obfuscation key from the unique identifier of a publication
+///
+/// Adobe obfuscation expects the unique identifier to be a UUID, such as
+/// `urn:uuid:5f1b2b8e-7f7c-4b3a-9b43-2f1f9a5d8c11`, and uses its 16 bytes as the key.
+///
+/// ## Parameters
+/// - `unique_id`: The unique identifier of the EPUB publication
+///
+/// ## Return
+/// - `Vec<u8>`: The 16 bytes of the UUID, or the bytes of the unique identifier
+///   itself when it is not a UUID
+pub fn adobe_obfuscation_key(unique_id: &str) -> Vec<u8> {
+    let trimmed = unique_id.trim();
+    let uuid = trimmed.strip_prefix("urn:uuid:").unwrap_or(trimmed);
+    let hex = uuid.chars().filter(|&c| c != '-').collect::<String>();
+
+    if hex.len() == 32 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..32)
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).expect("Unreachable"))
+            .collect()
+    } else {
+        unique_id.as_bytes().to_vec()
+    }
+}
+
+/// Checks whether the data starts with the signature of a font file
+///
+/// Recognizes OpenType (`OTTO`), TrueType (`0x00010000`, `0x00020000`, `true`, `typ1`)
+/// and WOFF (`wOFF`, `wOF2`) fonts. A font that is de-obfuscated with a wrong key
+/// loses its signature, which makes this check a cheap way to verify the key.
+pub(crate) fn is_font_data(data: &[u8]) -> bool {
+    if data.len() < 4 {
+        return false;
+    }
+
+    matches!(
+        &data[0..4],
+        b"OTTO" | b"\x00\x01\x00\x00" | b"\x00\x02\x00\x00" | b"true" | b"typ1" | b"wOFF" | b"wOF2"
+    )
+}
+
+/// XORs the beginning of the data with a repeating key
+fn xor_obfuscation(data: &[u8], key: &[u8], limit: usize) -> Vec<u8> {
+    let mut obfuscated_data = data.to_vec();
+    if key.is_empty() {
+        return obfuscated_data;
+    }
+
+    for (index, byte) in obfuscated_data.iter_mut().take(limit).enumerate() {
+        *byte ^= key[index % key.len()];
+    }
+
+    obfuscated_data
+}
+
+/// Applies the IDPF font obfuscation with a derived key
+///
+/// The IDPF font obfuscation algorithm XORs the first 1040 bytes of the font file
+/// with the key. Because XOR is its own inverse, the same operation obfuscates and
+/// de-obfuscates the data.
+///
+/// ## Parameters
+/// - `data`: The font data
+/// - `key`: The key derived by `idpf_obfuscation_key`
+///
+/// ## Return
+/// - `Vec<u8>`: The obfuscated or de-obfuscated font data
+pub fn apply_idpf_obfuscation(data: &[u8], key: &[u8]) -> Vec<u8> {
+    xor_obfuscation(data, key, 1040)
+}
+
+/// Applies the Adobe font obfuscation with a derived key
+///
+/// The Adobe font obfuscation algorithm XORs the first 1024 bytes of the font file
+/// with the key. Because XOR is its own inverse, the same operation obfuscates and
+/// de-obfuscates the data.
+///
+/// ## Parameters
+/// - `data`: The font data
+/// - `key`: The key derived by `adobe_obfuscation_key`
+///
+/// ## Return
+/// - `Vec<u8>`: The obfuscated or de-obfuscated font data
+pub fn apply_adobe_obfuscation(data: &[u8], key: &[u8]) -> Vec<u8> {
+    xor_obfuscation(data, key, 1024)
+}
+
 /// Encrypts the font file using the IDPF font obfuscation algorithm
 ///
 /// The IDPF font obfuscation algorithm XORs the first 1040 bytes of the font file
@@ -193,24 +1355,7 @@ pub fn remove_leading_slash<P: AsRef<std::path::Path>>(path: P) -> PathBuf {
 ///   (http://www.idpf.org/2008/embedding).
 /// - Only processes the first 1040 bytes of the font file; the rest remains unchanged.
 pub fn idpf_font_encryption(data: &[u8], key: &str) -> Vec<u8> {
-    if data.is_empty() {
-        return Vec::new();
-    }
-
-    let hash = {
-        let mut hasher = Sha1::new();
-        hasher.update(key.as_bytes());
-        hasher.finalize()
-    };
-
-    let mut obfuscated_data = data.to_vec();
-    let limit = cmp::min(1040, data.len());
-
-    for (index, byte) in obfuscated_data.iter_mut().take(limit).enumerate() {
-        *byte ^= hash[index % hash.len()]
-    }
-
-    obfuscated_data
+    apply_idpf_obfuscation(data, &idpf_obfuscation_key(key))
 }
 
 /// Decrypts a file encrypted using the IDPF obfuscation algorithm
@@ -248,18 +1393,7 @@ pub fn idpf_font_dencryption(data: &[u8], key: &str) -> Vec<u8> {
 ///   (http://ns.adobe.com/pdf/enc#RC).
 /// - Only processes the first 1024 bytes of the font file; the rest remains unchanged.
 pub fn adobe_font_encryption(data: &[u8], key: &str) -> Vec<u8> {
-    if data.is_empty() {
-        return Vec::new();
-    }
-
-    let mut obfuscated_data = data.to_vec();
-    let limit = cmp::min(1024, data.len());
-
-    for (index, byte) in obfuscated_data.iter_mut().take(limit).enumerate() {
-        *byte ^= key.as_bytes()[index % key.len()];
-    }
-
-    obfuscated_data
+    apply_adobe_obfuscation(data, &adobe_obfuscation_key(key))
 }
 
 /// Decrypts a file encrypted using the Adobe font obfuscation algorithm
@@ -295,6 +1429,16 @@ pub fn adobe_font_dencryption(data: &[u8], key: &str) -> Vec<u8> {
 ///   results may be unreadable; caution should be exercised when using such streams.
 pub trait DecodeBytes {
     fn decode(&self) -> Result<String, EpubError>;
+
+    /// Decodes the byte data into a string, replacing invalid sequences
+    ///
+    /// The byte order mark is handled in the same way as `decode`, but invalid
+    /// sequences are replaced with the replacement character (U+FFFD) instead of
+    /// causing an error, so that best-effort text is always returned.
+    ///
+    /// ## Notes
+    /// - Without a BOM, the data is always decoded as UTF-8.
+    fn decode_lossy(&self) -> String;
 }
 
 impl DecodeBytes for Vec<u8> {
@@ -368,6 +1512,28 @@ impl DecodeBytes for Vec<u8> {
             }
         }
     }
+
+    fn decode_lossy(&self) -> String {
+        let decode_utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+            let utf16_units = bytes
+                .chunks_exact(2)
+                .map(|b| from_bytes([b[0], b[1]]))
+                .collect::<Vec<u16>>();
+
+            let mut str = String::from_utf16_lossy(&utf16_units);
+            if bytes.len() % 2 != 0 {
+                str.push(char::REPLACEMENT_CHARACTER);
+            }
+            str
+        };
+
+        match self.as_slice() {
+            [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+            [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+            [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+            _ => String::from_utf8_lossy(self).into_owned(),
+        }
+    }
 }
 
 /// Provides functionality for normalizing whitespace characters
@@ -559,7 +1725,34 @@ impl XmlReader {
     /// ## Return
     /// - `Ok(XmlElement)`: The root element of the XML element tree
     /// - `Err(EpubError)`: An error occurred during parsing
+    ///
+    /// ## Notes
+    /// - Malformed XML is reported as `EpubError::XmlParse`, carrying the position
+    ///   where the parser stopped.
     pub fn parse(content: &str) -> Result<XmlElement, EpubError> {
+        Self::parse_tree(content, None)
+    }
+
+    /// Parses an XML string up to the end of the first element with the given name
+    ///
+    /// The parsing stops at the end tag of the element, so the rest of the document is
+    /// neither read nor built. The returned tree holds the element together with the
+    /// elements that precede it, and its ancestors up to the root element.
+    ///
+    /// ## Parameters
+    /// - `content`: The XML string to be parsed
+    /// - `name`: The local name of the element, without namespace prefix
+    ///
+    /// ## Return
+    /// - `Ok(XmlElement)`: The root element of the partial XML element tree, or of the
+    ///   whole tree if no element has the given name
+    /// - `Err(EpubError)`: An error occurred during parsing
+    pub fn parse_until(content: &str, name: &str) -> Result<XmlElement, EpubError> {
+        Self::parse_tree(content, Some(name))
+    }
+
+    /// Builds the element tree, stopping after the first element named `stop_after`
+    fn parse_tree(content: &str, stop_after: Option<&str>) -> Result<XmlElement, EpubError> {
         if content.is_empty() {
             return Err(EpubError::EmptyDataError);
         }
@@ -571,6 +1764,7 @@ impl XmlReader {
         let mut buf = Vec::new();
         let mut stack = Vec::<XmlElement>::new();
         let mut root = None;
+        let mut stopped = false;
         let mut namespace_map = HashMap::new();
 
         // Read XML events
@@ -613,6 +1807,8 @@ impl XmlReader {
                 // End of an element
                 Ok(Event::End(_)) => {
                     if let Some(element) = stack.pop() {
+                        let is_last = stop_after.is_some_and(|name| element.name == name);
+
                         // If the stack is empty,
                         // the current element is the root element
                         if stack.is_empty() {
@@ -622,6 +1818,11 @@ impl XmlReader {
                             // the current element is a child element of the last element in the stack
                             parent.children.push(element);
                         }
+
+                        if is_last {
+                            stopped = true;
+                            break;
+                        }
                     }
                 }
 
@@ -652,11 +1853,18 @@ impl XmlReader {
                         element.attributes.insert(attr_key, attr_value);
                     }
 
+                    let is_last = stop_after.is_some_and(|name| element.name == name);
+
                     // We can almost certainly assert that a self-closing element cannot be
                     // the root node of an XML file, so this will definitely be executed.
                     if let Some(parent) = stack.last_mut() {
                         parent.children.push(element);
                     }
+
+                    if is_last {
+                        stopped = true;
+                        break;
+                    }
                 }
 
                 // Text node
@@ -676,7 +1884,13 @@ impl XmlReader {
                     }
                 }
 
-                Err(err) => return Err(err.into()),
+                Err(err) => {
+                    return Err(EpubError::XmlParse {
+                        message: err.to_string(),
+                        position: XmlPosition::from_offset(content, reader.error_position()),
+                        source: err,
+                    });
+                }
 
                 // Ignore the following events (elements):
                 // Comment, PI, Declaration, Doctype, GeneralRef
@@ -684,6 +1898,16 @@ impl XmlReader {
             }
         }
 
+        // close the ancestors of the element the parsing stopped after
+        if stopped {
+            while let Some(element) = stack.pop() {
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            }
+        }
+
         if let Some(element) = root.as_mut() {
             Self::assign_namespace(element, &namespace_map);
         }
@@ -723,11 +1947,272 @@ mod tests {
     use crate::{
         error::EpubError,
         utils::{
-            DecodeBytes, NormalizeWhitespace, adobe_font_dencryption, adobe_font_encryption,
-            idpf_font_dencryption, idpf_font_encryption,
+            CfiPath, CfiStep, DecodeBytes, NormalizeWhitespace, XmlReader, adobe_font_dencryption,
+            adobe_font_encryption, adobe_obfuscation_key, apply_adobe_obfuscation,
+            apply_idpf_obfuscation, escape_cfi_assertion, extract_chapter_title,
+            extract_plain_text, guess_language_by_script, idpf_font_dencryption,
+            idpf_font_encryption, idpf_obfuscation_key, is_valid_language_tag,
+            normalize_container_path, parse_cfi_path, parse_clock_value,
         },
     };
 
+    #[test]
+    fn test_normalize_container_path() {
+        assert_eq!(
+            normalize_container_path("EPUB/text/ch1.xhtml"),
+            "EPUB/text/ch1.xhtml"
+        );
+        assert_eq!(
+            normalize_container_path("EPUB\\text\\ch1.xhtml"),
+            "EPUB/text/ch1.xhtml"
+        );
+        assert_eq!(
+            normalize_container_path("EPUB\\./text//ch1.xhtml"),
+            "EPUB/text/ch1.xhtml"
+        );
+        assert_eq!(normalize_container_path("./ch1.xhtml"), "ch1.xhtml");
+        assert_eq!(
+            normalize_container_path("..\\images/cover.png"),
+            "../images/cover.png"
+        );
+        assert_eq!(
+            normalize_container_path("\\EPUB\\ch1.xhtml"),
+            "/EPUB/ch1.xhtml"
+        );
+        assert_eq!(normalize_container_path(""), "");
+    }
+
+    #[test]
+    fn test_parse_cfi_path() {
+        let path = parse_cfi_path("/6/4[chap^[01^]ref]!/4/2/1:0");
+        assert!(path.is_ok());
+        assert_eq!(
+            path.unwrap(),
+            CfiPath {
+                segments: vec![
+                    vec![
+                        CfiStep { index: 6, assertion: None },
+                        CfiStep {
+                            index: 4,
+                            assertion: Some("chap[01]ref".to_string())
+                        },
+                    ],
+                    vec![
+                        CfiStep { index: 4, assertion: None },
+                        CfiStep { index: 2, assertion: None },
+                        CfiStep { index: 1, assertion: None },
+                    ],
+                ],
+                char_offset: Some(0),
+            }
+        );
+
+        let path = parse_cfi_path("/6/4");
+        assert!(path.is_ok());
+        assert_eq!(path.unwrap().char_offset, None);
+
+        assert!(parse_cfi_path("").is_err());
+        assert!(parse_cfi_path("/6/4!").is_err());
+        assert!(parse_cfi_path("/6/x").is_err());
+        assert!(parse_cfi_path("/6/4[id").is_err());
+        assert!(parse_cfi_path("/6[a][b]").is_err());
+        assert!(parse_cfi_path("/6/4:1/2").is_err());
+        assert!(parse_cfi_path("/6/4~2.5").is_err());
+        assert!(parse_cfi_path("/6/4,/2,/3").is_err());
+    }
+
+    #[test]
+    fn test_escape_cfi_assertion() {
+        assert_eq!(escape_cfi_assertion("chap01ref"), "chap01ref");
+        assert_eq!(escape_cfi_assertion("a[b]^c,d"), "a^[b^]^^c^,d");
+    }
+
+    #[test]
+    fn test_extract_plain_text() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <html xmlns="http://www.w3.org/1999/xhtml">
+              <head><title>Title</title><style>p { color: red; }</style></head>
+              <body>
+                <h1>Chapter   One</h1>
+                <p>Fish &amp; chips,<br/>caf&#233;   and <em>t</em>ea.</p>
+                <script>let x = 1;</script>
+                <div><p>Second</p>paragraph</div>
+              </body>
+            </html>"#;
+
+        let text = extract_plain_text(content);
+        assert!(text.is_ok());
+        assert_eq!(
+            text.unwrap(),
+            "Chapter One\nFish & chips,\ncafé and tea.\nSecond\nparagraph"
+        );
+
+        assert!(extract_plain_text("<p>mismatched</div>").is_err());
+    }
+
+    #[test]
+    fn test_extract_chapter_title() {
+        let content = r#"<html xmlns="http://www.w3.org/1999/xhtml">
+              <head><title>Document   Title</title></head>
+              <body>
+                <h2></h2>
+                <section><h2>Fish &amp; <em>chips</em></h2><h1>Later</h1></section>
+              </body>
+            </html>"#;
+        assert_eq!(
+            extract_chapter_title(content).unwrap(),
+            Some("Fish & chips".to_string())
+        );
+
+        let content = r#"<html><head><title> Document
+            Title </title></head><body><p>Text</p></body></html>"#;
+        assert_eq!(
+            extract_chapter_title(content).unwrap(),
+            Some("Document Title".to_string())
+        );
+
+        let content = "<html><head><title/></head><body><p>Text</p></body></html>";
+        assert_eq!(extract_chapter_title(content).unwrap(), None);
+
+        let content = "<html><body><h1>Don&rsquo;t&nbsp;stop &mdash; ever</h1></body></html>";
+        assert_eq!(
+            extract_chapter_title(content).unwrap(),
+            Some("Don\u{2019}t stop \u{2014} ever".to_string())
+        );
+
+        assert!(extract_chapter_title("<h1>mismatched</h2>").is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_extract_first_image_src() {
+        use crate::utils::extract_first_image_src;
+
+        let content = r#"<html xmlns="http://www.w3.org/1999/xhtml">
+              <body>
+                <img src="" alt="empty"/>
+                <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+                  <image xlink:href="images/a&amp;b.png"/>
+                </svg>
+                <p><img src="images/later.jpg"/></p>
+              </body>
+            </html>"#;
+        assert_eq!(
+            extract_first_image_src(content).unwrap(),
+            Some("images/a&b.png".to_string())
+        );
+
+        let content = "<html><body><p>Text</p></body></html>";
+        assert_eq!(extract_first_image_src(content).unwrap(), None);
+
+        assert!(extract_first_image_src("<p></div><img src='a.png'/>").is_err());
+    }
+
+    #[test]
+    fn test_is_valid_language_tag() {
+        assert!(is_valid_language_tag("en"));
+        assert!(is_valid_language_tag("en-US"));
+        assert!(is_valid_language_tag("zh-Hant-TW"));
+        assert!(is_valid_language_tag("ast"));
+
+        assert!(!is_valid_language_tag(""));
+        assert!(!is_valid_language_tag("und"));
+        assert!(!is_valid_language_tag("zxx"));
+        assert!(!is_valid_language_tag("english"));
+        assert!(!is_valid_language_tag("en_US"));
+        assert!(!is_valid_language_tag("en-"));
+    }
+
+    #[test]
+    fn test_guess_language_by_script() {
+        assert_eq!(
+            guess_language_by_script("吾輩は猫である。名前はまだ無い。"),
+            Some("ja")
+        );
+        assert_eq!(guess_language_by_script("我们的书在桌子上。"), Some("zh"));
+        assert_eq!(guess_language_by_script("안녕하세요, 세계"), Some("ko"));
+        assert_eq!(guess_language_by_script("Война и мир"), Some("ru"));
+        assert_eq!(guess_language_by_script("שלום עולם"), Some("he"));
+
+        // Latin is used by too many languages
+        assert_eq!(guess_language_by_script("The quick brown fox"), None);
+        assert_eq!(
+            guess_language_by_script("Mostly English text with 一 word"),
+            None
+        );
+        assert_eq!(guess_language_by_script("123 ..."), None);
+    }
+
+    #[test]
+    fn test_parse_clock_value() {
+        use std::time::Duration;
+
+        assert_eq!(
+            parse_clock_value("01:23:45.678"),
+            Some(Duration::from_millis(5_025_678))
+        );
+        assert_eq!(parse_clock_value("0:00:05"), Some(Duration::from_secs(5)));
+        assert_eq!(
+            parse_clock_value("23:45.5"),
+            Some(Duration::from_millis(1_425_500))
+        );
+        assert_eq!(
+            parse_clock_value("600.5s"),
+            Some(Duration::from_millis(600_500))
+        );
+        assert_eq!(parse_clock_value("600"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_clock_value("1.5h"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_clock_value("90min"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_clock_value("250ms"), Some(Duration::from_millis(250)));
+
+        assert_eq!(parse_clock_value(""), None);
+        assert_eq!(parse_clock_value("1:60:00"), None);
+        assert_eq!(parse_clock_value("00:5"), None);
+        assert_eq!(parse_clock_value("-5s"), None);
+        assert_eq!(parse_clock_value("5.s"), None);
+        assert_eq!(parse_clock_value("5days"), None);
+    }
+
+    #[cfg(feature = "content-builder")]
+    #[test]
+    fn test_minify_css() {
+        use crate::utils::minify_css;
+
+        let css = r#"
+            /* base styles */
+            body, p {
+                font-family: "Times  New Roman", serif;
+                margin: 0 auto;
+            }
+            blockquote > p { font-style: italic; }
+            a :hover { color: red; }
+        "#;
+
+        assert_eq!(
+            minify_css(css),
+            r#"body,p{font-family:"Times  New Roman",serif;margin:0 auto;}blockquote>p{font-style:italic;}a :hover{color:red;}"#
+        );
+        assert_eq!(minify_css(""), "");
+    }
+
+    #[cfg(feature = "content-builder")]
+    #[test]
+    fn test_sanitize_file_name() {
+        use crate::utils::sanitize_file_name;
+
+        assert_eq!(sanitize_file_name("my photo (1).png"), "my-photo-1.png");
+        assert_eq!(sanitize_file_name("cover_image-2.JPG"), "cover_image-2.JPG");
+        assert_eq!(sanitize_file_name("  été -- 2024 .jpg"), "ete-2024.jpg");
+        assert_eq!(
+            sanitize_file_name("Straße Øresund.png"),
+            "Strasse-Oresund.png"
+        );
+        assert_eq!(sanitize_file_name("图片.png"), "resource.png");
+        assert_eq!(sanitize_file_name(".hidden"), "hidden");
+        assert_eq!(sanitize_file_name("archive.tar.gz"), "archive-tar.gz");
+        assert_eq!(sanitize_file_name("font.wo ff"), "font.woff");
+    }
+
     /// Test with empty data
     #[test]
     fn test_decode_empty_data() {
@@ -796,6 +2281,25 @@ mod tests {
         assert_eq!(result.unwrap(), "Hello, World!");
     }
 
+    /// Test lossy decoding of text containing invalid sequences
+    #[test]
+    fn test_decode_lossy() {
+        let data = vec![b'H', b'i', 0xFF, 0xFE, b'!', 0xC3];
+        assert_eq!(data.decode_lossy(), "Hi\u{FFFD}\u{FFFD}!\u{FFFD}");
+
+        let data = vec![0xEF, 0xBB, 0xBF, b'a', 0x80, b'b'];
+        assert_eq!(data.decode_lossy(), "a\u{FFFD}b");
+
+        // unpaired surrogate followed by an odd trailing byte
+        let data = vec![0xFE, 0xFF, 0xD8, 0x00, 0x00, b'b', 0x00];
+        assert_eq!(data.decode_lossy(), "\u{FFFD}b\u{FFFD}");
+
+        let data = vec![0xFF, 0xFE, b'o', 0x00, b'k', 0x00];
+        assert_eq!(data.decode_lossy(), "ok");
+
+        assert_eq!(Vec::new().decode_lossy(), "");
+    }
+
     /// Test text standardization containing various whitespace characters
     #[test]
     fn test_normalize_whitespace_trait() {
@@ -874,6 +2378,47 @@ mod tests {
         assert_eq!(twice, data);
     }
 
+    #[test]
+    fn test_idpf_obfuscation_key() {
+        let key = idpf_obfuscation_key("urn:uuid:12345");
+        assert_eq!(key.len(), 20);
+        assert_eq!(key, idpf_obfuscation_key(" urn:uuid:\t123\r\n45 "));
+        assert_ne!(key, idpf_obfuscation_key("urn:uuid:54321"));
+    }
+
+    #[test]
+    fn test_adobe_obfuscation_key() {
+        let key = adobe_obfuscation_key("urn:uuid:00112233-4455-6677-8899-aabbccddeeff");
+        assert_eq!(
+            key,
+            vec![
+                0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+                0xee, 0xff
+            ]
+        );
+        assert_eq!(
+            adobe_obfuscation_key("00112233445566778899AABBCCDDEEFF"),
+            key
+        );
+        assert_eq!(adobe_obfuscation_key("not-a-uuid"), b"not-a-uuid".to_vec());
+    }
+
+    #[test]
+    fn test_font_obfuscation_round_trip() {
+        let font = std::fs::read("./test_case/le-murmure.otf").unwrap();
+        let unique_id = "urn:uuid:00112233-4455-6677-8899-aabbccddeeff";
+
+        let obfuscated = apply_idpf_obfuscation(&font, &idpf_obfuscation_key(unique_id));
+        assert_ne!(obfuscated[..1040], font[..1040]);
+        assert_eq!(obfuscated[1040..], font[1040..]);
+        assert_eq!(idpf_font_dencryption(&obfuscated, unique_id), font);
+
+        let obfuscated = apply_adobe_obfuscation(&font, &adobe_obfuscation_key(unique_id));
+        assert_ne!(obfuscated[..1024], font[..1024]);
+        assert_eq!(obfuscated[1024..], font[1024..]);
+        assert_eq!(adobe_font_dencryption(&obfuscated, unique_id), font);
+    }
+
     #[test]
     fn test_adobe_font_encryption_empty_data() {
         let data = vec![];
@@ -946,4 +2491,68 @@ mod tests {
 
         assert_eq!(decrypted, data);
     }
+
+    #[test]
+    fn test_xml_reader_parse_malformed_xml() {
+        let content = "<package>\n  <metadata>\n  </manifest>\n</package>";
+        let result = XmlReader::parse(content);
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            EpubError::XmlParse { message, position, source } => {
+                assert!(!message.is_empty());
+                assert_eq!(message, source.to_string());
+                assert!(position.offset > 0);
+                assert_eq!(position.line, 3);
+            }
+            err => panic!("Expected EpubError::XmlParse, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_xml_reader_parse_until() {
+        // the content after the metadata is malformed, but never read
+        let content = r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+            <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:title>Title</dc:title>
+                <meta property="dcterms:modified">2024-01-01T00:00:00Z</meta>
+            </metadata>
+            <manifest>
+                <item id="a" href="a.xhtml" media-type="application/xhtml+xml"/>
+            </spine>
+        </package>"#;
+        assert!(XmlReader::parse(content).is_err());
+
+        let package = XmlReader::parse_until(content, "metadata").unwrap();
+        assert_eq!(package.name, "package");
+        assert_eq!(package.get_attr("version"), Some("3.0".to_string()));
+        assert_eq!(package.children().count(), 1);
+
+        let metadata = package.find_children_by_name("metadata").next().unwrap();
+        assert_eq!(metadata.children().count(), 2);
+
+        let title = metadata.find_children_by_name("title").next().unwrap();
+        assert_eq!(
+            title.namespace,
+            Some("http://purl.org/dc/elements/1.1/".to_string())
+        );
+        assert_eq!(title.text(), "Title");
+
+        let package = XmlReader::parse_until(r#"<package><metadata/><manifest>"#, "metadata");
+        assert!(package.is_ok());
+        assert_eq!(package.unwrap().children().count(), 1);
+    }
+
+    #[test]
+    fn test_xml_reader_parse_error_source() {
+        let content = "<package>\n  <metadata>\n  </manifest>\n</package>";
+        let err = XmlReader::parse(content).unwrap_err();
+
+        let source = std::error::Error::source(&err);
+        assert!(source.is_some());
+
+        let source = source.unwrap();
+        assert!(source.downcast_ref::<quick_xml::Error>().is_some());
+        assert!(err.to_string().ends_with(&source.to_string()));
+    }
 }